@@ -0,0 +1,54 @@
+/* C API for testnet4-reorg-calculator (build with `cargo build -p reorg-calc-ffi`). */
+#ifndef REORG_CALC_H
+#define REORG_CALC_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define REORG_CALC_OK 0
+#define REORG_CALC_INVALID_ARGUMENT 1
+#define REORG_CALC_NODE_UNREACHABLE 2
+#define REORG_CALC_AUTH_FAILED 3
+#define REORG_CALC_FORK_ABOVE_TIP 4
+#define REORG_CALC_OTHER 5
+/* The library panicked; a bug. The call had no effect beyond it. */
+#define REORG_CALC_PANIC 6
+
+typedef struct ReorgCalcClient ReorgCalcClient;
+
+typedef struct {
+    uint64_t fork_height;
+    uint64_t current_height;
+    uint64_t blocks_to_reorg;
+    double total_work;
+    double current_difficulty;
+    double blocks_needed;
+    double time_required_hours;
+    double time_required_days;
+    double hashrate_required;
+    int64_t timestamp;
+} ReorgCalcResult;
+
+/* Connect to a node; on success *out holds a handle for reorg_calc_free. */
+int reorg_calc_connect(const char *url, const char *user, const char *password,
+                       size_t connections, ReorgCalcClient **out);
+
+/* Calculate requirements to reorg from fork_height to the current tip.
+   Prints nothing, even for chain work that has to be scanned. */
+int reorg_calc_calculate(const ReorgCalcClient *client, uint64_t fork_height,
+                         double hashrate, double target_days, ReorgCalcResult *out);
+
+void reorg_calc_free(ReorgCalcClient *client);
+
+/* Message for the last failed call on this thread, or NULL. */
+const char *reorg_calc_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* REORG_CALC_H */
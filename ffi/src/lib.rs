@@ -0,0 +1,197 @@
+//! C API for calling the calculator from non-Rust software.
+//!
+//! Built as its own cdylib, `libreorg_calc`, so the calculator library
+//! carries no C symbols. See `include/reorg_calc.h` for the matching
+//! declarations. Every function
+//! returns a status code; on failure `reorg_calc_last_error` describes what
+//! went wrong. A panic never unwinds into the caller: it is reported as
+//! [`REORG_CALC_PANIC`] instead.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use testnet4_reorg_calculator::{calculate_reorg_requirements_with, ReorgCalcError, RpcPool, ScanOptions};
+
+pub const REORG_CALC_OK: c_int = 0;
+pub const REORG_CALC_INVALID_ARGUMENT: c_int = 1;
+pub const REORG_CALC_NODE_UNREACHABLE: c_int = 2;
+pub const REORG_CALC_AUTH_FAILED: c_int = 3;
+pub const REORG_CALC_FORK_ABOVE_TIP: c_int = 4;
+pub const REORG_CALC_OTHER: c_int = 5;
+pub const REORG_CALC_PANIC: c_int = 6;
+
+/// Opaque connection handle returned by [`reorg_calc_connect`].
+pub struct ReorgCalcClient(RpcPool);
+
+/// Plain-data copy of a `ReorgCalculation`.
+#[repr(C)]
+pub struct ReorgCalcResult {
+    pub fork_height: u64,
+    pub current_height: u64,
+    pub blocks_to_reorg: u64,
+    pub total_work: f64,
+    pub current_difficulty: f64,
+    pub blocks_needed: f64,
+    pub time_required_hours: f64,
+    pub time_required_days: f64,
+    pub hashrate_required: f64,
+    /// Unix timestamp (seconds) of the calculation.
+    pub timestamp: i64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn status_for(error: &ReorgCalcError) -> c_int {
+    set_last_error(&error.to_string());
+    match error {
+        ReorgCalcError::NodeUnreachable(_) => REORG_CALC_NODE_UNREACHABLE,
+        ReorgCalcError::AuthFailed => REORG_CALC_AUTH_FAILED,
+        ReorgCalcError::ForkAboveTip { .. } => REORG_CALC_FORK_ABOVE_TIP,
+        _ => REORG_CALC_OTHER,
+    }
+}
+
+/// Run `body`, returning `on_panic` with the panic as the last error if it
+/// panics.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload: Box<dyn Any + Send>| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".to_string());
+        set_last_error(&format!("internal error (panic): {}", message));
+        on_panic
+    })
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Option<&'a str> {
+    if ptr.is_null() {
+        set_last_error(&format!("{} is null", name));
+        return None;
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_last_error(&format!("{} is not valid UTF-8", name));
+            None
+        }
+    }
+}
+
+/// Connect to a node and store the handle in `*out`.
+///
+/// # Safety
+///
+/// `url`, `user` and `password` must be valid NUL-terminated strings and
+/// `out` must point to writable memory. The handle must be released with
+/// [`reorg_calc_free`].
+#[no_mangle]
+pub unsafe extern "C" fn reorg_calc_connect(
+    url: *const c_char,
+    user: *const c_char,
+    password: *const c_char,
+    connections: usize,
+    out: *mut *mut ReorgCalcClient,
+) -> c_int {
+    if out.is_null() {
+        set_last_error("out is null");
+        return REORG_CALC_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+    guard(REORG_CALC_PANIC, || {
+        let (Some(url), Some(user), Some(password)) = (
+            str_arg(url, "url"),
+            str_arg(user, "user"),
+            str_arg(password, "password"),
+        ) else {
+            return REORG_CALC_INVALID_ARGUMENT;
+        };
+
+        match RpcPool::new(url, user, password, connections) {
+            Ok(pool) => {
+                *out = Box::into_raw(Box::new(ReorgCalcClient(pool)));
+                REORG_CALC_OK
+            }
+            Err(e) => status_for(&e),
+        }
+    })
+}
+
+/// Calculate reorg requirements from `fork_height` and write them to `*out`.
+///
+/// Nothing is printed: chain work that has to be scanned is scanned without
+/// notes or a progress bar.
+///
+/// # Safety
+///
+/// `client` must come from [`reorg_calc_connect`] and not yet be freed;
+/// `out` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn reorg_calc_calculate(
+    client: *const ReorgCalcClient,
+    fork_height: u64,
+    hashrate: f64,
+    target_days: f64,
+    out: *mut ReorgCalcResult,
+) -> c_int {
+    if client.is_null() || out.is_null() {
+        set_last_error("client or out is null");
+        return REORG_CALC_INVALID_ARGUMENT;
+    }
+
+    let options = ScanOptions {
+        quiet: true,
+        ..ScanOptions::default()
+    };
+    guard(REORG_CALC_PANIC, || {
+        match calculate_reorg_requirements_with(&(*client).0, fork_height, hashrate, target_days, &options) {
+            Ok(calc) => {
+                *out = ReorgCalcResult {
+                    fork_height: calc.fork_height,
+                    current_height: calc.current_height,
+                    blocks_to_reorg: calc.blocks_to_reorg,
+                    total_work: calc.total_work,
+                    current_difficulty: calc.current_difficulty,
+                    blocks_needed: calc.blocks_needed,
+                    time_required_hours: calc.time_required_hours,
+                    time_required_days: calc.time_required_days,
+                    hashrate_required: calc.hashrate_required,
+                    timestamp: calc.timestamp.timestamp(),
+                };
+                REORG_CALC_OK
+            }
+            Err(e) => status_for(&e),
+        }
+    })
+}
+
+/// Release a handle from [`reorg_calc_connect`]. Null is ignored.
+///
+/// # Safety
+///
+/// `client` must come from [`reorg_calc_connect`] and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn reorg_calc_free(client: *mut ReorgCalcClient) {
+    if !client.is_null() {
+        guard((), || drop(Box::from_raw(client)));
+    }
+}
+
+/// Message for the last failed call on this thread, or null if none.
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn reorg_calc_last_error() -> *const c_char {
+    guard(ptr::null(), || LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr())))
+}
@@ -0,0 +1,394 @@
+//! Threshold alerts on monitoring results.
+//!
+//! A rule names a figure of the calculation, optionally the depth below the
+//! tip it watches, and a threshold, e.g. `hashrate_required@100<50TH`:
+//! notify when the hashrate required to reorg 100 blocks drops below
+//! 50 TH/s. Figures are `hashrate_required` (a hashrate), `time_required`
+//! (a duration, as for `--target-days`) and `blocks_needed`; comparisons
+//! are `<`, `<=`, `>` and `>=`. A rule fires when it starts to hold, and
+//! again only after it stopped holding in between. `reorg_depth`, e.g.
+//! `reorg_depth>=2`, instead watches the reorgs seen while monitoring and
+//! fires for every one as deep as the rule asks, and `competing`, e.g.
+//! `competing>1`, the node's other branches: the work a branch gained over
+//! the rolling window per unit the active chain gained, so above 1 someone
+//! is out-mining the honest chain on it.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::calc::ReorgCalculation;
+use crate::format::{format_days, format_hashrate};
+use crate::monitor::MonitorEventKind;
+use crate::network::NetworkParams;
+use crate::track::BranchRate;
+use crate::units::{parse_days, parse_hashrate};
+
+/// Figure of a calculation an alert rule compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    HashrateRequired,
+    /// In days.
+    TimeRequired,
+    BlocksNeeded,
+    /// Blocks displaced by a reorg that happened.
+    ReorgDepth,
+    /// Work a branch off the active chain gained per unit the active chain
+    /// gained.
+    Competing,
+}
+
+impl AlertMetric {
+    /// The figure of `calc`, or `None` for a metric of the node's chains.
+    fn value(self, calc: &ReorgCalculation) -> Option<f64> {
+        match self {
+            AlertMetric::HashrateRequired => Some(calc.hashrate_required),
+            AlertMetric::TimeRequired => Some(calc.time_required_days),
+            AlertMetric::BlocksNeeded => Some(calc.blocks_needed),
+            AlertMetric::ReorgDepth | AlertMetric::Competing => None,
+        }
+    }
+}
+
+/// How an alert rule compares its figure with the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Below,
+    AtMost,
+    Above,
+    AtLeast,
+}
+
+/// One alert rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    /// The rule as written, to name it in alerts.
+    pub text: String,
+    pub metric: AlertMetric,
+    /// Depth below the tip the rule watches, or `None` for every depth.
+    pub depth: Option<u64>,
+    pub comparison: Comparison,
+    /// In the metric's unit: H/s, days or blocks.
+    pub threshold: f64,
+}
+
+/// Parse an alert rule, e.g. `hashrate_required@100<50TH` or
+/// `time_required>=2d`.
+pub fn parse_alert_rule(text: &str) -> Result<AlertRule, String> {
+    let trimmed = text.trim();
+    let at = trimmed
+        .find(['<', '>'])
+        .ok_or_else(|| format!("expected a rule such as hashrate_required@100<50TH, got '{}'", trimmed))?;
+    let (subject, rest) = trimmed.split_at(at);
+    let (comparison, threshold) = if let Some(threshold) = rest.strip_prefix("<=") {
+        (Comparison::AtMost, threshold)
+    } else if let Some(threshold) = rest.strip_prefix(">=") {
+        (Comparison::AtLeast, threshold)
+    } else if let Some(threshold) = rest.strip_prefix('<') {
+        (Comparison::Below, threshold)
+    } else {
+        (Comparison::Above, &rest[1..])
+    };
+    let (name, depth) = match subject.split_once('@') {
+        Some((name, depth)) => {
+            let depth = depth.trim().parse().map_err(|_| format!("bad depth '{}' in alert rule", depth.trim()))?;
+            (name.trim(), Some(depth))
+        }
+        None => (subject.trim(), None),
+    };
+    let metric = match name.to_ascii_lowercase().as_str() {
+        "hashrate_required" | "hashrate" => AlertMetric::HashrateRequired,
+        "time_required" | "days" => AlertMetric::TimeRequired,
+        "blocks_needed" | "blocks" => AlertMetric::BlocksNeeded,
+        "reorg_depth" | "reorg" => AlertMetric::ReorgDepth,
+        "competing" => AlertMetric::Competing,
+        _ => {
+            return Err(format!(
+                "unknown figure '{}' in alert rule (expected hashrate_required, time_required, blocks_needed, \
+                 reorg_depth or competing)",
+                name
+            ))
+        }
+    };
+    if matches!(metric, AlertMetric::ReorgDepth | AlertMetric::Competing) && depth.is_some() {
+        return Err(format!("'{}' watches the node's chains, which have no fork depth to give with @", trimmed));
+    }
+    let threshold = match metric {
+        AlertMetric::HashrateRequired => parse_hashrate(threshold)?,
+        AlertMetric::TimeRequired => parse_days(threshold)?,
+        AlertMetric::BlocksNeeded | AlertMetric::ReorgDepth => threshold
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|blocks| blocks.is_finite())
+            .ok_or_else(|| format!("expected a number of blocks, got '{}'", threshold.trim()))?,
+        AlertMetric::Competing => threshold
+            .trim()
+            .trim_end_matches(['x', 'X'])
+            .parse::<f64>()
+            .ok()
+            .filter(|ratio| ratio.is_finite())
+            .ok_or_else(|| format!("expected a ratio of work gained such as 1 or 1.5x, got '{}'", threshold.trim()))?,
+    };
+    Ok(AlertRule { text: trimmed.to_string(), metric, depth, comparison, threshold })
+}
+
+impl AlertRule {
+    /// Whether `value` meets the rule; infinite figures only ever exceed it.
+    fn holds(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Below => value < self.threshold,
+            Comparison::AtMost => value <= self.threshold,
+            Comparison::Above => value > self.threshold,
+            Comparison::AtLeast => value >= self.threshold,
+        }
+    }
+}
+
+/// A rule that started to hold, as sent to a webhook.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub time: DateTime<Utc>,
+    pub rule: String,
+    pub metric: AlertMetric,
+    pub depth: u64,
+    pub fork_height: u64,
+    pub current_height: u64,
+    /// The figure, or `None` if infinite, for an attack that never finishes.
+    pub value: Option<f64>,
+    pub threshold: f64,
+    /// Hashrate the calculation was made for, or measured on a competing
+    /// branch; `None` for a reorg.
+    pub hashrate: Option<f64>,
+    pub target_days: Option<f64>,
+}
+
+impl Alert {
+    /// `hashrate<1EH (depth 100, fork 3009: 18.37 GH/s)`, for a reorg
+    /// `reorg>=2 (3 blocks displaced from 2990)`, and for a competing branch
+    /// `competing>1 (branch from 2990, 12 blocks deep: 2.00x the active
+    /// chain's gain in work at 40.00 GH/s)`.
+    pub fn describe(&self) -> String {
+        match self.metric {
+            AlertMetric::ReorgDepth => {
+                return format!("{} ({} blocks displaced from {})", self.rule, self.depth, self.fork_height);
+            }
+            AlertMetric::Competing => {
+                let gain = self.value.map_or_else(
+                    || "gaining work while the active chain stood still".to_string(),
+                    |ratio| format!("{:.2}x the active chain's gain in work", ratio),
+                );
+                let rate = self.hashrate.map_or_else(String::new, |rate| format!(" at {}", format_hashrate(rate)));
+                return format!(
+                    "{} (branch from {}, {} blocks deep: {}{})",
+                    self.rule, self.fork_height, self.depth, gain, rate
+                );
+            }
+            _ => {}
+        }
+        let value = match (self.value, self.metric) {
+            (None, _) => "infinite".to_string(),
+            (Some(value), AlertMetric::HashrateRequired) => format_hashrate(value),
+            (Some(value), AlertMetric::TimeRequired) => format!("{} days", format_days(value)),
+            (Some(value), AlertMetric::BlocksNeeded | AlertMetric::ReorgDepth) => format!("{:.0} blocks", value),
+            (Some(value), AlertMetric::Competing) => format!("{:.2}x", value),
+        };
+        format!("{} (depth {}, fork {}: {})", self.rule, self.depth, self.fork_height, value)
+    }
+
+    /// The alert as a monitor event, for the sinks that record those.
+    pub fn event_kind(&self) -> MonitorEventKind {
+        MonitorEventKind::AlertFired {
+            rule: self.rule.clone(),
+            fork_height: self.fork_height,
+            value: self.value,
+        }
+    }
+}
+
+/// Alert rules with what each last saw, so they fire once per crossing.
+#[derive(Debug, Clone, Default)]
+pub struct AlertRules {
+    rules: Vec<AlertRule>,
+    /// Whether rule `i` held at depth `d` at its last calculation, or for
+    /// `competing`, on the branch forking at height `d` at its last reading.
+    holding: HashMap<(usize, u64), bool>,
+}
+
+impl AlertRules {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        AlertRules { rules, holding: HashMap::new() }
+    }
+
+    /// The alerts `calc`, made for `hashrate`, fires: the rules watching its
+    /// depth that hold now but did not at the depth's last calculation.
+    pub fn check(&mut self, calc: &ReorgCalculation, hashrate: f64) -> Vec<Alert> {
+        let depth = calc.current_height.saturating_sub(calc.fork_height);
+        let mut alerts = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.depth.is_some_and(|watched| watched != depth) {
+                continue;
+            }
+            let Some(value) = rule.metric.value(calc) else {
+                continue;
+            };
+            let holds = rule.holds(value);
+            let held = self.holding.insert((i, depth), holds).unwrap_or(false);
+            if holds && !held {
+                alerts.push(Alert {
+                    time: calc.timestamp,
+                    rule: rule.text.clone(),
+                    metric: rule.metric,
+                    depth,
+                    fork_height: calc.fork_height,
+                    current_height: calc.current_height,
+                    value: value.is_finite().then_some(value),
+                    threshold: rule.threshold,
+                    hashrate: Some(hashrate),
+                    target_days: calc.target_days,
+                });
+            }
+        }
+        alerts
+    }
+
+    /// The alerts a reorg seen at `time`, displacing `depth` blocks from
+    /// `fork_height` up for a new tip at `current_height`, fires: every
+    /// `reorg_depth` rule it meets.
+    pub fn check_reorg(&self, time: DateTime<Utc>, fork_height: u64, depth: u64, current_height: u64) -> Vec<Alert> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.metric == AlertMetric::ReorgDepth && rule.holds(depth as f64))
+            .map(|rule| Alert {
+                time,
+                rule: rule.text.clone(),
+                metric: rule.metric,
+                depth,
+                fork_height,
+                current_height,
+                value: Some(depth as f64),
+                threshold: rule.threshold,
+                hashrate: None,
+                target_days: None,
+            })
+            .collect()
+    }
+
+    /// The rules that held at their last check, by text, with the depth or
+    /// branch each held for, to carry over to a later run.
+    pub fn held(&self) -> Vec<(String, u64)> {
+        let mut held: Vec<(String, u64)> = self
+            .holding
+            .iter()
+            .filter(|(_, holds)| **holds)
+            .map(|((i, key), _)| (self.rules[*i].text.clone(), *key))
+            .collect();
+        held.sort();
+        held
+    }
+
+    /// Mark the rules in `held`, as [`AlertRules::held`] gave them, as
+    /// holding, so they only fire again after they stop holding; rules no
+    /// longer given are dropped.
+    pub fn restore(&mut self, held: &[(String, u64)]) {
+        for (text, key) in held {
+            if let Some(i) = self.rules.iter().position(|rule| &rule.text == text) {
+                self.holding.insert((i, *key), true);
+            }
+        }
+    }
+
+    /// Whether any rule watches the node's competing branches.
+    pub fn watches_branches(&self) -> bool {
+        self.rules.iter().any(|rule| rule.metric == AlertMetric::Competing)
+    }
+
+    /// The alerts `rates`, measured on a node whose next block needs the
+    /// work of `params`, fire: the `competing` rules each branch meets now
+    /// but did not at its last reading.
+    pub fn check_branches(&mut self, rates: &[BranchRate], params: &NetworkParams) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.metric != AlertMetric::Competing {
+                continue;
+            }
+            for rate in rates {
+                let progress = &rate.progress;
+                let ratio = rate.ratio();
+                let holds = rate.branch_gain > 0.0 && rule.holds(ratio);
+                let held = self.holding.insert((i, progress.fork_height), holds).unwrap_or(false);
+                if holds && !held {
+                    alerts.push(Alert {
+                        time: progress.time,
+                        rule: rule.text.clone(),
+                        metric: rule.metric,
+                        depth: (progress.public_height + 1).saturating_sub(progress.fork_height),
+                        fork_height: progress.fork_height,
+                        current_height: progress.public_height,
+                        value: ratio.is_finite().then_some(ratio),
+                        threshold: rule.threshold,
+                        hashrate: Some(rate.hashrate(params)),
+                        target_days: None,
+                    });
+                }
+            }
+        }
+        alerts
+    }
+}
+
+/// POST `payload`, an [`Alert`] or a chat message, as JSON to the webhook
+/// at `url`.
+///
+/// The built-in HTTP client speaks plain HTTP only.
+#[cfg(feature = "webhook")]
+pub fn post_webhook(url: &str, payload: &impl Serialize) -> crate::error::Result<()> {
+    use crate::error::ReorgCalcError;
+
+    let body = serde_json::to_vec(payload).map_err(|e| ReorgCalcError::Webhook(e.to_string()))?;
+    let response = minreq::post(url)
+        .with_timeout(30)
+        .with_header("Content-Type", "application/json")
+        .with_body(body)
+        .send()
+        .map_err(|e| ReorgCalcError::Webhook(format!("{}: {}", url, e)))?;
+    if !(200..300).contains(&response.status_code) {
+        let reason = response.as_str().unwrap_or("").trim().to_string();
+        return Err(ReorgCalcError::Webhook(format!("{} returned HTTP {} {}", url, response.status_code, reason)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_parse_figure_depth_and_threshold() {
+        let rule = parse_alert_rule("hashrate_required@100<50TH").unwrap();
+        assert_eq!(rule.metric, AlertMetric::HashrateRequired);
+        assert_eq!(rule.depth, Some(100));
+        assert_eq!(rule.comparison, Comparison::Below);
+        assert_eq!(rule.threshold, 50e12);
+
+        let rule = parse_alert_rule(" time_required >= 2d ").unwrap();
+        assert_eq!(rule.text, "time_required >= 2d");
+        assert_eq!((rule.metric, rule.depth, rule.comparison), (AlertMetric::TimeRequired, None, Comparison::AtLeast));
+        assert_eq!(rule.threshold, 2.0);
+
+        let rule = parse_alert_rule("blocks<=6").unwrap();
+        assert_eq!((rule.metric, rule.comparison, rule.threshold), (AlertMetric::BlocksNeeded, Comparison::AtMost, 6.0));
+        let rule = parse_alert_rule("competing>1.5x").unwrap();
+        assert_eq!((rule.metric, rule.comparison, rule.threshold), (AlertMetric::Competing, Comparison::Above, 1.5));
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert!(parse_alert_rule("hashrate_required").is_err());
+        assert!(parse_alert_rule("speed<5TH").is_err());
+        assert!(parse_alert_rule("hashrate@deep<5TH").is_err());
+        assert!(parse_alert_rule("reorg_depth@10>=2").is_err());
+        assert!(parse_alert_rule("blocks<many").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Async access to a [`BlockSource`] on a tokio runtime.
+//!
+//! This is not an async RPC client: every call is the blocking one, run on
+//! tokio's blocking pool with at most `concurrency` calls in flight, so an
+//! async caller such as the TUI is not stalled while it waits. Watch and
+//! daemon mode keep using the blocking sources directly.
+
+use bitcoin::Work;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::calc::{calculate_reorg_requirements_with, candidate_fork_heights, Adjustments, ReorgCalculation};
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, ChainTip};
+use crate::warning;
+use crate::work::{block_work, chain_work_from_chainwork, ScanOptions, SCAN_BATCH_SIZE};
+
+/// A [`BlockSource`] shared across tokio tasks.
+pub struct AsyncBlockSource<S> {
+    inner: Arc<S>,
+    permits: Arc<Semaphore>,
+}
+
+impl<S> Clone for AsyncBlockSource<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            permits: Arc::clone(&self.permits),
+        }
+    }
+}
+
+impl<S: BlockSource + Send + 'static> AsyncBlockSource<S> {
+    /// Wrap `source`, allowing up to `concurrency` RPC calls at once.
+    pub fn new(source: S, concurrency: usize) -> Self {
+        Self {
+            inner: Arc::new(source),
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&S) -> Result<T> + Send + 'static,
+    {
+        let _permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|e| ReorgCalcError::Task(e.to_string()))?;
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(|e| ReorgCalcError::Task(e.to_string()))?
+    }
+
+    /// Current best block.
+    pub async fn get_tip(&self) -> Result<ChainTip> {
+        self.run(|source| source.get_tip()).await
+    }
+
+    /// Compact target of the block at `height`.
+    pub async fn get_bits(&self, height: u64) -> Result<u32> {
+        self.run(move |source| source.get_bits(height)).await
+    }
+
+    /// Cumulative chain work up to and including the block at `height`.
+    pub async fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.run(move |source| source.get_chainwork(height)).await
+    }
+
+    /// Proof-of-work parameters of the chain being read.
+    pub async fn get_network(&self) -> Result<NetworkParams> {
+        self.run(|source| source.get_network()).await
+    }
+
+    /// Difficulty the network currently requires for the next block.
+    pub async fn get_difficulty(&self) -> Result<f64> {
+        self.run(|source| source.get_difficulty()).await
+    }
+
+    /// Async counterpart of [`crate::calculate_chain_work`].
+    pub async fn calculate_chain_work(&self, fork_height: u64, current_height: u64) -> Result<Work> {
+        let result = self
+            .run(move |source| chain_work_from_chainwork(source, fork_height, current_height))
+            .await;
+        match result {
+            Err(ReorgCalcError::Unsupported(_)) => self.scan_chain_work(fork_height, current_height).await,
+            result => result,
+        }
+    }
+
+    /// Async counterpart of [`crate::scan_chain_work`], fetching batches of
+    /// headers concurrently.
+    pub async fn scan_chain_work(&self, fork_height: u64, current_height: u64) -> Result<Work> {
+        let mut tasks = JoinSet::new();
+        for (index, start) in (fork_height..=current_height).step_by(SCAN_BATCH_SIZE).enumerate() {
+            let heights: Vec<u64> = (start..=(start + SCAN_BATCH_SIZE as u64 - 1).min(current_height)).collect();
+            let source = self.clone();
+            tasks.spawn(async move { (index, source.run(move |source| source.get_bits_batch(&heights)).await) });
+        }
+
+        // Sum in height order so the result matches the blocking scan exactly
+        let mut batches = vec![Vec::new(); tasks.len()];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, bits) = joined.map_err(|e| ReorgCalcError::Task(e.to_string()))?;
+            batches[index] = bits?;
+        }
+        let zero = Work::from_be_bytes([0; 32]);
+        Ok(batches.into_iter().flatten().fold(zero, |total, bits| total + block_work(bits)))
+    }
+
+    /// Async counterpart of [`crate::calculate_reorg_requirements`],
+    /// including its handling of blocks found during the scan. Prints no
+    /// progress, which would garble a terminal UI.
+    pub async fn calculate_reorg_requirements(
+        &self,
+        fork_height: u64,
+        hashrate: f64,
+        target_days: f64,
+    ) -> Result<ReorgCalculation> {
+        self.run(move |source| {
+            let options = ScanOptions {
+                quiet: true,
+                ..ScanOptions::default()
+            };
+            calculate_reorg_requirements_with(source, fork_height, hashrate, target_days, &options)
+        })
+        .await
+    }
+
+    /// Calculations, with `adjustments` applied, for every candidate fork
+    /// height that can be reorged within `max_days`, see
+    /// [`crate::find_viable_target_heights_with`]. The candidates are
+    /// calculated concurrently, and one that fails is skipped with a warning.
+    pub async fn find_viable_calculations(
+        &self,
+        hashrate: f64,
+        max_days: f64,
+        adjustments: Adjustments,
+    ) -> Result<Vec<ReorgCalculation>> {
+        let current_height = self.get_tip().await?.height;
+        let params = self.get_network().await?;
+        let candidates = candidate_fork_heights(current_height);
+        let mut tasks = JoinSet::new();
+        for (index, height) in candidates.iter().copied().enumerate() {
+            let source = self.clone();
+            tasks.spawn(async move {
+                let calc = source
+                    .run(move |source| {
+                        let options = ScanOptions {
+                            quiet: true,
+                            ..ScanOptions::default()
+                        };
+                        let calc = calculate_reorg_requirements_with(source, height, hashrate, max_days, &options)?;
+                        adjustments.apply(source, calc, hashrate, max_days, &params)
+                    })
+                    .await;
+                (index, calc)
+            });
+        }
+
+        // Keep the candidates' order
+        let mut calcs = vec![None; tasks.len()];
+        while let Some(joined) = tasks.join_next().await {
+            let (index, calc) = joined.map_err(|e| ReorgCalcError::Task(e.to_string()))?;
+            match calc {
+                Ok(calc) => calcs[index] = Some(calc),
+                Err(e) => warning!("Warning: Failed to calculate for height {}: {}", candidates[index], e),
+            }
+        }
+        Ok(calcs.into_iter().flatten().filter(|calc| calc.time_required_days <= max_days).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::{find_viable_target_heights_with, Margin};
+    use crate::fixture::{TestChain, DIFFICULTY_1};
+    use crate::{HASHES_PER_DIFFICULTY, SECONDS_PER_DAY};
+
+    #[tokio::test]
+    async fn viable_calculations_match_the_blocking_scan() {
+        let hashrate = HASHES_PER_DIFFICULTY;
+        let max_days = 20.0 / SECONDS_PER_DAY;
+        let margin = Adjustments { margin: Margin { blocks: 10, percent: 0.0 }, ..Adjustments::default() };
+        // Ten blocks of margin leave only the 2-block reorg within 20 seconds
+        for (adjustments, expected) in [(Adjustments::default(), vec![99, 90]), (margin, vec![99])] {
+            let chain = TestChain::uniform(101, DIFFICULTY_1).without_chainwork();
+            let blocking = find_viable_target_heights_with(&chain, hashrate, max_days, &adjustments).unwrap();
+            let source = AsyncBlockSource::new(chain, 4);
+            let calcs = source.find_viable_calculations(hashrate, max_days, adjustments).await.unwrap();
+            let heights: Vec<u64> = calcs.iter().map(|calc| calc.fork_height).collect();
+            assert_eq!((&heights, &blocking), (&expected, &expected));
+        }
+    }
+}
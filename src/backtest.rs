@@ -0,0 +1,144 @@
+//! How exposed the chain has been over time.
+//!
+//! A [`ChainAt`] reads a source as it stood when an earlier block was the
+//! tip, so any calculation can be made as of then: the work behind each
+//! fork and the difficulty the attacker would have mined at are the ones
+//! of that day. `backtest` runs the deepest-fork search on the last block
+//! of every day in a range, a time series of what a hashrate could have
+//! reorged.
+
+use bitcoin::{block::Header, Block, BlockHash, Transaction, Txid, Work};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+
+use crate::calc::{fork_height_at_time, ReorgCalculation};
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, ChainTip};
+
+/// `source` cut off at an earlier tip: blocks above it do not exist yet.
+pub struct ChainAt<S> {
+    source: S,
+    tip: ChainTip,
+}
+
+impl<S: BlockSource> ChainAt<S> {
+    /// `source` as it stood when the block at `height` was its tip.
+    pub fn new(source: S, height: u64) -> Result<Self> {
+        let tip_height = source.get_tip()?.height;
+        if height > tip_height {
+            return Err(ReorgCalcError::ForkAboveTip { fork_height: height, tip_height });
+        }
+        let hash = source.get_block_hash(height)?;
+        Ok(ChainAt { source, tip: ChainTip { height, hash } })
+    }
+
+    fn check(&self, height: u64) -> Result<()> {
+        if height > self.tip.height {
+            return Err(ReorgCalcError::InvalidData(format!(
+                "block {} is above the tip {} the chain is read at",
+                height, self.tip.height
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_all(&self, heights: &[u64]) -> Result<()> {
+        heights.iter().try_for_each(|&height| self.check(height))
+    }
+}
+
+impl<S: BlockSource> BlockSource for ChainAt<S> {
+    fn get_tip(&self) -> Result<ChainTip> {
+        Ok(self.tip)
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.check(height)?;
+        self.source.get_block_hash(height)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        self.check(height)?;
+        self.source.get_header(height)
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        self.check_all(heights)?;
+        self.source.get_headers_batch(heights)
+    }
+
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        self.check(height)?;
+        self.source.get_bits(height)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        self.check_all(heights)?;
+        self.source.get_bits_batch(heights)
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.check(height)?;
+        self.source.get_chainwork(height)
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        self.check_all(heights)?;
+        self.source.get_chainwork_batch(heights)
+    }
+
+    /// The difficulty of the tip block, which the calculations mine at.
+    fn get_difficulty(&self) -> Result<f64> {
+        Ok(self.get_network()?.difficulty(self.get_bits(self.tip.height)?))
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        Ok(self.source.get_transaction_height(txid)?.filter(|&height| height <= self.tip.height))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        Ok(self.source.get_block_height(hash)?.filter(|&height| height <= self.tip.height))
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        self.check_all(heights)?;
+        self.source.get_coinbases(heights)
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        self.check_all(heights)?;
+        self.source.get_blocks(heights)
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        self.source.get_network()
+    }
+}
+
+/// The deepest reorg a hashrate could have finished in time on one day.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestDay {
+    pub date: NaiveDate,
+    /// Last block timestamped on or before the day.
+    pub tip_height: u64,
+    /// Calculation for the deepest fork viable against that tip, or `None`
+    /// if not even the tip block was.
+    pub deepest: Option<ReorgCalculation>,
+}
+
+/// Height of the last block timestamped on or before `date`, or `None` if
+/// the chain had not started by its end.
+pub fn day_tip_height<S: BlockSource + ?Sized>(source: &S, date: NaiveDate) -> Result<Option<u64>> {
+    match fork_height_at_time(source, day_end(date)) {
+        Ok(height) => Ok(Some(height)),
+        Err(ReorgCalcError::BeforeGenesis(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Last second of `date` in UTC.
+pub fn day_end(date: NaiveDate) -> DateTime<Utc> {
+    let next = date.checked_add_days(Days::new(1)).unwrap_or(date);
+    next.and_time(NaiveTime::MIN).and_utc() - chrono::Duration::seconds(1)
+}
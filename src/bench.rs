@@ -0,0 +1,63 @@
+//! Double-SHA256 benchmark of the local machine.
+//!
+//! Hashes 80-byte block headers with a changing nonce on every thread for a
+//! fixed time, the same work a CPU miner does, so the measured rate can
+//! stand in for `--hashrate` in "could these machines do it" experiments.
+
+use bitcoin::hashes::{sha256d, Hash};
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Hashes between checks of the clock.
+const BATCH: u32 = 4096;
+
+/// Result of [`benchmark_sha256d`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Benchmark {
+    pub threads: usize,
+    /// Headers hashed across all threads.
+    pub hashes: u64,
+    /// Wall-clock time the threads ran for.
+    pub seconds: f64,
+    /// Measured rate in hashes/second.
+    pub hashrate: f64,
+}
+
+/// Hash block headers on `threads` threads for `duration`.
+pub fn benchmark_sha256d(duration: Duration, threads: usize) -> Benchmark {
+    let threads = threads.max(1);
+    let start = Instant::now();
+    let hashes: u64 = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| scope.spawn(move || hash_until(start + duration, worker as u64)))
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("benchmark thread panicked")).sum()
+    });
+    let seconds = start.elapsed().as_secs_f64();
+    Benchmark {
+        threads,
+        hashes,
+        seconds,
+        hashrate: hashes as f64 / seconds,
+    }
+}
+
+/// Hash headers until `deadline`, returning how many were hashed.
+fn hash_until(deadline: Instant, worker: u64) -> u64 {
+    // Distinct headers per thread, as distinct extranonces would give
+    let mut header = [0u8; 80];
+    header[..8].copy_from_slice(&worker.to_le_bytes());
+    let mut hashes = 0;
+    let mut extranonce = 0u64;
+    while Instant::now() < deadline {
+        header[8..16].copy_from_slice(&extranonce.to_le_bytes());
+        for nonce in 0..BATCH {
+            header[76..].copy_from_slice(&nonce.to_le_bytes());
+            black_box(sha256d::Hash::hash(black_box(&header)));
+        }
+        hashes += u64::from(BATCH);
+        extranonce += 1;
+    }
+    hashes
+}
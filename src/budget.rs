@@ -0,0 +1,241 @@
+//! Attack cost when hashrate is rented.
+//!
+//! Hashrate marketplaces price by the TH/s per day, so an attack at
+//! `hashrate` for `days` costs the price times `hashrate` in TH/s times
+//! `days`. Amounts carry an optional currency code; without a conversion
+//! they can only be compared in the same currency.
+//!
+//! Mining the hashrate yourself costs electricity instead: the hashes the
+//! attack computes times the hardware's joules per terahash.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::calc::ReorgCalculation;
+
+/// An amount of money, e.g. `500USD` or `0.01BTC`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: f64,
+    /// Upper-case currency code, when one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+impl Money {
+    /// The same currency as `self`, with a different amount.
+    pub fn with_amount(&self, amount: f64) -> Money {
+        Money {
+            amount,
+            currency: self.currency.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.currency {
+            Some(currency) => write!(f, "{} {}", format_amount(self.amount), currency),
+            None => f.write_str(&format_amount(self.amount)),
+        }
+    }
+}
+
+/// Format an amount with cents, or with satoshi precision below 1 so that
+/// BTC prices stay readable.
+pub fn format_amount(amount: f64) -> String {
+    if amount.abs() >= 1.0 || amount == 0.0 {
+        format!("{:.2}", amount)
+    } else {
+        format!("{:.8}", amount)
+    }
+}
+
+/// Rental price of SHA-256 hashrate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RentalPrice {
+    /// Where the price came from.
+    pub source: String,
+    /// Price of 1 TH/s for one day, in `currency`.
+    pub price_per_th_day: f64,
+    pub currency: String,
+}
+
+impl RentalPrice {
+    /// Cost of renting `hashrate` hashes/second for a day.
+    pub fn cost_per_day(&self, hashrate: f64) -> f64 {
+        self.price_per_th_day * hashrate / 1e12
+    }
+}
+
+/// Price of one bitcoin in a fiat currency, as quoted at `timestamp`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    /// Upper-case fiat currency code, e.g. `USD`.
+    pub currency: String,
+    /// Units of `currency` per BTC.
+    pub per_btc: f64,
+    /// Where the rate came from.
+    pub source: String,
+    /// When the source last updated the rate.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ExchangeRate {
+    /// `money` in `currency`, converting between BTC and this rate's fiat
+    /// currency; `None` when neither side is BTC or the fiat currency.
+    pub fn convert(&self, money: &Money, currency: &str) -> Option<Money> {
+        let from = money.currency.as_deref()?;
+        let amount = if from.eq_ignore_ascii_case(currency) {
+            money.amount
+        } else if from == "BTC" && currency.eq_ignore_ascii_case(&self.currency) {
+            money.amount * self.per_btc
+        } else if from.eq_ignore_ascii_case(&self.currency) && currency == "BTC" {
+            money.amount / self.per_btc
+        } else {
+            return None;
+        };
+        Some(Money {
+            amount,
+            currency: Some(currency.to_ascii_uppercase()),
+        })
+    }
+
+    /// A BTC `money` in this rate's fiat currency.
+    pub fn to_fiat(&self, money: &Money) -> Option<Money> {
+        if money.currency.as_deref() != Some("BTC") {
+            return None;
+        }
+        self.convert(money, &self.currency)
+    }
+}
+
+/// Electricity an attack uses when mining on owned hardware.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyUse {
+    /// Hardware efficiency in joules per terahash.
+    pub joules_per_th: f64,
+    /// Energy over the whole attack, in kilowatt-hours.
+    pub kwh: f64,
+    /// Price of a kilowatt-hour, when one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_per_kwh: Option<Money>,
+}
+
+impl EnergyUse {
+    /// Cost of the energy at `price_per_kwh`.
+    pub fn cost(&self) -> Option<Money> {
+        self.price_per_kwh.as_ref().map(|price| price.with_amount(price.amount * self.kwh))
+    }
+}
+
+/// Energy of mining for as long as `calc` takes at `hashrate`, on
+/// hardware using `joules_per_th`; infinite when it never finishes.
+///
+/// Only the share of `hashrate` that is mining, per `calc.efficiency`,
+/// draws power.
+pub fn energy_use(
+    calc: &ReorgCalculation,
+    hashrate: f64,
+    joules_per_th: f64,
+    price_per_kwh: Option<Money>,
+) -> EnergyUse {
+    let hashes = hashrate * calc.efficiency.unwrap_or(1.0) * calc.time_required_hours * 3600.0;
+    EnergyUse {
+        joules_per_th,
+        kwh: hashes / 1e12 * joules_per_th / 3.6e6,
+        price_per_kwh,
+    }
+}
+
+/// Parse an amount with an optional currency code, e.g. `500USD`,
+/// `0.01 BTC` or `250`.
+pub fn parse_money(text: &str) -> Result<Money, String> {
+    let trimmed = text.trim();
+    let split = trimmed.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(trimmed.len());
+    let (number, currency) = trimmed.split_at(split);
+    let amount: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected an amount such as 500USD or 0.01BTC, got '{}'", trimmed))?;
+    if !(amount >= 0.0 && amount.is_finite()) {
+        return Err("amount must be a non-negative number".to_string());
+    }
+    if !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("invalid currency code '{}'", currency));
+    }
+    Ok(Money {
+        amount,
+        currency: (!currency.is_empty()).then(|| currency.to_ascii_uppercase()),
+    })
+}
+
+/// Cost of renting `hashrate` for as long as `calc` takes, at
+/// `price_per_th_day`; infinite when it never finishes.
+pub fn rental_cost(calc: &ReorgCalculation, hashrate: f64, price_per_th_day: f64) -> f64 {
+    price_per_th_day * hashrate / 1e12 * calc.time_required_days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::calculate_from_work;
+    use crate::network::NetworkParams;
+
+    /// Ten difficulty-1 blocks taking two days.
+    fn two_day_attack() -> ReorgCalculation {
+        let hashrate = 10.0 * crate::HASHES_PER_DIFFICULTY / (2.0 * crate::SECONDS_PER_DAY);
+        calculate_from_work(0, 9, 10.0, 1.0, hashrate, 1.0, &NetworkParams::default())
+    }
+
+    #[test]
+    fn amounts_parse_with_an_optional_currency() {
+        let money = parse_money(" 0.01 btc").unwrap();
+        assert_eq!(money, Money { amount: 0.01, currency: Some("BTC".to_string()) });
+        assert_eq!(parse_money("250").unwrap(), Money { amount: 250.0, currency: None });
+        assert_eq!(parse_money("500USD").unwrap().with_amount(2.5).to_string(), "2.50 USD");
+        assert!(parse_money("-5USD").is_err());
+        assert!(parse_money("5U$D").is_err());
+        assert!(parse_money("USD").is_err());
+    }
+
+    #[test]
+    fn rentals_cost_the_price_per_terahash_day() {
+        let calc = two_day_attack();
+        assert_eq!(rental_cost(&calc, 100e12, 0.05), 10.0);
+        let price = RentalPrice { source: "test".to_string(), price_per_th_day: 0.05, currency: "USD".to_string() };
+        assert_eq!(price.cost_per_day(100e12), 5.0);
+        assert_eq!(format_amount(1234.5), "1234.50");
+        assert_eq!(format_amount(0.000_123_45), "0.00012345");
+        assert_eq!(format_amount(0.0), "0.00");
+    }
+
+    #[test]
+    fn energy_counts_only_the_mining_share() {
+        let mut calc = two_day_attack();
+        let price = parse_money("0.10USD").ok();
+        // 100 TH/s for 48 hours at 20 J/TH is 96 kWh
+        let energy = energy_use(&calc, 100e12, 20.0, price);
+        assert!((energy.kwh - 96.0).abs() < 1e-9);
+        assert_eq!(energy.cost().map(|cost| cost.currency), Some(Some("USD".to_string())));
+        calc.efficiency = Some(0.5);
+        assert!((energy_use(&calc, 100e12, 20.0, None).kwh - 48.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exchange_rates_convert_between_btc_and_their_currency() {
+        let rate = ExchangeRate {
+            currency: "USD".to_string(),
+            per_btc: 50_000.0,
+            source: "test".to_string(),
+            timestamp: Utc::now(),
+        };
+        let btc = parse_money("0.5BTC").unwrap();
+        assert_eq!(rate.to_fiat(&btc), parse_money("25000USD").ok());
+        assert_eq!(rate.convert(&parse_money("25000usd").unwrap(), "BTC"), Some(btc.clone()));
+        assert_eq!(rate.convert(&btc, "btc"), Some(btc));
+        assert_eq!(rate.convert(&parse_money("10EUR").unwrap(), "BTC"), None);
+        assert_eq!(rate.to_fiat(&parse_money("10USD").unwrap()), None);
+    }
+}
@@ -0,0 +1,358 @@
+//! Persistent header cache.
+//!
+//! Headers are stored by block hash in an append-only file of raw 80-byte
+//! records. A cached run is only used after walking it back from the node's
+//! current hash at the top height through `prev_blockhash`, so entries left
+//! behind by a reorg are never read. Each fetched batch is appended as soon
+//! as it arrives, which lets an interrupted scan pick up where it stopped.
+//!
+//! Once the file holds the whole chain, a [`HeaderStore`] serves it without
+//! a node: heights, chain work and difficulty all follow from the headers.
+
+use bitcoin::{
+    block::Header, blockdata::constants::genesis_block, consensus, hashes::Hash, Block, BlockHash, Network, Transaction,
+    Txid, Work,
+};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{consecutive_runs, BlockSource, BranchTip, ChainTip};
+
+const HEADER_SIZE: usize = 80;
+
+/// A [`BlockSource`] that keeps every header it fetches in a local file.
+pub struct CachedSource<S> {
+    inner: S,
+    headers: Mutex<HashMap<BlockHash, Header>>,
+    file: Mutex<File>,
+}
+
+impl<S: BlockSource> CachedSource<S> {
+    /// Wrap `inner`, loading and extending the cache file at `path`.
+    pub fn open(inner: S, path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        let headers = read_headers(&mut file)?;
+        // Drop a torn trailing record so later appends stay aligned
+        let len = file.metadata()?.len();
+        let whole = len - len % HEADER_SIZE as u64;
+        if whole != len {
+            file.set_len(whole)?;
+        }
+        Ok(Self {
+            inner,
+            headers: Mutex::new(headers),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Number of headers currently cached.
+    pub fn cached_headers(&self) -> usize {
+        self.headers.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn insert(&self, headers: &[Header]) -> Result<()> {
+        let mut cache = self.headers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut bytes = Vec::with_capacity(headers.len() * HEADER_SIZE);
+        for header in headers {
+            if cache.insert(header.block_hash(), *header).is_none() {
+                bytes.extend(consensus::serialize(header));
+            }
+        }
+        drop(cache);
+
+        if !bytes.is_empty() {
+            let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+            file.write_all(&bytes)?;
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Headers of `count` consecutive blocks starting at `start`, reading
+    /// the cached top of the run and fetching only what lies below it.
+    fn run(&self, start: u64, count: u64) -> Result<Vec<Header>> {
+        let top = start + count - 1;
+        let mut hash = self.inner.get_block_hash(top)?;
+
+        // Walk down from the top while the chain is in the cache
+        let mut cached = Vec::new();
+        {
+            let cache = self.headers.lock().unwrap_or_else(|e| e.into_inner());
+            while (cached.len() as u64) < count {
+                match cache.get(&hash) {
+                    Some(header) => {
+                        hash = header.prev_blockhash;
+                        cached.push(*header);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let missing = count - cached.len() as u64;
+        let mut headers = Vec::with_capacity(count as usize);
+        if missing > 0 {
+            let heights: Vec<u64> = (start..start + missing).collect();
+            let fetched = self.inner.get_headers_batch(&heights)?;
+            // The fetched part has to end at the block the cached part builds on
+            if fetched.last().map(Header::block_hash) != Some(hash) {
+                return Err(ReorgCalcError::InvalidData(format!(
+                    "chain changed while reading blocks {} to {}",
+                    start, top
+                )));
+            }
+            self.insert(&fetched)?;
+            headers = fetched;
+        }
+
+        cached.reverse();
+        headers.extend(cached);
+        Ok(headers)
+    }
+}
+
+/// Every header in a cache file, by hash.
+fn read_headers(file: &mut File) -> Result<HashMap<BlockHash, Header>> {
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    // A partial trailing record is what an interrupted write leaves; skip it
+    // here and let `CachedSource::open` cut it off
+    bytes
+        .chunks_exact(HEADER_SIZE)
+        .map(|record| {
+            let header: Header = consensus::deserialize(record)
+                .map_err(|e| ReorgCalcError::InvalidData(format!("corrupt header cache: {}", e)))?;
+            Ok((header.block_hash(), header))
+        })
+        .collect()
+}
+
+impl<S: BlockSource> BlockSource for CachedSource<S> {
+    fn get_tip(&self) -> Result<ChainTip> {
+        self.inner.get_tip()
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.inner.get_block_hash(height)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        Ok(self.run(height, 1)?.remove(0))
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        let mut headers = Vec::with_capacity(heights.len());
+        for (start, count) in consecutive_runs(heights) {
+            headers.extend(self.run(start, count)?);
+        }
+        Ok(headers)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        let headers = self.get_headers_batch(heights)?;
+        Ok(headers.iter().map(|header| header.bits.to_consensus()).collect())
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.inner.get_chainwork(height)
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        self.inner.get_chainwork_batch(heights)
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        self.inner.get_difficulty()
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        self.inner.get_transaction_height(txid)
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        self.inner.get_block_height(hash)
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        self.inner.get_coinbases(heights)
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        self.inner.get_blocks(heights)
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        self.inner.get_chain_tips()
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        self.inner.get_network()
+    }
+}
+
+/// The chain held in a header cache file, read without a node.
+///
+/// The active chain is the one with the most work from the genesis block,
+/// as a node would pick it; blocks a reorg left in the file sit on lighter
+/// branches and are not part of it. The network is the one whose genesis
+/// block the chain starts from, or testnet4 if it is none of the known.
+pub struct HeaderStore {
+    chain: Vec<Header>,
+    chainwork: Vec<Work>,
+    heights: HashMap<BlockHash, u64>,
+    network: NetworkParams,
+}
+
+impl HeaderStore {
+    /// Load the chain cached at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let headers = read_headers(&mut File::open(path)?)?;
+        let mut children: HashMap<BlockHash, Vec<&Header>> = HashMap::new();
+        for header in headers.values() {
+            children.entry(header.prev_blockhash).or_default().push(header);
+        }
+        let genesis = match children.get(&BlockHash::all_zeros()).map(Vec::as_slice) {
+            Some([genesis]) => *genesis,
+            Some(_) => return Err(ReorgCalcError::InvalidData("header cache holds several genesis blocks".into())),
+            None => {
+                return Err(ReorgCalcError::InvalidData(
+                    "header cache does not reach back to the genesis block; fill it with sync-headers".into(),
+                ))
+            }
+        };
+
+        // Total work up to every block linked to genesis, keeping the heaviest tip
+        let mut work = HashMap::from([(genesis.block_hash(), genesis.work())]);
+        let mut best = (genesis.work(), genesis.block_hash());
+        let mut pending = vec![genesis.block_hash()];
+        while let Some(hash) = pending.pop() {
+            let total = work[&hash];
+            for child in children.get(&hash).into_iter().flatten() {
+                let child_total = total + child.work();
+                let child_hash = child.block_hash();
+                if child_total > best.0 {
+                    best = (child_total, child_hash);
+                }
+                work.insert(child_hash, child_total);
+                pending.push(child_hash);
+            }
+        }
+
+        let mut chain = Vec::new();
+        let mut hash = best.1;
+        while let Some(header) = headers.get(&hash) {
+            chain.push(*header);
+            hash = header.prev_blockhash;
+        }
+        chain.reverse();
+
+        let chainwork = chain.iter().map(|header| work[&header.block_hash()]).collect();
+        let heights = chain.iter().enumerate().map(|(height, header)| (header.block_hash(), height as u64)).collect();
+        let network = [Network::Bitcoin, Network::Testnet, Network::Testnet4, Network::Signet, Network::Regtest]
+            .into_iter()
+            .map(NetworkParams::from)
+            .find(|params| genesis_block(params.consensus()).block_hash() == genesis.block_hash())
+            .unwrap_or_default();
+        Ok(Self { chain, chainwork, heights, network })
+    }
+
+    fn check(&self, height: u64) -> Result<usize> {
+        let tip_height = self.chain.len() as u64 - 1;
+        if height > tip_height {
+            return Err(ReorgCalcError::InvalidData(format!(
+                "block {} is above the cached tip {}",
+                height, tip_height
+            )));
+        }
+        Ok(height as usize)
+    }
+}
+
+impl BlockSource for HeaderStore {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let height = self.chain.len() as u64 - 1;
+        Ok(ChainTip { height, hash: self.chain[height as usize].block_hash() })
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        Ok(self.chain[self.check(height)?])
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        Ok(self.chainwork[self.check(height)?])
+    }
+
+    /// The difficulty of the tip block, which the calculations mine at.
+    fn get_difficulty(&self) -> Result<f64> {
+        let tip = self.chain.last().expect("the chain holds at least the genesis block");
+        Ok(self.network.difficulty(tip.bits.to_consensus()))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        Ok(self.heights.get(hash).copied())
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        Ok(self.network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{temp_path, TestChain, DIFFICULTY_1, DIFFICULTY_8};
+
+    fn write_headers(path: &Path, headers: &[Header]) {
+        let bytes: Vec<u8> = headers.iter().flat_map(consensus::serialize).collect();
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn a_torn_record_is_cut_off_before_appending() {
+        let path = temp_path("torn.cache");
+        let chain = TestChain::uniform(20, DIFFICULTY_1);
+        write_headers(&path, &chain.headers()[..10]);
+        // Half of the next record, as an interrupted write leaves it
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&consensus::serialize(&chain.headers()[10])[..HEADER_SIZE / 2]).unwrap();
+        drop(file);
+
+        let cached = CachedSource::open(chain, &path).unwrap();
+        assert_eq!(cached.cached_headers(), 10);
+        let headers = cached.get_headers_batch(&(0..20).collect::<Vec<_>>()).unwrap();
+        assert_eq!(cached.cached_headers(), 20);
+        drop(cached);
+
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 20 * HEADER_SIZE as u64);
+        let reopened = CachedSource::open(TestChain::uniform(20, DIFFICULTY_1), &path).unwrap();
+        assert_eq!(reopened.cached_headers(), 20);
+        assert_eq!(reopened.get_headers_batch(&(0..20).collect::<Vec<_>>()).unwrap(), headers);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn the_store_follows_the_heaviest_branch() {
+        let path = temp_path("branches.cache");
+        let light = TestChain::uniform(12, DIFFICULTY_1);
+        // The same first five blocks, then fewer but heavier ones
+        let mut bits = vec![DIFFICULTY_1; 5];
+        bits.extend([DIFFICULTY_8; 3]);
+        let heavy = TestChain::new(&bits);
+        let mut headers = light.headers().to_vec();
+        headers.extend_from_slice(&heavy.headers()[5..]);
+        write_headers(&path, &headers);
+
+        let store = HeaderStore::open(&path).unwrap();
+        assert_eq!(store.get_tip().unwrap(), heavy.tip_at(7));
+        assert_eq!(store.get_chainwork(7).unwrap(), heavy.get_chainwork(7).unwrap());
+        assert_eq!(store.get_block_height(&light.tip_at(11).hash).unwrap(), None);
+        assert_eq!(store.get_difficulty().unwrap(), 8.0);
+        assert!(store.get_header(8).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1196 @@
+//! Reorg requirement calculations.
+
+use bitcoin::{BlockHash, Txid, Work};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::{EnergyUse, ExchangeRate, RentalPrice};
+use crate::devices::Device;
+use crate::error::{ReorgCalcError, Result};
+use crate::group::GroupShare;
+use crate::impact::TransactionImpact;
+use crate::source::{BlockSource, ChainTip};
+use crate::wallet::WalletImpact;
+use crate::warning;
+use crate::timeline::{check_timeline, parent_timestamps, AttackPlan, TimelineCheck};
+use crate::min_difficulty::{plan_min_difficulty_blocks, MinDifficultyStrategy};
+use crate::miners::MinerBlocks;
+use crate::network::NetworkParams;
+use crate::race::{race_odds, RaceOdds};
+use crate::schedule::ScheduleDay;
+use crate::retarget::{project_next_retarget, simulate_attack, AttackEpoch, EpochStart, NextRetarget};
+use crate::simulate::{simulate_mining, MiningSimulation};
+use crate::storm::BlockStorms;
+use crate::work::{
+    block_work, blocks_to_match, calculate_chain_work_with, span_work, BlockIntervals, MinDifficultyBlocks, NetworkHashrate,
+    ScanOptions, SpanEpoch,
+};
+use crate::SECONDS_PER_DAY;
+
+/// Version of the serialized [`ReorgCalculation`] layout.
+///
+/// Bumped whenever a field is removed or changes meaning, so stored results
+/// can be told apart across releases. Adding fields keeps the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How many times a calculation follows a tip that advanced during the scan
+/// before reporting the result as stale.
+const MAX_TIP_EXTENSIONS: usize = 3;
+
+/// Result of a reorg calculation for a single fork height.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorgCalculation {
+    /// Layout version, see [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+    pub fork_height: u64,
+    pub current_height: u64,
+    /// Hash of the block at `fork_height`, the first one replaced, when
+    /// read from a chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_hash: Option<BlockHash>,
+    /// Hash of the block at `current_height`, when read from a chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip_hash: Option<BlockHash>,
+    /// Number of existing blocks from the fork height to the tip (inclusive).
+    pub blocks_to_reorg: u64,
+    /// Sum of the difficulties of the blocks being replaced.
+    pub total_work: f64,
+    /// Exact expected number of hashes behind `total_work`, when it was
+    /// computed from chain work rather than given as a float.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_hashes: Option<Work>,
+    pub current_difficulty: f64,
+    /// Blocks at the current difficulty needed to match the existing work,
+    /// plus `margin`.
+    pub blocks_needed: f64,
+    /// Time to mine `blocks_needed` with the provided hashrate.
+    pub time_required_hours: f64,
+    pub time_required_days: f64,
+    /// Hashrate needed to mine `blocks_needed` within the target time.
+    pub hashrate_required: f64,
+    /// Target time in days behind `hashrate_required`; absent from results
+    /// stored before it was recorded.
+    #[serde(default)]
+    pub target_days: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+    /// Set when the chain moved on from `current_height` before the
+    /// calculation finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<TipChange>,
+    /// Buffer included in `blocks_needed`, time and hashrate.
+    #[serde(default, skip_serializing_if = "Margin::is_zero")]
+    pub margin: Margin,
+    /// Honest-chain growth included in `blocks_needed`, time and hashrate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub honest_growth: Option<HonestGrowth>,
+    /// Attacker difficulty per retarget epoch, when `blocks_needed` and the
+    /// time come from simulating retargets rather than the current
+    /// difficulty alone.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attacker_epochs: Vec<AttackEpoch>,
+    /// Minimum-difficulty blocks among those being replaced, when the span
+    /// was scanned for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_difficulty: Option<MinDifficultyBlocks>,
+    /// Transactions of a node wallet in the blocks being replaced, when it
+    /// was checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<WalletImpact>,
+    /// Transactions in the blocks being replaced, when the blocks were
+    /// fetched for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<TransactionImpact>,
+    /// Miners of the blocks being replaced, most blocks first, when their
+    /// coinbases were fetched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub miners: Vec<MinerBlocks>,
+    /// Runs of minimum-difficulty blocks among those being replaced, when
+    /// the span was scanned for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storms: Option<BlockStorms>,
+    /// Times between the span's blocks, when the span was scanned for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intervals: Option<BlockIntervals>,
+    /// The span's work per retarget epoch, when the span was scanned for it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub span_epochs: Vec<SpanEpoch>,
+    /// Minimum-difficulty blocks the attacker could add under the 20-minute
+    /// rule; not counted in `blocks_needed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_difficulty_strategy: Option<MinDifficultyStrategy>,
+    /// Timestamp rules checked on the planned attack chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<TimelineCheck>,
+    /// Monte Carlo spread of the time required with the provided hashrate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<MiningSimulation>,
+    /// Odds of ever overtaking the honest chain with the provided hashrate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub race: Option<RaceOdds>,
+    /// Honest network hashrate measured over the last blocks of the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_hashrate: Option<NetworkHashrate>,
+    /// The public chain's next retarget and the requirement at the
+    /// difficulty projected for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_retarget: Option<NextRetarget>,
+    /// Day-by-day plan of the attack with the provided hashrate.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleDay>,
+    /// Hashrate required for each of several target times, when more than
+    /// one was asked for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_hashrates: Vec<TargetHashrate>,
+    /// Share of the provided hashrate mining; the time is for the effective
+    /// hashrate and the hashrates required are nominal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub efficiency: Option<f64>,
+    /// Extra work the attacker mines for its own stale blocks and
+    /// propagation losses, as a percentage; included in the time and
+    /// hashrate but not in `blocks_needed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overhead_percent: Option<f64>,
+    /// Current marketplace price of rented hashrate, for the cost of each
+    /// hashrate required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rental_price: Option<RentalPrice>,
+    /// Hardware model each hashrate required is also counted in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_device: Option<Device>,
+    /// Electricity used mining the attack on owned hardware.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<EnergyUse>,
+    /// BTC exchange rate the BTC amounts are also shown in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<ExchangeRate>,
+    /// The attack's work measured in time at mainnet's hashrate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mainnet: Option<MainnetEquivalent>,
+    /// Each participant's part when a group mines the attack together.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group: Vec<GroupShare>,
+    /// Name of the scenario-file run this came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+}
+
+/// How long mainnet would take to mine the work of an attack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MainnetEquivalent {
+    /// Mainnet hashrate compared against, in H/s.
+    pub hashrate: f64,
+    /// Where the hashrate came from, e.g. `mempool.space`.
+    pub source: String,
+    /// Seconds for mainnet to mine `blocks_needed` at the current difficulty.
+    pub seconds: f64,
+}
+
+/// Hashrate needed to finish the attack within `days`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetHashrate {
+    pub days: f64,
+    pub hashrate: f64,
+}
+
+/// Work the honest network adds while the attack runs.
+///
+/// When it mines at least as fast as the attacker the attack never catches
+/// up; `added_blocks` and the time figures are then infinite (`null` in JSON).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HonestGrowth {
+    /// Hashrate assumed for the honest network, in hashes/second.
+    pub hashrate: f64,
+    /// Blocks at the current difficulty added to the requirement during
+    /// the attack with the provided hashrate.
+    pub added_blocks: f64,
+    /// Blocks that overtake the honest chain within the target time, the
+    /// basis of `hashrate_required`.
+    pub target_blocks: f64,
+}
+
+/// Extra blocks demanded on top of matching the existing chain work.
+///
+/// Equal work doesn't win a reorg, and the honest chain keeps growing while
+/// the attacker mines, so real attempts want some headroom.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Margin {
+    /// Fixed number of extra blocks.
+    pub blocks: u64,
+    /// Extra blocks as a percentage of the bare requirement, rounded up.
+    pub percent: f64,
+}
+
+impl Margin {
+    pub fn is_zero(&self) -> bool {
+        self.blocks == 0 && self.percent == 0.0
+    }
+
+    /// `blocks` plus the margin; the percentage is applied first.
+    pub fn apply(&self, blocks: f64) -> f64 {
+        (blocks * (1.0 + self.percent / 100.0)).ceil() + self.blocks as f64
+    }
+}
+
+/// Refinements applied on top of the bare requirement.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Adjustments {
+    pub margin: Margin,
+    /// Honest network hashrate in hashes/second; when set, its chain is
+    /// modeled as growing during the attack.
+    pub honest_hashrate: Option<f64>,
+    /// Simulate retargets on the attacker's chain.
+    pub retarget: bool,
+    /// Plan minimum-difficulty blocks for the attacker's chain.
+    pub min_difficulty_strategy: bool,
+    /// Project the public chain's next retarget.
+    pub next_retarget: bool,
+    /// Number of Monte Carlo runs of the attack, if any.
+    pub simulate: Option<u64>,
+    /// Honest network hashrate in hashes/second to race against for the
+    /// success probability.
+    pub race_hashrate: Option<f64>,
+    /// Network hashrate measured over recent blocks, to report with the
+    /// result.
+    pub network_hashrate: Option<NetworkHashrate>,
+    /// Share of the provided hashrate actually mining, for rigs that are not
+    /// always online; `None` means all of it.
+    pub efficiency: Option<f64>,
+    /// Percentage of extra work lost to the attacker's own stale blocks and
+    /// propagation.
+    pub overhead_percent: Option<f64>,
+}
+
+impl Adjustments {
+    /// Apply honest growth, then retargeting, then the margin on top, and
+    /// finally plan minimum-difficulty blocks around the result. Plans that
+    /// simulate the attacker's chain get their timestamps checked, and the
+    /// Monte Carlo simulation, race odds and next-retarget projection work
+    /// from the finished requirement. With an `efficiency` or `overhead_percent`, `calc` is
+    /// taken as worked out at the nominal `hashrate` and everything runs at
+    /// the share of it that extends the reorg chain.
+    pub fn apply<S: BlockSource + ?Sized>(
+        &self,
+        source: &S,
+        calc: ReorgCalculation,
+        hashrate: f64,
+        target_days: f64,
+        params: &NetworkParams,
+    ) -> Result<ReorgCalculation> {
+        let (calc, hashrate) = self.derate(calc, hashrate);
+        let mut calc = match self.honest_hashrate {
+            Some(honest_hashrate) => calc.with_honest_growth(honest_hashrate, hashrate, target_days, params),
+            None => calc,
+        };
+        if self.retarget {
+            let epoch = EpochStart::of(source, calc.fork_height, params)?;
+            calc = calc.with_retargeting(epoch, hashrate, params);
+        }
+        calc = calc.with_margin(self.margin);
+        if self.min_difficulty_strategy {
+            let parent_time = source.get_header(calc.fork_height.saturating_sub(1))?.time;
+            calc.min_difficulty_strategy = plan_min_difficulty_blocks(
+                calc.blocks_needed,
+                calc.current_difficulty,
+                calc.time_required_days * SECONDS_PER_DAY,
+                calc.timestamp.timestamp(),
+                parent_time,
+                params,
+            );
+        }
+        if (self.retarget || self.min_difficulty_strategy) && calc.blocks_needed.is_finite() {
+            let parent_times = parent_timestamps(source, calc.fork_height)?;
+            calc.timeline = Some(check_timeline(&calc.attack_plan(), &parent_times, params));
+        }
+        let mut calc = self.finish(calc, hashrate, target_days, params);
+        if self.next_retarget {
+            calc.next_retarget = Some(project_next_retarget(source, &calc, params)?);
+        }
+        Ok(calc)
+    }
+
+    /// [`Self::apply`] for a calculation with no chain behind it, such as
+    /// one from [`calculate_from_work`]. Retargeting, min-difficulty
+    /// planning and the next-retarget projection need block headers and are
+    /// unsupported.
+    pub fn apply_offline(
+        &self,
+        calc: ReorgCalculation,
+        hashrate: f64,
+        target_days: f64,
+        params: &NetworkParams,
+    ) -> Result<ReorgCalculation> {
+        if self.retarget || self.min_difficulty_strategy || self.next_retarget {
+            return Err(ReorgCalcError::Unsupported("retargeting and min-difficulty planning without block headers"));
+        }
+        let (calc, hashrate) = self.derate(calc, hashrate);
+        let calc = match self.honest_hashrate {
+            Some(honest_hashrate) => calc.with_honest_growth(honest_hashrate, hashrate, target_days, params),
+            None => calc,
+        };
+        let calc = calc.with_margin(self.margin);
+        Ok(self.finish(calc, hashrate, target_days, params))
+    }
+
+    /// Rescale the time of `calc` from `hashrate` to the effective hashrate,
+    /// and return that.
+    fn derate(&self, mut calc: ReorgCalculation, hashrate: f64) -> (ReorgCalculation, f64) {
+        calc.efficiency = self.efficiency;
+        calc.overhead_percent = self.overhead_percent;
+        let factor = calc.hashrate_overhead();
+        calc.time_required_hours *= factor;
+        calc.time_required_days *= factor;
+        (calc, hashrate / factor)
+    }
+
+    /// Add the simulation and race odds of the finished requirement, and
+    /// turn the hashrate required into the nominal one.
+    fn finish(&self, mut calc: ReorgCalculation, hashrate: f64, target_days: f64, params: &NetworkParams) -> ReorgCalculation {
+        if let Some(runs) = self.simulate {
+            calc.simulation = Some(simulate_mining(&calc, runs, hashrate, target_days, params));
+        }
+        if let Some(honest_hashrate) = self.race_hashrate {
+            calc.race = Some(race_odds(hashrate, honest_hashrate, calc.lead_blocks()));
+        }
+        calc.network_hashrate = self.network_hashrate;
+        calc.hashrate_required *= calc.hashrate_overhead();
+        calc
+    }
+}
+
+impl ReorgCalculation {
+    /// Blocks at the current difficulty that match the existing work, plus
+    /// the margin: the gap to close before any honest growth.
+    pub fn lead_blocks(&self) -> f64 {
+        self.margin.apply(self.total_work / self.current_difficulty)
+    }
+
+    /// Nominal hashrate per unit that ends up extending the reorg chain,
+    /// after `efficiency` and `overhead_percent`.
+    pub fn hashrate_overhead(&self) -> f64 {
+        (1.0 + self.overhead_percent.unwrap_or(0.0) / 100.0) / self.efficiency.unwrap_or(1.0)
+    }
+
+    /// Block schedule of the attack as calculated, for [`check_timeline`].
+    pub fn attack_plan(&self) -> AttackPlan {
+        let strategy = self.min_difficulty_strategy;
+        AttackPlan {
+            fork_height: self.fork_height,
+            real_blocks: self.blocks_needed as u64,
+            block_seconds: self.time_required_days * SECONDS_PER_DAY / self.blocks_needed,
+            upfront_min_difficulty: strategy.map_or(0, |strategy| strategy.upfront_blocks),
+            min_difficulty_per_block: strategy.map_or(0.0, |strategy| strategy.per_real_block),
+            start_time: self.timestamp.timestamp(),
+        }
+    }
+
+    /// Assume the honest network keeps mining at `honest_hashrate` for as
+    /// long as the attack takes, and raise `blocks_needed`, time and
+    /// hashrate so the attacker's chain still overtakes it.
+    ///
+    /// Starts from the matching requirement, so apply it before any margin.
+    pub fn with_honest_growth(
+        mut self,
+        honest_hashrate: f64,
+        hashrate: f64,
+        target_days: f64,
+        params: &NetworkParams,
+    ) -> Self {
+        let existing = self.total_work / self.current_difficulty;
+        let block_hashes = self.current_difficulty * params.hashes_per_difficulty();
+        
+        // Both chains mine at the same difficulty, so every attacker block
+        // gives the honest network `ratio` blocks' worth of time
+        let ratio = honest_hashrate / hashrate;
+        let blocks_needed = if ratio < 1.0 {
+            // The continuous solution is a lower bound; step up to the
+            // smallest whole block count that stays ahead
+            let mut blocks = (existing / (1.0 - ratio)).ceil();
+            loop {
+                let next = (existing + blocks * ratio).ceil();
+                if next <= blocks {
+                    break blocks;
+                }
+                blocks = next;
+            }
+        } else {
+            f64::INFINITY
+        };
+        
+        let time_seconds = blocks_needed * block_hashes / hashrate;
+        self.blocks_needed = blocks_needed;
+        self.time_required_hours = time_seconds / 3600.0;
+        self.time_required_days = time_seconds / SECONDS_PER_DAY;
+        
+        // Within the target time the honest chain grows by a known amount
+        let target_seconds = target_days * SECONDS_PER_DAY;
+        let honest_blocks = honest_hashrate * target_seconds / block_hashes;
+        let target_blocks = (existing + honest_blocks).ceil();
+        self.hashrate_required = target_blocks * block_hashes / target_seconds;
+        
+        self.honest_growth = Some(HonestGrowth {
+            hashrate: honest_hashrate,
+            added_blocks: blocks_needed - existing.ceil(),
+            target_blocks,
+        });
+        self
+    }
+    
+    /// Replace the constant-difficulty block count with a simulation of the
+    /// attacker's chain retargeting from `epoch`, the epoch of the fork.
+    ///
+    /// Only `blocks_needed` and the time change; the work to mine, and so
+    /// `hashrate_required`, stays the same.
+    pub fn with_retargeting(mut self, epoch: EpochStart, hashrate: f64, params: &NetworkParams) -> Self {
+        if !self.blocks_needed.is_finite() {
+            return self;
+        }
+        // With honest growth the count already covers the extra work
+        let work = match self.honest_growth {
+            Some(_) => self.blocks_needed * self.current_difficulty,
+            None => self.total_work,
+        };
+        let simulation = simulate_attack(self.fork_height, epoch, work, hashrate, self.timestamp.timestamp() as f64, params);
+        
+        self.blocks_needed = simulation.blocks as f64;
+        self.time_required_hours = simulation.seconds / 3600.0;
+        self.time_required_days = simulation.seconds / SECONDS_PER_DAY;
+        self.attacker_epochs = simulation.epochs;
+        self
+    }
+    
+    /// Hashrate needed to finish within `days`, given that
+    /// `hashrate_required` is the one for `target_days`.
+    pub fn hashrate_for_days(&self, days: f64, target_days: f64, params: &NetworkParams) -> f64 {
+        let seconds = days * SECONDS_PER_DAY;
+        match self.honest_growth {
+            // The honest chain grows for longer the longer the attack takes
+            Some(growth) => {
+                let block_hashes = self.current_difficulty * params.hashes_per_difficulty();
+                let honest_blocks = growth.hashrate * seconds / block_hashes;
+                let target_blocks = (self.total_work / self.current_difficulty + honest_blocks).ceil();
+                self.margin.apply(target_blocks) * block_hashes / seconds * self.hashrate_overhead()
+            }
+            None => self.hashrate_required * target_days / days,
+        }
+    }
+    
+    /// Measure the work of `blocks_needed` against mainnet's `hashrate`,
+    /// quoted by `source`.
+    pub fn with_mainnet(mut self, hashrate: f64, source: &str, params: &NetworkParams) -> Self {
+        self.mainnet = Some(MainnetEquivalent {
+            hashrate,
+            source: source.to_string(),
+            seconds: self.blocks_needed * self.current_difficulty * params.hashes_per_difficulty() / hashrate,
+        });
+        self
+    }
+    
+    /// Fill `target_hashrates` for each of `days`; see [`Self::hashrate_for_days`].
+    pub fn with_target_days(mut self, days: &[f64], target_days: f64, params: &NetworkParams) -> Self {
+        self.target_hashrates = days
+            .iter()
+            .map(|&days| TargetHashrate {
+                days,
+                hashrate: self.hashrate_for_days(days, target_days, params),
+            })
+            .collect();
+        self
+    }
+    
+    /// The time for mining at `hashrate` instead. Meant for a bare
+    /// calculation, whose time follows from `blocks_needed` alone.
+    pub fn at_hashrate(mut self, hashrate: f64, params: &NetworkParams) -> Self {
+        let seconds = self.blocks_needed * self.current_difficulty * params.hashes_per_difficulty() / hashrate;
+        self.time_required_hours = seconds / 3600.0;
+        self.time_required_days = seconds / SECONDS_PER_DAY;
+        self
+    }
+    
+    /// Add `margin` to `blocks_needed`, scaling time and hashrate with the
+    /// number of blocks. Meant for a bare calculation; margins applied
+    /// twice compound.
+    pub fn with_margin(mut self, margin: Margin) -> Self {
+        let bare_blocks = self.blocks_needed;
+        // With honest growth the required hashrate rests on its own count
+        let hashrate_blocks = self.honest_growth.map_or(bare_blocks, |growth| growth.target_blocks);
+        
+        // An attack that never finishes stays that way
+        if bare_blocks.is_finite() {
+            self.blocks_needed = margin.apply(bare_blocks);
+            let scale = self.blocks_needed / bare_blocks;
+            self.time_required_hours *= scale;
+            self.time_required_days *= scale;
+        }
+        
+        let target_blocks = margin.apply(hashrate_blocks);
+        self.hashrate_required *= target_blocks / hashrate_blocks;
+        if let Some(growth) = &mut self.honest_growth {
+            growth.target_blocks = target_blocks;
+        }
+        self.margin = margin;
+        self
+    }
+}
+
+/// Chain tip seen after a calculation that no longer matches its figures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TipChange {
+    pub tip_height: u64,
+    pub tip_hash: BlockHash,
+    /// Whether the calculated tip was reorged out rather than built upon.
+    pub reorged: bool,
+}
+
+impl TipChange {
+    /// Blocks the tip is ahead of (or, after a reorg, behind) `current_height`.
+    pub fn delta(&self, current_height: u64) -> i64 {
+        self.tip_height as i64 - current_height as i64
+    }
+}
+
+/// Fork height that removes a block with `confirmations` confirmations,
+/// counting the tip as the first.
+pub fn confirmation_fork_height(tip_height: u64, confirmations: u64) -> Result<u64> {
+    if confirmations == 0 || confirmations > tip_height + 1 {
+        return Err(ReorgCalcError::InvalidConfirmations { confirmations, tip_height });
+    }
+    Ok(tip_height + 1 - confirmations)
+}
+
+/// Fork height that removes the transaction `txid` from the chain: the
+/// height of the block confirming it.
+pub fn transaction_fork_height<S: BlockSource + ?Sized>(source: &S, txid: &Txid) -> Result<u64> {
+    source.get_transaction_height(txid)?.ok_or(ReorgCalcError::TransactionUnconfirmed(*txid))
+}
+
+/// Height of the block `hash`, to pin a fork point that stays put as the
+/// chain grows.
+pub fn block_fork_height<S: BlockSource + ?Sized>(source: &S, hash: &BlockHash) -> Result<u64> {
+    source.get_block_height(hash)?.ok_or(ReorgCalcError::BlockReorgedOut(*hash))
+}
+
+/// Highest block timestamped at or before `time`, found by binary search.
+///
+/// Block timestamps only roughly increase, so near `time` the result can be
+/// off by the few blocks whose timestamps are out of order.
+pub fn fork_height_at_time<S: BlockSource + ?Sized>(source: &S, time: DateTime<Utc>) -> Result<u64> {
+    let at_or_before = |height: u64| -> Result<bool> {
+        Ok(i64::from(source.get_header(height)?.time) <= time.timestamp())
+    };
+    if !at_or_before(0)? {
+        return Err(ReorgCalcError::BeforeGenesis(time));
+    }
+    
+    // Block `low` is always at or before `time`; those above `high` are after it
+    let (mut low, mut high) = (0, source.get_tip()?.height);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if at_or_before(mid)? {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Calculate the work, time and hashrate needed to reorg from `fork_height`
+/// to the current tip.
+///
+/// `hashrate` is in hashes/second and is used for the time estimate;
+/// `target_days` is used for the required hashrate estimate.
+pub fn calculate_reorg_requirements<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    hashrate: f64,
+    target_days: f64,
+) -> Result<ReorgCalculation> {
+    calculate_reorg_requirements_with(source, fork_height, hashrate, target_days, &ScanOptions::default())
+}
+
+/// [`calculate_reorg_requirements`] with checkpoint settings for sources
+/// whose chain work has to be scanned.
+pub fn calculate_reorg_requirements_with<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    hashrate: f64,
+    target_days: f64,
+    options: &ScanOptions,
+) -> Result<ReorgCalculation> {
+    let mut tip = source.get_tip()?;
+    
+    if fork_height > tip.height {
+        return Err(ReorgCalcError::ForkAboveTip {
+            fork_height,
+            tip_height: tip.height,
+        });
+    }
+    
+    // The attacker mines at the tip's target, the same one getdifficulty reports
+    let mut tip_work = block_work(source.get_bits(tip.height)?);
+    let params = source.get_network()?;
+    
+    let mut total_work = calculate_chain_work_with(source, fork_height, tip.height, options)?;
+    
+    // Blocks keep arriving during long scans; add their work while the
+    // scanned tip is still on the active chain
+    let mut stale = None;
+    for extensions in 0..=MAX_TIP_EXTENSIONS {
+        let latest = source.get_tip()?;
+        if latest == tip {
+            break;
+        }
+        let extends = latest.height > tip.height && source.get_block_hash(tip.height)? == tip.hash;
+        if !extends || extensions == MAX_TIP_EXTENSIONS {
+            stale = Some(TipChange {
+                tip_height: latest.height,
+                tip_hash: latest.hash,
+                reorged: !extends,
+            });
+            break;
+        }
+        total_work = total_work + extension_work(source, tip, latest, options)?;
+        tip_work = block_work(source.get_bits(latest.height)?);
+        tip = latest;
+    }
+    
+    let mut calc = calculate_from_chain_work(fork_height, tip.height, total_work, tip_work, hashrate, target_days, &params);
+    calc.fork_hash = Some(source.get_block_hash(fork_height)?);
+    calc.tip_hash = Some(tip.hash);
+    calc.stale = stale;
+    Ok(calc)
+}
+
+/// Work of the blocks added on top of `tip` up to `latest`.
+fn extension_work<S: BlockSource + ?Sized>(source: &S, tip: ChainTip, latest: ChainTip, options: &ScanOptions) -> Result<Work> {
+    // The checkpoint file belongs to the main scan, so extensions don't save progress
+    let options = ScanOptions {
+        checkpoint: None,
+        resume: false,
+        ..*options
+    };
+    calculate_chain_work_with(source, tip.height + 1, latest.height, &options)
+}
+
+/// Derive the reorg requirements from exact chain work figures.
+///
+/// `total_work` is the work being replaced and `block_work` the work of one
+/// block at the current target; the number of blocks needed to match them is
+/// computed without rounding. Difficulty figures are relative to the limit
+/// in `params`.
+pub fn calculate_from_chain_work(
+    fork_height: u64,
+    current_height: u64,
+    total_work: Work,
+    block_work: Work,
+    hashrate: f64,
+    target_days: f64,
+    params: &NetworkParams,
+) -> ReorgCalculation {
+    let blocks_needed = blocks_to_match(total_work, block_work) as f64;
+    let mut calc = build_calculation(
+        fork_height,
+        current_height,
+        params.work_to_difficulty(total_work),
+        params.work_to_difficulty(block_work),
+        blocks_needed,
+        hashrate,
+        target_days,
+        params,
+    );
+    calc.total_hashes = Some(total_work);
+    calc
+}
+
+/// Derive the reorg requirements from already-known chain figures in
+/// difficulty units.
+///
+/// Float counterpart of [`calculate_from_chain_work`], for callers that
+/// obtained `total_work` and `current_difficulty` some other way.
+pub fn calculate_from_work(
+    fork_height: u64,
+    current_height: u64,
+    total_work: f64,
+    current_difficulty: f64,
+    hashrate: f64,
+    target_days: f64,
+    params: &NetworkParams,
+) -> ReorgCalculation {
+    // Calculate blocks needed to exceed existing chain work
+    let blocks_needed = (total_work / current_difficulty).ceil();
+    build_calculation(fork_height, current_height, total_work, current_difficulty, blocks_needed, hashrate, target_days, params)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_calculation(
+    fork_height: u64,
+    current_height: u64,
+    total_work: f64,
+    current_difficulty: f64,
+    blocks_needed: f64,
+    hashrate: f64,
+    target_days: f64,
+    params: &NetworkParams,
+) -> ReorgCalculation {
+    let hashes_per_difficulty = params.hashes_per_difficulty();
+    let blocks_to_reorg = current_height - fork_height + 1;
+    
+    // Calculate time required with given hashrate
+    let time_per_block_seconds = (current_difficulty * hashes_per_difficulty) / hashrate;
+    let total_time_seconds = blocks_needed * time_per_block_seconds;
+    let time_required_hours = total_time_seconds / 3600.0;
+    let time_required_days = total_time_seconds / SECONDS_PER_DAY;
+    
+    // Calculate hashrate required for target time
+    let target_seconds = target_days * SECONDS_PER_DAY;
+    let hashrate_required = (blocks_needed * current_difficulty * hashes_per_difficulty) / target_seconds;
+    
+    ReorgCalculation {
+        schema_version: SCHEMA_VERSION,
+        fork_height,
+        current_height,
+        fork_hash: None,
+        tip_hash: None,
+        blocks_to_reorg,
+        total_work,
+        total_hashes: None,
+        current_difficulty,
+        blocks_needed,
+        time_required_hours,
+        time_required_days,
+        hashrate_required,
+        target_days: Some(target_days),
+        timestamp: Utc::now(),
+        stale: None,
+        margin: Margin::default(),
+        honest_growth: None,
+        attacker_epochs: Vec::new(),
+        min_difficulty: None,
+        wallet: None,
+        transactions: None,
+        miners: Vec::new(),
+        storms: None,
+        intervals: None,
+        span_epochs: Vec::new(),
+        min_difficulty_strategy: None,
+        timeline: None,
+        simulation: None,
+        race: None,
+        network_hashrate: None,
+        next_retarget: None,
+        schedule: Vec::new(),
+        target_hashrates: Vec::new(),
+        efficiency: None,
+        overhead_percent: None,
+        rental_price: None,
+        reference_device: None,
+        energy: None,
+        exchange_rate: None,
+        mainnet: None,
+        group: Vec::new(),
+        scenario: None,
+    }
+}
+
+/// Test a fixed set of depths below the tip and return the fork heights that
+/// can be reorged within `max_days` at `hashrate`.
+///
+/// Chainwork for every candidate is fetched in one batch when the source
+/// supports it; otherwise each height is calculated separately.
+pub fn find_viable_target_heights<S: BlockSource + ?Sized>(source: &S, hashrate: f64, max_days: f64) -> Result<Vec<u64>> {
+    find_viable_target_heights_with(source, hashrate, max_days, &Adjustments::default())
+}
+
+/// [`find_viable_target_heights`] judging each height with `adjustments`
+/// applied.
+pub fn find_viable_target_heights_with<S: BlockSource + ?Sized>(
+    source: &S,
+    hashrate: f64,
+    max_days: f64,
+    adjustments: &Adjustments,
+) -> Result<Vec<u64>> {
+    let current_height = source.get_tip()?.height;
+    let candidates = candidate_fork_heights(current_height);
+    let params = source.get_network()?;
+    match viable_from_chainwork(source, current_height, &candidates, hashrate, max_days, adjustments) {
+        Err(ReorgCalcError::Unsupported(_)) => {}
+        result => return result,
+    }
+    
+    let mut viable_heights = Vec::new();
+    
+    for height in candidates {
+        match calculate_reorg_requirements(source, height, hashrate, max_days) {
+            Ok(calc) => {
+                if adjustments.apply(source, calc, hashrate, max_days, &params)?.time_required_days <= max_days {
+                    viable_heights.push(height);
+                }
+            }
+            Err(e) => {
+                warning!("Warning: Failed to calculate for height {}: {}", height, e);
+            }
+        }
+    }
+    
+    Ok(viable_heights)
+}
+
+/// Deepest fork height whose calculation, with `adjustments` applied,
+/// passes `accept`, or `None` if not even the tip does.
+///
+/// Binary search, so `accept` has to fail for every height below the first
+/// one it rejects, as it does for limits on time or cost. The Monte Carlo
+/// simulation only runs for the result.
+pub fn find_deepest_fork_height<S: BlockSource + ?Sized>(
+    source: &S,
+    hashrate: f64,
+    target_days: f64,
+    adjustments: &Adjustments,
+    accept: impl Fn(&ReorgCalculation) -> bool,
+) -> Result<Option<ReorgCalculation>> {
+    let params = source.get_network()?;
+    let probe = Adjustments { simulate: None, ..*adjustments };
+    let calculate = |height: u64, adjustments: &Adjustments| {
+        let calc = calculate_reorg_requirements(source, height, hashrate, target_days)?;
+        adjustments.apply(source, calc, hashrate, target_days, &params)
+    };
+    
+    // Invariant: `high` is accepted and everything below `low` is not
+    let mut high = source.get_tip()?.height;
+    if !accept(&calculate(high, &probe)?) {
+        return Ok(None);
+    }
+    let mut low = 0;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if accept(&calculate(middle, &probe)?) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    calculate(high, adjustments).map(Some)
+}
+
+/// One calculation of `fork_height` per hashrate in `hashrates`, with
+/// `adjustments` applied, to compare against a range of target times.
+///
+/// The span's work is fetched once. The Monte Carlo simulation is skipped.
+pub fn calculate_matrix<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    hashrates: &[f64],
+    target_days: f64,
+    adjustments: &Adjustments,
+) -> Result<Vec<ReorgCalculation>> {
+    let Some(&first) = hashrates.first() else {
+        return Ok(Vec::new());
+    };
+    let params = source.get_network()?;
+    let adjustments = Adjustments { simulate: None, ..*adjustments };
+    let calc = calculate_reorg_requirements(source, fork_height, first, target_days)?;
+    hashrates
+        .iter()
+        .map(|&hashrate| {
+            let calc = calc.clone().at_hashrate(hashrate, &params);
+            adjustments.apply(source, calc, hashrate, target_days, &params)
+        })
+        .collect()
+}
+
+/// [`find_viable_target_heights_with`] over `candidates` instead of
+/// [`candidate_fork_heights`].
+pub fn find_viable_target_heights_among<S: BlockSource + ?Sized>(
+    source: &S,
+    candidates: &[u64],
+    hashrate: f64,
+    max_days: f64,
+    adjustments: &Adjustments,
+) -> Result<Vec<u64>> {
+    let calculations = calculate_sweep(source, candidates, hashrate, max_days, adjustments)?;
+    Ok(calculations
+        .iter()
+        .filter(|calc| calc.time_required_days <= max_days)
+        .map(|calc| calc.fork_height)
+        .collect())
+}
+
+fn viable_from_chainwork<S: BlockSource + ?Sized>(
+    source: &S,
+    current_height: u64,
+    candidates: &[u64],
+    hashrate: f64,
+    max_days: f64,
+    adjustments: &Adjustments,
+) -> Result<Vec<u64>> {
+    let tip_block_work = block_work(source.get_bits(current_height)?);
+    let params = source.get_network()?;
+    
+    // Parents of every candidate (all above zero), then the tip
+    let mut heights: Vec<u64> = candidates.iter().map(|&height| height - 1).collect();
+    heights.push(current_height);
+    let works = source.get_chainwork_batch(&heights)?;
+    let tip_work = works[works.len() - 1];
+    
+    let mut viable_heights = Vec::new();
+    for (&fork_height, &parent_work) in candidates.iter().zip(&works) {
+        let total_work = span_work(parent_work, tip_work).ok_or_else(|| {
+            ReorgCalcError::InvalidData(format!("chainwork below height {} exceeds chainwork at the tip", fork_height))
+        })?;
+        let calc = calculate_from_chain_work(fork_height, current_height, total_work, tip_block_work, hashrate, max_days, &params);
+        let calc = adjustments.apply(source, calc, hashrate, max_days, &params)?;
+        if calc.time_required_days <= max_days {
+            viable_heights.push(fork_height);
+        }
+    }
+    Ok(viable_heights)
+}
+
+/// Fork heights `start`, `start + step`, ... up to and including `end`.
+pub fn sweep_fork_heights(start: u64, end: u64, step: u64) -> Vec<u64> {
+    (start..=end).step_by(step.max(1) as usize).collect()
+}
+
+/// Requirements for every height in `fork_heights` against the same tip,
+/// with `adjustments` applied.
+///
+/// The chain is read once for the whole set rather than once per height.
+pub fn calculate_sweep<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_heights: &[u64],
+    hashrate: f64,
+    target_days: f64,
+    adjustments: &Adjustments,
+) -> Result<Vec<ReorgCalculation>> {
+    let tip = source.get_tip()?;
+    let current_height = tip.height;
+    if let Some(&fork_height) = fork_heights.iter().find(|&&height| height > current_height) {
+        return Err(ReorgCalcError::ForkAboveTip {
+            fork_height,
+            tip_height: current_height,
+        });
+    }
+    let tip_block_work = block_work(source.get_bits(current_height)?);
+    let params = source.get_network()?;
+    
+    let span_works = match sweep_from_chainwork(source, current_height, fork_heights) {
+        Err(ReorgCalcError::Unsupported(_)) => sweep_from_headers(source, current_height, fork_heights)?,
+        result => result?,
+    };
+    fork_heights
+        .iter()
+        .zip(span_works)
+        .map(|(&fork_height, total_work)| {
+            let mut calc = calculate_from_chain_work(
+                fork_height,
+                current_height,
+                total_work,
+                tip_block_work,
+                hashrate,
+                target_days,
+                &params,
+            );
+            calc.tip_hash = Some(tip.hash);
+            adjustments.apply(source, calc, hashrate, target_days, &params)
+        })
+        .collect()
+}
+
+/// Work from each fork height to the tip, from the parents' chainwork.
+fn sweep_from_chainwork<S: BlockSource + ?Sized>(source: &S, current_height: u64, fork_heights: &[u64]) -> Result<Vec<Work>> {
+    let zero = Work::from_be_bytes([0; 32]);
+    let mut heights: Vec<u64> = fork_heights.iter().filter(|&&height| height > 0).map(|&height| height - 1).collect();
+    heights.push(current_height);
+    let works = source.get_chainwork_batch(&heights)?;
+    let tip_work = works[works.len() - 1];
+    
+    let mut parent_works = works.into_iter();
+    fork_heights
+        .iter()
+        .map(|&fork_height| {
+            // Everything from genesis is the tip's own chainwork
+            let parent_work = if fork_height == 0 { zero } else { parent_works.next().unwrap_or(zero) };
+            span_work(parent_work, tip_work).ok_or_else(|| {
+                ReorgCalcError::InvalidData(format!("chainwork below height {} exceeds chainwork at the tip", fork_height))
+            })
+        })
+        .collect()
+}
+
+/// Work from each fork height to the tip, summed from one pass over the
+/// headers above the lowest of them.
+fn sweep_from_headers<S: BlockSource + ?Sized>(source: &S, current_height: u64, fork_heights: &[u64]) -> Result<Vec<Work>> {
+    let zero = Work::from_be_bytes([0; 32]);
+    let Some(&lowest) = fork_heights.iter().min() else {
+        return Ok(Vec::new());
+    };
+    let heights: Vec<u64> = (lowest..=current_height).collect();
+    let bits = source.get_bits_batch(&heights)?;
+    
+    // suffix[i] is the work from `lowest + i` to the tip
+    let mut suffix = vec![zero; bits.len() + 1];
+    for (i, &bits) in bits.iter().enumerate().rev() {
+        suffix[i] = suffix[i + 1] + block_work(bits);
+    }
+    Ok(fork_heights.iter().map(|&fork_height| suffix[(fork_height - lowest) as usize]).collect())
+}
+
+/// Fork heights tested by [`find_viable_target_heights`], going back in time
+/// from `current_height`.
+pub fn candidate_fork_heights(current_height: u64) -> Vec<u64> {
+    [1, 10, 50, 100, 500, 1000, 5000]
+        .iter()
+        .map(|&depth| current_height.saturating_sub(depth))
+        .filter(|&height| height > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{assert_close, TestChain, DIFFICULTY_1, DIFFICULTY_8};
+    use crate::HASHES_PER_DIFFICULTY;
+    use bitcoin::hashes::Hash;
+
+    /// Mines one difficulty-1 block a second.
+    const ONE_BLOCK_PER_SECOND: f64 = HASHES_PER_DIFFICULTY;
+
+    fn days(seconds: f64) -> f64 {
+        seconds / SECONDS_PER_DAY
+    }
+
+    /// 60 difficulty-1 blocks, then 41 at difficulty 8 up to the tip at 100.
+    fn mixed_bits() -> Vec<u32> {
+        let mut bits = vec![DIFFICULTY_1; 60];
+        bits.extend([DIFFICULTY_8; 41]);
+        bits
+    }
+
+    #[test]
+    fn requirements_follow_the_span_work() {
+        let chain = TestChain::uniform(101, DIFFICULTY_1);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        assert_eq!((calc.current_height, calc.blocks_to_reorg), (100, 10));
+        assert_eq!((calc.total_work, calc.current_difficulty, calc.blocks_needed), (10.0, 1.0, 10.0));
+        assert_eq!(calc.time_required_days, days(10.0));
+        assert_eq!(calc.hashrate_required, 10.0 * ONE_BLOCK_PER_SECOND / SECONDS_PER_DAY);
+        assert_eq!(calc.fork_hash, Some(chain.tip_at(91).hash));
+
+        assert!(matches!(
+            calculate_reorg_requirements(&chain, 101, ONE_BLOCK_PER_SECOND, 1.0),
+            Err(ReorgCalcError::ForkAboveTip { fork_height: 101, tip_height: 100 })
+        ));
+    }
+
+    #[test]
+    fn scanned_and_chainwork_spans_agree() {
+        let bits = mixed_bits();
+        let calc = calculate_reorg_requirements(&TestChain::new(&bits), 50, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        let scanned =
+            calculate_reorg_requirements(&TestChain::new(&bits).without_chainwork(), 50, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        // 10 blocks of difficulty 1 and 41 of difficulty 8, matched at difficulty 8
+        assert_close(calc.total_work, 338.0);
+        assert_eq!((calc.current_difficulty, calc.blocks_needed), (8.0, 43.0));
+        assert_eq!(scanned.total_hashes, calc.total_hashes);
+        assert_eq!(scanned.blocks_needed, calc.blocks_needed);
+    }
+
+    #[test]
+    fn calculations_follow_a_tip_that_advanced() {
+        let chain = TestChain::uniform(104, DIFFICULTY_1).with_tips(&[100, 103]);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        assert_eq!((calc.current_height, calc.blocks_needed, calc.stale), (103, 13.0, None));
+        assert_eq!(calc.tip_hash, Some(chain.tip_at(103).hash));
+
+        // Tips that keep moving are followed only so far
+        let chain = TestChain::uniform(106, DIFFICULTY_1).with_tips(&[100, 101, 102, 103, 104]);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        assert_eq!(calc.current_height, 103);
+        assert_eq!(calc.stale, Some(TipChange { tip_height: 104, tip_hash: chain.tip_at(104).hash, reorged: false }));
+    }
+
+    #[test]
+    fn a_reorged_tip_leaves_the_calculation_stale() {
+        let chain = TestChain::uniform(104, DIFFICULTY_1);
+        let latest = chain.tip_at(103);
+        let orphan = ChainTip { height: 100, hash: BlockHash::all_zeros() };
+        let chain = chain.with_tip_sequence(vec![orphan, latest]);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        assert_eq!((calc.current_height, calc.blocks_needed), (100, 10.0));
+        assert_eq!(calc.stale, Some(TipChange { tip_height: 103, tip_hash: latest.hash, reorged: true }));
+    }
+
+    #[test]
+    fn margins_scale_blocks_time_and_hashrate() {
+        let chain = TestChain::uniform(101, DIFFICULTY_1);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+        let margin = Margin { blocks: 2, percent: 25.0 };
+        assert!(Margin::default().is_zero() && !margin.is_zero());
+
+        // 10 blocks and 25% rounds up to 13, plus 2
+        let calc = calc.with_margin(margin);
+        assert_eq!((calc.blocks_needed, calc.lead_blocks()), (15.0, 15.0));
+        assert_close(calc.time_required_days, days(15.0));
+        assert_close(calc.hashrate_required, 15.0 * ONE_BLOCK_PER_SECOND / SECONDS_PER_DAY);
+    }
+
+    #[test]
+    fn honest_growth_is_outpaced_in_whole_blocks() {
+        let params = NetworkParams::default();
+        let chain = TestChain::uniform(101, DIFFICULTY_1);
+        let calc = calculate_reorg_requirements(&chain, 91, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+
+        // 13 blocks leave the honest chain at 10 + 3.25 blocks, still ahead
+        let grown = calc.clone().with_honest_growth(ONE_BLOCK_PER_SECOND / 4.0, ONE_BLOCK_PER_SECOND, 1.0, &params);
+        assert_eq!(grown.blocks_needed, 14.0);
+        assert_close(grown.time_required_days, days(14.0));
+        let growth = grown.honest_growth.unwrap();
+        assert_eq!(growth.added_blocks, 4.0);
+        // A day at a quarter of a block a second adds 21600 blocks
+        assert_eq!(growth.target_blocks, 21610.0);
+        assert_close(grown.hashrate_required, 21610.0 * ONE_BLOCK_PER_SECOND / SECONDS_PER_DAY);
+
+        let outpaced = calc.with_honest_growth(ONE_BLOCK_PER_SECOND, ONE_BLOCK_PER_SECOND, 1.0, &params);
+        assert_eq!((outpaced.blocks_needed, outpaced.time_required_days), (f64::INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn viable_heights_are_found_with_and_without_chainwork() {
+        // Candidates 99, 90 and 50 need 2, 11 and 51 seconds
+        for chain in [TestChain::uniform(101, DIFFICULTY_1), TestChain::uniform(101, DIFFICULTY_1).without_chainwork()] {
+            let viable = find_viable_target_heights(&chain, ONE_BLOCK_PER_SECOND, days(20.0)).unwrap();
+            assert_eq!(viable, [99, 90]);
+        }
+    }
+
+    #[test]
+    fn deepest_fork_height_is_the_last_accepted() {
+        let chain = TestChain::uniform(101, DIFFICULTY_1);
+        let adjustments = Adjustments::default();
+        let deepest = find_deepest_fork_height(&chain, ONE_BLOCK_PER_SECOND, 1.0, &adjustments, |calc| {
+            calc.blocks_needed <= 30.0
+        });
+        assert_eq!(deepest.unwrap().map(|calc| calc.fork_height), Some(71));
+        let none = find_deepest_fork_height(&chain, ONE_BLOCK_PER_SECOND, 1.0, &adjustments, |_| false);
+        assert!(none.unwrap().is_none());
+    }
+
+    #[test]
+    fn sweeps_agree_with_single_calculations() {
+        let heights = sweep_fork_heights(0, 100, 25);
+        assert_eq!(heights, [0, 25, 50, 75, 100]);
+        let adjustments = Adjustments::default();
+        for chain in [TestChain::new(&mixed_bits()), TestChain::new(&mixed_bits()).without_chainwork()] {
+            let sweep = calculate_sweep(&chain, &heights, ONE_BLOCK_PER_SECOND, 1.0, &adjustments).unwrap();
+            for (calc, &height) in sweep.iter().zip(&heights) {
+                let single = calculate_reorg_requirements(&chain, height, ONE_BLOCK_PER_SECOND, 1.0).unwrap();
+                assert_eq!(calc.fork_height, height);
+                assert_eq!((calc.total_hashes, calc.blocks_needed), (single.total_hashes, single.blocks_needed));
+            }
+            assert!(matches!(
+                calculate_sweep(&chain, &[50, 101], ONE_BLOCK_PER_SECOND, 1.0, &adjustments),
+                Err(ReorgCalcError::ForkAboveTip { fork_height: 101, .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Messages for Discord and Slack incoming webhooks and Telegram bots.
+//!
+//! Alerts and saved calculations become short chat messages: Discord takes
+//! `{"username", "content"}` with its Markdown, Slack `{"text"}` with its
+//! own mrkdwn, and a Telegram bot's `sendMessage` `{"chat_id", "text"}` in
+//! its HTML mode. All three only accept HTTPS, which the built-in HTTP
+//! client lacks, so posting to them needs a build of minreq with TLS or an
+//! HTTP relay in front of them.
+
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::alert::Alert;
+use crate::calc::ReorgCalculation;
+use crate::format::{format_days, format_hashrate};
+
+/// Name messages are posted under where the service allows one.
+pub const CHAT_USERNAME: &str = "testnet4-reorg";
+/// Default Telegram Bot API endpoint.
+pub const TELEGRAM_API: &str = "https://api.telegram.org";
+/// Calculations listed in one message before the rest are only counted.
+const MAX_MESSAGE_LINES: usize = 10;
+
+/// Team chat service messages are formatted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatService {
+    Discord,
+    Slack,
+    Telegram,
+}
+
+impl fmt::Display for ChatService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChatService::Discord => "Discord",
+            ChatService::Slack => "Slack",
+            ChatService::Telegram => "Telegram",
+        })
+    }
+}
+
+/// An incoming webhook and the service it posts to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatWebhook {
+    pub service: ChatService,
+    pub url: String,
+}
+
+/// Parse a chat webhook: a Discord or Slack webhook URL, told apart by its
+/// host, or `discord=URL` or `slack=URL` for one behind a relay.
+pub fn parse_chat_webhook(text: &str) -> Result<ChatWebhook, String> {
+    let text = text.trim();
+    if let Some((service, url)) = text.split_once('=').filter(|(service, _)| !service.contains('/')) {
+        let service = match service.trim().to_ascii_lowercase().as_str() {
+            "discord" => ChatService::Discord,
+            "slack" => ChatService::Slack,
+            other => return Err(format!("unknown chat service '{}' (expected discord or slack)", other)),
+        };
+        return Ok(ChatWebhook { service, url: url.trim().to_string() });
+    }
+    let host = text.split("://").nth(1).unwrap_or(text).split(['/', ':']).next().unwrap_or("");
+    let service = if host.ends_with("discord.com") || host.ends_with("discordapp.com") {
+        ChatService::Discord
+    } else if host == "hooks.slack.com" {
+        ChatService::Slack
+    } else {
+        return Err(format!(
+            "cannot tell whether {} is a Discord or Slack webhook; write discord=URL or slack=URL",
+            text
+        ));
+    };
+    Ok(ChatWebhook { service, url: text.to_string() })
+}
+
+/// A Telegram bot posting to one chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelegramBot {
+    /// Bot token from @BotFather, e.g. `123456:ABC-DEF...`.
+    pub token: String,
+    /// Chat to post to: a numeric id or `@channelname`.
+    pub chat_id: String,
+    /// Bot API endpoint, [`TELEGRAM_API`] unless relayed.
+    pub api_url: String,
+}
+
+impl TelegramBot {
+    /// `message`, made for [`ChatService::Telegram`], addressed to the chat.
+    pub fn payload(&self, mut message: Value) -> Value {
+        message["chat_id"] = json!(self.chat_id);
+        message
+    }
+
+    /// Send `message`, made for [`ChatService::Telegram`]; the token is
+    /// kept out of errors, since it is part of the URL.
+    #[cfg(feature = "webhook")]
+    pub fn send(&self, message: Value) -> crate::error::Result<()> {
+        use crate::error::ReorgCalcError;
+
+        let url = format!("{}/bot{}/sendMessage", self.api_url.trim_end_matches('/'), self.token);
+        crate::alert::post_webhook(&url, &self.payload(message)).map_err(|e| match e {
+            ReorgCalcError::Webhook(reason) => ReorgCalcError::Webhook(reason.replace(&self.token, "<token>")),
+            e => e,
+        })
+    }
+}
+
+impl ChatService {
+    /// `alert` as a message.
+    pub fn alert_message(self, alert: &Alert) -> Value {
+        self.message(format!("{} {}", self.bold("Reorg alert:"), self.escape(&alert.describe())))
+    }
+
+    /// `runs`, each a calculation and the hashrate it was made for, as a
+    /// message with a line per calculation.
+    pub fn calculations_message(self, runs: &[(ReorgCalculation, f64)]) -> Value {
+        let mut lines: Vec<String> = runs
+            .iter()
+            .take(MAX_MESSAGE_LINES)
+            .map(|(calc, hashrate)| self.calculation_line(calc, *hashrate))
+            .collect();
+        if runs.len() > MAX_MESSAGE_LINES {
+            lines.push(format!("... and {} more", runs.len() - MAX_MESSAGE_LINES));
+        }
+        self.message(lines.join("\n"))
+    }
+
+    /// `Reorg from block 2901 (100 below tip 3001): 68 blocks, 0.18 days at
+    /// 300.00 GH/s; 18.37 GH/s for 3 days (viable)`, the fork in bold.
+    fn calculation_line(self, calc: &ReorgCalculation, hashrate: f64) -> String {
+        let depth = calc.current_height.saturating_sub(calc.fork_height);
+        let time = if calc.time_required_days.is_finite() {
+            format!("{} days", format_days(calc.time_required_days))
+        } else {
+            "never".to_string()
+        };
+        let mut line = format!(
+            "{} ({} below tip {}): {:.0} blocks, {} at {}",
+            self.bold(&format!("Reorg from block {}", calc.fork_height)),
+            depth,
+            calc.current_height,
+            calc.blocks_needed,
+            time,
+            format_hashrate(hashrate)
+        );
+        if let Some(days) = calc.target_days {
+            let viable = if calc.time_required_days <= days { "viable" } else { "not viable" };
+            line += &format!(
+                "; {} for {} days ({})",
+                format_hashrate(calc.hashrate_required),
+                format_days(days),
+                viable
+            );
+        }
+        line
+    }
+
+    /// `text` with the characters the service treats as markup escaped:
+    /// Slack reads `<` as the start of a link or mention, Telegram as a tag.
+    fn escape(self, text: &str) -> String {
+        match self {
+            ChatService::Discord => text.to_string(),
+            ChatService::Slack | ChatService::Telegram => {
+                text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+            }
+        }
+    }
+
+    fn bold(self, text: &str) -> String {
+        match self {
+            ChatService::Discord => format!("**{}**", text),
+            ChatService::Slack => format!("*{}*", text),
+            ChatService::Telegram => format!("<b>{}</b>", text),
+        }
+    }
+
+    fn message(self, text: String) -> Value {
+        match self {
+            ChatService::Discord => json!({ "username": CHAT_USERNAME, "content": text }),
+            ChatService::Slack => json!({ "text": text }),
+            ChatService::Telegram => json!({ "text": text, "parse_mode": "HTML" }),
+        }
+    }
+}
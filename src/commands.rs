@@ -0,0 +1,796 @@
+//! The calculator's commands that finish with results.
+//!
+//! Each reads the chain from a [`BlockSource`], makes its calculations at
+//! the hashrate and target time given with the [`Model`] of the run, prints
+//! what it found with [`say!`](crate::say) and returns the calculations for
+//! the [`ResultSinks`](crate::ResultSinks) and reports. The monitors that
+//! keep recalculating are in [`watch`](crate::watch).
+
+use bitcoin::{BlockHash, Txid};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::backtest::{day_end, day_tip_height, BacktestDay, ChainAt};
+use crate::bench::benchmark_sha256d;
+use crate::budget::{rental_cost, Money};
+use crate::cache::CachedSource;
+use crate::calc::{
+    block_fork_height, calculate_from_work, calculate_matrix, calculate_reorg_requirements,
+    calculate_reorg_requirements_with, calculate_sweep, confirmation_fork_height, find_deepest_fork_height,
+    find_viable_target_heights_among, find_viable_target_heights_with, fork_height_at_time, sweep_fork_heights,
+    transaction_fork_height, Adjustments, Margin, ReorgCalculation,
+};
+use crate::config::write_env_var;
+use crate::error::{ReorgCalcError, Result, ResultContext};
+use crate::forecast::{forecast_viability, measure_difficulty_trend};
+use crate::format::{
+    display_backtest, display_branches, display_calculation, display_forecast, display_group, display_intervals,
+    display_matrix, display_min_difficulty, display_miners, display_scenarios, display_span_epochs, display_storms,
+    display_sweep, display_tip_comparison, display_transactions, format_days, format_hashrate, in_fiat, write_block_csv,
+    write_block_jsonl,
+};
+#[cfg(feature = "rpc")]
+use crate::format::display_wallet;
+use crate::group::{split_work, Participant};
+use crate::ics::write_ics;
+use crate::impact::measure_transaction_impact;
+use crate::miners::identify_miners;
+use crate::model::Model;
+use crate::network::NetworkParams;
+#[cfg(feature = "plots")]
+use crate::plot::plot_calculation;
+use crate::say;
+use crate::scenario::Scenario;
+use crate::source::BlockSource;
+use crate::storm::find_block_storms;
+use crate::track::{branch_progress, common_fork_height, tip_work};
+#[cfg(feature = "rpc")]
+use crate::wallet::wallet_impact;
+use crate::work::{
+    chain_work_from_chainwork, count_min_difficulty_blocks, measure_block_intervals, scan_block_works,
+    scan_chain_work_with, span_epochs, BlockWork, ScanOptions,
+};
+
+/// Where a calculation forks from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForkPoint {
+    /// Wherever the command suggests, usually 100 blocks below the tip.
+    #[default]
+    Suggested,
+    Height(u64),
+    /// This many blocks below the tip, resolved at every calculation.
+    Depth(u64),
+    /// The last block timestamped at or before this time.
+    Time(DateTime<Utc>),
+    /// The block confirming this transaction, to erase it.
+    Transaction(Txid),
+    /// This block, pinned by hash so the fork point stays put as the chain
+    /// grows.
+    Block(BlockHash),
+}
+
+impl ForkPoint {
+    /// Fork height to calculate for, or `None` for the suggested default.
+    pub fn resolve(&self, source: &dyn BlockSource) -> Result<Option<u64>> {
+        match *self {
+            ForkPoint::Suggested => Ok(None),
+            ForkPoint::Height(fork_height) => Ok(Some(fork_height)),
+            ForkPoint::Depth(depth) => Ok(Some(source.get_tip()?.height.saturating_sub(depth))),
+            ForkPoint::Time(time) => {
+                let fork_height = fork_height_at_time(source, time)?;
+                say!("Last block at or before {}: {}", time.format("%Y-%m-%d %H:%M:%S UTC"), fork_height);
+                Ok(Some(fork_height))
+            }
+            ForkPoint::Transaction(txid) => {
+                let fork_height = transaction_fork_height(source, &txid)?;
+                let confirmations = source.get_tip()?.height.saturating_sub(fork_height) + 1;
+                say!("Transaction {} is in block {} ({} confirmations)", txid, fork_height, confirmations);
+                Ok(Some(fork_height))
+            }
+            ForkPoint::Block(hash) => Ok(Some(block_fork_height(source, &hash)?)),
+        }
+    }
+}
+
+/// Calculate for `fork_height`, or 100 blocks below the tip, and print it.
+pub fn run_calc(
+    source: &dyn BlockSource,
+    fork_height: Option<u64>,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+    options: &ScanOptions,
+) -> Result<ReorgCalculation> {
+    let (fork_height, suggested) = match fork_height {
+        Some(fork_height) => (fork_height, false),
+        None => {
+            // Default: calculate for a recent block that should be viable
+            let current_height = source.get_tip()?.height;
+            let suggested_height = current_height.saturating_sub(100); // Go back 100 blocks
+            say!("\nNo fork height specified. Calculating for suggested height: {}", suggested_height);
+            (suggested_height, true)
+        }
+    };
+
+    let calc = calculate_reorg_requirements_with(source, fork_height, hashrate, target_days, options)?;
+    let params = source.get_network()?;
+    let calc = model.adjustments(source)?.apply(source, calc, hashrate, target_days, &params)?;
+    let calc = model.annotate(calc, hashrate, target_days, &params);
+    display_calculation(&calc, hashrate);
+
+    if suggested {
+        say!("\nTo calculate for a specific height, use: calc --fork-height <height>");
+        say!("To find all viable heights, use: batch");
+    }
+    Ok(calc)
+}
+
+/// What `calc` can look into besides the work: each is scanned from the
+/// calculation's span, printed and added to it.
+#[derive(Debug, Clone, Copy)]
+pub struct CalcExtras<'a> {
+    /// Sum the span block by block and compare with the chainwork result.
+    pub verify_work: bool,
+    pub min_difficulty: bool,
+    pub intervals: bool,
+    pub miners: bool,
+    /// Count the transactions of the blocks the reorg would orphan.
+    pub transactions: bool,
+    /// List the orphaned transactions of at least this many satoshis too.
+    pub min_value: Option<u64>,
+    /// The node's client for a wallet loaded on it, and the wallet's name,
+    /// to list the wallet's transactions the reorg would unconfirm.
+    #[cfg(feature = "rpc")]
+    pub wallet: Option<(&'a bitcoincore_rpc::Client, &'a str)>,
+    pub storms: bool,
+    /// Shortest run of minimum-difficulty blocks counted as a storm.
+    pub storm_blocks: u64,
+    /// File to write every block of the span to, as JSON lines if it ends
+    /// in `.jsonl` and CSV otherwise.
+    pub per_block: Option<&'a Path>,
+    /// Break the span's work down by retarget epoch.
+    pub epochs: bool,
+}
+
+/// Add the `extras` asked for to `calc`, scanning its span of `source`.
+pub fn add_calc_extras(
+    source: &dyn BlockSource,
+    calc: &mut ReorgCalculation,
+    extras: &CalcExtras,
+    options: &ScanOptions,
+) -> Result<()> {
+    if extras.min_difficulty {
+        let blocks = count_min_difficulty_blocks(source, calc.fork_height, calc.current_height)?;
+        display_min_difficulty(&blocks);
+        calc.min_difficulty = Some(blocks);
+    }
+    if extras.transactions {
+        let impact = measure_transaction_impact(source, calc.fork_height, calc.current_height, extras.min_value)?;
+        display_transactions(&impact);
+        calc.transactions = Some(impact);
+    }
+    #[cfg(feature = "rpc")]
+    if let Some((client, name)) = extras.wallet {
+        let new_chain_blocks = if calc.blocks_needed.is_finite() { calc.blocks_needed.ceil() as u64 } else { 0 };
+        let impact = wallet_impact(client, name, source, calc.fork_height, new_chain_blocks)?;
+        display_wallet(&impact);
+        calc.wallet = Some(impact);
+    }
+    if extras.miners {
+        calc.miners = identify_miners(source, calc.fork_height, calc.current_height)?;
+        display_miners(&calc.miners);
+    }
+    if extras.storms {
+        let storms = find_block_storms(source, calc.fork_height, calc.current_height, extras.storm_blocks)?;
+        display_storms(&storms);
+        calc.storms = Some(storms);
+    }
+    if extras.intervals {
+        let intervals = measure_block_intervals(source, calc.fork_height, calc.current_height)?;
+        display_intervals(&intervals, source.get_network()?.consensus().pow_target_spacing);
+        calc.intervals = Some(intervals);
+    }
+    if extras.verify_work {
+        verify_chain_work(source, calc, options)?;
+    }
+    if extras.per_block.is_some() || extras.epochs {
+        let blocks = scan_block_works(source, calc.fork_height, calc.current_height)?;
+        if let Some(path) = extras.per_block {
+            write_per_block(&blocks, path)?;
+        }
+        if extras.epochs {
+            calc.span_epochs = span_epochs(&blocks, &source.get_network()?);
+            display_span_epochs(&calc.span_epochs);
+        }
+    }
+    Ok(())
+}
+
+/// Calculate for `fork_height` at each of `hashrates` against each target
+/// time of `model`, and print the matrix.
+pub fn run_matrix(
+    source: &dyn BlockSource,
+    fork_height: Option<u64>,
+    hashrates: &[f64],
+    target_days: f64,
+    model: Model,
+    feasible_only: bool,
+) -> Result<Vec<ReorgCalculation>> {
+    let fork_height = match fork_height {
+        Some(fork_height) => fork_height,
+        None => source.get_tip()?.height.saturating_sub(100),
+    };
+    let days = match model.target_days {
+        [] => vec![target_days],
+        days => days.to_vec(),
+    };
+    let adjustments = model.adjustments(source)?;
+    let calculations = calculate_matrix(source, fork_height, hashrates, target_days, &adjustments)?;
+    display_matrix(&calculations, hashrates, &days, feasible_only);
+    Ok(calculations)
+}
+
+/// Calculate each of `scenarios`, falling back to `hashrate`, `target_days`
+/// and `model` for what a scenario leaves out, and compare them.
+pub fn run_scenarios(
+    source: &dyn BlockSource,
+    scenarios: &[Scenario],
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Vec<ReorgCalculation>> {
+    let mut calculations = Vec::with_capacity(scenarios.len());
+    for scenario in scenarios {
+        say!("\n=== Scenario: {} ===", scenario.name);
+        let fork_height = match scenario.fork_height {
+            Some(fork_height) => fork_height,
+            None => source.get_tip()?.height.saturating_sub(scenario.depth.unwrap_or(100)),
+        };
+        let model = Model {
+            margin: Margin {
+                blocks: scenario.margin_blocks.unwrap_or(model.margin.blocks),
+                percent: scenario.margin_percent.unwrap_or(model.margin.percent),
+            },
+            ..model
+        };
+        let hashrate = scenario.hashrate.unwrap_or(hashrate);
+        let target_days = scenario.target_days.unwrap_or(target_days);
+        let mut calc = run_calc(source, Some(fork_height), hashrate, target_days, model, &ScanOptions::default())?;
+        calc.scenario = Some(scenario.name.clone());
+        calculations.push(calc);
+    }
+    display_scenarios(&calculations);
+    Ok(calculations)
+}
+
+/// Calculate for `members` mining together and split the work between them.
+pub fn run_group(
+    source: &dyn BlockSource,
+    fork_height: Option<u64>,
+    members: &[Participant],
+    target_days: f64,
+    model: Model,
+) -> Result<ReorgCalculation> {
+    let hashrate = members.iter().map(|member| member.hashrate).sum();
+    let mut calc = run_calc(source, fork_height, hashrate, target_days, model, &ScanOptions::default())?;
+    calc.group = split_work(&calc, members);
+    display_group(&calc, target_days);
+    Ok(calc)
+}
+
+/// Chain figures for `manual`, in difficulty units.
+#[derive(Debug, Clone, Copy)]
+pub struct ManualChain {
+    pub current_height: u64,
+    pub current_difficulty: f64,
+    pub fork_height: u64,
+    /// Total work of the blocks being replaced.
+    pub total_work: Option<f64>,
+    /// Average difficulty to assume for the blocks being replaced, if
+    /// their total work is not given; the current difficulty by default.
+    pub average_difficulty: Option<f64>,
+}
+
+/// Measure this machine's double-SHA256 hashrate for `seconds` on `jobs`
+/// threads, one per CPU by default, and save it as the default hashrate
+/// in `.env` if `save`.
+pub fn run_bench(seconds: u64, jobs: Option<u64>, save: bool) -> Result<()> {
+    let threads = match jobs {
+        Some(jobs) => jobs as usize,
+        None => thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+    say!("Hashing block headers on {} threads for {} seconds...", threads, seconds);
+    let bench = benchmark_sha256d(Duration::from_secs(seconds), threads);
+    say!("\n=== SHA256d Benchmark ===");
+    say!("Hashes: {} in {:.2} seconds", bench.hashes, bench.seconds);
+    say!(
+        "Hashrate: {} ({} per thread)",
+        format_hashrate(bench.hashrate),
+        format_hashrate(bench.hashrate / threads as f64)
+    );
+    let value = format!("{:.0}", bench.hashrate);
+    if save {
+        write_env_var(Path::new(".env"), "DEFAULT_HASHRATE", &value)?;
+        say!("Saved DEFAULT_HASHRATE={} to .env; it is now the default --hashrate", value);
+    } else {
+        say!("Use it with --hashrate {}, or rerun with --save to make it the default", value);
+    }
+    Ok(())
+}
+
+/// Calculate from the figures of `chain` alone, without a node.
+pub fn run_manual(chain: ManualChain, hashrate: f64, target_days: f64, model: Model) -> Result<ReorgCalculation> {
+    if chain.fork_height > chain.current_height {
+        return Err(ReorgCalcError::ForkAboveTip {
+            fork_height: chain.fork_height,
+            tip_height: chain.current_height,
+        });
+    }
+    let positive = |value: f64| value > 0.0 && value.is_finite();
+    if !positive(chain.current_difficulty) {
+        return Err(ReorgCalcError::InvalidInput("--current-difficulty must be a positive number".to_string()));
+    }
+    let blocks_to_reorg = chain.current_height - chain.fork_height + 1;
+    let total_work = match (chain.total_work, chain.average_difficulty) {
+        (Some(total_work), _) => total_work,
+        (None, Some(average)) => average * blocks_to_reorg as f64,
+        (None, None) => {
+            say!("Assuming the replaced blocks average the current difficulty");
+            chain.current_difficulty * blocks_to_reorg as f64
+        }
+    };
+    if !positive(total_work) {
+        return Err(ReorgCalcError::InvalidInput(
+            "--total-work and --average-difficulty must be positive numbers".to_string(),
+        ));
+    }
+
+    let params = NetworkParams::default();
+    let calc = calculate_from_work(
+        chain.fork_height,
+        chain.current_height,
+        total_work,
+        chain.current_difficulty,
+        hashrate,
+        target_days,
+        &params,
+    );
+    let calc = model.offline_adjustments()?.apply_offline(calc, hashrate, target_days, &params)?;
+    let calc = model.annotate(calc, hashrate, target_days, &params);
+    display_calculation(&calc, hashrate);
+    Ok(calc)
+}
+
+/// Calculate what it takes to reorg out a transaction with `confirmations`.
+pub fn run_confirmations(
+    source: &dyn BlockSource,
+    confirmations: u64,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<ReorgCalculation> {
+    let tip_height = source.get_tip()?.height;
+    let fork_height = confirmation_fork_height(tip_height, confirmations)?;
+    say!(
+        "\nA transaction with {} confirmations is in block {}; reorging it out replaces {} blocks",
+        confirmations, fork_height, confirmations
+    );
+    run_calc(source, Some(fork_height), hashrate, target_days, model, &ScanOptions::default())
+}
+
+/// Headers fetched into the cache per batch, so an interrupted sync keeps
+/// what it fetched.
+const SYNC_BATCH: u64 = 10_000;
+
+/// Fill `cached` with the headers of the whole active chain.
+pub fn sync_headers(cached: &CachedSource<&dyn BlockSource>) -> Result<()> {
+    let tip = cached.get_tip()?;
+    let before = cached.cached_headers();
+    say!("\nFetching headers 0 to {} into the cache...", tip.height);
+    for start in (0..=tip.height).step_by(SYNC_BATCH as usize) {
+        let heights: Vec<u64> = (start..=tip.height.min(start + SYNC_BATCH - 1)).collect();
+        cached.get_headers_batch(&heights)?;
+    }
+    say!(
+        "Header cache: {} headers, {} new, through block {} ({})",
+        cached.cached_headers(),
+        cached.cached_headers() - before,
+        tip.height,
+        tip.hash
+    );
+    Ok(())
+}
+
+fn verify_chain_work(source: &dyn BlockSource, calc: &ReorgCalculation, options: &ScanOptions) -> Result<()> {
+    let from_chainwork = chain_work_from_chainwork(source, calc.fork_height, calc.current_height)?;
+    let scanned = scan_chain_work_with(source, calc.fork_height, calc.current_height, options)?;
+    let params = source.get_network()?;
+    say!("\n=== Chain Work Verification ===");
+    say!("From chainwork: {:.2}", params.work_to_difficulty(from_chainwork));
+    say!("From block scan: {:.2}", params.work_to_difficulty(scanned));
+    say!("Exact match: {}", if from_chainwork == scanned { "yes" } else { "no" });
+    Ok(())
+}
+
+/// Blocks named as the heaviest after a `--per-block` scan.
+const HEAVIEST_BLOCKS: usize = 5;
+
+/// Write `blocks` to `path` for `--per-block`, and name the heaviest.
+fn write_per_block(blocks: &[BlockWork], path: &Path) -> Result<()> {
+    let file = fs::File::create(path).context(format!("cannot create --per-block file {}", path.display()))?;
+    let mut writer = io::BufWriter::new(file);
+    if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("jsonl")) {
+        write_block_jsonl(&mut writer, blocks)?;
+    } else {
+        write_block_csv(&mut writer, blocks)?;
+    }
+    writer.flush()?;
+    say!("Per-block work of {} blocks saved to: {}", blocks.len(), path.display());
+
+    let mut heaviest: Vec<_> = blocks.iter().collect();
+    heaviest.sort_by(|a, b| b.work_share.total_cmp(&a.work_share));
+    let heaviest: Vec<String> = heaviest
+        .iter()
+        .take(HEAVIEST_BLOCKS)
+        .map(|block| format!("{} ({:.2}%)", block.height, 100.0 * block.work_share))
+        .collect();
+    say!("Heaviest blocks: {}", heaviest.join(", "));
+    Ok(())
+}
+
+/// Find the fork heights among `candidates`, or at the built-in depths,
+/// that can be reorged within `target_days`, and calculate each.
+pub fn run_batch(
+    source: &dyn BlockSource,
+    candidates: Option<&[u64]>,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Vec<ReorgCalculation>> {
+    let adjustments = model.adjustments(source)?;
+    let params = source.get_network()?;
+    let mut calculations = Vec::new();
+    say!(
+        "\nFinding viable target heights for {} within {} days...",
+        format_hashrate(hashrate),
+        format_days(target_days)
+    );
+    let viable_heights = match candidates {
+        Some(candidates) => {
+            say!("Testing {} fork heights from the heights file", candidates.len());
+            find_viable_target_heights_among(source, candidates, hashrate, target_days, &adjustments)?
+        }
+        None => find_viable_target_heights_with(source, hashrate, target_days, &adjustments)?,
+    };
+
+    if viable_heights.is_empty() {
+        say!(
+            "No viable target heights found within {} days with {}",
+            format_days(target_days),
+            format_hashrate(hashrate)
+        );
+    } else {
+        say!("Found {} viable target heights:", viable_heights.len());
+        for &height in &viable_heights {
+            let calc = calculate_reorg_requirements(source, height, hashrate, target_days)?;
+            let calc = adjustments.apply(source, calc, hashrate, target_days, &params)?;
+            let calc = model.annotate(calc, hashrate, target_days, &params);
+            display_calculation(&calc, hashrate);
+            calculations.push(calc);
+        }
+    }
+    Ok(calculations)
+}
+
+/// Calculate every `step`th fork height from `fork_start` to `fork_end`,
+/// the tip by default, and print them as a table.
+pub fn run_sweep(
+    source: &dyn BlockSource,
+    fork_start: u64,
+    fork_end: Option<u64>,
+    step: u64,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Vec<ReorgCalculation>> {
+    let fork_end = match fork_end {
+        Some(fork_end) => fork_end,
+        None => source.get_tip()?.height,
+    };
+    let fork_heights = sweep_fork_heights(fork_start, fork_end, step);
+    if fork_heights.is_empty() {
+        return Err(ReorgCalcError::InvalidInput(format!(
+            "--fork-start {} is above --fork-end {}",
+            fork_start, fork_end
+        )));
+    }
+    let adjustments = model.adjustments(source)?;
+    let calculations = calculate_sweep(source, &fork_heights, hashrate, target_days, &adjustments)?;
+    display_sweep(&calculations, hashrate, target_days);
+    Ok(calculations)
+}
+
+/// The deepest fork `hashrate` could have reorged within `target_days` on
+/// each day from `from` to `to`, today by default.
+pub fn run_backtest(
+    source: &dyn BlockSource,
+    from: NaiveDate,
+    to: Option<NaiveDate>,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Vec<ReorgCalculation>> {
+    let to = to.unwrap_or_else(|| Utc::now().date_naive());
+    if from > to {
+        return Err(ReorgCalcError::InvalidInput(format!("--from {} is after --to {}", from, to)));
+    }
+    say!(
+        "\nBacktesting {} to {}: the deepest fork {} could reorg within {} days on each day...",
+        from,
+        to,
+        format_hashrate(hashrate),
+        format_days(target_days)
+    );
+    let mut days = Vec::new();
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        let Some(tip_height) = day_tip_height(source, date)? else {
+            continue;
+        };
+        // Everything, the honest hashrate included, as it stood that day
+        let chain = ChainAt::new(source, tip_height)?;
+        let adjustments = model.adjustments(&chain)?;
+        let viable = |calc: &ReorgCalculation| calc.time_required_days <= target_days;
+        let mut deepest = find_deepest_fork_height(&chain, hashrate, target_days, &adjustments, viable)?;
+        if let Some(calc) = &mut deepest {
+            calc.timestamp = day_end(date).min(Utc::now());
+        }
+        days.push(BacktestDay { date, tip_height, deepest });
+    }
+    display_backtest(&days, hashrate, target_days);
+    Ok(days.into_iter().filter_map(|day| day.deepest).collect())
+}
+
+/// The calculation for `depth` blocks now if it is viable already, else
+/// at the first retarget ahead from which it is.
+pub fn run_forecast(
+    source: &dyn BlockSource,
+    depth: u64,
+    trend_epochs: u64,
+    horizon: u64,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Option<ReorgCalculation>> {
+    if depth == 0 {
+        return Err(ReorgCalcError::InvalidInput("--depth must be at least one block".to_string()));
+    }
+    let adjustments = model.adjustments(source)?;
+    let params = source.get_network()?;
+    let tip_height = source.get_tip()?.height;
+    let fork_height = (tip_height + 1).saturating_sub(depth);
+    let calc = calculate_reorg_requirements(source, fork_height, hashrate, target_days)?;
+    let calc = adjustments.apply(source, calc, hashrate, target_days, &params)?;
+    if calc.time_required_days <= target_days {
+        say!(
+            "\nA {}-block reorg is viable already: {:.2} days at {}",
+            calc.blocks_to_reorg,
+            calc.time_required_days,
+            format_hashrate(hashrate)
+        );
+        return Ok(Some(calc));
+    }
+
+    say!(
+        "\nForecasting when a {}-block reorg fits within {} days at {} (now {:.2} days)...",
+        depth,
+        format_days(target_days),
+        format_hashrate(hashrate),
+        calc.time_required_days
+    );
+    let trend = measure_difficulty_trend(source, trend_epochs)?;
+    // The projected chains have no headers to project their own next retarget from
+    let projection = Adjustments { next_retarget: false, ..adjustments };
+    let projected = forecast_viability(source, depth, hashrate, target_days, &projection, &trend, horizon)?;
+    display_forecast(depth, &trend, &projected, hashrate, target_days);
+    Ok(projected
+        .into_iter()
+        .last()
+        .map(|epoch| epoch.calculation)
+        .filter(|calc| calc.time_required_days <= target_days))
+}
+
+/// The deepest fork renting `hashrate` at `price` per TH/s per day, or the
+/// rental price of `model`, can reorg within `target_days` and `budget`.
+pub fn run_budget(
+    source: &dyn BlockSource,
+    budget: &Money,
+    price: Option<Money>,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Option<ReorgCalculation>> {
+    let price = match (price, model.rental) {
+        (Some(price), _) => price,
+        (None, Some(rental)) => Money {
+            amount: rental.price_per_th_day,
+            currency: Some(rental.currency.clone()),
+        },
+        (None, None) => {
+            return Err(ReorgCalcError::InvalidInput("budget needs --price-per-th-day or --rent-from".to_string()))
+        }
+    };
+    let price = match (&budget.currency, &price.currency) {
+        (Some(budget_currency), Some(price_currency)) if budget_currency != price_currency => model
+            .exchange_rate
+            .and_then(|rate| rate.convert(&price, budget_currency))
+            .ok_or_else(|| {
+                ReorgCalcError::InvalidInput(format!(
+                    "the budget is in {} but the price per TH/s per day in {}; add --fiat to convert",
+                    budget_currency, price_currency
+                ))
+            })?,
+        _ => price,
+    };
+    let currency = if budget.currency.is_some() { budget } else { &price };
+    let cost = |calc: &ReorgCalculation| rental_cost(calc, hashrate, price.amount);
+
+    say!(
+        "\nFinding the deepest fork height for {} within {} days and {}...",
+        format_hashrate(hashrate),
+        format_days(target_days),
+        currency.with_amount(budget.amount)
+    );
+    let adjustments = model.adjustments(source)?;
+    let found = find_deepest_fork_height(source, hashrate, target_days, &adjustments, |calc| {
+        calc.time_required_days <= target_days && cost(calc) <= budget.amount
+    })?;
+    let Some(calc) = found else {
+        say!("Not even the tip block can be reorged within {} days on this budget", format_days(target_days));
+        return Ok(None);
+    };
+
+    let params = source.get_network()?;
+    let calc = model.annotate(calc, hashrate, target_days, &params);
+    display_calculation(&calc, hashrate);
+    say!();
+    say!(
+        "=== Within Budget ({} at {} per TH/s per day) ===",
+        currency.with_amount(budget.amount),
+        currency.with_amount(price.amount)
+    );
+    say!("Deepest Fork Height: {} ({} blocks to reorg)", calc.fork_height, calc.blocks_to_reorg);
+    let total = currency.with_amount(cost(&calc));
+    say!(
+        "Rental Cost: {}{} for {:.2} hours ({:.2} days)",
+        total,
+        in_fiat(&calc, &total),
+        calc.time_required_hours,
+        calc.time_required_days
+    );
+    Ok(Some(calc))
+}
+
+/// Compare the tips of two nodes, each given with its name: their heights,
+/// hashes and chain work, and where their chains split.
+pub fn run_compare(first: (&str, &dyn BlockSource), second: (&str, &dyn BlockSource)) -> Result<()> {
+    let ((first_name, first), (second_name, second)) = (first, second);
+    let params = first.get_network()?;
+    if second.get_network()? != params {
+        return Err(ReorgCalcError::InvalidInput(format!(
+            "{} and {} are on different networks",
+            first_name, second_name
+        )));
+    }
+    let first_tip = tip_work(first)?;
+    let second_tip = tip_work(second)?;
+    let fork_height = common_fork_height(first, second)?;
+    display_tip_comparison([(first_name, &first_tip), (second_name, &second_tip)], fork_height, &params);
+    Ok(())
+}
+
+/// List the branches `source` knows of besides its active chain, with
+/// what completing each would take.
+pub fn run_chaintips(source: &dyn BlockSource, hashrate: f64, model: Model, target_days: f64) -> Result<()> {
+    let branches = branch_progress(source)?;
+    let honest_hashrate = model.honest_hashrate(source)?;
+    display_branches(&branches, hashrate, honest_hashrate, target_days, &source.get_network()?);
+    Ok(())
+}
+
+/// Write the timeline of the single calculation in `runs` to `path`.
+pub fn export_ics(runs: &[(ReorgCalculation, f64)], path: &Path) -> Result<()> {
+    let [(calc, hashrate)] = runs else {
+        return Err(ReorgCalcError::InvalidInput(format!("--ics needs a single result, got {}", runs.len())));
+    };
+    if calc.schedule.is_empty() {
+        return Err(ReorgCalcError::InvalidInput(
+            "the attack never finishes, so --ics has no timeline to export".to_string(),
+        ));
+    }
+    let file = fs::File::create(path).context(format!("cannot create --ics file {}", path.display()))?;
+    let mut writer = io::BufWriter::new(file);
+    write_ics(&mut writer, calc, *hashrate)?;
+    writer.flush()?;
+    say!("Attack timeline saved to: {}", path.display());
+    Ok(())
+}
+
+/// Draw the single calculation in `runs`, read from `source`, to `path`.
+pub fn plot_runs(source: &dyn BlockSource, runs: &[(ReorgCalculation, f64)], path: &Path) -> Result<()> {
+    let [(calc, hashrate)] = runs else {
+        return Err(ReorgCalcError::InvalidInput(format!("--plot needs a single result, got {}", runs.len())));
+    };
+    #[cfg(feature = "plots")]
+    {
+        plot_calculation(source, calc, *hashrate, path)?;
+        say!("Plot written to {}", path.display());
+        Ok(())
+    }
+    #[cfg(not(feature = "plots"))]
+    {
+        let _ = (source, calc, hashrate, path);
+        Err(ReorgCalcError::InvalidInput("plots not available. Compile with --features plots".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{temp_path, TestChain, DIFFICULTY_1};
+
+    #[test]
+    fn fork_points_resolve_against_the_tip() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        assert_eq!(ForkPoint::Suggested.resolve(&chain).unwrap(), None);
+        assert_eq!(ForkPoint::Height(120).resolve(&chain).unwrap(), Some(120));
+        assert_eq!(ForkPoint::Depth(50).resolve(&chain).unwrap(), Some(249));
+        let hash = chain.tip_at(200).hash;
+        assert_eq!(ForkPoint::Block(hash).resolve(&chain).unwrap(), Some(200));
+    }
+
+    #[test]
+    fn the_suggested_fork_is_100_blocks_below_the_tip() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        let calc = run_calc(&chain, None, 1e12, 3.0, Model::default(), &ScanOptions::default()).unwrap();
+        assert_eq!((calc.fork_height, calc.current_height), (199, 299));
+    }
+
+    #[test]
+    fn a_sweep_needs_its_start_below_its_end() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        let err = run_sweep(&chain, 250, Some(200), 1, 1e12, 3.0, Model::default()).unwrap_err();
+        assert!(matches!(err, ReorgCalcError::InvalidInput(_)));
+        assert_eq!(run_sweep(&chain, 200, Some(250), 10, 1e12, 3.0, Model::default()).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn manual_runs_take_the_figures_given() {
+        let chain = ManualChain {
+            current_height: 1000,
+            current_difficulty: 2.0,
+            fork_height: 991,
+            total_work: None,
+            average_difficulty: None,
+        };
+        let calc = run_manual(chain, 1e12, 3.0, Model::default()).unwrap();
+        assert_eq!(calc.blocks_to_reorg, 10);
+        let above = ManualChain { fork_height: 1001, ..chain };
+        assert!(matches!(run_manual(above, 1e12, 3.0, Model::default()), Err(ReorgCalcError::ForkAboveTip { .. })));
+    }
+
+    #[test]
+    fn the_ics_timeline_needs_a_single_finished_attack() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        let path = temp_path("commands.ics");
+        let calc = run_calc(&chain, Some(290), 1e12, 3.0, Model { schedule: true, ..Model::default() }, &ScanOptions::default())
+            .unwrap();
+        assert!(matches!(export_ics(&[], &path), Err(ReorgCalcError::InvalidInput(_))));
+        export_ics(&[(calc, 1e12)], &path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("BEGIN:VCALENDAR"));
+        let _ = fs::remove_file(path);
+    }
+}
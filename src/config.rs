@@ -0,0 +1,132 @@
+//! Configuration loaded from the environment and `.env`.
+
+use dotenvy::dotenv;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chat::{TelegramBot, TELEGRAM_API};
+use crate::error::{ReorgCalcError, Result};
+use crate::units::{parse_days, parse_hashrate};
+
+/// Defaults read from `.env` / environment variables.
+///
+/// Command line arguments override these in the binary.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub rpc_url: String,
+    pub rpc_user: String,
+    pub rpc_password: String,
+    pub rpc_port: u16,
+    /// Default hashrate in hashes/second.
+    pub default_hashrate: f64,
+    /// Default target completion time in days.
+    pub target_days: f64,
+    /// Default percentage of extra work for the attacker's stale blocks and
+    /// propagation losses.
+    pub overhead_percent: f64,
+    /// Bot to send alerts and results to, from `TELEGRAM_BOT_TOKEN` and
+    /// `TELEGRAM_CHAT_ID` (and `TELEGRAM_API_URL` for a relay).
+    pub telegram: Option<TelegramBot>,
+}
+
+impl Config {
+    /// Load configuration from `.env` (if present) and the environment,
+    /// falling back to built-in defaults.
+    pub fn load() -> Result<Self> {
+        dotenv().ok();
+
+        let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:48337".to_string());
+        let rpc_user = env::var("RPC_USER").unwrap_or_else(|_| "myusername".to_string());
+        let rpc_password = env::var("RPC_PASSWORD").unwrap_or_else(|_| "mypassword".to_string());
+        let rpc_port = parse_var("RPC_PORT", "48337")?;
+        let default_hashrate = parse_var_with("DEFAULT_HASHRATE", "1000000000000000", parse_hashrate)?;
+        let target_days = parse_var_with("TARGET_DAYS", "3", parse_days)?;
+        let overhead_percent = parse_var("OVERHEAD_PERCENT", "0")?;
+        let telegram = telegram_bot()?;
+
+        Ok(Self {
+            rpc_url,
+            rpc_user,
+            rpc_password,
+            rpc_port,
+            default_hashrate,
+            target_days,
+            overhead_percent,
+            telegram,
+        })
+    }
+}
+
+/// The Telegram bot configured, if `TELEGRAM_BOT_TOKEN` is set; it needs
+/// `TELEGRAM_CHAT_ID` too.
+fn telegram_bot() -> Result<Option<TelegramBot>> {
+    let var = |key| env::var(key).ok().filter(|value: &String| !value.trim().is_empty());
+    match (var("TELEGRAM_BOT_TOKEN"), var("TELEGRAM_CHAT_ID")) {
+        (Some(token), Some(chat_id)) => Ok(Some(TelegramBot {
+            token: token.trim().to_string(),
+            chat_id: chat_id.trim().to_string(),
+            api_url: var("TELEGRAM_API_URL").unwrap_or_else(|| TELEGRAM_API.to_string()),
+        })),
+        (None, None) => Ok(None),
+        (Some(_), None) => Err(ReorgCalcError::Config {
+            key: "TELEGRAM_CHAT_ID".to_string(),
+            value: String::new(),
+            reason: "needed with TELEGRAM_BOT_TOKEN".to_string(),
+        }),
+        (None, Some(chat_id)) => Err(ReorgCalcError::Config {
+            key: "TELEGRAM_CHAT_ID".to_string(),
+            value: chat_id,
+            reason: "needs TELEGRAM_BOT_TOKEN".to_string(),
+        }),
+    }
+}
+
+/// Set `key` to `value` in the env file at `path`, replacing an existing
+/// assignment or appending one, and creating the file if needed.
+pub fn write_env_var(path: &Path, key: &str, value: &str) -> Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let assignment = format!("{}={}", key, value);
+    let mut replaced = false;
+    let mut lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let assigns_key = line
+                .trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='));
+            if assigns_key && !replaced {
+                replaced = true;
+                assignment.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !replaced {
+        lines.push(assignment);
+    }
+    fs::write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Parse an environment variable, using `default` when it is unset.
+fn parse_var<T: FromStr>(key: &str, default: &str) -> Result<T> {
+    parse_var_with(key, default, |value| value.parse().map_err(|_| "not a valid number".to_string()))
+}
+
+/// Parse an environment variable with `parse`, using `default` when it is unset.
+fn parse_var_with<T>(key: &str, default: &str, parse: impl Fn(&str) -> std::result::Result<T, String>) -> Result<T> {
+    let value = env::var(key).unwrap_or_else(|_| default.to_string());
+    parse(&value).map_err(|reason| ReorgCalcError::Config {
+        key: key.to_string(),
+        value,
+        reason,
+    })
+}
@@ -0,0 +1,145 @@
+//! Table of mining hardware, built in and from a user file.
+//!
+//! Lets the attacker's hashrate be given as a number of known machines
+//! instead of raw hashes/second. Built-in figures are the manufacturers'
+//! nominal ratings at stock settings; a hardware file adds or overrides
+//! models without a code change. It is a TOML subset, one `[[device]]`
+//! table per model:
+//!
+//! ```toml
+//! [[device]]
+//! name = "Antminer S21 XP"
+//! id = "antminer-s21xp"   # optional, derived from the name
+//! hashrate = "270TH"      # or a number in hashes/second
+//! watts = 3645
+//! cost = 5800             # optional price of one unit
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::toml_subset::{parse_tables, Table};
+use crate::units::parse_hashrate;
+
+/// Default hardware file, read from the working directory when present.
+pub const HARDWARE_FILE: &str = "hardware.toml";
+
+/// Model hashrates required are counted in unless another is chosen.
+pub const REFERENCE_DEVICE: &str = "antminer-s21";
+
+/// A mining device model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    /// Identifier used on the command line, e.g. `antminer-s19xp`.
+    pub id: String,
+    /// Display name, e.g. `Antminer S19 XP`.
+    pub name: String,
+    /// Hashrate of one unit in hashes/second.
+    pub hashrate: f64,
+    /// Power draw of one unit in watts.
+    pub power_watts: f64,
+    /// Price of one unit, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+impl Device {
+    /// Efficiency in joules per terahash.
+    pub fn joules_per_th(&self) -> f64 {
+        self.power_watts / (self.hashrate / 1e12)
+    }
+}
+
+/// `(id, name, TH/s, W)` of the built-in devices.
+const BUILTIN: &[(&str, &str, f64, f64)] = &[
+    ("antminer-s9", "Antminer S9", 13.5, 1323.0),
+    ("antminer-s17", "Antminer S17", 56.0, 2520.0),
+    ("antminer-s19", "Antminer S19", 95.0, 3250.0),
+    ("antminer-s19-pro", "Antminer S19 Pro", 110.0, 3250.0),
+    ("antminer-s19j-pro", "Antminer S19j Pro", 100.0, 3050.0),
+    ("antminer-s19xp", "Antminer S19 XP", 140.0, 3010.0),
+    ("antminer-s21", "Antminer S21", 200.0, 3500.0),
+    ("antminer-s21-pro", "Antminer S21 Pro", 234.0, 3510.0),
+    ("whatsminer-m30s", "Whatsminer M30S", 88.0, 3344.0),
+    ("whatsminer-m50s", "Whatsminer M50S", 126.0, 3276.0),
+    ("whatsminer-m60s", "Whatsminer M60S", 172.0, 3422.0),
+    ("avalon-a1246", "AvalonMiner A1246", 90.0, 3420.0),
+    ("bitaxe-ultra", "Bitaxe Ultra", 0.5, 12.0),
+];
+
+/// Every built-in device.
+pub fn builtin_devices() -> Vec<Device> {
+    BUILTIN
+        .iter()
+        .map(|&(id, name, terahashes, power_watts)| Device {
+            id: id.to_string(),
+            name: name.to_string(),
+            hashrate: terahashes * 1e12,
+            power_watts,
+            cost: None,
+        })
+        .collect()
+}
+
+/// The built-in devices plus those in the hardware file at `path`, whose
+/// entries replace built-in ones with the same id.
+pub fn load_devices(path: &Path) -> Result<Vec<Device>> {
+    let text = fs::read_to_string(path)?;
+    let file_devices = parse_devices(&text).map_err(|(line, reason)| ReorgCalcError::DeviceFile {
+        path: path.display().to_string(),
+        line,
+        reason,
+    })?;
+    let mut devices = builtin_devices();
+    for device in file_devices {
+        match devices.iter_mut().find(|known| known.id.eq_ignore_ascii_case(&device.id)) {
+            Some(known) => *known = device,
+            None => devices.push(device),
+        }
+    }
+    Ok(devices)
+}
+
+/// Parse the `[[device]]` tables of a hardware file, failing with the line
+/// number and reason.
+pub fn parse_devices(text: &str) -> std::result::Result<Vec<Device>, (usize, String)> {
+    parse_tables(text, "device")?.into_iter().map(parse_device).collect()
+}
+
+fn parse_device(table: Table) -> std::result::Result<Device, (usize, String)> {
+    let mut id = None;
+    let mut name = None;
+    let mut hashrate = None;
+    let mut power_watts = None;
+    let mut cost = None;
+    for (line, key, value) in table.entries {
+        let at = |reason: String| (line, reason);
+        match key.as_str() {
+            "id" => id = Some(value.string(&key).map_err(at)?),
+            "name" => name = Some(value.string(&key).map_err(at)?),
+            "hashrate" => match value.number_or(&key, parse_hashrate).map_err(at)? {
+                rate if rate > 0.0 => hashrate = Some(rate),
+                _ => return Err(at("hashrate must be positive".to_string())),
+            },
+            "watts" => power_watts = Some(value.number(&key).map_err(at)?),
+            "cost" => cost = Some(value.number(&key).map_err(at)?),
+            _ => return Err(at(format!("unknown key {}; expected id, name, hashrate, watts or cost", key))),
+        }
+    }
+    let missing = |key: &str| (table.line, format!("device is missing {}", key));
+    let name: String = name.ok_or_else(|| missing("name"))?;
+    Ok(Device {
+        id: id.unwrap_or_else(|| name.to_ascii_lowercase().replace(' ', "-")),
+        name,
+        hashrate: hashrate.ok_or_else(|| missing("hashrate"))?,
+        power_watts: power_watts.ok_or_else(|| missing("watts"))?,
+        cost,
+    })
+}
+
+/// Look up `id` among `devices`, ignoring case.
+pub fn find_device<'a>(devices: &'a [Device], id: &str) -> Option<&'a Device> {
+    devices.iter().find(|device| device.id.eq_ignore_ascii_case(id))
+}
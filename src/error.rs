@@ -0,0 +1,202 @@
+//! Error type for the calculator library.
+
+#[cfg(feature = "rpc")]
+use bitcoincore_rpc::jsonrpc;
+use bitcoin::{BlockHash, Txid};
+use chrono::{DateTime, Utc};
+use std::{error, fmt, io};
+
+/// Errors produced by the calculator library.
+#[derive(Debug)]
+pub enum ReorgCalcError {
+    /// The node could not be reached (connection refused, timeout, bad URL).
+    NodeUnreachable(String),
+    /// The node rejected the RPC credentials.
+    AuthFailed,
+    /// The requested fork height is above the current chain tip.
+    ForkAboveTip { fork_height: u64, tip_height: u64 },
+    /// No block at the current height has that many confirmations.
+    InvalidConfirmations { confirmations: u64, tip_height: u64 },
+    /// Even the genesis block is timestamped after the requested fork time.
+    BeforeGenesis(DateTime<Utc>),
+    /// The transaction is not in a block on the active chain.
+    TransactionUnconfirmed(Txid),
+    /// The block is not on the active chain.
+    BlockReorgedOut(BlockHash),
+    /// An RPC call failed for another reason.
+    #[cfg(feature = "rpc")]
+    Rpc {
+        context: String,
+        source: bitcoincore_rpc::Error,
+    },
+    /// The node returned data the calculator could not interpret.
+    InvalidData(String),
+    /// A configuration value could not be parsed.
+    Config { key: String, value: String, reason: String },
+    /// A hashrate marketplace could not be queried.
+    Marketplace(String),
+    /// A BTC exchange rate could not be fetched.
+    PriceFeed(String),
+    /// The mainnet hashrate could not be fetched.
+    MainnetFeed(String),
+    /// A chart could not be drawn or written.
+    Plot(String),
+    /// The results database could not be read or written.
+    Database(String),
+    /// Points could not be written to InfluxDB.
+    Influx(String),
+    /// A message could not be published to the MQTT broker.
+    Mqtt(String),
+    /// An alert could not be delivered to its webhook.
+    Webhook(String),
+    /// A report template could not be read, parsed or rendered.
+    Template(String),
+    /// A scenario file could not be parsed.
+    ScenarioFile { path: String, line: usize, reason: String },
+    /// A hardware database file could not be parsed.
+    DeviceFile { path: String, line: usize, reason: String },
+    /// Reading or writing a local file failed.
+    Io(io::Error),
+    /// A background task panicked or was cancelled.
+    Task(String),
+    /// The data source cannot provide what was asked for.
+    Unsupported(&'static str),
+    /// A scan was stopped on request after saving its progress.
+    Interrupted { next_height: u64 },
+    /// Options are out of range, do not fit together or need a feature
+    /// this build lacks.
+    InvalidInput(String),
+    /// Another error, with what was being done when it happened.
+    Context {
+        context: String,
+        source: Box<ReorgCalcError>,
+    },
+}
+
+/// Result alias used throughout the library.
+pub type Result<T> = std::result::Result<T, ReorgCalcError>;
+
+impl fmt::Display for ReorgCalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReorgCalcError::NodeUnreachable(reason) => write!(f, "Failed to connect to Bitcoin node: {}", reason),
+            ReorgCalcError::AuthFailed => write!(f, "RPC authentication failed (check RPC_USER/RPC_PASSWORD)"),
+            ReorgCalcError::ForkAboveTip { fork_height, tip_height } => write!(
+                f,
+                "Fork height {} exceeds current chain height {}",
+                fork_height, tip_height
+            ),
+            ReorgCalcError::InvalidConfirmations { confirmations, tip_height } => write!(
+                f,
+                "No block has {} confirmations at chain height {}",
+                confirmations, tip_height
+            ),
+            ReorgCalcError::TransactionUnconfirmed(txid) => {
+                write!(f, "Transaction {} is not confirmed; there is nothing to reorg", txid)
+            }
+            ReorgCalcError::BlockReorgedOut(hash) => {
+                write!(f, "Block {} is no longer on the active chain; it was reorged out", hash)
+            }
+            ReorgCalcError::BeforeGenesis(time) => write!(
+                f,
+                "No block is timestamped at or before {}",
+                time.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            #[cfg(feature = "rpc")]
+            ReorgCalcError::Rpc { context, source } => write!(f, "{}: {}", context, source),
+            ReorgCalcError::InvalidData(reason) => write!(f, "Invalid data from node: {}", reason),
+            ReorgCalcError::Config { key, value, reason } => {
+                write!(f, "Invalid {} value '{}': {}", key, value, reason)
+            }
+            ReorgCalcError::Marketplace(reason) => write!(f, "Rental price lookup failed: {}", reason),
+            ReorgCalcError::PriceFeed(reason) => write!(f, "Exchange rate lookup failed: {}", reason),
+            ReorgCalcError::MainnetFeed(reason) => write!(f, "Mainnet hashrate lookup failed: {}", reason),
+            ReorgCalcError::Plot(reason) => write!(f, "Plot failed: {}", reason),
+            ReorgCalcError::Database(reason) => write!(f, "Results database error: {}", reason),
+            ReorgCalcError::Influx(reason) => write!(f, "InfluxDB write failed: {}", reason),
+            ReorgCalcError::Mqtt(reason) => write!(f, "MQTT publish failed: {}", reason),
+            ReorgCalcError::Webhook(reason) => write!(f, "Webhook delivery failed: {}", reason),
+            ReorgCalcError::Template(reason) => write!(f, "Template failed: {}", reason),
+            ReorgCalcError::ScenarioFile { path, line, reason } => write!(f, "{}:{}: {}", path, line, reason),
+            ReorgCalcError::DeviceFile { path, line, reason } => write!(f, "{}:{}: {}", path, line, reason),
+            ReorgCalcError::Io(e) => write!(f, "I/O error: {}", e),
+            ReorgCalcError::Task(reason) => write!(f, "Background task failed: {}", reason),
+            ReorgCalcError::Unsupported(what) => write!(f, "Not supported by this data source: {}", what),
+            ReorgCalcError::Interrupted { next_height } => write!(
+                f,
+                "Scan interrupted before block {}; progress saved, rerun with --resume to continue",
+                next_height
+            ),
+            ReorgCalcError::InvalidInput(reason) => write!(f, "{}", reason),
+            ReorgCalcError::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl error::Error for ReorgCalcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "rpc")]
+            ReorgCalcError::Rpc { source, .. } => Some(source),
+            ReorgCalcError::Io(e) => Some(e),
+            ReorgCalcError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReorgCalcError {
+    fn from(e: io::Error) -> Self {
+        ReorgCalcError::Io(e)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl ReorgCalcError {
+    /// Classify an RPC failure, pulling out connection and auth problems so
+    /// callers can tell them apart from ordinary call errors.
+    pub fn from_rpc(source: bitcoincore_rpc::Error, context: impl Into<String>) -> Self {
+        use jsonrpc::simple_http::Error as HttpError;
+
+        if let bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Transport(ref e)) = source {
+            match e.downcast_ref::<HttpError>() {
+                Some(HttpError::HttpErrorCode(401 | 403)) => return ReorgCalcError::AuthFailed,
+                Some(e @ (HttpError::SocketError(_) | HttpError::InvalidUrl { .. })) => {
+                    return ReorgCalcError::NodeUnreachable(e.to_string())
+                }
+                _ => {}
+            }
+        }
+        ReorgCalcError::Rpc {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+/// `anyhow`-style context for RPC results.
+#[cfg(feature = "rpc")]
+pub(crate) trait RpcContext<T> {
+    fn rpc_context(self, context: impl Into<String>) -> Result<T>;
+}
+
+#[cfg(feature = "rpc")]
+impl<T> RpcContext<T> for std::result::Result<T, bitcoincore_rpc::Error> {
+    fn rpc_context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| ReorgCalcError::from_rpc(e, context))
+    }
+}
+
+/// `anyhow`-style context for any result the library can fail with.
+pub(crate) trait ResultContext<T> {
+    fn context(self, context: impl Into<String>) -> Result<T>;
+}
+
+impl<T, E: Into<ReorgCalcError>> ResultContext<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| ReorgCalcError::Context {
+            context: context.into(),
+            source: Box::new(e.into()),
+        })
+    }
+}
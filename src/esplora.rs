@@ -0,0 +1,149 @@
+//! Esplora-style HTTP explorer backend.
+//!
+//! Reads the chain from the REST API served by esplora/electrs instances
+//! (such as mempool.space), so calculations can run without a local node.
+//! The HTTP client is pluggable through [`HttpGet`], letting hosts like a
+//! wasm runtime provide their own transport.
+
+use bitcoin::{block::Header, consensus::encode::deserialize_hex, BlockHash, Transaction, Txid, Work};
+use serde::Deserialize;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::source::{BlockSource, ChainTip};
+use crate::work::bits_to_difficulty;
+
+/// Minimal blocking HTTP GET used by [`EsploraSource`].
+pub trait HttpGet: Sync {
+    /// Fetch `url` and return the response body as text.
+    fn get(&self, url: &str) -> Result<String>;
+}
+
+/// [`HttpGet`] implementation backed by `minreq` (plain HTTP only).
+pub struct MinreqHttp;
+
+impl HttpGet for MinreqHttp {
+    fn get(&self, url: &str) -> Result<String> {
+        let response = minreq::get(url)
+            .with_timeout(30)
+            .send()
+            .map_err(|e| ReorgCalcError::NodeUnreachable(format!("{}: {}", url, e)))?;
+        if response.status_code != 200 {
+            return Err(ReorgCalcError::InvalidData(format!(
+                "{} returned HTTP {}",
+                url, response.status_code
+            )));
+        }
+        response.as_str()
+            .map(str::to_string)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("{}: {}", url, e)))
+    }
+}
+
+#[derive(Deserialize)]
+struct TransactionStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct BlockStatus {
+    in_best_chain: bool,
+    height: Option<u64>,
+}
+
+/// A [`BlockSource`] reading from an esplora REST API.
+///
+/// Esplora does not expose cumulative chainwork, so
+/// [`BlockSource::get_chainwork`] is unsupported; chain work is computed by
+/// scanning headers instead.
+pub struct EsploraSource<H = MinreqHttp> {
+    base_url: String,
+    http: H,
+}
+
+impl EsploraSource<MinreqHttp> {
+    /// Connect to the API at `base_url`, e.g. `http://localhost:3002/api`.
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http(base_url, MinreqHttp)
+    }
+}
+
+impl<H: HttpGet> EsploraSource<H> {
+    /// Use a custom HTTP transport.
+    pub fn with_http(base_url: &str, http: H) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<String> {
+        self.http.get(&format!("{}{}", self.base_url, path))
+    }
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash> {
+        parse_hash(&self.get(&format!("/block-height/{}", height))?)
+    }
+}
+
+impl<H: HttpGet> BlockSource for EsploraSource<H> {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let height = self.get("/blocks/tip/height")?;
+        let height = height.trim().parse()
+            .map_err(|_| ReorgCalcError::InvalidData(format!("bad tip height: {}", height)))?;
+        let hash = parse_hash(&self.get("/blocks/tip/hash")?)?;
+        Ok(ChainTip { height, hash })
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.block_hash(height)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        let hash = self.block_hash(height)?;
+        let hex = self.get(&format!("/block/{}/header", hash))?;
+        deserialize_hex(hex.trim())
+            .map_err(|e| ReorgCalcError::InvalidData(format!("bad header for height {}: {}", height, e)))
+    }
+
+    fn get_chainwork(&self, _height: u64) -> Result<Work> {
+        Err(ReorgCalcError::Unsupported("chainwork"))
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        let tip = self.get_tip()?;
+        Ok(bits_to_difficulty(self.get_bits(tip.height)?))
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        let body = self.get(&format!("/tx/{}/status", txid))?;
+        let status: TransactionStatus = serde_json::from_str(&body)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("bad status for transaction {}: {}", txid, e)))?;
+        Ok(status.block_height.filter(|_| status.confirmed))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        let body = self.get(&format!("/block/{}/status", hash))?;
+        let status: BlockStatus = serde_json::from_str(&body)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("bad status for block {}: {}", hash, e)))?;
+        Ok(status.height.filter(|_| status.in_best_chain))
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        heights
+            .iter()
+            .map(|&height| {
+                let hash = self.block_hash(height)?;
+                let txid = self.get(&format!("/block/{}/txid/0", hash))?;
+                let hex = self.get(&format!("/tx/{}/hex", txid.trim()))?;
+                deserialize_hex(hex.trim())
+                    .map_err(|e| ReorgCalcError::InvalidData(format!("bad coinbase for height {}: {}", height, e)))
+            })
+            .collect()
+    }
+}
+
+fn parse_hash(text: &str) -> Result<BlockHash> {
+    text.trim().parse()
+        .map_err(|_| ReorgCalcError::InvalidData(format!("bad block hash: {}", text.trim())))
+}
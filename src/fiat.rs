@@ -0,0 +1,63 @@
+//! BTC exchange rates for showing costs in fiat.
+//!
+//! Reads the current rate from CoinGecko's `simple/price` endpoint, or any
+//! URL answering in the same format. As with the marketplaces, the built-in
+//! HTTP client speaks plain HTTP only, so the HTTPS default needs a build of
+//! minreq with TLS, or a URL pointing at an HTTP mirror or proxy.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::budget::ExchangeRate;
+use crate::error::{ReorgCalcError, Result};
+
+/// CoinGecko endpoint the rate is read from; `{}` is the currency code.
+const COINGECKO_URL: &str =
+    "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies={}&include_last_updated_at=true";
+
+#[derive(Deserialize)]
+struct SimplePrice {
+    bitcoin: HashMap<String, f64>,
+}
+
+/// Fetch the price of a bitcoin in `currency` (e.g. `USD`, `EUR`) from `url`
+/// or CoinGecko.
+pub fn fetch_exchange_rate(currency: &str, url: Option<&str>) -> Result<ExchangeRate> {
+    let default_url = COINGECKO_URL.replace("{}", &currency.to_ascii_lowercase());
+    let url = url.unwrap_or(&default_url);
+    let response = minreq::get(url)
+        .with_timeout(30)
+        .send()
+        .map_err(|e| ReorgCalcError::PriceFeed(format!("{}: {}", url, e)))?;
+    if response.status_code != 200 {
+        return Err(ReorgCalcError::PriceFeed(format!("{} returned HTTP {}", url, response.status_code)));
+    }
+    let body = response.as_str().map_err(|e| ReorgCalcError::PriceFeed(format!("{}: {}", url, e)))?;
+    parse_simple_price(body, currency)
+}
+
+/// Read the rate for `currency` from a CoinGecko `simple/price` response.
+pub fn parse_simple_price(body: &str, currency: &str) -> Result<ExchangeRate> {
+    let bad_response = |reason: String| ReorgCalcError::PriceFeed(format!("unexpected CoinGecko response: {}", reason));
+    let price: SimplePrice = serde_json::from_str(body).map_err(|e| bad_response(e.to_string()))?;
+    let key = currency.to_ascii_lowercase();
+    let per_btc = price
+        .bitcoin
+        .get(&key)
+        .copied()
+        .filter(|rate| *rate > 0.0 && rate.is_finite())
+        .ok_or_else(|| bad_response(format!("no positive {} price", currency.to_ascii_uppercase())))?;
+    // Without an update time the rate is as of now
+    let timestamp = price
+        .bitcoin
+        .get("last_updated_at")
+        .and_then(|seconds| DateTime::from_timestamp(*seconds as i64, 0))
+        .unwrap_or_else(Utc::now);
+    Ok(ExchangeRate {
+        currency: currency.to_ascii_uppercase(),
+        per_btc,
+        source: "CoinGecko".to_string(),
+        timestamp,
+    })
+}
@@ -0,0 +1,164 @@
+//! In-memory chain for tests.
+
+use bitcoin::{
+    block::{Header, Version},
+    hashes::Hash,
+    BlockHash, CompactTarget, TxMerkleNode, Work,
+};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, ChainTip};
+
+/// Compact target of a difficulty-1 block on mainnet and the testnets.
+pub(crate) const DIFFICULTY_1: u32 = 0x1d00ffff;
+
+/// Compact target of a difficulty-8 block.
+pub(crate) const DIFFICULTY_8: u32 = 0x1c1fffe0;
+
+/// Timestamp of the first block.
+pub(crate) const GENESIS_TIME: u32 = 1_700_000_000;
+
+/// A chain of headers linked by hash, one block per `spacing` seconds.
+pub(crate) struct TestChain {
+    headers: Vec<Header>,
+    chainwork: Vec<Work>,
+    /// Whether `get_chainwork` answers, or the chain has to be scanned.
+    with_chainwork: bool,
+    /// Tips `get_tip` returns in turn; the last one stays.
+    tips: Mutex<VecDeque<ChainTip>>,
+    network: NetworkParams,
+}
+
+impl TestChain {
+    /// Chain whose block at each height has the compact target in `bits`.
+    pub(crate) fn new(bits: &[u32]) -> Self {
+        Self::with_spacing(bits, 600)
+    }
+
+    /// [`Self::new`] with blocks `spacing` seconds apart.
+    pub(crate) fn with_spacing(bits: &[u32], spacing: u32) -> Self {
+        let mut headers: Vec<Header> = Vec::with_capacity(bits.len());
+        for (height, &bits) in bits.iter().enumerate() {
+            headers.push(Header {
+                version: Version::ONE,
+                prev_blockhash: headers.last().map_or(BlockHash::all_zeros(), Header::block_hash),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: GENESIS_TIME + spacing * height as u32,
+                bits: CompactTarget::from_consensus(bits),
+                nonce: height as u32,
+            });
+        }
+        let chainwork = headers
+            .iter()
+            .scan(Work::from_be_bytes([0; 32]), |total, header| {
+                *total = *total + header.work();
+                Some(*total)
+            })
+            .collect();
+        let chain = Self {
+            headers,
+            chainwork,
+            with_chainwork: true,
+            tips: Mutex::new(VecDeque::new()),
+            network: NetworkParams::default(),
+        };
+        let tip = chain.tip_at(chain.headers.len() as u64 - 1);
+        chain.tips.lock().unwrap().push_back(tip);
+        chain
+    }
+
+    /// `len` blocks of the compact target `bits`.
+    pub(crate) fn uniform(len: usize, bits: u32) -> Self {
+        Self::new(&vec![bits; len])
+    }
+
+    /// Answer no chainwork lookups, as a headers-only source.
+    pub(crate) fn without_chainwork(mut self) -> Self {
+        self.with_chainwork = false;
+        self
+    }
+
+    /// Report the tips at `heights` in turn, the last one from then on.
+    pub(crate) fn with_tips(self, heights: &[u64]) -> Self {
+        let tips = heights.iter().map(|&height| self.tip_at(height)).collect();
+        self.with_tip_sequence(tips)
+    }
+
+    /// Report `tips` in turn, the last one from then on.
+    pub(crate) fn with_tip_sequence(self, tips: Vec<ChainTip>) -> Self {
+        *self.tips.lock().unwrap() = tips.into();
+        self
+    }
+
+    /// Tip at `height` of this chain.
+    pub(crate) fn tip_at(&self, height: u64) -> ChainTip {
+        ChainTip {
+            height,
+            hash: self.headers[height as usize].block_hash(),
+        }
+    }
+
+    /// Headers from the first block up.
+    pub(crate) fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+
+    fn check(&self, height: u64) -> Result<usize> {
+        match (height as usize) < self.headers.len() {
+            true => Ok(height as usize),
+            false => Err(ReorgCalcError::InvalidData(format!("no block at height {}", height))),
+        }
+    }
+}
+
+impl BlockSource for TestChain {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let mut tips = self.tips.lock().unwrap();
+        let tip = tips[0];
+        if tips.len() > 1 {
+            tips.pop_front();
+        }
+        Ok(tip)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        Ok(self.headers[self.check(height)?])
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        if !self.with_chainwork {
+            return Err(ReorgCalcError::Unsupported("chainwork"));
+        }
+        Ok(self.chainwork[self.check(height)?])
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        let tip = self.tips.lock().unwrap()[0].height;
+        Ok(self.network.difficulty(self.get_bits(tip)?))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        Ok(self.headers.iter().position(|header| header.block_hash() == *hash).map(|height| height as u64))
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        Ok(self.network)
+    }
+}
+
+/// A path under the temporary directory that no other test uses.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("reorg-calc-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Assert that `actual` equals `expected` up to float rounding.
+#[track_caller]
+pub(crate) fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() <= expected.abs() * 1e-12, "{} is not {}", actual, expected);
+}
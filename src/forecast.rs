@@ -0,0 +1,161 @@
+//! When a fork depth out of reach today comes within it.
+//!
+//! The work behind a fixed depth changes only as the chain moves on: blocks
+//! above the tip replace the oldest ones of the span at the difficulty of
+//! their own epoch, and the attacker mines at that difficulty too. A
+//! falling difficulty makes the same depth lighter at every retarget. The
+//! trend is measured over past epochs as a steady change per retarget, and
+//! the requirement projected at the first block of each epoch ahead until
+//! it fits within the target time.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::calc::{calculate_from_work, Adjustments, ReorgCalculation};
+use crate::error::{ReorgCalcError, Result};
+use crate::retarget::epoch_difficulty;
+use crate::source::BlockSource;
+use crate::work::calculate_chain_work;
+
+/// Past epochs the difficulty trend is measured over by default.
+pub const TREND_EPOCHS: u64 = 8;
+
+/// Retargets projected ahead by default.
+pub const FORECAST_EPOCHS: u64 = 26;
+
+/// How the difficulty has moved over recent retarget epochs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DifficultyTrend {
+    /// Complete epochs measured.
+    pub epochs: u64,
+    /// Difficulty of the current epoch.
+    pub difficulty: f64,
+    /// Factor difficulty changes by per retarget, from a log-linear fit
+    /// over the epochs; below 1 while it is falling.
+    pub change_per_epoch: f64,
+    /// Average span of an epoch, seconds.
+    pub epoch_seconds: f64,
+}
+
+/// Requirement for the depth against the chain projected at one epoch
+/// ahead.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectedEpoch {
+    /// Retargets from now; the next one is 1.
+    pub retargets: u64,
+    /// First block of the epoch.
+    pub start_height: u64,
+    /// When that block is expected.
+    pub start_time: DateTime<Utc>,
+    pub difficulty: f64,
+    /// Calculation for the depth below the epoch's first block, as of its
+    /// expected time.
+    pub calculation: ReorgCalculation,
+}
+
+/// Measure the difficulty trend over the last `epochs` complete retarget
+/// epochs of `source`.
+pub fn measure_difficulty_trend<S: BlockSource + ?Sized>(source: &S, epochs: u64) -> Result<DifficultyTrend> {
+    let params = source.get_network()?;
+    let interval = params.consensus().difficulty_adjustment_interval();
+    let tip_height = source.get_tip()?.height;
+    let current = tip_height / interval * interval;
+    let epochs = epochs.min(current / interval);
+    if epochs == 0 {
+        return Err(ReorgCalcError::InvalidData(
+            "the chain has no complete retarget epoch to measure the difficulty trend over".into(),
+        ));
+    }
+
+    let starts: Vec<u64> = (0..=epochs).map(|epoch| current - (epochs - epoch) * interval).collect();
+    let difficulties = starts
+        .iter()
+        .map(|&start| epoch_difficulty(source, start, tip_height, &params))
+        .collect::<Result<Vec<f64>>>()?;
+
+    // Least-squares slope of the log difficulty against the epoch number
+    let count = difficulties.len() as f64;
+    let mean_epoch = (count - 1.0) / 2.0;
+    let mean_log = difficulties.iter().map(|difficulty| difficulty.ln()).sum::<f64>() / count;
+    let (covariance, variance) =
+        difficulties.iter().enumerate().fold((0.0, 0.0), |(covariance, variance), (epoch, difficulty)| {
+            let offset = epoch as f64 - mean_epoch;
+            (covariance + offset * (difficulty.ln() - mean_log), variance + offset * offset)
+        });
+
+    let first = source.get_header(starts[0])?.time;
+    let last = source.get_header(current)?.time;
+    Ok(DifficultyTrend {
+        epochs,
+        difficulty: difficulties[epochs as usize],
+        change_per_epoch: (covariance / variance).exp(),
+        epoch_seconds: f64::from(last.saturating_sub(first)) / epochs as f64,
+    })
+}
+
+/// Project the requirement to reorg `depth` blocks at the first block of
+/// each of the next `horizon` epochs, stopping at the first that fits
+/// within `target_days`.
+///
+/// The rest of the current epoch is mined at its difficulty and
+/// every later epoch at `trend` times the one before. Blocks arrive at the
+/// trend's average pace from the tip's timestamp.
+pub fn forecast_viability<S: BlockSource + ?Sized>(
+    source: &S,
+    depth: u64,
+    hashrate: f64,
+    target_days: f64,
+    adjustments: &Adjustments,
+    trend: &DifficultyTrend,
+    horizon: u64,
+) -> Result<Vec<ProjectedEpoch>> {
+    let params = source.get_network()?;
+    let interval = params.consensus().difficulty_adjustment_interval();
+    let tip = source.get_tip()?;
+    let tip_time = i64::from(source.get_header(tip.height)?.time);
+    let difficulty = trend.difficulty;
+    let next = (tip.height / interval + 1) * interval;
+    let seconds_per_block = trend.epoch_seconds / interval as f64;
+    // Difficulty of the projected block at `height`, above the tip
+    let difficulty_at = |height: u64| match height.checked_sub(next) {
+        Some(above) => difficulty * trend.change_per_epoch.powi((above / interval + 1) as i32),
+        None => difficulty,
+    };
+
+    let mut projected = Vec::new();
+    for retargets in 1..=horizon {
+        let start_height = next + (retargets - 1) * interval;
+        let fork_height = (start_height + 1).saturating_sub(depth);
+        let mut total_work = 0.0;
+        if fork_height <= tip.height {
+            total_work += params.work_to_difficulty(calculate_chain_work(source, fork_height, tip.height)?);
+        }
+        // Projected blocks come in runs of one difficulty up to each retarget
+        let mut height = fork_height.max(tip.height + 1);
+        while height < start_height {
+            let run_end = ((height / interval + 1) * interval).min(start_height);
+            total_work += (run_end - height) as f64 * difficulty_at(height);
+            height = run_end;
+        }
+
+        let epoch_difficulty = difficulty_at(start_height);
+        let calc =
+            calculate_from_work(fork_height, start_height - 1, total_work, epoch_difficulty, hashrate, target_days, &params);
+        let mut calc = adjustments.apply_offline(calc, hashrate, target_days, &params)?;
+        let seconds = (start_height - tip.height) as f64 * seconds_per_block;
+        let start_time = DateTime::from_timestamp(tip_time + seconds as i64, 0).unwrap_or_default();
+        calc.timestamp = start_time;
+        let viable = calc.time_required_days <= target_days;
+        projected.push(ProjectedEpoch {
+            retargets,
+            start_height,
+            start_time,
+            difficulty: epoch_difficulty,
+            calculation: calc,
+        });
+        if viable {
+            break;
+        }
+    }
+    Ok(projected)
+}
@@ -0,0 +1,1694 @@
+//! Human-readable output of calculation results.
+//!
+//! Tables are aligned to their contents, with viable results in green and
+//! the rest in red when stdout is a terminal, unless `NO_COLOR` is set or
+//! [`set_color`] turned colour off.
+//!
+//! Hashrates, difficulties and chain work are shown with [`set_precision`]
+//! decimals, two by default. Difficulty and work also get thousands
+//! separators; the separator and decimal mark follow the numeric locale
+//! (`LC_ALL`, `LC_NUMERIC`, then `LANG`), so `de_DE` prints `1.234,56` and
+//! `fr_FR` `1 234,56`, with `1,234.56` for any other or no locale.
+
+use bitcoin::Work;
+use chrono::{DateTime, Utc};
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{Cell, CellAlignment, Color, Table};
+use serde::Serialize;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::backtest::BacktestDay;
+use crate::budget::{format_amount, Money};
+use crate::calc::{MainnetEquivalent, ReorgCalculation};
+use crate::error::{ReorgCalcError, Result};
+use crate::forecast::{DifficultyTrend, ProjectedEpoch};
+use crate::impact::TransactionImpact;
+use crate::network::NetworkParams;
+use crate::schedule::MAX_SCHEDULE_DAYS;
+use crate::simulate::MiningSimulation;
+use crate::timeline::{TimelineCheck, TimestampViolation};
+use crate::miners::MinerBlocks;
+use crate::source::BranchTip;
+use crate::storm::BlockStorms;
+use crate::track::{AttackProgress, TipWork};
+use crate::wallet::WalletImpact;
+use crate::work::{work_to_decimal, BlockIntervals, BlockWork, MinDifficultyBlocks, SpanEpoch};
+use crate::{say, SECONDS_PER_DAY};
+
+/// Attacker epochs listed before the rest are summarized.
+const MAX_EPOCH_LINES: usize = 10;
+
+/// Miners listed before the rest are summarized.
+const MAX_MINER_ROWS: usize = 20;
+
+/// Cleared by [`set_color`].
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// Turn coloured output on or off, e.g. for `--no-color`; even on, there is
+/// no colour unless stdout is a terminal and `NO_COLOR` is unset or empty.
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// Set by [`set_precision`].
+static PRECISION: AtomicUsize = AtomicUsize::new(2);
+
+/// Show hashrates, difficulties and chain work with `decimals` decimals,
+/// e.g. for `--precision`.
+pub fn set_precision(decimals: usize) {
+    PRECISION.store(decimals, Ordering::Relaxed);
+}
+
+fn precision() -> usize {
+    PRECISION.load(Ordering::Relaxed)
+}
+
+/// The thousands separator and decimal mark of the numeric locale.
+fn separators() -> (char, char) {
+    static SEPARATORS: OnceLock<(char, char)> = OnceLock::new();
+    *SEPARATORS.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .find_map(|key| env::var(key).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+        let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match language {
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" => ('.', ','),
+            // A narrow no-break space, so a figure never wraps apart
+            "fr" | "ru" | "sv" | "fi" | "nb" | "nn" | "no" | "cs" | "sk" | "pl" | "uk" | "hu" | "bg" => {
+                ('\u{202f}', ',')
+            }
+            _ => (',', '.'),
+        }
+    })
+}
+
+/// `value` with `decimals` decimals and the locale's decimal mark, its
+/// integer part grouped in thousands if `grouped`.
+fn localize(value: f64, decimals: usize, grouped: bool) -> String {
+    let text = format!("{:.*}", decimals, value);
+    if !value.is_finite() {
+        return text;
+    }
+    let (separator, mark) = separators();
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.as_str()),
+    };
+    let (integer, fraction) = match text.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+    let mut localized = sign.to_string();
+    if grouped {
+        localized.push_str(&group(integer, separator));
+    } else {
+        localized.push_str(integer);
+    }
+    if let Some(fraction) = fraction {
+        localized.push(mark);
+        localized.push_str(fraction);
+    }
+    localized
+}
+
+/// The digits of `integer` in groups of three, split by `separator`.
+fn group(integer: &str, separator: char) -> String {
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Format an exact number of hashes, e.g.
+/// `490,306,744,318,203,068 (log2_work=58.766678)`, the logarithm as the
+/// node logs it.
+pub fn format_hashes(hashes: Work) -> String {
+    format!("{} (log2_work={:.6})", group(&work_to_decimal(hashes), separators().0), hashes.log2())
+}
+
+/// Format a difficulty or an amount of chain work, e.g. `18,273,645.23`.
+pub fn format_figure(value: f64) -> String {
+    localize(value, precision(), true)
+}
+
+fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+        && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal()
+}
+
+/// A table with `header`, its columns after the first `left` right-aligned.
+fn table(header: &[String], left: usize) -> Table {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED).set_header(header);
+    if color() {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    for column in table.column_iter_mut().skip(left) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+    table
+}
+
+/// `text` in green when `viable`, in red otherwise.
+fn verdict(text: impl ToString, viable: bool) -> Cell {
+    Cell::new(text).fg(if viable { Color::Green } else { Color::Red })
+}
+
+/// `text` for a terminal line, in green or red when its viability is known
+/// and colour is on.
+fn paint(text: &str, viable: Option<bool>) -> String {
+    match viable {
+        Some(viable) if color() => format!("\x1b[{}m{}\x1b[0m", if viable { 32 } else { 31 }, text),
+        _ => text.to_string(),
+    }
+}
+
+fn header(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Machine-readable results of one run, with what produced them.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report<'a> {
+    /// Version of this crate.
+    pub version: &'static str,
+    pub generated_at: DateTime<Utc>,
+    /// Subcommand that ran, e.g. `calc`.
+    pub command: &'a str,
+    pub target_days: f64,
+    pub results: Vec<ReportEntry<'a>>,
+}
+
+/// A calculation and the hashrate it was made for.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportEntry<'a> {
+    pub hashrate: f64,
+    #[serde(flatten)]
+    pub calculation: &'a ReorgCalculation,
+}
+
+impl<'a> Report<'a> {
+    /// Report `runs`, each a calculation and its hashrate, made now.
+    pub fn new(command: &'a str, target_days: f64, runs: &'a [(ReorgCalculation, f64)]) -> Self {
+        Report {
+            version: env!("CARGO_PKG_VERSION"),
+            generated_at: Utc::now(),
+            command,
+            target_days,
+            results: runs
+                .iter()
+                .map(|(calculation, hashrate)| ReportEntry { hashrate: *hashrate, calculation })
+                .collect(),
+        }
+    }
+}
+
+/// Format a hashrate in H/s using the largest fitting unit (kH/s up to EH/s).
+pub fn format_hashrate(hashrate: f64) -> String {
+    let (scale, unit) = if hashrate >= 1e18 {
+        (1e18, "EH/s")
+    } else if hashrate >= 1e15 {
+        (1e15, "PH/s")
+    } else if hashrate >= 1e12 {
+        (1e12, "TH/s")
+    } else if hashrate >= 1e9 {
+        (1e9, "GH/s")
+    } else if hashrate >= 1e6 {
+        (1e6, "MH/s")
+    } else if hashrate >= 1e3 {
+        (1e3, "kH/s")
+    } else {
+        return format!("{:.0} H/s", hashrate);
+    };
+    format!("{} {}", localize(hashrate / scale, precision(), true), unit)
+}
+
+/// Format satoshis as bitcoin without trailing zeros, e.g. `0.5 BTC`.
+pub fn format_btc(sats: u64) -> String {
+    let text = format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000);
+    format!("{} BTC", text.trim_end_matches('0').trim_end_matches('.'))
+}
+
+/// Format a number of days with at most two decimals, e.g. `3` or `2.14`.
+pub fn format_days(days: f64) -> String {
+    let text = format!("{:.2}", days);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Format a span of seconds roughly, in the largest fitting unit up to
+/// days, e.g. `~4.2 seconds` or `~3.5 hours`.
+fn approximate_duration(seconds: f64) -> String {
+    let (value, unit) = if seconds >= SECONDS_PER_DAY {
+        (seconds / SECONDS_PER_DAY, "days")
+    } else if seconds >= 3600.0 {
+        (seconds / 3600.0, "hours")
+    } else if seconds >= 60.0 {
+        (seconds / 60.0, "minutes")
+    } else if seconds >= 1.0 {
+        (seconds, "seconds")
+    } else if seconds >= 1e-3 {
+        (seconds * 1e3, "milliseconds")
+    } else if seconds >= 1e-6 {
+        (seconds * 1e6, "microseconds")
+    } else {
+        return "under a microsecond".to_string();
+    };
+    format!("~{} {}", localize(value, 1, true), unit)
+}
+
+/// `~4.2 seconds of mainnet's 800.00 EH/s (mempool.space)`.
+fn mainnet_note(mainnet: &MainnetEquivalent) -> String {
+    format!(
+        "{} of mainnet's {} ({})",
+        approximate_duration(mainnet.seconds),
+        format_hashrate(mainnet.hashrate),
+        mainnet.source
+    )
+}
+
+/// Print a calculation to stdout.
+pub fn display_calculation(calc: &ReorgCalculation, provided_hashrate: f64) {
+    say!("\n=== Testnet4 Reorg Calculation ===");
+    say!("Timestamp: {}", calc.timestamp.format("%Y-%m-%d %H:%M:%S UTC"));
+    say!("Fork Height: {}", calc.fork_height);
+    say!("Current Height: {}", calc.current_height);
+    if let Some(note) = stale_note(calc) {
+        say!("Warning: {}", note);
+    }
+    say!("Blocks to Reorg: {}", calc.blocks_to_reorg);
+    say!("Total Existing Chain Work: {}", format_figure(calc.total_work));
+    if let Some(hashes) = calc.total_hashes {
+        say!("Expected Hashes: {}", format_hashes(hashes));
+    }
+    say!("Current Difficulty: {}", format_figure(calc.current_difficulty));
+    if let Some(network) = calc.network_hashrate {
+        say!("Network Hashrate: {} over the last {} blocks", format_hashrate(network.hashrate), network.blocks);
+    }
+    say!("New Chain Blocks Needed: {:.0}{}", calc.blocks_needed, margin_note(calc));
+    if let Some(growth) = calc.honest_growth {
+        say!(
+            "Honest Chain Growth: {:.0} blocks at {} during the attack",
+            growth.added_blocks,
+            format_hashrate(growth.hashrate)
+        );
+    }
+    if let Some(blocks) = &calc.min_difficulty {
+        display_min_difficulty(blocks);
+    }
+    if !calc.attacker_epochs.is_empty() {
+        say!("Attacker Difficulty by Epoch:");
+        for epoch in calc.attacker_epochs.iter().take(MAX_EPOCH_LINES) {
+            say!(
+                "  from block {}: {} blocks at {}",
+                epoch.start_height,
+                epoch.blocks,
+                format_figure(epoch.difficulty)
+            );
+        }
+        if calc.attacker_epochs.len() > MAX_EPOCH_LINES {
+            say!("  ... {} more epochs", calc.attacker_epochs.len() - MAX_EPOCH_LINES);
+        }
+    }
+    if let Some(overhead) = calc.overhead_percent {
+        say!(
+            "Blocks Mined Including Overhead: {:.0} ({}% for stale blocks and propagation)",
+            (calc.blocks_needed * (1.0 + overhead / 100.0)).ceil(),
+            overhead
+        );
+    }
+    if let Some(strategy) = &calc.min_difficulty_strategy {
+        say!(
+            "Min-Difficulty Blocks Insertable: {} ({} up front, {:.2} per real block)",
+            strategy.inserted_blocks, strategy.upfront_blocks, strategy.per_real_block
+        );
+        say!(
+            "Reorg Chain Length: {:.0} blocks; their work replaces {:.4} real blocks",
+            strategy.chain_blocks, strategy.real_blocks_saved
+        );
+    }
+    if let Some(timeline) = &calc.timeline {
+        display_timeline(timeline);
+    }
+    say!();
+    match calc.efficiency {
+        Some(efficiency) => say!(
+            "=== With Your Hashrate ({} at {}% efficiency) ===",
+            format_hashrate(provided_hashrate),
+            100.0 * efficiency
+        ),
+        None => say!("=== With Your Hashrate ({}) ===", format_hashrate(provided_hashrate)),
+    }
+    let viable = calc.target_days.map(|days| calc.time_required_days <= days);
+    if calc.time_required_hours.is_finite() {
+        let time = format!("{:.2} hours ({:.2} days)", calc.time_required_hours, calc.time_required_days);
+        say!("Time Required: {}", paint(&time, viable));
+    } else {
+        say!("Time Required: {}", paint("never (the honest network mines at least as fast)", viable));
+    }
+    if let Some(energy) = calc.energy.as_ref().filter(|energy| energy.kwh.is_finite()) {
+        say!("Energy: {:.2} kWh at {:.2} J/TH", energy.kwh, energy.joules_per_th);
+        if let (Some(cost), Some(price)) = (energy.cost(), &energy.price_per_kwh) {
+            say!("Electricity Cost: {}{} at {} per kWh", cost, in_fiat(calc, &cost), price);
+        }
+    }
+    if let Some(simulation) = &calc.simulation {
+        display_simulation(simulation);
+    }
+    if !calc.schedule.is_empty() {
+        display_schedule(calc);
+    }
+    if let Some(race) = &calc.race {
+        say!(
+            "Chance of Ever Overtaking ({} honest, {:.1}% of the hashrate, {:.0} blocks behind): {}",
+            format_hashrate(race.honest_hashrate),
+            100.0 * race.attacker_share,
+            race.deficit_blocks,
+            format_probability(race.probability)
+        );
+    }
+    say!();
+    if calc.target_hashrates.is_empty() {
+        match calc.target_days {
+            Some(days) => say!("=== For Target Time ({} days) ===", format_days(days)),
+            None => say!("=== For Target Time ==="),
+        }
+        say!(
+            "Hashrate Required: {}{}{}",
+            format_hashrate(calc.hashrate_required),
+            machines(calc, calc.hashrate_required),
+            rent(calc, calc.hashrate_required)
+        );
+    } else {
+        say!("=== For Target Times ===");
+        for target in &calc.target_hashrates {
+            say!(
+                "Hashrate Required ({} days): {}{}{}",
+                format_days(target.days),
+                format_hashrate(target.hashrate),
+                machines(calc, target.hashrate),
+                rent(calc, target.hashrate)
+            );
+        }
+    }
+    
+    if let Some(mainnet) = &calc.mainnet {
+        say!("\nMainnet Equivalent: {}", mainnet_note(mainnet));
+    }
+    
+    if let Some(next) = &calc.next_retarget {
+        say!("\n=== Next Retarget ===");
+        say!(
+            "Height: {} in {} blocks, expected around {}",
+            next.height,
+            next.blocks_remaining,
+            next.eta.format("%Y-%m-%d %H:%M UTC")
+        );
+        say!(
+            "Projected Difficulty: {} ({:+.2}% from {})",
+            format_figure(next.projected_difficulty),
+            100.0 * (next.projected_difficulty / next.difficulty - 1.0),
+            format_figure(next.difficulty)
+        );
+        say!("Blocks Needed: {:.0} (now {:.0})", next.blocks_needed, calc.blocks_needed);
+        let viable = calc.target_days.map(|days| next.time_required_days <= days);
+        say!("Time Required: {}", paint(&format!("{:.2} days", next.time_required_days), viable));
+        say!("Hashrate Required: {}", format_hashrate(next.hashrate_required));
+    }
+    
+    if calc.blocks_needed <= 1.0 {
+        say!("\nNote: A single high-difficulty block may suffice due to Testnet4's 20-minute rule.");
+    }
+}
+
+/// Print each participant's part of a group attack and whether the group
+/// finishes within `target_days`.
+pub fn display_group(calc: &ReorgCalculation, target_days: f64) {
+    let combined: f64 = calc.group.iter().map(|member| member.hashrate).sum();
+    say!("\n=== Group Split ({} participants, {} combined) ===", calc.group.len(), format_hashrate(combined));
+    let mut table = table(&header(&["Participant", "Hashrate", "Share", "Work", "Expected Blocks"]), 1);
+    for member in &calc.group {
+        table.add_row(vec![
+            member.name.clone(),
+            format_hashrate(member.hashrate),
+            format!("{:.1}%", 100.0 * member.share),
+            format_figure(member.work),
+            format!("{:.1}", member.expected_blocks),
+        ]);
+    }
+    say!("{}", table);
+    let verdict = if calc.time_required_days <= target_days {
+        format!("yes, {} of {} days", format_days(calc.time_required_days), format_days(target_days))
+    } else if calc.time_required_days.is_finite() {
+        format!(
+            "no, {} days against {} (needs {} combined)",
+            format_days(calc.time_required_days),
+            format_days(target_days),
+            format_hashrate(calc.hashrate_required)
+        )
+    } else {
+        "no, the group never catches up".to_string()
+    };
+    say!("Meets Deadline: {}", paint(&verdict, Some(calc.time_required_days <= target_days)));
+}
+
+/// Print how `calc` moved since `previous`, an earlier calculation for the
+/// same fork target, e.g. the last cycle of `watch`.
+pub fn display_changes(previous: &ReorgCalculation, calc: &ReorgCalculation) {
+    let elapsed = (calc.timestamp - previous.timestamp).num_milliseconds() as f64 / 1000.0;
+    say!("\n=== Since the Previous Run ({} ago) ===", approximate_duration(elapsed));
+    let count = |value: f64| format!("{:.0}", value);
+    let height = |calc: &ReorgCalculation| calc.current_height as f64;
+    say!("Current Height: {} ({})", calc.current_height, change(height(previous), height(calc), count));
+    if calc.fork_height != previous.fork_height {
+        let fork = |calc: &ReorgCalculation| calc.fork_height as f64;
+        say!("Fork Height: {} ({})", calc.fork_height, change(fork(previous), fork(calc), count));
+    }
+    let depth = |calc: &ReorgCalculation| calc.blocks_to_reorg as f64;
+    say!("Blocks to Reorg: {} ({})", calc.blocks_to_reorg, change(depth(previous), depth(calc), count));
+    say!(
+        "Total Existing Chain Work: {} ({})",
+        format_figure(calc.total_work),
+        change(previous.total_work, calc.total_work, format_figure)
+    );
+    say!(
+        "New Chain Blocks Needed: {:.0} ({})",
+        calc.blocks_needed,
+        change(previous.blocks_needed, calc.blocks_needed, count)
+    );
+    say!(
+        "Time Required: {} days ({})",
+        format_days(calc.time_required_days),
+        change(previous.time_required_days, calc.time_required_days, format_days)
+    );
+    if let Some(days) = calc.target_days {
+        say!(
+            "Hashrate Required ({} days): {} ({})",
+            format_days(days),
+            format_hashrate(calc.hashrate_required),
+            change(previous.hashrate_required, calc.hashrate_required, format_hashrate)
+        );
+    }
+}
+
+/// Print how much deeper the fork point of `calc` got buried since `first`,
+/// the calculation for it at the start of a `watch`: the blocks, work and
+/// hashrate added, and the rate blocks were added at.
+pub fn display_burial(first: &ReorgCalculation, calc: &ReorgCalculation) {
+    let hours = (calc.timestamp - first.timestamp).num_milliseconds() as f64 / 3.6e6;
+    let blocks = calc.blocks_to_reorg.saturating_sub(first.blocks_to_reorg);
+    say!(
+        "\n=== Burial of Block {} Since {} ===",
+        calc.fork_height,
+        first.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    say!(
+        "Blocks to Reorg: {} -> {} (+{}, {} blocks per hour)",
+        first.blocks_to_reorg,
+        calc.blocks_to_reorg,
+        blocks,
+        localize(blocks as f64 / hours, 2, true)
+    );
+    say!(
+        "Total Existing Chain Work: {} -> {} ({})",
+        format_figure(first.total_work),
+        format_figure(calc.total_work),
+        growth(first.total_work, calc.total_work)
+    );
+    if let Some(days) = calc.target_days {
+        say!(
+            "Hashrate Required ({} days): {} -> {} ({})",
+            format_days(days),
+            format_hashrate(first.hashrate_required),
+            format_hashrate(calc.hashrate_required),
+            growth(first.hashrate_required, calc.hashrate_required)
+        );
+    }
+}
+
+/// Print where the attack chain of `progress` stands, with when it is
+/// projected to overtake mining at `hashrate` against the public chain
+/// growing at `honest_hashrate`.
+pub fn display_progress(progress: &AttackProgress, hashrate: f64, honest_hashrate: f64, params: &NetworkParams) {
+    say!("\n=== Attack Progress at {} ===", progress.time.format("%Y-%m-%d %H:%M:%S UTC"));
+    say!(
+        "Fork Height: {} (public tip {}, attack tip {})",
+        progress.fork_height, progress.public_height, progress.attack_height
+    );
+    say!("Public Chain Work Since Fork: {}", format_figure(progress.public_work));
+    say!(
+        "Attack Chain Work Since Fork: {} ({}% complete)",
+        format_figure(progress.attack_work),
+        localize(progress.percent_complete(), 1, true)
+    );
+    if progress.overtaken() {
+        let lead = format!("the attack chain leads by {} work", format_figure(-progress.work_gap()));
+        say!("Overtaken: {}", paint(&lead, Some(true)));
+        return;
+    }
+    say!("Work Gap: {}", format_figure(progress.work_gap()));
+    say!(
+        "Blocks Remaining: {} at difficulty {}",
+        progress.blocks_remaining(),
+        format_figure(progress.attack_difficulty)
+    );
+    let rates = format!("{} against {} honest", format_hashrate(hashrate), format_hashrate(honest_hashrate));
+    match progress.days_to_overtake(hashrate, honest_hashrate, params) {
+        Some(days) => {
+            // Gaps far beyond any calendar have no date
+            let at = chrono::Duration::try_milliseconds((days * SECONDS_PER_DAY * 1000.0) as i64)
+                .and_then(|wait| progress.time.checked_add_signed(wait))
+                .map_or_else(String::new, |at| format!(" ({})", at.format("%Y-%m-%d %H:%M UTC")));
+            say!(
+                "Projected Overtake: in {}{} at {}",
+                approximate_duration(days * SECONDS_PER_DAY),
+                at,
+                rates
+            );
+        }
+        None => say!("Projected Overtake: {} at {}", paint("never, the gap only grows", Some(false)), rates),
+    }
+}
+
+/// Print the tips of two nodes, each named by the endpoint it was read
+/// from, and the work one is behind the other; the chains split after
+/// `fork_height - 1`, the last block both have.
+pub fn display_tip_comparison(nodes: [(&str, &TipWork); 2], fork_height: u64, params: &NetworkParams) {
+    say!("\n=== Tip Comparison ===");
+    for (name, node) in nodes {
+        say!("{}", name);
+        say!("  Height: {}", node.tip.height);
+        say!("  Hash: {}", node.tip.hash);
+        say!("  Chain Work: {}", format_hashes(node.chainwork));
+    }
+    let [(first_name, first), (second_name, second)] = nodes;
+    if first.tip.hash == second.tip.hash {
+        say!("Same Tip: the nodes agree");
+        return;
+    }
+    let last_common = fork_height.saturating_sub(1);
+    let (first_past, second_past) = (first.tip.height - last_common, second.tip.height - last_common);
+    if first_past == 0 || second_past == 0 {
+        let (lagging, blocks) = if first_past == 0 { ("first", second_past) } else { ("second", first_past) };
+        say!(
+            "Last Common Block: {}, the {} tip; the other node is {} blocks further on the same chain",
+            last_common, lagging, blocks
+        );
+    } else {
+        say!(
+            "Last Common Block: {} ({} blocks below the first tip, {} below the second)",
+            last_common, first_past, second_past
+        );
+    }
+    if first.chainwork == second.chainwork {
+        say!("Work Deficit: none, both tips have the same work; each node keeps the one it saw first");
+        return;
+    }
+    let (ahead_name, behind_name, behind, deficit) = if first.chainwork > second.chainwork {
+        (first_name, second_name, second, first.chainwork - second.chainwork)
+    } else {
+        (second_name, first_name, first, second.chainwork - first.chainwork)
+    };
+    let difficulty = params.work_to_difficulty(deficit);
+    say!("Ahead: {}", ahead_name);
+    say!("Work Deficit of {}: {}", behind_name, format_hashes(deficit));
+    say!(
+        "  = {} difficulty, {} blocks at its next difficulty ({}) to match",
+        format_figure(difficulty),
+        localize((difficulty / behind.difficulty).ceil(), 0, true),
+        format_figure(behind.difficulty)
+    );
+}
+
+/// `+12.5%`: the growth from `first` to `current`.
+fn growth(first: f64, current: f64) -> String {
+    if !(first > 0.0 && first.is_finite() && current.is_finite()) {
+        return "n/a".to_string();
+    }
+    format!("{:+.1}%", 100.0 * (current / first - 1.0))
+}
+
+/// `+1.5`, `-2 GH/s` or `unchanged`: the move from `previous` to `current`,
+/// its size formatted with `format`.
+fn change(previous: f64, current: f64, format: impl Fn(f64) -> String) -> String {
+    if previous == current {
+        return "unchanged".to_string();
+    }
+    if !previous.is_finite() {
+        return "was infinite".to_string();
+    }
+    if !current.is_finite() {
+        return format!("was {}", format(previous));
+    }
+    let delta = current - previous;
+    let size = format(delta.abs());
+    if size == format(0.0) {
+        return "unchanged".to_string();
+    }
+    format!("{}{}", if delta > 0.0 { "+" } else { "-" }, size)
+}
+
+/// Columns of [`write_csv`], in order. New columns are only ever added at
+/// the end.
+pub const CSV_COLUMNS: &[&str] = &[
+    "fork_height",
+    "current_height",
+    "blocks_to_reorg",
+    "total_work",
+    "current_difficulty",
+    "blocks_needed",
+    "hashrate",
+    "time_required_hours",
+    "time_required_days",
+    "target_days",
+    "hashrate_required",
+    "viable",
+    "timestamp",
+];
+
+/// Write `runs`, each a calculation and the hashrate it was made for, as CSV
+/// with a header row of [`CSV_COLUMNS`].
+pub fn write_csv(mut writer: impl Write, runs: &[(ReorgCalculation, f64)]) -> Result<()> {
+    writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+    for (calc, hashrate) in runs {
+        let viable = calc.target_days.map_or(String::new(), |days| (calc.time_required_days <= days).to_string());
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            calc.fork_height,
+            calc.current_height,
+            calc.blocks_to_reorg,
+            calc.total_work,
+            calc.current_difficulty,
+            calc.blocks_needed,
+            hashrate,
+            calc.time_required_hours,
+            calc.time_required_days,
+            calc.target_days.map_or(String::new(), |days| days.to_string()),
+            calc.hashrate_required,
+            viable,
+            calc.timestamp.to_rfc3339()
+        )?;
+    }
+    Ok(())
+}
+
+/// Columns of [`write_block_csv`], in order.
+pub const BLOCK_CSV_COLUMNS: &[&str] = &["height", "bits", "difficulty", "cumulative_work", "work_share"];
+
+/// Write `blocks` as CSV with a header row of [`BLOCK_CSV_COLUMNS`].
+pub fn write_block_csv(mut writer: impl Write, blocks: &[BlockWork]) -> Result<()> {
+    writeln!(writer, "{}", BLOCK_CSV_COLUMNS.join(","))?;
+    for block in blocks {
+        writeln!(
+            writer,
+            "{},{:08x},{},{},{}",
+            block.height, block.bits, block.difficulty, block.cumulative_work, block.work_share
+        )?;
+    }
+    Ok(())
+}
+
+/// Write `blocks` as JSON lines, one object per block.
+pub fn write_block_jsonl(mut writer: impl Write, blocks: &[BlockWork]) -> Result<()> {
+    for block in blocks {
+        let line = serde_json::to_string(block).map_err(|e| ReorgCalcError::InvalidData(e.to_string()))?;
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// `calc` as one line of space-separated `key=value` pairs, e.g.
+/// `fork=97000 blocks_needed=412 days=2.31 hashrate_req=1.20e15
+/// viable=true`, for scripts; `viable` is left out without a target time.
+pub fn summary_line(calc: &ReorgCalculation) -> String {
+    let mut line = format!(
+        "fork={} blocks_needed={:.0} days={:.2} hashrate_req={:.2e}",
+        calc.fork_height, calc.blocks_needed, calc.time_required_days, calc.hashrate_required
+    );
+    if let Some(days) = calc.target_days {
+        line += &format!(" viable={}", calc.time_required_days <= days);
+    }
+    line
+}
+
+/// Write `runs` as one [`summary_line`] each.
+pub fn write_summary(mut writer: impl Write, runs: &[(ReorgCalculation, f64)]) -> Result<()> {
+    for (calc, _) in runs {
+        writeln!(writer, "{}", summary_line(calc))?;
+    }
+    Ok(())
+}
+
+/// Write `runs`, each a calculation and the hashrate it was made for, as a
+/// Markdown summary: the parameters, a results table and the caveats that
+/// apply, for pasting into issues and notes.
+pub fn write_markdown(
+    mut writer: impl Write,
+    command: &str,
+    target_days: f64,
+    runs: &[(ReorgCalculation, f64)],
+) -> Result<()> {
+    let Some((first, first_hashrate)) = runs.first() else {
+        writeln!(writer, "# Testnet4 Reorg Calculation\n\nNo results.")?;
+        return Ok(());
+    };
+    let one_hashrate = runs.iter().all(|(_, hashrate)| hashrate == first_hashrate);
+    writeln!(writer, "# Testnet4 Reorg Calculation\n")?;
+    writeln!(
+        writer,
+        "Generated {} by testnet4-reorg-calculator {} (`{}`).\n",
+        Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+        env!("CARGO_PKG_VERSION"),
+        command
+    )?;
+    
+    writeln!(writer, "## Parameters\n")?;
+    writeln!(writer, "| Parameter | Value |\n| --- | --- |")?;
+    writeln!(writer, "| Current height | {} |", first.current_height)?;
+    if let Some(tip_hash) = first.tip_hash {
+        writeln!(writer, "| Tip | `{}` |", tip_hash)?;
+    }
+    writeln!(writer, "| Current difficulty | {:.2} |", first.current_difficulty)?;
+    if one_hashrate {
+        writeln!(writer, "| Hashrate | {} |", format_hashrate(*first_hashrate))?;
+    }
+    writeln!(writer, "| Target time | {} days |", format_days(target_days))?;
+    if !first.margin.is_zero() {
+        writeln!(writer, "| Margin | {} blocks + {}% |", first.margin.blocks, first.margin.percent)?;
+    }
+    if let Some(growth) = &first.honest_growth {
+        writeln!(writer, "| Honest hashrate | {} |", format_hashrate(growth.hashrate))?;
+    }
+    if let Some(efficiency) = first.efficiency {
+        writeln!(writer, "| Efficiency | {:.0}% |", efficiency * 100.0)?;
+    }
+    if let Some(overhead) = first.overhead_percent {
+        writeln!(writer, "| Overhead | {}% |", overhead)?;
+    }
+    
+    writeln!(writer, "\n## Results\n")?;
+    write!(writer, "| Fork height | Blocks to reorg | Blocks needed |")?;
+    if !one_hashrate {
+        write!(writer, " Hashrate |")?;
+    }
+    writeln!(writer, " Time | Hashrate for {} days | Viable |", format_days(target_days))?;
+    writeln!(writer, "| ---: | ---: | ---: |{} ---: | ---: | :---: |", if one_hashrate { "" } else { " ---: |" })?;
+    for (calc, hashrate) in runs {
+        write!(writer, "| {} | {} | {:.2} |", calc.fork_height, calc.blocks_to_reorg, calc.blocks_needed)?;
+        if !one_hashrate {
+            write!(writer, " {} |", format_hashrate(*hashrate))?;
+        }
+        let time = if !calc.time_required_days.is_finite() {
+            "never".to_string()
+        } else if calc.time_required_days < 1.0 {
+            format!("{:.2} hours", calc.time_required_hours)
+        } else {
+            format!("{} days", format_days(calc.time_required_days))
+        };
+        let viable = if calc.time_required_days <= target_days { "yes" } else { "no" };
+        writeln!(writer, " {} | {} | {} |", time, format_hashrate(calc.hashrate_required), viable)?;
+    }
+    
+    for (calc, _) in runs.iter().filter(|(calc, _)| !calc.schedule.is_empty()) {
+        writeln!(writer, "\n## Schedule from fork height {}\n", calc.fork_height)?;
+        writeln!(writer, "| Day | Ends (UTC) | Blocks mined | Work done |\n| ---: | --- | ---: | ---: |")?;
+        for day in &calc.schedule {
+            writeln!(
+                writer,
+                "| {} | {} | {:.0} | {:.1}% |",
+                day.day,
+                day.ends_at.format("%Y-%m-%d %H:%M"),
+                day.blocks,
+                100.0 * day.work_share
+            )?;
+        }
+    }
+    
+    writeln!(writer, "\n## Caveats\n")?;
+    writeln!(
+        writer,
+        "- Testnet4's 20-minute rule lets a block be mined at difficulty 1 when its timestamp is more \
+         than 20 minutes after its parent's, except for the first block of a retarget period. Such \
+         blocks add almost no work, so they can lengthen the attacker's chain but not shorten the \
+         attack; the figures above count work only."
+    )?;
+    if let Some(blocks) = &first.min_difficulty {
+        writeln!(
+            writer,
+            "- {} of the {} replaced blocks were mined at difficulty 1, {:.4}% of their work.",
+            blocks.count,
+            blocks.span_blocks,
+            blocks.work_share * 100.0
+        )?;
+    }
+    if let Some(impact) = first.wallet.as_ref().filter(|impact| !impact.transactions.is_empty()) {
+        writeln!(
+            writer,
+            "- {} transactions of wallet '{}' are in the replaced blocks and lose their confirmations; mined \
+             again, they would have at most {}, fewer than now for {} of them.",
+            impact.transactions.len(),
+            impact.wallet,
+            impact.new_chain_confirmations,
+            impact.below_current()
+        )?;
+    }
+    if let Some(impact) = &first.transactions {
+        writeln!(
+            writer,
+            "- The replaced blocks confirm {} transactions with {} outputs moving {}; the reorg sends them \
+             back to the mempool, and any the new chain leaves out or double-spends never confirm.",
+            impact.transactions,
+            impact.outputs,
+            format_btc(impact.value)
+        )?;
+    }
+    if let Some(top) = first.miners.first() {
+        writeln!(
+            writer,
+            "- The replaced blocks came from {} miners; {} mined the most, {} of them ({:.1}%), and loses \
+             their rewards to the reorg.",
+            first.miners.len(),
+            top.miner,
+            top.blocks,
+            100.0 * top.share
+        )?;
+    }
+    if let Some(storms) = first.storms.as_ref().filter(|storms| storms.storm_blocks > 0) {
+        writeln!(
+            writer,
+            "- {} of the replaced blocks are in {} block storms, runs of difficulty-1 blocks mined in seconds. \
+             They hold {:.4}% of the work, so the reorg costs about what the other {} blocks do.",
+            storms.storm_blocks,
+            storms.storms.len(),
+            storms.work_share * 100.0,
+            storms.other_blocks()
+        )?;
+    }
+    if let Some(strategy) = &first.min_difficulty_strategy {
+        writeln!(
+            writer,
+            "- Inserting {} difficulty-1 blocks would replace only {:.4} real blocks' worth of work.",
+            strategy.inserted_blocks, strategy.real_blocks_saved
+        )?;
+    }
+    if first.honest_growth.is_none() {
+        writeln!(writer, "- The honest chain is assumed to stop growing while the attack runs.")?;
+    }
+    if first.simulation.is_none() {
+        writeln!(writer, "- Times are expected values; block discovery is random and real runs vary widely.")?;
+    }
+    for (calc, _) in runs {
+        if let Some(note) = stale_note(calc) {
+            writeln!(writer, "- Fork height {}: {}.", calc.fork_height, note)?;
+        }
+    }
+    Ok(())
+}
+
+/// ` (≈ X/day to rent on ...)` for `hashrate` when `calc` has a rental
+/// price, otherwise nothing.
+/// ` (≈ 9× Antminer S21)` for `hashrate` when `calc` has a reference
+/// device, otherwise nothing.
+fn machines(calc: &ReorgCalculation, hashrate: f64) -> String {
+    calc.reference_device.as_ref().map_or_else(String::new, |device| {
+        let units = hashrate / device.hashrate;
+        if !units.is_finite() {
+            String::new()
+        } else if units >= 1.0 {
+            format!(" (≈ {}× {})", localize(units.ceil(), 0, true), device.name)
+        } else if units >= 1e-3 {
+            format!(" (≈ {}% of one {})", localize(100.0 * units, 1, false), device.name)
+        } else {
+            format!(" (under 0.1% of one {})", device.name)
+        }
+    })
+}
+
+fn rent(calc: &ReorgCalculation, hashrate: f64) -> String {
+    calc.rental_price.as_ref().map_or_else(String::new, |price| {
+        let per_day = Money {
+            amount: price.cost_per_day(hashrate),
+            currency: Some(price.currency.clone()),
+        };
+        let fiat = calc.exchange_rate.as_ref().and_then(|rate| rate.to_fiat(&per_day));
+        match fiat {
+            Some(fiat) => format!(" (≈ {}/day or {}/day to rent on {})", per_day, fiat, price.source),
+            None => format!(" (≈ {}/day to rent on {})", per_day, price.source),
+        }
+    })
+}
+
+/// ` (≈ X USD)` for a BTC `amount` when `calc` has an exchange rate,
+/// otherwise nothing.
+pub fn in_fiat(calc: &ReorgCalculation, amount: &Money) -> String {
+    calc.exchange_rate
+        .as_ref()
+        .and_then(|rate| rate.to_fiat(amount))
+        .map_or_else(String::new, |fiat| format!(" (≈ {})", fiat))
+}
+
+/// Print a sweep of fork heights as a table, marking those that can be
+/// reorged within `target_days`.
+pub fn display_sweep(calculations: &[ReorgCalculation], provided_hashrate: f64, target_days: f64) {
+    say!("\n=== Fork Height Sweep ({}) ===", format_hashrate(provided_hashrate));
+    let mut header = header(&["Fork Height", "Depth", "Blocks Needed", "Time (days)"]);
+    header.push(format!("Hashrate ({} days)", format_days(target_days)));
+    header.push("Viable".to_string());
+    let mut table = table(&header, 0);
+    for calc in calculations {
+        let viable = calc.time_required_days <= target_days;
+        table.add_row(vec![
+            Cell::new(calc.fork_height),
+            Cell::new(calc.blocks_to_reorg),
+            Cell::new(format!("{:.0}", calc.blocks_needed)),
+            Cell::new(format!("{:.2}", calc.time_required_days)),
+            Cell::new(format_hashrate(calc.hashrate_required)),
+            verdict(if viable { "yes" } else { "no" }, viable),
+        ]);
+    }
+    say!("{}", table);
+}
+
+/// Print the deepest fork `provided_hashrate` could have reorged within
+/// `target_days` on each day of a backtest, and the day it was deepest.
+pub fn display_backtest(days: &[BacktestDay], provided_hashrate: f64, target_days: f64) {
+    say!(
+        "\n=== Backtest: Deepest Reorg in {} Days at {} ===",
+        format_days(target_days),
+        format_hashrate(provided_hashrate)
+    );
+    if days.is_empty() {
+        say!("No days in the range had blocks yet");
+        return;
+    }
+    let header = header(&["Date", "Tip Height", "Deepest Fork", "Blocks to Reorg", "Time (days)"]);
+    let mut table = table(&header, 1);
+    for day in days {
+        let mut row = vec![Cell::new(day.date), Cell::new(day.tip_height)];
+        match &day.deepest {
+            Some(calc) => row.extend([
+                Cell::new(calc.fork_height),
+                verdict(calc.blocks_to_reorg, true),
+                Cell::new(format!("{:.2}", calc.time_required_days)),
+            ]),
+            None => row.extend([Cell::new("none"), verdict(0, false), Cell::new("")]),
+        }
+        table.add_row(row);
+    }
+    say!("{}", table);
+    let depth = |day: &BacktestDay| day.deepest.as_ref().map_or(0, |calc| calc.blocks_to_reorg);
+    let most = days.iter().max_by_key(|day| depth(day)).expect("days is not empty");
+    let least = days.iter().min_by_key(|day| depth(day)).expect("days is not empty");
+    say!(
+        "Most exposed: {} ({} blocks); least exposed: {} ({} blocks)",
+        most.date,
+        depth(most),
+        least.date,
+        depth(least)
+    );
+}
+
+/// Print the difficulty `trend` and the requirement for `depth` blocks
+/// at each retarget `projected` ahead, with the first at which
+/// `provided_hashrate` reorgs them within `target_days`.
+pub fn display_forecast(
+    depth: u64,
+    trend: &DifficultyTrend,
+    projected: &[ProjectedEpoch],
+    provided_hashrate: f64,
+    target_days: f64,
+) {
+    say!(
+        "\n=== Forecast: {} Blocks in {} Days at {} ===",
+        depth,
+        format_days(target_days),
+        format_hashrate(provided_hashrate)
+    );
+    say!(
+        "Difficulty trend: {:+.2}% per retarget over the last {} epochs (one every {:.1} days), now {}",
+        100.0 * (trend.change_per_epoch - 1.0),
+        trend.epochs,
+        trend.epoch_seconds / SECONDS_PER_DAY,
+        format_figure(trend.difficulty)
+    );
+    let header = header(&["Retarget", "Height", "Expected", "Difficulty", "Blocks Needed", "Time (days)"]);
+    let mut table = table(&header, 1);
+    for epoch in projected {
+        let calc = &epoch.calculation;
+        let viable = calc.time_required_days <= target_days;
+        table.add_row(vec![
+            Cell::new(epoch.retargets),
+            Cell::new(epoch.start_height),
+            Cell::new(epoch.start_time.format("%Y-%m-%d %H:%M")),
+            Cell::new(format_figure(epoch.difficulty)),
+            Cell::new(format!("{:.0}", calc.blocks_needed)),
+            verdict(format!("{:.2}", calc.time_required_days), viable),
+        ]);
+    }
+    say!("{}", table);
+    match projected.last() {
+        Some(epoch) if epoch.calculation.time_required_days <= target_days => say!(
+            "Viable from the retarget at height {}, expected around {}",
+            epoch.start_height,
+            epoch.start_time.format("%Y-%m-%d %H:%M UTC")
+        ),
+        _ => say!("Not viable within the next {} retargets at this trend", projected.len()),
+    }
+}
+
+/// Print the branches a node knows of besides its active chain, each with
+/// its progress against it: the share of the work done, the gap and the
+/// blocks left, when `hashrate` would complete it against the chain growing
+/// at `honest_hashrate`, and the hashrate that does in `target_days`.
+pub fn display_branches(
+    branches: &[(BranchTip, AttackProgress)],
+    hashrate: f64,
+    honest_hashrate: f64,
+    target_days: f64,
+    params: &NetworkParams,
+) {
+    let Some((_, first)) = branches.first() else {
+        say!("\nNo branches off the active chain: the node has seen no competing blocks");
+        return;
+    };
+    say!(
+        "\n=== Branches Off the Active Chain (tip {}, {} honest) ===",
+        first.public_height,
+        format_hashrate(honest_hashrate)
+    );
+    let mut header = header(&["Status", "Tip Height", "Fork Height", "Blocks", "Done", "Work Gap", "Blocks Left"]);
+    header.push(format!("Time at {}", format_hashrate(hashrate)));
+    header.push(format!("Hashrate ({} days)", format_days(target_days)));
+    let mut table = table(&header, 1);
+    for (branch, progress) in branches {
+        let time = match progress.days_to_overtake(hashrate, honest_hashrate, params) {
+            _ if progress.overtaken() => verdict("ahead", true),
+            Some(days) => verdict(format!("{} days", format_days(days)), days <= target_days),
+            None => verdict("never", false),
+        };
+        table.add_row(vec![
+            Cell::new(branch.status.name()),
+            Cell::new(branch.height),
+            Cell::new(progress.fork_height),
+            Cell::new(branch.branch_length),
+            Cell::new(format!("{}%", localize(progress.percent_complete(), 1, true))),
+            Cell::new(format_figure(progress.work_gap().max(0.0))),
+            Cell::new(progress.blocks_remaining()),
+            time,
+            Cell::new(format_hashrate(progress.hashrate_to_overtake(target_days, honest_hashrate, params))),
+        ]);
+    }
+    say!("{}", table);
+    if branches.iter().any(|(_, progress)| progress.overtaken()) {
+        say!("Branches past 100% have more work than the active chain; the node switches once it has their blocks");
+    }
+}
+
+/// Print stored calculations, each with the hashrate it was made for, one
+/// per line, marking those that fit their own target time.
+pub fn display_history(runs: &[(ReorgCalculation, f64)]) {
+    say!("\n=== Stored Calculations ({}) ===", runs.len());
+    let mut table = table(
+        &header(&["Timestamp", "Fork Height", "Tip", "Hashrate", "Time (days)", "Required", "Target", "Viable"]),
+        1,
+    );
+    for (calc, hashrate) in runs {
+        let viable = calc.target_days.map(|days| calc.time_required_days <= days);
+        table.add_row(vec![
+            Cell::new(calc.timestamp.format("%Y-%m-%d %H:%M:%S")),
+            Cell::new(calc.fork_height),
+            Cell::new(calc.current_height),
+            Cell::new(format_hashrate(*hashrate)),
+            Cell::new(format!("{:.2}", calc.time_required_days)),
+            Cell::new(format_hashrate(calc.hashrate_required)),
+            Cell::new(calc.target_days.map_or_else(|| "-".to_string(), |days| format!("{}d", format_days(days)))),
+            match viable {
+                Some(viable) => verdict(if viable { "yes" } else { "no" }, viable),
+                None => Cell::new("-"),
+            },
+        ]);
+    }
+    say!("{}", table);
+}
+
+/// Print how long each of `calculations`, one per hashrate, takes against
+/// each of `target_days`: the days needed, marked `*` where that fits the
+/// target, or with `feasible_only` just whether it does.
+pub fn display_matrix(calculations: &[ReorgCalculation], hashrates: &[f64], target_days: &[f64], feasible_only: bool) {
+    let Some(first) = calculations.first() else {
+        return;
+    };
+    say!(
+        "\n=== Sensitivity Matrix (fork height {}, {} blocks to reorg) ===",
+        first.fork_height, first.blocks_to_reorg
+    );
+    let mut header = vec!["Hashrate".to_string()];
+    header.extend(target_days.iter().map(|days| format!("{} days", format_days(*days))));
+    let mut table = table(&header, 0);
+    for (calc, hashrate) in calculations.iter().zip(hashrates) {
+        let mut row = vec![Cell::new(format_hashrate(*hashrate))];
+        for &days in target_days {
+            let fits = calc.time_required_days <= days;
+            let text = match (feasible_only, fits) {
+                (true, true) => "yes".to_string(),
+                (true, false) => "no".to_string(),
+                (false, _) if !calc.time_required_days.is_finite() => "never".to_string(),
+                (false, true) => format!("{:.2}*", calc.time_required_days),
+                (false, false) => format!("{:.2}", calc.time_required_days),
+            };
+            row.push(verdict(text, fits));
+        }
+        table.add_row(row);
+    }
+    say!("{}", table);
+    if !feasible_only {
+        say!("Cells are the days needed; * marks those within the column's target time");
+    }
+}
+
+/// Print the results of a scenario file side by side, marking the runs that
+/// finish within their target time.
+pub fn display_scenarios(calculations: &[ReorgCalculation]) {
+    say!("\n=== Scenarios ===");
+    let mut table = table(
+        &header(&[
+            "Scenario",
+            "Fork Height",
+            "Depth",
+            "Blocks Needed",
+            "Time (days)",
+            "Target",
+            "Hashrate Needed",
+            "Viable",
+        ]),
+        1,
+    );
+    for calc in calculations {
+        let viable = calc.time_required_days <= calc.target_days.unwrap_or(f64::INFINITY);
+        table.add_row(vec![
+            Cell::new(calc.scenario.as_deref().unwrap_or("-")),
+            Cell::new(calc.fork_height),
+            Cell::new(calc.blocks_to_reorg),
+            Cell::new(format!("{:.0}", calc.blocks_needed)),
+            Cell::new(format!("{:.2}", calc.time_required_days)),
+            Cell::new(calc.target_days.map_or_else(|| "-".to_string(), format_days)),
+            Cell::new(format_hashrate(calc.hashrate_required)),
+            verdict(if viable { "yes" } else { "no" }, viable),
+        ]);
+    }
+    say!("{}", table);
+}
+
+/// Print `calc`'s day-by-day attack plan as a table.
+fn display_schedule(calc: &ReorgCalculation) {
+    let mut table = table(&header(&["Day", "Ends (UTC)", "Blocks Mined", "Work Done"]), 0);
+    for day in &calc.schedule {
+        table.add_row(vec![
+            day.day.to_string(),
+            day.ends_at.format("%Y-%m-%d %H:%M").to_string(),
+            localize(day.blocks, 0, true),
+            format!("{:.1}%", 100.0 * day.work_share),
+        ]);
+    }
+    say!("Attack Schedule:\n{}", table);
+    match calc.schedule.last().filter(|day| day.work_share >= 1.0) {
+        Some(day) => say!(
+            "Projected Overtake: {} (day {})",
+            day.ends_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            day.day
+        ),
+        None => say!(
+            "Projected Overtake: day {}, after the {} days planned",
+            calc.time_required_days.ceil(),
+            MAX_SCHEDULE_DAYS
+        ),
+    }
+}
+
+/// Print a span's work per retarget epoch as a table.
+pub fn display_span_epochs(epochs: &[SpanEpoch]) {
+    say!("\n=== Span by Retarget Epoch ===");
+    let mut table = table(&header(&["Epoch Start", "Difficulty", "Blocks", "Min-Difficulty", "Work", "Share"]), 0);
+    for epoch in epochs {
+        table.add_row(vec![
+            epoch.start_height.to_string(),
+            format_figure(epoch.difficulty),
+            epoch.blocks.to_string(),
+            epoch.min_difficulty_blocks.to_string(),
+            format_figure(epoch.work),
+            format!("{:.2}%", 100.0 * epoch.work_share),
+        ]);
+    }
+    say!("{}", table);
+}
+
+/// Print how much of a span is minimum-difficulty blocks.
+pub fn display_min_difficulty(blocks: &MinDifficultyBlocks) {
+    say!(
+        "Min-Difficulty Blocks: {} of {} ({:.1}%), {} work ({:.4}% of the total)",
+        blocks.count,
+        blocks.span_blocks,
+        100.0 * blocks.count as f64 / blocks.span_blocks as f64,
+        format_figure(blocks.work),
+        100.0 * blocks.work_share
+    );
+}
+
+/// Print the transactions a reorg of the span would unconfirm.
+pub fn display_transactions(impact: &TransactionImpact) {
+    say!(
+        "Transactions Unconfirmed: {} in {} blocks, {} outputs moving {} (plus {} in coinbases)",
+        impact.transactions,
+        impact.blocks,
+        impact.outputs,
+        format_btc(impact.value),
+        format_btc(impact.coinbase_value)
+    );
+    let Some(threshold) = impact.threshold else {
+        return;
+    };
+    say!("Transactions of {} or More: {}", format_btc(threshold), impact.large.len());
+    for tx in impact.large.iter().take(MAX_MINER_ROWS) {
+        say!("  {} in block {}: {}", tx.txid, tx.height, format_btc(tx.value));
+    }
+    if impact.large.len() > MAX_MINER_ROWS {
+        say!("  ... and {} more", impact.large.len() - MAX_MINER_ROWS);
+    }
+}
+
+/// Print the wallet transactions a reorg of the span would unconfirm.
+pub fn display_wallet(impact: &WalletImpact) {
+    if impact.transactions.is_empty() {
+        say!("Wallet '{}': no transactions in the replaced blocks", impact.wallet);
+        return;
+    }
+    say!(
+        "\n=== Wallet '{}': {} Transactions Unconfirmed ===",
+        impact.wallet,
+        impact.transactions.len()
+    );
+    let mut table = table(&header(&["Txid", "Category", "Amount", "Block", "Confirmations"]), 0);
+    for tx in &impact.transactions {
+        let amount = format_btc(tx.amount.unsigned_abs());
+        table.add_row(vec![
+            tx.txid.to_string(),
+            tx.category.clone(),
+            if tx.amount < 0 { format!("-{}", amount) } else { amount },
+            tx.height.to_string(),
+            tx.confirmations.to_string(),
+        ]);
+    }
+    say!("{}", table);
+    say!(
+        "Mined again on the new chain they would have at most {} confirmations, fewer than now for {}.",
+        impact.new_chain_confirmations,
+        impact.below_current()
+    );
+}
+
+/// Print who mined the blocks of a span.
+pub fn display_miners(miners: &[MinerBlocks]) {
+    say!("\n=== Miners of the Replaced Blocks ===");
+    let mut table = table(&header(&["Miner", "Blocks", "Share", "Heights", "Payout"]), 0);
+    for miner in miners.iter().take(MAX_MINER_ROWS) {
+        table.add_row(vec![
+            miner.miner.clone(),
+            miner.blocks.to_string(),
+            format!("{:.1}%", 100.0 * miner.share),
+            format!("{}-{}", miner.first_height, miner.last_height),
+            // Untagged miners are already named by their payout
+            miner.payout.clone().filter(|_| miner.tagged).unwrap_or_default(),
+        ]);
+    }
+    say!("{}", table);
+    if miners.len() > MAX_MINER_ROWS {
+        let rest = &miners[MAX_MINER_ROWS..];
+        say!("... and {} more with {} blocks", rest.len(), rest.iter().map(|miner| miner.blocks).sum::<u64>());
+    }
+}
+
+/// Print the block storms of a span and what they mean for the reorg.
+pub fn display_storms(storms: &BlockStorms) {
+    say!(
+        "Block Storms (runs of {}+ min-difficulty blocks): {}, {} of {} blocks, {:.4}% of the work",
+        storms.min_blocks,
+        storms.storms.len(),
+        storms.storm_blocks,
+        storms.span_blocks,
+        100.0 * storms.work_share
+    );
+    for storm in storms.storms.iter().take(MAX_EPOCH_LINES) {
+        say!(
+            "  {}-{}: {} blocks, timestamps advancing {}",
+            storm.start_height,
+            storm.end_height,
+            storm.blocks,
+            approximate_duration(storm.timestamp_seconds as f64)
+        );
+    }
+    if storms.storms.len() > MAX_EPOCH_LINES {
+        say!("  ... and {} more", storms.storms.len() - MAX_EPOCH_LINES);
+    }
+    if storms.storm_blocks > 0 {
+        say!(
+            "Note: the storms add next to no work, so reorging these {} blocks costs about as much as the other {}.",
+            storms.span_blocks,
+            storms.other_blocks()
+        );
+    }
+}
+
+/// Format an interval in seconds as minutes and seconds, e.g. `12m 05s`.
+fn format_interval(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.unsigned_abs();
+    match seconds {
+        0..=59 => format!("{}{}s", sign, seconds),
+        60..=3599 => format!("{}{}m {:02}s", sign, seconds / 60, seconds % 60),
+        _ => format!("{}{}h {:02}m", sign, seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// Print a span's inter-block times against the network's `target_spacing`
+/// in seconds.
+pub fn display_intervals(intervals: &BlockIntervals, target_spacing: u64) {
+    say!(
+        "Block Intervals ({} blocks): mean {} ({:.0}% of the {} target), median {}, P95 {}",
+        intervals.count,
+        format_interval(intervals.mean_seconds.round() as i64),
+        100.0 * intervals.mean_seconds / target_spacing as f64,
+        format_interval(target_spacing as i64),
+        format_interval(intervals.median_seconds.round() as i64),
+        format_interval(intervals.p95_seconds)
+    );
+    let gaps: Vec<String> = intervals
+        .largest
+        .iter()
+        .map(|gap| format!("{} before block {}", format_interval(gap.seconds), gap.height))
+        .collect();
+    say!("Largest Gaps: {}", gaps.join(", "));
+}
+
+/// Print the Monte Carlo spread of the time required.
+fn display_simulation(simulation: &MiningSimulation) {
+    let days = |value: Option<f64>| match value {
+        Some(days) => format!("{:.2}", days),
+        None => format!(">{:.0}", simulation.horizon_days),
+    };
+    say!(
+        "Simulated Time ({} runs): P10 {} / P50 {} / P90 {} days",
+        simulation.runs,
+        days(simulation.p10_days),
+        days(simulation.p50_days),
+        days(simulation.p90_days)
+    );
+    say!(
+        "Chance of Finishing Within {} Days: {:.1}%",
+        format_days(simulation.target_days),
+        100.0 * simulation.within_target
+    );
+}
+
+/// Print the timestamp check of a planned attack chain.
+fn display_timeline(timeline: &TimelineCheck) {
+    let checked = if timeline.truncated {
+        format!("first {} blocks", timeline.blocks_checked)
+    } else {
+        format!("{} blocks", timeline.blocks_checked)
+    };
+    if timeline.is_clean() {
+        say!("Planned Timestamps: valid ({} checked)", checked);
+        return;
+    }
+    for violation in &timeline.first_violations {
+        match *violation {
+            TimestampViolation::MedianTimePast { height, time, median_time_past } => say!(
+                "Warning: {} planned blocks are not after median-time-past and must be timestamped later \
+                 (first at height {}: {} <= {})",
+                timeline.median_time_past_raised, height, time, median_time_past
+            ),
+            TimestampViolation::FutureDrift { height, time, limit } => say!(
+                "Warning: {} planned blocks are more than 2 hours in the future and would be rejected \
+                 (first at height {}: {} > {})",
+                timeline.future_drift_violations, height, time, limit
+            ),
+        }
+    }
+    say!("Planned Timestamps: {} checked", checked);
+}
+
+/// Append calculations to `filename` in plain text.
+pub fn save_to_file(calculations: &[ReorgCalculation], filename: impl AsRef<Path>, provided_hashrate: f64) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filename.as_ref())?;
+    
+    writeln!(file, "\n=== Testnet4 Reorg Calculations - {} ===", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"))?;
+    
+    for calc in calculations {
+        if let Some(scenario) = &calc.scenario {
+            writeln!(file, "\nScenario: {}", scenario)?;
+            writeln!(file, "Fork Height: {}", calc.fork_height)?;
+        } else {
+            writeln!(file, "\nFork Height: {}", calc.fork_height)?;
+        }
+        writeln!(file, "Current Height: {}", calc.current_height)?;
+        if let Some(note) = stale_note(calc) {
+            writeln!(file, "Stale: {}", note)?;
+        }
+        writeln!(file, "Blocks to Reorg: {}", calc.blocks_to_reorg)?;
+        writeln!(file, "Total Work: {:.2}", calc.total_work)?;
+        if let Some(hashes) = calc.total_hashes {
+            writeln!(file, "Expected Hashes: {} (log2_work={:.6})", work_to_decimal(hashes), hashes.log2())?;
+        }
+        writeln!(file, "Current Difficulty: {:.2}", calc.current_difficulty)?;
+        if let Some(network) = calc.network_hashrate {
+            writeln!(file, "Network Hashrate: {} ({} blocks)", format_hashrate(network.hashrate), network.blocks)?;
+        }
+        writeln!(file, "Blocks Needed: {:.0}{}", calc.blocks_needed, margin_note(calc))?;
+        if let Some(next) = &calc.next_retarget {
+            writeln!(
+                file,
+                "Next Retarget: height {} in {} blocks, difficulty {:.2} -> {:.2}, {:.0} blocks needed, {:.2} days",
+                next.height,
+                next.blocks_remaining,
+                next.difficulty,
+                next.projected_difficulty,
+                next.blocks_needed,
+                next.time_required_days
+            )?;
+        }
+        if let Some(growth) = calc.honest_growth {
+            writeln!(file, "Honest Chain Growth: {:.0} blocks at {}", growth.added_blocks, format_hashrate(growth.hashrate))?;
+        }
+        if let Some(impact) = &calc.wallet {
+            writeln!(
+                file,
+                "Wallet '{}': {} transactions unconfirmed, {} below their current confirmations",
+                impact.wallet,
+                impact.transactions.len(),
+                impact.below_current()
+            )?;
+        }
+        if let Some(impact) = &calc.transactions {
+            writeln!(
+                file,
+                "Transactions Unconfirmed: {} ({} outputs, {})",
+                impact.transactions,
+                impact.outputs,
+                format_btc(impact.value)
+            )?;
+        }
+        if !calc.miners.is_empty() {
+            let miners: Vec<String> =
+                calc.miners.iter().take(MAX_MINER_ROWS).map(|miner| format!("{} {}", miner.miner, miner.blocks)).collect();
+            writeln!(file, "Miners: {}", miners.join(", "))?;
+        }
+        if let Some(storms) = &calc.storms {
+            writeln!(
+                file,
+                "Block Storms: {} ({} of {} blocks, {:.4}% of the work)",
+                storms.storms.len(),
+                storms.storm_blocks,
+                storms.span_blocks,
+                100.0 * storms.work_share
+            )?;
+        }
+        if let Some(intervals) = &calc.intervals {
+            writeln!(
+                file,
+                "Block Intervals: mean {:.0}s, median {:.0}s, P95 {}s, largest {}s",
+                intervals.mean_seconds,
+                intervals.median_seconds,
+                intervals.p95_seconds,
+                intervals.largest.first().map_or(0, |gap| gap.seconds)
+            )?;
+        }
+        if let Some(blocks) = &calc.min_difficulty {
+            writeln!(
+                file,
+                "Min-Difficulty Blocks: {} of {}, {:.4}% of the work",
+                blocks.count,
+                blocks.span_blocks,
+                100.0 * blocks.work_share
+            )?;
+        }
+        if let Some(overhead) = calc.overhead_percent {
+            writeln!(file, "Stale/Propagation Overhead: {}%", overhead)?;
+        }
+        if let Some(energy) = calc.energy.as_ref().filter(|energy| energy.kwh.is_finite()) {
+            writeln!(file, "Energy: {:.2} kWh at {:.2} J/TH", energy.kwh, energy.joules_per_th)?;
+            if let Some(cost) = energy.cost() {
+                writeln!(file, "Electricity Cost: {}{}", cost, in_fiat(calc, &cost))?;
+            }
+        }
+        for member in &calc.group {
+            writeln!(
+                file,
+                "Participant {}: {}, {:.1}% of the work, {:.1} blocks expected",
+                member.name,
+                format_hashrate(member.hashrate),
+                100.0 * member.share,
+                member.expected_blocks
+            )?;
+        }
+        if let Some(rate) = &calc.exchange_rate {
+            writeln!(
+                file,
+                "Exchange Rate: {:.2} {} per BTC ({}, {})",
+                rate.per_btc,
+                rate.currency,
+                rate.source,
+                rate.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+            )?;
+        }
+        if let Some(mainnet) = &calc.mainnet {
+            writeln!(file, "Mainnet Equivalent: {}", mainnet_note(mainnet))?;
+        }
+        if let Some(price) = &calc.rental_price {
+            writeln!(
+                file,
+                "Rental Price: {} {} per TH/s per day ({})",
+                format_amount(price.price_per_th_day),
+                price.currency,
+                price.source
+            )?;
+        }
+        if let Some(strategy) = &calc.min_difficulty_strategy {
+            writeln!(
+                file,
+                "Min-Difficulty Blocks Insertable: {} (chain length {:.0})",
+                strategy.inserted_blocks, strategy.chain_blocks
+            )?;
+        }
+        if let Some(timeline) = &calc.timeline {
+            writeln!(
+                file,
+                "Timestamp Corrections: {} below median-time-past, {} beyond future drift",
+                timeline.median_time_past_raised, timeline.future_drift_violations
+            )?;
+        }
+        if !calc.attacker_epochs.is_empty() {
+            writeln!(file, "Attacker Epochs: {}", calc.attacker_epochs.len())?;
+        }
+        let efficiency = calc.efficiency.map_or_else(String::new, |efficiency| format!(" at {}%", 100.0 * efficiency));
+        writeln!(
+            file,
+            "Time Required ({}{}): {:.2} days",
+            format_hashrate(provided_hashrate),
+            efficiency,
+            calc.time_required_days
+        )?;
+        if let Some(simulation) = &calc.simulation {
+            writeln!(
+                file,
+                "Simulated Median: {}, Within {} Days: {:.1}%",
+                simulation.p50_days.map_or_else(|| "never".to_string(), |days| format!("{:.2} days", days)),
+                format_days(simulation.target_days),
+                100.0 * simulation.within_target
+            )?;
+        }
+        if let Some(race) = &calc.race {
+            writeln!(file, "Chance of Ever Overtaking: {}", format_probability(race.probability))?;
+        }
+        if calc.target_hashrates.is_empty() {
+            let target = calc
+                .target_days
+                .map_or_else(|| "target time".to_string(), |days| format!("{} days", format_days(days)));
+            writeln!(
+                file,
+                "Hashrate for {}: {}{}{}",
+                target,
+                format_hashrate(calc.hashrate_required),
+                machines(calc, calc.hashrate_required),
+                rent(calc, calc.hashrate_required)
+            )?;
+        }
+        for target in &calc.target_hashrates {
+            writeln!(
+                file,
+                "Hashrate for {} days: {}{}{}",
+                format_days(target.days),
+                format_hashrate(target.hashrate),
+                machines(calc, target.hashrate),
+                rent(calc, target.hashrate)
+            )?;
+        }
+        writeln!(file, "Timestamp: {}", calc.timestamp.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        writeln!(file, "---")?;
+    }
+    
+    say!("Results saved to: {}", filename.as_ref().display());
+    Ok(())
+}
+
+/// Format a probability as a percentage, keeping tiny ones readable.
+fn format_probability(probability: f64) -> String {
+    if probability > 0.0 && probability < 1e-4 {
+        format!("{:.2e}", probability)
+    } else {
+        format!("{:.2}%", 100.0 * probability)
+    }
+}
+
+/// Describe the margin included in `blocks_needed`, if any.
+fn margin_note(calc: &ReorgCalculation) -> String {
+    let margin = calc.margin;
+    match (margin.blocks, margin.percent) {
+        (0, 0.0) => String::new(),
+        (0, percent) => format!(" (including a {}% margin)", percent),
+        (blocks, 0.0) => format!(" (including a {}-block margin)", blocks),
+        (blocks, percent) => format!(" (including a {}% + {}-block margin)", percent, blocks),
+    }
+}
+
+/// Describe a chain move recorded in [`ReorgCalculation::stale`].
+fn stale_note(calc: &ReorgCalculation) -> Option<String> {
+    let change = calc.stale?;
+    let delta = change.delta(calc.current_height);
+    Some(if change.reorged {
+        format!(
+            "the chain reorged during the calculation; the tip is now {} ({:+} blocks), rerun for current figures",
+            change.tip_height, delta
+        )
+    } else {
+        format!(
+            "the tip kept advancing during the calculation and is now {} ({:+} blocks)",
+            change.tip_height, delta
+        )
+    })
+}
@@ -0,0 +1,69 @@
+//! Splitting an attack between several participants.
+//!
+//! A coordinated exercise mines one reorg chain with the participants'
+//! combined hashrate. Each one contributes work, and on average finds
+//! blocks, in proportion to their share of that hashrate.
+
+use serde::{Deserialize, Serialize};
+
+use crate::calc::ReorgCalculation;
+use crate::units::parse_hashrate;
+use crate::HASHES_PER_DIFFICULTY;
+
+/// A participant and the hashrate they bring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Participant {
+    pub name: String,
+    /// Hashrate in hashes/second.
+    pub hashrate: f64,
+}
+
+/// One participant's part of an attack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupShare {
+    pub name: String,
+    pub hashrate: f64,
+    /// Fraction of the combined hashrate.
+    pub share: f64,
+    /// Work they mine, in difficulty units.
+    pub work: f64,
+    /// Blocks they can expect to find, overhead included.
+    pub expected_blocks: f64,
+}
+
+/// Parse `name=hashrate`, e.g. `alice=5TH`.
+pub fn parse_participant(text: &str) -> Result<Participant, String> {
+    let (name, hashrate) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=hashrate such as alice=5TH, got '{}'", text))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing participant name in '{}'", text));
+    }
+    Ok(Participant {
+        name: name.to_string(),
+        hashrate: parse_hashrate(hashrate)?,
+    })
+}
+
+/// Each participant's share of `calc`, mined at their combined hashrate.
+pub fn split_work(calc: &ReorgCalculation, participants: &[Participant]) -> Vec<GroupShare> {
+    let combined: f64 = participants.iter().map(|participant| participant.hashrate).sum();
+    let overhead = 1.0 + calc.overhead_percent.unwrap_or(0.0) / 100.0;
+    // Hashes actually computed: the effective hashrate for the whole time
+    let hashes = combined * calc.efficiency.unwrap_or(1.0) * calc.time_required_hours * 3600.0;
+    let blocks = calc.blocks_needed * overhead;
+    participants
+        .iter()
+        .map(|participant| {
+            let share = participant.hashrate / combined;
+            GroupShare {
+                name: participant.name.clone(),
+                hashrate: participant.hashrate,
+                share,
+                work: share * hashes / HASHES_PER_DIFFICULTY,
+                expected_blocks: share * blocks,
+            }
+        })
+        .collect()
+}
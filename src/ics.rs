@@ -0,0 +1,126 @@
+//! iCalendar export of an attack's timeline.
+//!
+//! Writes one `.ics` calendar holding the attack window, from the start of
+//! the calculation to the projected overtake, with reminders an hour before
+//! each end, and an event at the end of every day of its [`schedule`] for
+//! the milestones. Calendar apps import it as is.
+//!
+//! [`schedule`]: crate::schedule
+
+use chrono::{DateTime, Duration, Utc};
+use std::io::Write;
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+use crate::format::format_hashrate;
+use crate::SECONDS_PER_DAY;
+
+/// How long each milestone event lasts in the calendar.
+const MILESTONE_MINUTES: i64 = 15;
+
+/// Write `calc`'s attack, mined at `hashrate`, as an iCalendar file.
+pub fn write_ics(mut writer: impl Write, calc: &ReorgCalculation, hashrate: f64) -> Result<()> {
+    let start = calc.timestamp;
+    let end = start + Duration::milliseconds((calc.time_required_days * SECONDS_PER_DAY * 1000.0) as i64);
+    let uid = |name: &str| format!("{}-{}-{}@testnet4-reorg-calculator", name, calc.fork_height, stamp(start));
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//testnet4-reorg-calculator//{}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid("attack")),
+        format!("DTSTAMP:{}", stamp(Utc::now())),
+        format!("DTSTART:{}", stamp(start)),
+        format!("DTEND:{}", stamp(end)),
+        format!("SUMMARY:{}", text(&format!("Testnet4 reorg attack from block {}", calc.fork_height))),
+        format!(
+            "DESCRIPTION:{}",
+            text(&format!(
+                "Mine {:.0} blocks at {} to replace blocks {} to {}. Projected overtake: {}.",
+                calc.blocks_needed,
+                format_hashrate(hashrate),
+                calc.fork_height,
+                calc.current_height,
+                end.format("%Y-%m-%d %H:%M:%S UTC")
+            ))
+        ),
+    ];
+    lines.extend(alarm("TRIGGER:-PT1H", "The reorg attack starts in an hour"));
+    lines.extend(alarm("TRIGGER;RELATED=END:-PT1H", "The reorg attack is projected to overtake in an hour"));
+    lines.push("END:VEVENT".to_string());
+
+    for day in &calc.schedule {
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", uid(&format!("day{}", day.day))),
+            format!("DTSTAMP:{}", stamp(Utc::now())),
+            format!("DTSTART:{}", stamp(day.ends_at)),
+            format!("DTEND:{}", stamp(day.ends_at + Duration::minutes(MILESTONE_MINUTES))),
+            format!(
+                "SUMMARY:{}",
+                text(&format!(
+                    "Reorg attack day {}: {:.0} blocks, {:.1}% of the work",
+                    day.day,
+                    day.blocks,
+                    100.0 * day.work_share
+                ))
+            ),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines {
+        write!(writer, "{}\r\n", fold(&line))?;
+    }
+    Ok(())
+}
+
+/// A display alarm firing at `trigger`.
+fn alarm(trigger: &str, description: &str) -> [String; 5] {
+    [
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        trigger.to_string(),
+        format!("DESCRIPTION:{}", text(description)),
+        "END:VALARM".to_string(),
+    ]
+}
+
+/// `time` in UTC, as iCalendar writes it.
+fn stamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// `value` escaped for a text property.
+fn text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `line` folded into lines of at most 75 octets, continuations starting
+/// with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
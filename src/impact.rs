@@ -0,0 +1,119 @@
+//! The transactions a reorg would unconfirm.
+//!
+//! Every transaction in the replaced blocks drops back to the mempool when
+//! the fork wins, and any the attacker leaves out or double-spends never
+//! confirm again. Counting them, their outputs and the value they move
+//! puts a size on the reorg beyond its depth; the coinbases are counted
+//! apart, as their rewards vanish with the blocks.
+
+use bitcoin::Txid;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::output::progress_bar;
+use crate::progress;
+use crate::source::BlockSource;
+
+/// Full blocks requested from the source per call; far larger than a
+/// header, so fewer than a scan takes.
+const BLOCK_BATCH_SIZE: usize = 50;
+
+/// A transaction moving at least the listing threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LargeTransaction {
+    pub txid: Txid,
+    pub height: u64,
+    /// Total of its outputs, satoshis.
+    pub value: u64,
+}
+
+/// Transactions in the blocks a reorg replaces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionImpact {
+    pub blocks: u64,
+    /// Transactions other than the coinbases.
+    pub transactions: u64,
+    /// Outputs of those transactions.
+    pub outputs: u64,
+    /// Total of those outputs, satoshis.
+    pub value: u64,
+    /// Total the coinbases pay, satoshis.
+    pub coinbase_value: u64,
+    /// Transactions are listed from this value up, satoshis.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<u64>,
+    /// Transactions moving at least `threshold`, largest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub large: Vec<LargeTransaction>,
+}
+
+impl TransactionImpact {
+    fn empty(threshold: Option<u64>) -> Self {
+        TransactionImpact {
+            blocks: 0,
+            transactions: 0,
+            outputs: 0,
+            value: 0,
+            coinbase_value: 0,
+            threshold,
+            large: Vec::new(),
+        }
+    }
+
+    fn add(mut self, other: TransactionImpact) -> Self {
+        self.blocks += other.blocks;
+        self.transactions += other.transactions;
+        self.outputs += other.outputs;
+        self.value += other.value;
+        self.coinbase_value += other.coinbase_value;
+        self.large.extend(other.large);
+        self
+    }
+}
+
+/// Count the transactions in the blocks from `fork_height` to
+/// `current_height` inclusive, listing those whose outputs total at least
+/// `threshold` satoshis when one is given.
+///
+/// Needs every full block, so the whole span is downloaded.
+pub fn measure_transaction_impact<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+    threshold: Option<u64>,
+) -> Result<TransactionImpact> {
+    progress!("Fetching blocks {} to {} for their transactions...", fork_height, current_height);
+    let pb = progress_bar(current_height + 1 - fork_height);
+    let heights: Vec<u64> = (fork_height..=current_height).collect();
+    let parts = heights
+        .par_chunks(BLOCK_BATCH_SIZE)
+        .map(|heights| {
+            let blocks = source.get_blocks(heights)?;
+            pb.inc(heights.len() as u64);
+            let mut impact = TransactionImpact::empty(threshold);
+            for (&height, block) in heights.iter().zip(&blocks) {
+                impact.blocks += 1;
+                for tx in &block.txdata {
+                    let value: u64 = tx.output.iter().map(|output| output.value.to_sat()).sum();
+                    if tx.is_coinbase() {
+                        impact.coinbase_value += value;
+                        continue;
+                    }
+                    impact.transactions += 1;
+                    impact.outputs += tx.output.len() as u64;
+                    impact.value += value;
+                    if threshold.is_some_and(|threshold| value >= threshold) {
+                        impact.large.push(LargeTransaction { txid: tx.compute_txid(), height, value });
+                    }
+                }
+            }
+            Ok(impact)
+        })
+        .collect::<Result<Vec<_>>>();
+    pb.finish_and_clear();
+
+    let mut impact = parts?.into_iter().fold(TransactionImpact::empty(threshold), TransactionImpact::add);
+    impact.large.sort_by(|a, b| b.value.cmp(&a.value).then(a.height.cmp(&b.height)));
+    Ok(impact)
+}
@@ -0,0 +1,102 @@
+//! InfluxDB line protocol output.
+//!
+//! Each calculation is one point in the `reorg_calculation` measurement,
+//! tagged with its depth below the tip (and the scenario, if any) so that
+//! repeated runs at the same depth form one series. Infinite figures, for
+//! attacks that never finish, are left out of the point, since line
+//! protocol has no value for them.
+
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+
+/// Measurement every point is written to.
+pub const INFLUX_MEASUREMENT: &str = "reorg_calculation";
+
+/// `calc`, made for `hashrate`, as one line of line protocol, without the
+/// trailing newline.
+pub fn influx_line(calc: &ReorgCalculation, hashrate: f64) -> String {
+    let mut line = INFLUX_MEASUREMENT.to_string();
+    let _ = write!(line, ",depth={}", calc.current_height.saturating_sub(calc.fork_height));
+    if let Some(scenario) = &calc.scenario {
+        let _ = write!(line, ",scenario={}", escape_tag(scenario));
+    }
+
+    let mut fields = vec![
+        format!("fork_height={}i", calc.fork_height),
+        format!("current_height={}i", calc.current_height),
+        format!("blocks_to_reorg={}i", calc.blocks_to_reorg),
+    ];
+    let floats = [
+        ("total_work", calc.total_work),
+        ("current_difficulty", calc.current_difficulty),
+        ("blocks_needed", calc.blocks_needed),
+        ("hashrate", hashrate),
+        ("time_required_days", calc.time_required_days),
+        ("hashrate_required", calc.hashrate_required),
+    ];
+    fields.extend(
+        floats
+            .iter()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(key, value)| format!("{}={:?}", key, value)),
+    );
+    if let Some(days) = calc.target_days {
+        fields.push(format!("target_days={:?}", days));
+        fields.push(format!("viable={}", calc.time_required_days <= days));
+    }
+    let timestamp = calc.timestamp.timestamp_nanos_opt().unwrap_or_default();
+    let _ = write!(line, " {} {}", fields.join(","), timestamp);
+    line
+}
+
+/// Write `runs`, each a calculation and the hashrate it was made for, as
+/// line protocol, one point per line.
+pub fn write_influx(mut writer: impl Write, runs: &[(ReorgCalculation, f64)]) -> Result<()> {
+    for (calc, hashrate) in runs {
+        writeln!(writer, "{}", influx_line(calc, *hashrate))?;
+    }
+    Ok(())
+}
+
+/// Escape a tag value: commas, equals signs and spaces are significant in
+/// line protocol.
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// POST `runs` as line protocol to an InfluxDB write endpoint, e.g.
+/// `http://host:8086/api/v2/write?org=o&bucket=b&precision=ns` or the 1.x
+/// `http://host:8086/write?db=d`, with `token` sent as
+/// `Authorization: Token ...` when given.
+///
+/// The built-in HTTP client speaks plain HTTP only.
+#[cfg(feature = "influx")]
+pub fn post_influx(url: &str, token: Option<&str>, runs: &[(ReorgCalculation, f64)]) -> Result<()> {
+    use crate::error::ReorgCalcError;
+
+    let mut body = Vec::new();
+    write_influx(&mut body, runs)?;
+    let mut request = minreq::post(url)
+        .with_timeout(30)
+        .with_header("Content-Type", "text/plain; charset=utf-8")
+        .with_body(body);
+    if let Some(token) = token {
+        request = request.with_header("Authorization", format!("Token {}", token));
+    }
+    let response = request.send().map_err(|e| ReorgCalcError::Influx(format!("{}: {}", url, e)))?;
+    if !(200..300).contains(&response.status_code) {
+        let reason = response.as_str().unwrap_or("").trim().to_string();
+        return Err(ReorgCalcError::Influx(format!("{} returned HTTP {} {}", url, response.status_code, reason)));
+    }
+    Ok(())
+}
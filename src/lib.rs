@@ -0,0 +1,194 @@
+//! Core library for the Testnet4 reorg calculator.
+//!
+//! Everything needed to estimate the work, time and hashrate required to
+//! reorganize Testnet4 from a given fork height lives here, so it can be
+//! reused from other tooling: the commands, the monitors that keep
+//! recalculating, and the reports and result sinks they write to. The
+//! `testnet4-reorg-calculator` binary is a thin CLI wrapper around this
+//! crate, parsing arguments and dispatching to it.
+//!
+//! ```no_run
+//! use testnet4_reorg_calculator::{calculate_reorg_requirements, connect_to_node};
+//!
+//! let client = connect_to_node("http://127.0.0.1:48337", "user", "pass")?;
+//! let calc = calculate_reorg_requirements(&client, 100_000, 1e15, 3.0)?;
+//! println!("{} blocks needed", calc.blocks_needed);
+//! # Ok::<(), testnet4_reorg_calculator::ReorgCalcError>(())
+//! ```
+
+pub mod alert;
+pub mod backtest;
+#[cfg(feature = "async")]
+pub mod async_source;
+pub mod bench;
+pub mod budget;
+pub mod cache;
+pub mod calc;
+pub mod chat;
+pub mod commands;
+pub mod config;
+pub mod devices;
+pub mod error;
+#[cfg(feature = "esplora")]
+pub mod esplora;
+#[cfg(feature = "fiat")]
+pub mod fiat;
+#[cfg(test)]
+mod fixture;
+pub mod forecast;
+pub mod format;
+pub mod group;
+pub mod impact;
+pub mod ics;
+pub mod influx;
+#[cfg(feature = "mainnet")]
+pub mod mainnet;
+#[cfg(feature = "market")]
+pub mod market;
+pub mod min_difficulty;
+pub mod miners;
+pub mod model;
+pub mod monitor;
+pub mod mqtt;
+pub mod network;
+pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet_file;
+#[cfg(feature = "plots")]
+pub mod plot;
+#[cfg(feature = "postgres")]
+pub mod postgres_sink;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod race;
+pub mod report;
+pub mod results_log;
+pub mod retarget;
+pub mod rotation;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod scenario;
+pub mod schedule;
+pub mod server;
+pub mod simulate;
+pub mod sinks;
+pub mod snapshot;
+pub mod source;
+pub mod storm;
+pub mod state;
+#[cfg(feature = "sqlite")]
+pub mod store;
+#[cfg(feature = "templates")]
+pub mod template;
+pub mod timeline;
+pub mod track;
+mod toml_subset;
+pub mod units;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wallet;
+pub mod watch;
+pub mod work;
+pub mod zmq;
+
+pub use alert::{parse_alert_rule, Alert, AlertMetric, AlertRule, AlertRules, Comparison};
+#[cfg(feature = "webhook")]
+pub use alert::post_webhook;
+#[cfg(feature = "async")]
+pub use async_source::AsyncBlockSource;
+pub use backtest::{day_end, day_tip_height, BacktestDay, ChainAt};
+pub use bench::{benchmark_sha256d, Benchmark};
+pub use budget::{
+    energy_use, format_amount, parse_money, rental_cost, EnergyUse, ExchangeRate, Money, RentalPrice,
+};
+pub use cache::{CachedSource, HeaderStore};
+pub use calc::{
+    block_fork_height, calculate_from_chain_work, calculate_from_work, calculate_reorg_requirements, calculate_reorg_requirements_with,
+    calculate_matrix, calculate_sweep, candidate_fork_heights, confirmation_fork_height, find_deepest_fork_height,
+    find_viable_target_heights, find_viable_target_heights_among, find_viable_target_heights_with, fork_height_at_time,
+    sweep_fork_heights, transaction_fork_height, Adjustments, HonestGrowth, MainnetEquivalent, Margin, ReorgCalculation,
+    TargetHashrate, TipChange, SCHEMA_VERSION,
+};
+pub use chat::{parse_chat_webhook, ChatService, ChatWebhook, TelegramBot, CHAT_USERNAME, TELEGRAM_API};
+pub use commands::{
+    add_calc_extras, export_ics, plot_runs, run_backtest, run_batch, run_bench, run_budget, run_calc, run_chaintips,
+    run_compare, run_confirmations, run_forecast, run_group, run_manual, run_matrix, run_scenarios, run_sweep,
+    sync_headers, CalcExtras, ForkPoint, ManualChain,
+};
+pub use config::{write_env_var, Config};
+pub use devices::{builtin_devices, find_device, load_devices, parse_devices, Device, HARDWARE_FILE, REFERENCE_DEVICE};
+pub use error::{ReorgCalcError, Result};
+pub use forecast::{forecast_viability, measure_difficulty_trend, DifficultyTrend, ProjectedEpoch, FORECAST_EPOCHS, TREND_EPOCHS};
+pub use format::{
+    display_backtest, display_branches, display_burial, display_calculation, display_changes, display_forecast, display_group,
+    display_history, display_intervals, display_matrix, display_miners, display_min_difficulty, display_transactions, display_wallet, display_storms, display_progress, display_scenarios, display_span_epochs,
+    display_sweep, display_tip_comparison, format_btc, format_days, format_figure, format_hashes, format_hashrate, in_fiat, save_to_file,
+    set_color, set_precision, summary_line, write_block_csv, write_block_jsonl, write_csv, write_markdown,
+    write_summary, Report, ReportEntry, BLOCK_CSV_COLUMNS, CSV_COLUMNS,
+};
+pub use group::{parse_participant, split_work, GroupShare, Participant};
+pub use ics::write_ics;
+pub use influx::{influx_line, write_influx, INFLUX_MEASUREMENT};
+#[cfg(feature = "influx")]
+pub use influx::post_influx;
+pub use monitor::{tip_events, MonitorEvent, MonitorEventKind, SeenBlock, SeenChain, REORG_WINDOW};
+pub use model::Model;
+pub use mqtt::{MqttPublisher, MQTT_TOPIC};
+pub use network::NetworkParams;
+pub use output::{output, progress_bar, set_output, Output};
+#[cfg(feature = "parquet")]
+pub use parquet_file::write_parquet;
+#[cfg(feature = "plots")]
+pub use plot::plot_calculation;
+#[cfg(feature = "postgres")]
+pub use postgres_sink::PostgresSink;
+#[cfg(feature = "esplora")]
+pub use esplora::EsploraSource;
+#[cfg(feature = "fiat")]
+pub use fiat::fetch_exchange_rate;
+#[cfg(feature = "mainnet")]
+pub use mainnet::fetch_mainnet_hashrate;
+#[cfg(feature = "market")]
+pub use market::{fetch_rental_price, Marketplace};
+#[cfg(feature = "rest")]
+pub use rest::RestSource;
+#[cfg(feature = "rpc")]
+pub use rpc::{connect_to_node, RpcPool};
+pub use report::{ReportFormat, ReportOutput, ReportSink};
+pub use results_log::{ResultsLog, RESULTS_LOG};
+pub use scenario::{load_scenarios, parse_scenarios, Scenario, SCENARIO_FILE};
+pub use schedule::{attack_schedule, ScheduleDay, MAX_SCHEDULE_DAYS};
+pub use server::{serve, ServeDefaults};
+pub use sinks::{save_results, ResultSinks};
+pub use snapshot::{RecordingSource, ReplaySource, Snapshot};
+pub use impact::{measure_transaction_impact, LargeTransaction, TransactionImpact};
+pub use miners::{coinbase_payout, coinbase_tag, identify_miners, MinerBlocks};
+pub use source::{BlockSource, BranchStatus, BranchTip, ChainTip};
+pub use storm::{find_block_storms, BlockStorm, BlockStorms, STORM_BLOCKS};
+pub use state::MonitorState;
+#[cfg(feature = "sqlite")]
+pub use store::{ResultsStore, StoreQuery, RESULTS_DB};
+#[cfg(feature = "templates")]
+pub use template::ReportTemplate;
+pub use rotation::{parse_rotation, Rotation};
+pub use track::{
+    attack_progress, branch_progress, common_fork_height, tip_work, AttackProgress, BranchRate, BranchWatch, TipWork,
+};
+pub use units::{parse_btc, parse_days, parse_hashrate, parse_interval, parse_size};
+pub use zmq::HashBlockSubscriber;
+pub use wallet::{WalletImpact, WalletTransaction};
+pub use watch::{run_countdown, run_daemon, run_track, run_watch, BlockTrigger};
+#[cfg(feature = "rpc")]
+pub use wallet::wallet_impact;
+pub use work::{
+    bits_to_difficulty, block_work, blocks_to_match, calculate_chain_work, calculate_chain_work_with,
+    chain_work_from_chainwork, count_min_difficulty_blocks, estimate_network_hashrate, get_block_difficulty,
+    measure_network_hashrate, scan_block_works, scan_chain_work, scan_chain_work_with, span_epochs, work_to_decimal,
+    measure_block_intervals, BlockGap, BlockIntervals, BlockWork, MinDifficultyBlocks, NetworkHashrate, ScanCheckpoint,
+    ScanOptions, SpanEpoch, HASHRATE_WINDOW, LARGEST_GAPS,
+};
+
+/// Expected number of hashes to find a block at difficulty 1 (2^32).
+pub const HASHES_PER_DIFFICULTY: f64 = 4294967296.0;
+/// Number of seconds in a day.
+pub const SECONDS_PER_DAY: f64 = 86400.0;
@@ -0,0 +1,44 @@
+//! Current mainnet hashrate, for putting testnet4 attacks in proportion.
+//!
+//! Reads mempool.space's mining statistics, or any URL answering in the
+//! same format. As with the marketplaces, the built-in HTTP client speaks
+//! plain HTTP only, so the HTTPS default needs a build of minreq with TLS,
+//! or a URL pointing at an HTTP mirror or proxy.
+
+use serde::Deserialize;
+
+use crate::error::{ReorgCalcError, Result};
+
+/// Endpoint the hashrate is read from.
+pub const MEMPOOL_URL: &str = "https://mempool.space/api/v1/mining/hashrate/3d";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MiningHashrate {
+    current_hashrate: f64,
+}
+
+/// Fetch mainnet's current hashrate in H/s from `url` or mempool.space.
+pub fn fetch_mainnet_hashrate(url: Option<&str>) -> Result<f64> {
+    let url = url.unwrap_or(MEMPOOL_URL);
+    let response = minreq::get(url)
+        .with_timeout(30)
+        .send()
+        .map_err(|e| ReorgCalcError::MainnetFeed(format!("{}: {}", url, e)))?;
+    if response.status_code != 200 {
+        return Err(ReorgCalcError::MainnetFeed(format!("{} returned HTTP {}", url, response.status_code)));
+    }
+    let body = response.as_str().map_err(|e| ReorgCalcError::MainnetFeed(format!("{}: {}", url, e)))?;
+    parse_mining_hashrate(body)
+}
+
+/// Read the current hashrate from a mempool.space `mining/hashrate`
+/// response.
+pub fn parse_mining_hashrate(body: &str) -> Result<f64> {
+    let bad_response =
+        |reason: String| ReorgCalcError::MainnetFeed(format!("unexpected mempool.space response: {}", reason));
+    let mining: MiningHashrate = serde_json::from_str(body).map_err(|e| bad_response(e.to_string()))?;
+    Some(mining.current_hashrate)
+        .filter(|hashrate| *hashrate > 0.0 && hashrate.is_finite())
+        .ok_or_else(|| bad_response("no positive currentHashrate".to_string()))
+}
@@ -0,0 +1,148 @@
+//! Current SHA-256 rental prices from hashrate marketplaces.
+//!
+//! Queries the public price endpoints of NiceHash and MiningRigRentals and
+//! normalizes the answer to a price per TH/s per day. The built-in HTTP
+//! client speaks plain HTTP only, so the HTTPS defaults need a build of
+//! minreq with TLS, or a URL pointing at an HTTP mirror or proxy.
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::budget::RentalPrice;
+use crate::error::{ReorgCalcError, Result};
+
+/// A hashrate marketplace with a public price endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marketplace {
+    NiceHash,
+    MiningRigRentals,
+}
+
+impl Marketplace {
+    /// Public endpoint the price is read from.
+    pub fn default_url(self) -> &'static str {
+        match self {
+            Marketplace::NiceHash => "https://api2.nicehash.com/main/api/v2/public/simplemultialgo/info",
+            Marketplace::MiningRigRentals => "https://www.miningrigrentals.com/api/v2/info/algos/sha256",
+        }
+    }
+}
+
+impl fmt::Display for Marketplace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Marketplace::NiceHash => "NiceHash",
+            Marketplace::MiningRigRentals => "MiningRigRentals",
+        })
+    }
+}
+
+impl FromStr for Marketplace {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "nicehash" => Ok(Marketplace::NiceHash),
+            "mrr" | "miningrigrentals" => Ok(Marketplace::MiningRigRentals),
+            _ => Err(format!("unknown marketplace '{}' (expected nicehash or mrr)", name)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NiceHashInfo {
+    #[serde(rename = "miningAlgorithms")]
+    mining_algorithms: Vec<NiceHashAlgorithm>,
+}
+
+#[derive(Deserialize)]
+struct NiceHashAlgorithm {
+    algorithm: String,
+    /// BTC per TH/s per day for the SHA-256 markets.
+    paying: String,
+}
+
+#[derive(Deserialize)]
+struct MrrResponse {
+    data: MrrAlgorithm,
+}
+
+#[derive(Deserialize)]
+struct MrrAlgorithm {
+    suggested_price: MrrPrice,
+}
+
+#[derive(Deserialize)]
+struct MrrPrice {
+    amount: String,
+    currency: String,
+    /// Hashrate-time the amount is for, e.g. `th*day`.
+    unit: String,
+}
+
+/// Fetch the current price from `marketplace`, at `url` or its default
+/// endpoint.
+pub fn fetch_rental_price(marketplace: Marketplace, url: Option<&str>) -> Result<RentalPrice> {
+    let url = url.unwrap_or(marketplace.default_url());
+    let response = minreq::get(url)
+        .with_timeout(30)
+        .send()
+        .map_err(|e| ReorgCalcError::Marketplace(format!("{}: {}", url, e)))?;
+    if response.status_code != 200 {
+        return Err(ReorgCalcError::Marketplace(format!("{} returned HTTP {}", url, response.status_code)));
+    }
+    let body = response.as_str().map_err(|e| ReorgCalcError::Marketplace(format!("{}: {}", url, e)))?;
+    match marketplace {
+        Marketplace::NiceHash => parse_nicehash(body),
+        Marketplace::MiningRigRentals => parse_mining_rig_rentals(body),
+    }
+}
+
+/// Read the SHA-256 price from a NiceHash `simplemultialgo/info` response.
+pub fn parse_nicehash(body: &str) -> Result<RentalPrice> {
+    let info: NiceHashInfo = serde_json::from_str(body).map_err(|e| bad_response("NiceHash", e))?;
+    let algorithm = ["SHA256ASICBOOST", "SHA256"]
+        .iter()
+        .find_map(|name| info.mining_algorithms.iter().find(|algorithm| algorithm.algorithm == *name))
+        .ok_or_else(|| bad_response("NiceHash", "no SHA-256 market"))?;
+    Ok(RentalPrice {
+        source: Marketplace::NiceHash.to_string(),
+        price_per_th_day: parse_amount("NiceHash", &algorithm.paying)?,
+        currency: "BTC".to_string(),
+    })
+}
+
+/// Read the suggested price from a MiningRigRentals `info/algos/sha256`
+/// response.
+pub fn parse_mining_rig_rentals(body: &str) -> Result<RentalPrice> {
+    let response: MrrResponse = serde_json::from_str(body).map_err(|e| bad_response("MiningRigRentals", e))?;
+    let price = response.data.suggested_price;
+    let per_unit = parse_amount("MiningRigRentals", &price.amount)?;
+    let unit = price.unit.to_ascii_lowercase();
+    let scale = match unit.strip_suffix("*day").unwrap_or(&unit) {
+        "mh" => 1e6,
+        "gh" => 1e3,
+        "th" => 1.0,
+        "ph" => 1e-3,
+        "eh" => 1e-6,
+        _ => return Err(bad_response("MiningRigRentals", format!("unknown price unit {}", price.unit))),
+    };
+    Ok(RentalPrice {
+        source: Marketplace::MiningRigRentals.to_string(),
+        price_per_th_day: per_unit * scale,
+        currency: price.currency.to_ascii_uppercase(),
+    })
+}
+
+fn parse_amount(marketplace: &str, amount: &str) -> Result<f64> {
+    amount
+        .parse()
+        .ok()
+        .filter(|amount: &f64| *amount >= 0.0 && amount.is_finite())
+        .ok_or_else(|| bad_response(marketplace, format!("bad price {}", amount)))
+}
+
+fn bad_response(marketplace: &str, reason: impl fmt::Display) -> ReorgCalcError {
+    ReorgCalcError::Marketplace(format!("unexpected {} response: {}", marketplace, reason))
+}
@@ -0,0 +1,105 @@
+//! Attacker use of the testnet 20-minute rule.
+//!
+//! On networks that allow minimum-difficulty blocks, a block whose timestamp
+//! is more than twice the target spacing after its parent's may be mined at
+//! the proof-of-work limit. An attacker can insert such blocks almost for
+//! free, limited only by timestamps: each one moves the chain's clock
+//! forward by the gap, and block times may not run more than two hours
+//! ahead of real time. They lengthen the chain but add next to no work.
+
+use serde::{Deserialize, Serialize};
+
+use crate::network::NetworkParams;
+
+/// Furthest a block timestamp may be ahead of the node's clock, in seconds.
+pub const MAX_FUTURE_DRIFT: u64 = 2 * 60 * 60;
+
+/// Minimum-difficulty blocks an attacker can add to the reorg chain.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinDifficultyStrategy {
+    /// Blocks that fit in the timestamp slack between the fork point and
+    /// real time, available as soon as the attack starts.
+    pub upfront_blocks: u64,
+    /// Blocks that fit in the time it takes to mine one real block.
+    pub per_real_block: f64,
+    /// Blocks inserted over the whole attack.
+    pub inserted_blocks: u64,
+    /// Length of the reorg chain including them.
+    pub chain_blocks: f64,
+    /// Work they add, in difficulty units.
+    pub work: f64,
+    /// Real blocks that work replaces; usually a tiny fraction of one.
+    pub real_blocks_saved: f64,
+}
+
+/// Seconds each minimum-difficulty block advances the chain's timestamps.
+pub fn min_difficulty_gap(params: &NetworkParams) -> u64 {
+    params.consensus().pow_target_spacing * 2 + 1
+}
+
+/// Plan minimum-difficulty insertions for an attack that mines
+/// `real_blocks` blocks at `difficulty` over `attack_seconds`, starting at
+/// Unix time `start_time` on top of a fork parent timestamped `parent_time`.
+///
+/// Returns `None` when the network has no 20-minute rule or the attack never
+/// finishes.
+pub fn plan_min_difficulty_blocks(
+    real_blocks: f64,
+    difficulty: f64,
+    attack_seconds: f64,
+    start_time: i64,
+    parent_time: u32,
+    params: &NetworkParams,
+) -> Option<MinDifficultyStrategy> {
+    if !params.consensus().allow_min_difficulty_blocks || !attack_seconds.is_finite() {
+        return None;
+    }
+    let gap = min_difficulty_gap(params) as f64;
+
+    // Timestamps may run from the fork parent up to two hours past real time
+    let slack = (start_time + MAX_FUTURE_DRIFT as i64 - parent_time as i64).max(0) as f64;
+    let upfront_blocks = (slack / gap).floor() as u64;
+    let per_real_block = if real_blocks > 0.0 { attack_seconds / real_blocks / gap } else { 0.0 };
+    let inserted_blocks = ((slack + attack_seconds) / gap).floor() as u64;
+
+    // Each one carries the work of the proof-of-work limit, difficulty 1
+    let work = inserted_blocks as f64;
+    Some(MinDifficultyStrategy {
+        upfront_blocks,
+        per_real_block,
+        inserted_blocks,
+        chain_blocks: real_blocks + inserted_blocks as f64,
+        work,
+        real_blocks_saved: work / difficulty,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network;
+
+    #[test]
+    fn insertions_fill_the_timestamp_slack() {
+        let params = NetworkParams::default();
+        assert_eq!(min_difficulty_gap(&params), 1201);
+
+        // Two hours of slack hold 5 blocks, and 16,010 more seconds 10 more
+        let strategy = plan_min_difficulty_blocks(10.0, 1000.0, 12_010.0, 1_000_000, 1_000_000, &params).unwrap();
+        assert_eq!((strategy.upfront_blocks, strategy.inserted_blocks), (5, 15));
+        assert_eq!((strategy.per_real_block, strategy.chain_blocks, strategy.work), (1.0, 25.0, 15.0));
+        assert_eq!(strategy.real_blocks_saved, 0.015);
+
+        // A parent timestamped beyond the drift limit leaves no slack
+        let strategy = plan_min_difficulty_blocks(10.0, 1000.0, 12_010.0, 1_000_000, 1_010_000, &params).unwrap();
+        assert_eq!((strategy.upfront_blocks, strategy.inserted_blocks), (0, 10));
+    }
+
+    #[test]
+    fn no_plan_without_the_rule_or_an_end() {
+        let mainnet = NetworkParams::from(Network::Bitcoin);
+        assert_eq!(plan_min_difficulty_blocks(10.0, 1000.0, 6000.0, 0, 0, &mainnet), None);
+        let testnet4 = NetworkParams::default();
+        assert_eq!(plan_min_difficulty_blocks(f64::INFINITY, 1000.0, f64::INFINITY, 0, 0, &testnet4), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Who mined the blocks a reorg replaces.
+//!
+//! Pools mark their blocks with a tag in the coinbase scriptSig, after the
+//! height BIP34 puts first, and pay the reward to their own addresses. A
+//! reorg orphans those blocks and takes the rewards back, so grouping the
+//! span by tag shows which miners a planned test reorg lands on. Blocks
+//! without a readable tag are grouped by where they pay instead.
+
+use bitcoin::{script::Instruction, Address, Transaction};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::Result;
+use crate::network::NetworkParams;
+use crate::output::progress_bar;
+use crate::progress;
+use crate::source::BlockSource;
+use crate::work::SCAN_BATCH_SIZE;
+
+/// Shortest run of printable characters taken for a tag.
+const MIN_TAG_LENGTH: usize = 4;
+
+/// Blocks of a span one miner produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinerBlocks {
+    /// Coinbase tag, or the payout address of blocks without one.
+    pub miner: String,
+    /// Whether `miner` is a tag rather than an address.
+    pub tagged: bool,
+    /// Address the miner's first block in the span pays, if it is a
+    /// standard one.
+    pub payout: Option<String>,
+    pub blocks: u64,
+    /// Fraction of the span's blocks.
+    pub share: f64,
+    pub first_height: u64,
+    pub last_height: u64,
+}
+
+/// The tag a coinbase carries: the first `/`-delimited name if there is
+/// one, as most pools write it, and otherwise the longest printable run.
+pub fn coinbase_tag(coinbase: &Transaction) -> Option<String> {
+    let script = &coinbase.input.first()?.script_sig;
+    // Skip the BIP34 height, whose bytes can happen to be printable
+    let start = match script.instructions().next() {
+        Some(Ok(Instruction::PushBytes(height))) => 1 + height.len(),
+        Some(Ok(Instruction::Op(_))) => 1,
+        _ => 0,
+    };
+    let bytes = script.as_bytes().get(start..).unwrap_or_default();
+    let runs: Vec<String> = bytes
+        .split(|byte| !(0x20..=0x7e).contains(byte))
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .filter(|run| run.len() >= MIN_TAG_LENGTH)
+        .collect();
+
+    let slashed = runs.iter().find_map(|run| {
+        let mut parts = run.split('/');
+        parts.next()?;
+        let name = parts.next()?.trim();
+        (parts.next().is_some() && !name.is_empty()).then(|| name.to_string())
+    });
+    slashed.or_else(|| runs.into_iter().max_by_key(|run| run.len()))
+}
+
+/// Address the coinbase pays its reward to: the first standard output with
+/// value.
+pub fn coinbase_payout(coinbase: &Transaction, params: &NetworkParams) -> Option<String> {
+    coinbase
+        .output
+        .iter()
+        .filter(|output| output.value.to_sat() > 0)
+        .find_map(|output| Address::from_script(&output.script_pubkey, params.network).ok())
+        .map(|address| address.to_string())
+}
+
+/// Group the blocks from `fork_height` to `current_height` inclusive by the
+/// miner that produced them, most blocks first.
+///
+/// Needs every block's coinbase, so the whole span is fetched; sources that
+/// only hold headers cannot answer.
+pub fn identify_miners<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+) -> Result<Vec<MinerBlocks>> {
+    let params = source.get_network()?;
+    let span_blocks = current_height + 1 - fork_height;
+    progress!("Fetching coinbases of blocks {} to {}...", fork_height, current_height);
+    let pb = progress_bar(span_blocks);
+    let heights: Vec<u64> = (fork_height..=current_height).collect();
+    let batches = heights
+        .par_chunks(SCAN_BATCH_SIZE)
+        .map(|heights| {
+            let coinbases = source.get_coinbases(heights)?;
+            pb.inc(heights.len() as u64);
+            Ok(coinbases.iter().map(|coinbase| (coinbase_tag(coinbase), coinbase_payout(coinbase, &params))).collect())
+        })
+        .collect::<Result<Vec<Vec<_>>>>();
+    pb.finish_and_clear();
+
+    let mut miners: Vec<MinerBlocks> = Vec::new();
+    let mut index: HashMap<(bool, String), usize> = HashMap::new();
+    for (height, (tag, payout)) in heights.into_iter().zip(batches?.into_iter().flatten()) {
+        let (tagged, miner) = match (tag, &payout) {
+            (Some(tag), _) => (true, tag),
+            (None, Some(address)) => (false, address.clone()),
+            (None, None) => (false, "untagged".to_string()),
+        };
+        let position = *index.entry((tagged, miner.clone())).or_insert_with(|| {
+            miners.push(MinerBlocks {
+                miner,
+                tagged,
+                payout,
+                blocks: 0,
+                share: 0.0,
+                first_height: height,
+                last_height: height,
+            });
+            miners.len() - 1
+        });
+        let entry = &mut miners[position];
+        entry.blocks += 1;
+        entry.last_height = height;
+    }
+    for miner in &mut miners {
+        miner.share = miner.blocks as f64 / span_blocks as f64;
+    }
+    miners.sort_by(|a, b| b.blocks.cmp(&a.blocks).then(a.first_height.cmp(&b.first_height)));
+    Ok(miners)
+}
@@ -0,0 +1,204 @@
+//! The settings a run's calculations are made with.
+//!
+//! A [`Model`] gathers the margin, honest-growth, retarget, simulation and
+//! cost options of a run. [`Model::adjustments`] resolves it against a
+//! source into the [`Adjustments`] each calculation gets, measuring the
+//! network hashrate when the model needs it, and [`Model::annotate`] adds
+//! the figures reported alongside a calculation: the hashrate for each
+//! extra target time, the rental price, the energy used and the schedule.
+
+use crate::budget::{energy_use, ExchangeRate, Money, RentalPrice};
+use crate::calc::{Adjustments, Margin, ReorgCalculation};
+use crate::devices::Device;
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::schedule::attack_schedule;
+use crate::source::BlockSource;
+use crate::work::{estimate_network_hashrate, measure_network_hashrate, HASHRATE_WINDOW};
+
+/// Margin, honest-growth, retarget, min-difficulty, simulation, race,
+/// efficiency and overhead settings, and the target times, rental price,
+/// energy figures and exchange rate to report.
+#[derive(Debug, Clone, Copy)]
+pub struct Model<'a> {
+    pub margin: Margin,
+    pub honest_growth: bool,
+    pub honest_hashrate: Option<f64>,
+    /// Blocks to measure the network hashrate over.
+    pub hashrate_window: u64,
+    pub retarget: bool,
+    pub min_difficulty_strategy: bool,
+    pub next_retarget: bool,
+    pub simulate: Option<u64>,
+    pub race: bool,
+    pub schedule: bool,
+    pub efficiency: Option<f64>,
+    pub overhead_percent: Option<f64>,
+    /// Every target time to report the hashrate for, when there is more
+    /// than one.
+    pub target_days: &'a [f64],
+    /// Marketplace price, e.g. from `--rent-from`.
+    pub rental: Option<&'a RentalPrice>,
+    /// Hardware efficiency for the energy estimate.
+    pub joules_per_th: Option<f64>,
+    /// Electricity price, e.g. from `--power-cost`.
+    pub power_cost: Option<&'a Money>,
+    /// BTC rate, e.g. from `--fiat`.
+    pub exchange_rate: Option<&'a ExchangeRate>,
+    /// Mainnet hashrate and where it came from.
+    pub mainnet: Option<(f64, &'a str)>,
+    /// Model the hashrates required are counted in.
+    pub reference_device: Option<&'a Device>,
+}
+
+/// Nothing adjusted or added: no margin, no honest growth, one target
+/// time, and the network hashrate measured over [`HASHRATE_WINDOW`] blocks.
+impl Default for Model<'_> {
+    fn default() -> Self {
+        Model {
+            margin: Margin::default(),
+            honest_growth: false,
+            honest_hashrate: None,
+            hashrate_window: HASHRATE_WINDOW,
+            retarget: false,
+            min_difficulty_strategy: false,
+            next_retarget: false,
+            simulate: None,
+            race: false,
+            schedule: false,
+            efficiency: None,
+            overhead_percent: None,
+            target_days: &[],
+            rental: None,
+            joules_per_th: None,
+            power_cost: None,
+            exchange_rate: None,
+            mainnet: None,
+            reference_device: None,
+        }
+    }
+}
+
+impl Model<'_> {
+    /// The honest hashrate given, or measured over recent blocks.
+    pub fn honest_hashrate(&self, source: &dyn BlockSource) -> Result<f64> {
+        match self.honest_hashrate {
+            Some(hashrate) => Ok(hashrate),
+            None => estimate_network_hashrate(source, self.hashrate_window),
+        }
+    }
+
+    /// Resolve into [`Adjustments`], measuring the network hashrate over
+    /// recent blocks and assuming it for the honest one unless that was
+    /// given. Only a model that needs it fails when it cannot be measured.
+    pub fn adjustments(&self, source: &dyn BlockSource) -> Result<Adjustments> {
+        let assumed = self.honest_growth || self.race;
+        let network_hashrate = match measure_network_hashrate(source, self.hashrate_window) {
+            Ok(network) => Some(network),
+            Err(e) if assumed && self.honest_hashrate.is_none() => return Err(e),
+            Err(_) => None,
+        };
+        let honest_hashrate = match (assumed, self.honest_hashrate) {
+            (false, _) => None,
+            (true, Some(hashrate)) => Some(hashrate),
+            (true, None) => network_hashrate.map(|network| network.hashrate),
+        };
+        Ok(Adjustments {
+            margin: self.margin,
+            honest_hashrate: honest_hashrate.filter(|_| self.honest_growth),
+            retarget: self.retarget,
+            min_difficulty_strategy: self.min_difficulty_strategy,
+            next_retarget: self.next_retarget,
+            simulate: self.simulate,
+            race_hashrate: honest_hashrate.filter(|_| self.race),
+            network_hashrate,
+            efficiency: self.efficiency,
+            overhead_percent: self.overhead_percent,
+        })
+    }
+
+    /// [`Self::adjustments`] without a node to measure or scan; only a given
+    /// honest hashrate can be used.
+    pub fn offline_adjustments(&self) -> Result<Adjustments> {
+        if self.retarget || self.min_difficulty_strategy || self.next_retarget {
+            return Err(ReorgCalcError::InvalidInput(
+                "--retarget, --min-difficulty-strategy and --next-retarget need block headers from a node".to_string(),
+            ));
+        }
+        if (self.honest_growth || self.race) && self.honest_hashrate.is_none() {
+            return Err(ReorgCalcError::InvalidInput(
+                "--honest-growth and --race need --honest-hashrate without a node to measure it".to_string(),
+            ));
+        }
+        Ok(Adjustments {
+            margin: self.margin,
+            honest_hashrate: self.honest_hashrate.filter(|_| self.honest_growth),
+            retarget: false,
+            min_difficulty_strategy: false,
+            next_retarget: false,
+            simulate: self.simulate,
+            race_hashrate: self.honest_hashrate.filter(|_| self.race),
+            network_hashrate: None,
+            efficiency: self.efficiency,
+            overhead_percent: self.overhead_percent,
+        })
+    }
+
+    /// Add the hashrate for each extra target time, the rental price, the
+    /// reference device, the energy used mining at `hashrate`, the exchange
+    /// rate, the mainnet comparison and the schedule to `calc`.
+    pub fn annotate(
+        &self,
+        calc: ReorgCalculation,
+        hashrate: f64,
+        target_days: f64,
+        params: &NetworkParams,
+    ) -> ReorgCalculation {
+        let mut calc = match self.target_days {
+            [] => calc,
+            days => calc.with_target_days(days, target_days, params),
+        };
+        calc.rental_price = self.rental.cloned();
+        calc.reference_device = self.reference_device.cloned();
+        calc.energy = self
+            .joules_per_th
+            .map(|joules| energy_use(&calc, hashrate, joules, self.power_cost.cloned()));
+        calc.exchange_rate = self.exchange_rate.cloned();
+        let mut calc = match self.mainnet {
+            Some((hashrate, source)) => calc.with_mainnet(hashrate, source, params),
+            None => calc,
+        };
+        if self.schedule {
+            calc.schedule = attack_schedule(&calc, calc.timestamp);
+        }
+        calc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{TestChain, DIFFICULTY_1};
+
+    #[test]
+    fn offline_models_need_the_honest_hashrate_given() {
+        let race = Model { race: true, ..Model::default() };
+        assert!(matches!(race.offline_adjustments(), Err(ReorgCalcError::InvalidInput(_))));
+        let race = Model { honest_hashrate: Some(2e12), ..race };
+        assert_eq!(race.offline_adjustments().unwrap().race_hashrate, Some(2e12));
+        let retarget = Model { retarget: true, ..Model::default() };
+        assert!(matches!(retarget.offline_adjustments(), Err(ReorgCalcError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn the_honest_hashrate_is_measured_unless_given() {
+        let chain = TestChain::uniform(200, DIFFICULTY_1);
+        let growth = Model { honest_growth: true, ..Model::default() };
+        let measured = growth.adjustments(&chain).unwrap();
+        assert_eq!(measured.honest_hashrate, measured.network_hashrate.map(|network| network.hashrate));
+        assert!(measured.race_hashrate.is_none());
+        let given = Model { honest_hashrate: Some(5e12), ..growth };
+        assert_eq!(given.honest_hashrate(&chain).unwrap(), 5e12);
+        assert_eq!(given.adjustments(&chain).unwrap().honest_hashrate, Some(5e12));
+    }
+}
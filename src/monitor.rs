@@ -0,0 +1,151 @@
+//! Events seen while watching the chain.
+//!
+//! `watch` and `daemon` report these alongside their calculations, so a
+//! central sink records not just the figures but what the chain did
+//! between them. The last [`REORG_WINDOW`] blocks of the active chain are
+//! remembered, so a reorg is reported with the blocks it displaced.
+
+use bitcoin::BlockHash;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::error::Result;
+use crate::source::{BlockSource, ChainTip};
+
+/// Blocks below the tip [`tip_events`] remembers to name those a reorg
+/// displaces.
+pub const REORG_WINDOW: u64 = 100;
+
+/// Something that happened while monitoring.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorEvent {
+    pub time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: MonitorEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MonitorEventKind {
+    /// The best block changed.
+    NewTip { height: u64, hash: BlockHash },
+    /// The previously seen tip is no longer in the active chain.
+    Reorg {
+        /// Height and hash of the tip that was replaced.
+        height: u64,
+        hash: BlockHash,
+        /// Height of the lowest block displaced; if the reorg displaced every
+        /// remembered block, it went at least this deep.
+        fork_height: u64,
+        /// Blocks displaced, from `fork_height` to the replaced tip.
+        depth: u64,
+        /// The displaced blocks, lowest first.
+        displaced: Vec<SeenBlock>,
+    },
+    /// A recalculation failed; monitoring carries on.
+    RecalculationFailed { reason: String },
+    /// An alert rule started to hold (see [`crate::alert`]).
+    AlertFired {
+        rule: String,
+        fork_height: u64,
+        /// The figure the rule compared, or `None` if infinite.
+        value: Option<f64>,
+    },
+}
+
+impl MonitorEventKind {
+    /// Short name stored with the event, e.g. `new_tip`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MonitorEventKind::NewTip { .. } => "new_tip",
+            MonitorEventKind::Reorg { .. } => "reorg",
+            MonitorEventKind::RecalculationFailed { .. } => "recalculation_failed",
+            MonitorEventKind::AlertFired { .. } => "alert_fired",
+        }
+    }
+}
+
+/// A block of the active chain as it was seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeenBlock {
+    pub height: u64,
+    pub hash: BlockHash,
+    /// The block's timestamp.
+    pub time: DateTime<Utc>,
+}
+
+/// The blocks of the active chain seen last, up to [`REORG_WINDOW`] of
+/// them below the tip.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeenChain {
+    /// Consecutive blocks, oldest first.
+    blocks: VecDeque<SeenBlock>,
+}
+
+impl SeenChain {
+    /// The tip seen last, if any.
+    pub fn tip(&self) -> Option<ChainTip> {
+        self.blocks.back().map(|block| ChainTip { height: block.height, hash: block.hash })
+    }
+}
+
+impl MonitorEvent {
+    /// `kind`, happening now.
+    pub fn now(kind: MonitorEventKind) -> Self {
+        MonitorEvent { time: Utc::now(), kind }
+    }
+}
+
+/// Compare the chain with `seen`, the blocks seen before, and update it:
+/// the events are empty when nothing changed, otherwise a new tip,
+/// preceded by a reorg when the tip seen last has left the active chain.
+pub fn tip_events<S: BlockSource + ?Sized>(source: &S, seen: &mut SeenChain) -> Result<Vec<MonitorEvent>> {
+    let tip = source.get_tip()?;
+    let previous = seen.tip();
+    if previous == Some(tip) {
+        return Ok(Vec::new());
+    }
+    let mut events = Vec::new();
+    // Forget the seen blocks that have left the active chain, newest first
+    let mut displaced = Vec::new();
+    while let Some(&block) = seen.blocks.back() {
+        if block.height <= tip.height && source.get_block_hash(block.height)? == block.hash {
+            break;
+        }
+        displaced.push(block);
+        seen.blocks.pop_back();
+    }
+    displaced.reverse();
+    if let (Some(previous), Some(lowest)) = (previous, displaced.first()) {
+        events.push(MonitorEvent::now(MonitorEventKind::Reorg {
+            height: previous.height,
+            hash: previous.hash,
+            fork_height: lowest.height,
+            depth: previous.height - lowest.height + 1,
+            displaced,
+        }));
+    }
+    
+    let oldest = tip.height.saturating_sub(REORG_WINDOW);
+    let first = seen.tip().map_or(oldest, |last| last.height + 1).max(oldest);
+    if seen.tip().is_some_and(|last| last.height + 1 < first) {
+        seen.blocks.clear();
+    }
+    let heights: Vec<u64> = (first..=tip.height).collect();
+    for (height, header) in heights.iter().zip(source.get_headers_batch(&heights)?) {
+        seen.blocks.push_back(SeenBlock {
+            height: *height,
+            hash: header.block_hash(),
+            time: DateTime::from_timestamp(header.time.into(), 0).unwrap_or_default(),
+        });
+    }
+    while seen.blocks.len() as u64 > REORG_WINDOW + 1 {
+        seen.blocks.pop_front();
+    }
+    events.push(MonitorEvent::now(MonitorEventKind::NewTip {
+        height: tip.height,
+        hash: tip.hash,
+    }));
+    Ok(events)
+}
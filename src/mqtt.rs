@@ -0,0 +1,343 @@
+//! Publisher for MQTT brokers.
+//!
+//! Implements just enough of MQTT 3.1.1 (CONNECT with an optional username
+//! and password, PUBLISH at QoS 1) to hand monitoring results to a broker,
+//! so farm controllers and dashboards can subscribe to them. Under the
+//! topic prefix:
+//!
+//! - `<prefix>/depth/<n>`: the latest calculation `n` blocks below the tip,
+//!   as JSON, retained so a new subscriber gets it straight away.
+//! - `<prefix>/alert`: a reorg, a failed recalculation, an `--alert` rule
+//!   firing, or a depth becoming viable within its target time or ceasing
+//!   to be, as JSON.
+//!
+//! Connections are plain TCP; a dropped one is reopened on the next publish.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::calc::ReorgCalculation;
+use crate::error::{ReorgCalcError, Result};
+use crate::monitor::{MonitorEvent, MonitorEventKind};
+
+/// Default topic prefix.
+pub const MQTT_TOPIC: &str = "testnet4-reorg";
+const DEFAULT_PORT: u16 = 1883;
+/// Upper bound for the broker to answer a CONNECT or PUBLISH.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH_QOS1: u8 = 0x32;
+const PUBACK: u8 = 0x40;
+const FLAG_RETAIN: u8 = 0x01;
+
+/// The latest calculation at one depth.
+#[derive(Serialize)]
+struct DepthSummary<'a> {
+    time: DateTime<Utc>,
+    depth: u64,
+    fork_height: u64,
+    current_height: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scenario: Option<&'a str>,
+    hashrate: f64,
+    blocks_needed: f64,
+    /// `None` for an attack that never finishes.
+    time_required_days: Option<f64>,
+    hashrate_required: Option<f64>,
+    target_days: Option<f64>,
+    viable: Option<bool>,
+}
+
+/// A depth crossing its target time.
+#[derive(Serialize)]
+struct ViabilityAlert {
+    time: DateTime<Utc>,
+    kind: &'static str,
+    depth: u64,
+    fork_height: u64,
+    time_required_days: Option<f64>,
+    target_days: f64,
+}
+
+/// A connection to a broker, publishing under one topic prefix.
+pub struct MqttPublisher {
+    addr: String,
+    credentials: Option<(String, Option<String>)>,
+    client_id: String,
+    prefix: String,
+    stream: Option<TcpStream>,
+    packet_id: u16,
+    /// Whether each depth was viable at its last calculation.
+    viable: HashMap<u64, bool>,
+}
+
+impl MqttPublisher {
+    /// Connect to the broker at `url`, `mqtt://[user[:password]@]host[:port]`,
+    /// to publish under `prefix`.
+    pub fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let rest = url.strip_prefix("mqtt://").unwrap_or(url);
+        let (credentials, host) = match rest.rsplit_once('@') {
+            Some((user, host)) => {
+                let credentials = match user.split_once(':') {
+                    Some((user, password)) => (user.to_string(), Some(password.to_string())),
+                    None => (user.to_string(), None),
+                };
+                (Some(credentials), host)
+            }
+            None => (None, rest),
+        };
+        let host = host.trim_end_matches('/');
+        if host.is_empty() {
+            return Err(ReorgCalcError::Mqtt(format!("no broker host in {}", url)));
+        }
+        // An IPv6 address has colons of its own, and a port only after `]`
+        let has_port = host.rsplit_once(':').is_some_and(|(name, port)| {
+            port.parse::<u16>().is_ok() && (!name.contains(':') || name.ends_with(']'))
+        });
+        let addr = if has_port { host.to_string() } else { format!("{}:{}", host, DEFAULT_PORT) };
+
+        let mut publisher = Self {
+            addr,
+            credentials,
+            client_id: format!("testnet4-reorg-{}", std::process::id()),
+            prefix: prefix.trim_end_matches('/').to_string(),
+            stream: None,
+            packet_id: 0,
+            viable: HashMap::new(),
+        };
+        publisher.stream = Some(publisher.open()?);
+        Ok(publisher)
+    }
+
+    /// Publish `calc`, made for `hashrate`, to its depth's topic, and alert
+    /// if the depth became viable or stopped being so.
+    pub fn publish_calculation(&mut self, calc: &ReorgCalculation, hashrate: f64) -> Result<()> {
+        let depth = calc.current_height.saturating_sub(calc.fork_height);
+        let viable = calc.target_days.map(|days| calc.time_required_days <= days);
+        let summary = DepthSummary {
+            time: calc.timestamp,
+            depth,
+            fork_height: calc.fork_height,
+            current_height: calc.current_height,
+            scenario: calc.scenario.as_deref(),
+            hashrate,
+            blocks_needed: calc.blocks_needed,
+            time_required_days: finite(calc.time_required_days),
+            hashrate_required: finite(calc.hashrate_required),
+            target_days: calc.target_days,
+            viable,
+        };
+        self.publish(&format!("{}/depth/{}", self.prefix, depth), &json(&summary)?, true)?;
+
+        let (Some(viable), Some(target_days)) = (viable, calc.target_days) else {
+            return Ok(());
+        };
+        // A depth first seen viable is news; one first seen out of reach is not
+        let was_viable = self.viable.insert(depth, viable).unwrap_or(false);
+        if viable != was_viable {
+            let alert = ViabilityAlert {
+                time: calc.timestamp,
+                kind: if viable { "viable" } else { "no_longer_viable" },
+                depth,
+                fork_height: calc.fork_height,
+                time_required_days: finite(calc.time_required_days),
+                target_days,
+            };
+            self.publish(&format!("{}/alert", self.prefix), &json(&alert)?, false)?;
+        }
+        Ok(())
+    }
+
+    /// Publish `event` as an alert if it is a reorg or a failure; new tips
+    /// show up in the depth topics anyway.
+    pub fn publish_event(&mut self, event: &MonitorEvent) -> Result<()> {
+        if matches!(event.kind, MonitorEventKind::NewTip { .. }) {
+            return Ok(());
+        }
+        self.publish(&format!("{}/alert", self.prefix), &json(event)?, false)
+    }
+
+    /// Publish `payload` to `topic`, waiting for the broker to acknowledge
+    /// it, and retry once on a fresh connection if the first attempt fails.
+    pub fn publish(&mut self, topic: &str, payload: &[u8], retain: bool) -> Result<()> {
+        if let Some(stream) = &mut self.stream {
+            self.packet_id = self.packet_id.checked_add(1).unwrap_or(1);
+            if publish(stream, self.packet_id, topic, payload, retain).is_ok() {
+                return Ok(());
+            }
+        }
+        self.stream = None;
+        let mut stream = self.open()?;
+        self.packet_id = self.packet_id.checked_add(1).unwrap_or(1);
+        publish(&mut stream, self.packet_id, topic, payload, retain)
+            .map_err(|e| ReorgCalcError::Mqtt(format!("{} on {}: {}", topic, self.addr, e)))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Connect and log in.
+    fn open(&self) -> Result<TcpStream> {
+        let mut stream =
+            TcpStream::connect(&self.addr).map_err(|e| ReorgCalcError::Mqtt(format!("{}: {}", self.addr, e)))?;
+        stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+
+        // Clean session, no keep-alive: the broker never drops an idle client
+        let mut flags = 0x02;
+        let mut payload = Vec::new();
+        put_string(&mut payload, self.client_id.as_bytes());
+        if let Some((user, password)) = &self.credentials {
+            flags |= 0x80;
+            put_string(&mut payload, user.as_bytes());
+            if let Some(password) = password {
+                flags |= 0x40;
+                put_string(&mut payload, password.as_bytes());
+            }
+        }
+        let mut body = Vec::new();
+        put_string(&mut body, b"MQTT");
+        body.extend_from_slice(&[4, flags, 0, 0]);
+        body.extend_from_slice(&payload);
+        write_packet(&mut stream, CONNECT, &body)?;
+
+        let (kind, reply) = read_packet(&mut stream)?;
+        if kind & 0xf0 != CONNACK || reply.len() < 2 {
+            return Err(ReorgCalcError::Mqtt(format!("{} is not an MQTT broker", self.addr)));
+        }
+        let refused = match reply[1] {
+            0 => return Ok(stream),
+            1 => "unsupported protocol version",
+            2 => "client identifier rejected",
+            3 => "server unavailable",
+            4 => "bad user name or password",
+            5 => "not authorized",
+            _ => "connection refused",
+        };
+        Err(ReorgCalcError::Mqtt(format!("{}: {}", self.addr, refused)))
+    }
+}
+
+fn publish(stream: &mut TcpStream, packet_id: u16, topic: &str, payload: &[u8], retain: bool) -> io::Result<()> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 4);
+    put_string(&mut body, topic.as_bytes());
+    body.extend_from_slice(&packet_id.to_be_bytes());
+    body.extend_from_slice(payload);
+    write_packet(stream, PUBLISH_QOS1 | if retain { FLAG_RETAIN } else { 0 }, &body)?;
+    loop {
+        let (kind, reply) = read_packet(stream)?;
+        if kind & 0xf0 == PUBACK && reply.get(..2) == Some(&packet_id.to_be_bytes()[..]) {
+            return Ok(());
+        }
+    }
+}
+
+fn write_packet(stream: &mut TcpStream, kind: u8, body: &[u8]) -> io::Result<()> {
+    let mut packet = vec![kind];
+    // Remaining length: seven bits per byte, low bits first
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        packet.push(if length > 0 { byte | 0x80 } else { byte });
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    stream.write_all(&packet)
+}
+
+fn read_packet(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte)?;
+    let kind = byte[0];
+    let mut length = 0usize;
+    for shift in 0..4 {
+        stream.read_exact(&mut byte)?;
+        length |= ((byte[0] & 0x7f) as usize) << (7 * shift);
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0u8; length];
+            stream.read_exact(&mut body)?;
+            return Ok((kind, body));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "malformed MQTT packet length"))
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+fn json(value: &impl Serialize) -> Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| ReorgCalcError::Mqtt(e.to_string()))
+}
+
+/// `value`, or `null` for an attack that never finishes.
+fn finite(value: f64) -> Option<f64> {
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Both ends of a local connection.
+    fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn remaining_length_spans_bytes() {
+        let (mut client, mut server) = socket_pair();
+        for size in [0, 127, 200, 16_384] {
+            let body = vec![0xab; size];
+            write_packet(&mut client, PUBACK, &body).unwrap();
+            assert_eq!(read_packet(&mut server).unwrap(), (PUBACK, body));
+        }
+
+        write_packet(&mut client, PUBACK, &[0; 200]).unwrap();
+        let mut header = [0u8; 3];
+        server.read_exact(&mut header).unwrap();
+        assert_eq!(header, [PUBACK, 0xc8, 0x01]);
+
+        // No more than four length bytes
+        server.write_all(&[PUBACK, 0xff, 0xff, 0xff, 0xff, 0x01]).unwrap();
+        assert_eq!(read_packet(&mut client).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn publishes_wait_for_their_acknowledgement() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (kind, connect) = read_packet(&mut stream).unwrap();
+            assert_eq!(kind, CONNECT);
+            // Protocol name, level 4, user name and password, clean session
+            assert_eq!(&connect[..8], b"\x00\x04MQTT\x04\xc2");
+            write_packet(&mut stream, CONNACK, &[0, 0]).unwrap();
+
+            let (kind, publish) = read_packet(&mut stream).unwrap();
+            assert_eq!(kind, PUBLISH_QOS1 | FLAG_RETAIN);
+            assert_eq!(publish, b"\x00\x08tn4/test\x00\x01{}");
+            // A stray acknowledgement for another packet is skipped
+            write_packet(&mut stream, PUBACK, &[0, 9]).unwrap();
+            write_packet(&mut stream, PUBACK, &[0, 1]).unwrap();
+        });
+
+        let mut publisher = MqttPublisher::connect(&format!("mqtt://user:secret@{}", addr), "tn4/").unwrap();
+        publisher.publish("tn4/test", b"{}", true).unwrap();
+        broker.join().unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Per-network proof-of-work parameters.
+//!
+//! Difficulty 1 is defined by each network's easiest allowed target:
+//! `0x1d00ffff` on mainnet and the testnets, but much easier on signet and
+//! regtest. Consensus values come from rust-bitcoin's [`Params`].
+
+use bitcoin::consensus::Params;
+use bitcoin::{CompactTarget, Network, Target, Work};
+
+use crate::error::{ReorgCalcError, Result};
+use crate::HASHES_PER_DIFFICULTY;
+
+/// Proof-of-work reference values for one network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkParams {
+    pub network: Network,
+    /// Easiest allowed target; a block at this target has difficulty 1.
+    pub pow_limit: Target,
+}
+
+impl NetworkParams {
+    /// Look up a network by the `chain` name `getblockchaininfo` reports
+    /// (`main`, `test`, `testnet4`, `signet`, `regtest`).
+    pub fn from_chain(chain: &str) -> Result<Self> {
+        Network::from_core_arg(chain)
+            .map(Self::from)
+            .map_err(|_| ReorgCalcError::InvalidData(format!("unknown chain: {}", chain)))
+    }
+
+    /// Full consensus parameters of the network.
+    pub fn consensus(&self) -> &'static Params {
+        self.network.into()
+    }
+
+    /// Difficulty of a block with compact target `bits`.
+    pub fn difficulty(&self, bits: u32) -> f64 {
+        let target = Target::from_compact(CompactTarget::from_consensus(bits));
+        target_to_f64(self.pow_limit) / target_to_f64(target)
+    }
+
+    /// Express `work` in difficulty units, i.e. as a multiple of the work of
+    /// one difficulty-1 block.
+    ///
+    /// Conversion to `f64` loses precision; keep values as [`Work`] for any
+    /// comparison that must be exact.
+    pub fn work_to_difficulty(&self, work: Work) -> f64 {
+        work_to_f64(work) / work_to_f64(self.pow_limit.to_work())
+    }
+
+    /// Expected number of hashes to find a block at difficulty 1.
+    ///
+    /// This is [`HASHES_PER_DIFFICULTY`] on networks whose limit is
+    /// `0x1d00ffff`, scaled for networks with a different limit.
+    pub fn hashes_per_difficulty(&self) -> f64 {
+        HASHES_PER_DIFFICULTY * target_to_f64(Target::MAX) / target_to_f64(self.pow_limit)
+    }
+}
+
+impl From<Network> for NetworkParams {
+    fn from(network: Network) -> Self {
+        let params: &'static Params = network.into();
+        Self {
+            network,
+            pow_limit: params.max_attainable_target,
+        }
+    }
+}
+
+impl Default for NetworkParams {
+    /// Testnet4, the network this tool targets.
+    fn default() -> Self {
+        Network::Testnet4.into()
+    }
+}
+
+fn target_to_f64(target: Target) -> f64 {
+    bytes_to_f64(target.to_be_bytes())
+}
+
+/// `work` as a number of hashes.
+pub(crate) fn work_to_f64(work: Work) -> f64 {
+    bytes_to_f64(work.to_be_bytes())
+}
+
+fn bytes_to_f64(bytes: [u8; 32]) -> f64 {
+    bytes.iter().fold(0.0, |acc, &byte| acc * 256.0 + byte as f64)
+}
@@ -0,0 +1,95 @@
+//! Where the calculator's human-readable output goes.
+//!
+//! Tables, verdicts and notes are printed with [`say!`](crate::say) and
+//! warnings with [`warning!`](crate::warning), so a run that writes a report
+//! to stdout can move the rest to stderr, or drop it for `--quiet`, with
+//! [`set_output`]. Notes and bars tracking long scans go to stderr whatever
+//! the setting, unless output is hidden.
+
+use indicatif::ProgressBar;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Destination of [`say!`](crate::say) output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Stdout,
+    /// Leaves stdout to a report.
+    Stderr,
+    /// Nothing but errors is printed: no text, warnings or progress.
+    Hidden,
+}
+
+/// Set by [`set_output`].
+static OUTPUT: AtomicU8 = AtomicU8::new(Output::Stdout as u8);
+
+/// Send human-readable output to `output` from now on.
+pub fn set_output(output: Output) {
+    OUTPUT.store(output as u8, Ordering::Relaxed);
+}
+
+/// Where human-readable output goes now.
+pub fn output() -> Output {
+    match OUTPUT.load(Ordering::Relaxed) {
+        value if value == Output::Stderr as u8 => Output::Stderr,
+        value if value == Output::Hidden as u8 => Output::Hidden,
+        _ => Output::Stdout,
+    }
+}
+
+/// Print a line of text where [`set_output`] says; used by [`say!`](crate::say).
+#[doc(hidden)]
+pub fn print_line(line: fmt::Arguments) {
+    match output() {
+        Output::Stdout => println!("{}", line),
+        Output::Stderr => eprintln!("{}", line),
+        Output::Hidden => {}
+    }
+}
+
+/// Print a line on stderr unless output is hidden; used by
+/// [`warning!`](crate::warning) and [`progress!`](crate::progress).
+#[doc(hidden)]
+pub fn print_note(line: fmt::Arguments) {
+    if output() != Output::Hidden {
+        eprintln!("{}", line);
+    }
+}
+
+/// Progress bar of `len` steps on stderr, hidden along with the rest of the
+/// output.
+pub fn progress_bar(len: u64) -> ProgressBar {
+    if output() == Output::Hidden {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(len)
+    }
+}
+
+/// `println!` for tables, verdicts and notes, following [`set_output`].
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::output::print_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print_line(format_args!($($arg)*))
+    };
+}
+
+/// `eprintln!` for warnings, silenced when output is hidden.
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::output::print_note(format_args!($($arg)*))
+    };
+}
+
+/// `eprintln!` for notes on a long scan's progress, silenced when output is
+/// hidden.
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::output::print_note(format_args!($($arg)*))
+    };
+}
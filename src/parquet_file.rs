@@ -0,0 +1,135 @@
+//! Parquet output of calculation results.
+//!
+//! Same columns, in the same order, as the CSV table ([`CSV_COLUMNS`]), but
+//! typed and compressed so Polars, DuckDB or pandas load large sweeps
+//! without parsing text. `target_days` and `viable` are nullable, and
+//! `timestamp` is in microseconds since the epoch, UTC.
+//!
+//! [`CSV_COLUMNS`]: crate::format::CSV_COLUMNS
+
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+
+/// Rows per row group, so a reader can skip through a large sweep without
+/// loading all of it.
+const ROW_GROUP_ROWS: usize = 1 << 20;
+
+type Run = (ReorgCalculation, f64);
+
+/// How one column's values are read from a run.
+enum Column {
+    Height(fn(&Run) -> u64),
+    Double(fn(&Run) -> f64),
+    OptionalDouble(fn(&Run) -> Option<f64>),
+    OptionalBool(fn(&Run) -> Option<bool>),
+    Timestamp,
+}
+
+const COLUMNS: &[(&str, Column)] = &[
+    ("fork_height", Column::Height(|(calc, _)| calc.fork_height)),
+    ("current_height", Column::Height(|(calc, _)| calc.current_height)),
+    ("blocks_to_reorg", Column::Height(|(calc, _)| calc.blocks_to_reorg)),
+    ("total_work", Column::Double(|(calc, _)| calc.total_work)),
+    ("current_difficulty", Column::Double(|(calc, _)| calc.current_difficulty)),
+    ("blocks_needed", Column::Double(|(calc, _)| calc.blocks_needed)),
+    ("hashrate", Column::Double(|(_, hashrate)| *hashrate)),
+    ("time_required_hours", Column::Double(|(calc, _)| calc.time_required_hours)),
+    ("time_required_days", Column::Double(|(calc, _)| calc.time_required_days)),
+    ("target_days", Column::OptionalDouble(|(calc, _)| calc.target_days)),
+    ("hashrate_required", Column::Double(|(calc, _)| calc.hashrate_required)),
+    (
+        "viable",
+        Column::OptionalBool(|(calc, _)| calc.target_days.map(|days| calc.time_required_days <= days)),
+    ),
+    ("timestamp", Column::Timestamp),
+];
+
+/// Write `runs`, each a calculation and the hashrate it was made for, as a
+/// Parquet file.
+pub fn write_parquet<W: Write + Send>(writer: W, runs: &[Run]) -> Result<()> {
+    write(writer, runs).map_err(|e| io::Error::other(e).into())
+}
+
+fn write<W: Write + Send>(writer: W, runs: &[Run]) -> parquet::errors::Result<()> {
+    let schema = Arc::new(parse_message_type(&schema())?);
+    let properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+    let mut file = SerializedFileWriter::new(writer, schema, properties)?;
+    for group in runs.chunks(ROW_GROUP_ROWS) {
+        let mut row_group = file.next_row_group()?;
+        for (_, column) in COLUMNS {
+            let Some(mut writer) = row_group.next_column()? else {
+                break;
+            };
+            write_column(&mut writer, column, group)?;
+            writer.close()?;
+        }
+        row_group.close()?;
+    }
+    file.close()?;
+    Ok(())
+}
+
+fn schema() -> String {
+    let fields: Vec<String> = COLUMNS
+        .iter()
+        .map(|(name, column)| match column {
+            Column::Height(_) => format!("REQUIRED INT64 {} (INTEGER(64, false));", name),
+            Column::Double(_) => format!("REQUIRED DOUBLE {};", name),
+            Column::OptionalDouble(_) => format!("OPTIONAL DOUBLE {};", name),
+            Column::OptionalBool(_) => format!("OPTIONAL BOOLEAN {};", name),
+            Column::Timestamp => format!("REQUIRED INT64 {} (TIMESTAMP(MICROS, true));", name),
+        })
+        .collect();
+    format!("message reorg_calculation {{ {} }}", fields.join(" "))
+}
+
+fn write_column(writer: &mut SerializedColumnWriter, column: &Column, runs: &[Run]) -> parquet::errors::Result<()> {
+    match column {
+        Column::Height(value) => {
+            let values: Vec<i64> = runs.iter().map(|run| value(run) as i64).collect();
+            writer.typed::<Int64Type>().write_batch(&values, None, None)?;
+        }
+        Column::Double(value) => {
+            let values: Vec<f64> = runs.iter().map(value).collect();
+            writer.typed::<DoubleType>().write_batch(&values, None, None)?;
+        }
+        Column::OptionalDouble(value) => {
+            let (values, levels) = optional(runs, value);
+            writer.typed::<DoubleType>().write_batch(&values, Some(&levels), None)?;
+        }
+        Column::OptionalBool(value) => {
+            let (values, levels) = optional(runs, value);
+            writer.typed::<BoolType>().write_batch(&values, Some(&levels), None)?;
+        }
+        Column::Timestamp => {
+            let values: Vec<i64> = runs.iter().map(|(calc, _)| calc.timestamp.timestamp_micros()).collect();
+            writer.typed::<Int64Type>().write_batch(&values, None, None)?;
+        }
+    }
+    Ok(())
+}
+
+/// The present values of a nullable column and the definition level of
+/// every row: 1 where a value is present, 0 where it is null.
+fn optional<T>(runs: &[Run], value: &fn(&Run) -> Option<T>) -> (Vec<T>, Vec<i16>) {
+    let mut values = Vec::new();
+    let mut levels = Vec::with_capacity(runs.len());
+    for run in runs {
+        match value(run) {
+            Some(present) => {
+                values.push(present);
+                levels.push(1);
+            }
+            None => levels.push(0),
+        }
+    }
+    (values, levels)
+}
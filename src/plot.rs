@@ -0,0 +1,141 @@
+//! Chart images of a calculation.
+//!
+//! Draws two panels: the difficulty of every block being replaced, and the
+//! attacker's work over time against the honest chain's. The attacker line
+//! crosses the honest one where the reorg completes, give or take the
+//! margin. The file's extension picks the format: `.svg` is written as
+//! SVG, anything else as PNG.
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::Path;
+
+use crate::calc::ReorgCalculation;
+use crate::error::{ReorgCalcError, Result};
+use crate::source::BlockSource;
+
+/// Size of the image in pixels.
+const SIZE: (u32, u32) = (1200, 900);
+
+/// Points drawn along each work line.
+const PROJECTION_POINTS: usize = 200;
+
+/// Draw the difficulty of the span reorged by `calc` and the work
+/// projection at `hashrate` to `path`.
+pub fn plot_calculation<S: BlockSource + ?Sized>(
+    source: &S,
+    calc: &ReorgCalculation,
+    hashrate: f64,
+    path: &Path,
+) -> Result<()> {
+    let params = source.get_network()?;
+    let heights: Vec<u64> = (calc.fork_height..=calc.current_height).collect();
+    let bits = source.get_bits_batch(&heights)?;
+    let difficulties: Vec<(u64, f64)> =
+        heights.iter().zip(bits).map(|(&height, bits)| (height, params.difficulty(bits))).collect();
+
+    // Work in difficulty units per hour
+    let attacker_rate = if calc.time_required_hours.is_finite() && calc.time_required_hours > 0.0 {
+        calc.blocks_needed * calc.current_difficulty / calc.time_required_hours
+    } else {
+        let overhead = 1.0 + calc.overhead_percent.unwrap_or(0.0) / 100.0;
+        hashrate * calc.efficiency.unwrap_or(1.0) * 3600.0 / params.hashes_per_difficulty() / overhead
+    };
+    let honest_rate = calc
+        .honest_growth
+        .map_or(0.0, |growth| growth.hashrate * 3600.0 / params.hashes_per_difficulty());
+    let hours = if calc.time_required_hours.is_finite() && calc.time_required_hours > 0.0 {
+        calc.time_required_hours * 1.25
+    } else {
+        calc.target_days.unwrap_or(1.0) * 24.0 * 2.0
+    };
+    let projection = Projection {
+        hours,
+        attacker_rate,
+        honest_work: calc.total_work,
+        honest_rate,
+    };
+
+    let plot_error = |e: &dyn std::fmt::Display| ReorgCalcError::Plot(format!("{}: {}", path.display(), e));
+    if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("svg")) {
+        let root = SVGBackend::new(path, SIZE).into_drawing_area();
+        draw(&root, calc, &difficulties, &projection).map_err(|e| plot_error(&e))?;
+        root.present().map_err(|e| plot_error(&e))
+    } else {
+        let root = BitMapBackend::new(path, SIZE).into_drawing_area();
+        draw(&root, calc, &difficulties, &projection).map_err(|e| plot_error(&e))?;
+        root.present().map_err(|e| plot_error(&e))
+    }
+}
+
+/// Both chains' work over the first `hours` of the attack.
+struct Projection {
+    hours: f64,
+    /// Attacker work per hour, in difficulty units.
+    attacker_rate: f64,
+    /// Work of the blocks being replaced.
+    honest_work: f64,
+    /// Honest work added per hour, zero when growth is not modelled.
+    honest_rate: f64,
+}
+
+type DrawResult<DB> = std::result::Result<(), DrawingAreaErrorKind<<DB as DrawingBackend>::ErrorType>>;
+
+fn draw<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    calc: &ReorgCalculation,
+    difficulties: &[(u64, f64)],
+    projection: &Projection,
+) -> DrawResult<DB> {
+    root.fill(&WHITE)?;
+    let (top, bottom) = root.split_vertically(SIZE.1 / 2);
+
+    let max_difficulty = difficulties.iter().map(|&(_, difficulty)| difficulty).fold(1.0, f64::max);
+    let last_height = calc.current_height.max(calc.fork_height + 1);
+    let mut chart = ChartBuilder::on(&top)
+        .caption(format!("Difficulty, blocks {} to {}", calc.fork_height, calc.current_height), ("sans-serif", 24))
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(calc.fork_height..last_height, 0.0..max_difficulty * 1.1)?;
+    chart.configure_mesh().x_desc("Height").y_desc("Difficulty").draw()?;
+    chart.draw_series(LineSeries::new(difficulties.iter().copied(), &BLUE))?;
+
+    let honest = |hours: f64| projection.honest_work + projection.honest_rate * hours;
+    let attacker = |hours: f64| projection.attacker_rate * hours;
+    let max_work = attacker(projection.hours).max(honest(projection.hours)) * 1.1;
+    let mut chart = ChartBuilder::on(&bottom)
+        .caption("Work projection", ("sans-serif", 24))
+        .margin(15)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(0.0..projection.hours, 0.0..max_work)?;
+    chart
+        .configure_mesh()
+        .x_desc("Hours into the attack")
+        .y_desc("Work (difficulty units)")
+        .draw()?;
+    let points = |work: &dyn Fn(f64) -> f64| -> Vec<(f64, f64)> {
+        (0..=PROJECTION_POINTS)
+            .map(|i| {
+                let hours = projection.hours * i as f64 / PROJECTION_POINTS as f64;
+                (hours, work(hours))
+            })
+            .collect()
+    };
+    chart
+        .draw_series(LineSeries::new(points(&attacker), &RED))?
+        .label("Attacker")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+    chart
+        .draw_series(LineSeries::new(points(&honest), &BLACK))?
+        .label("Honest chain")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    Ok(())
+}
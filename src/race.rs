@@ -0,0 +1,64 @@
+//! Probability that the attacker's chain ever overtakes the honest one.
+//!
+//! With both chains growing at random, an attacker with less hashrate than
+//! the honest network can still get lucky, and one with more can still take
+//! a while. Treating the deficit as a random walk that moves one block
+//! towards the attacker with probability `q` and away with `p = 1 - q`
+//! gives Nakamoto's gambler's-ruin result: from `z` blocks behind the
+//! attacker catches up with probability `(q / p)^z`, and always does when
+//! `q >= p`.
+
+use serde::{Deserialize, Serialize};
+
+/// Odds of the attack succeeding against a growing honest chain.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RaceOdds {
+    /// Honest network hashrate raced against, in hashes/second.
+    pub honest_hashrate: f64,
+    /// Attacker share of the combined hashrate.
+    pub attacker_share: f64,
+    /// Blocks at the current difficulty the attacker starts behind.
+    pub deficit_blocks: f64,
+    /// Probability of ever overtaking, however long it takes.
+    pub probability: f64,
+}
+
+/// Race `hashrate` against `honest_hashrate` from `deficit_blocks` behind.
+pub fn race_odds(hashrate: f64, honest_hashrate: f64, deficit_blocks: f64) -> RaceOdds {
+    let attacker_share = hashrate / (hashrate + honest_hashrate);
+    RaceOdds {
+        honest_hashrate,
+        attacker_share,
+        deficit_blocks,
+        probability: catch_up_probability(attacker_share, deficit_blocks),
+    }
+}
+
+/// Probability that a chain with `attacker_share` of the hashrate ever
+/// closes a gap of `deficit_blocks`.
+pub fn catch_up_probability(attacker_share: f64, deficit_blocks: f64) -> f64 {
+    let honest_share = 1.0 - attacker_share;
+    if attacker_share >= honest_share {
+        1.0
+    } else {
+        (attacker_share / honest_share).powf(deficit_blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::assert_close;
+
+    #[test]
+    fn minority_attackers_catch_up_geometrically() {
+        assert_close(catch_up_probability(0.25, 2.0), 1.0 / 9.0);
+        assert_eq!(catch_up_probability(0.25, 0.0), 1.0);
+        assert_eq!(catch_up_probability(0.5, 100.0), 1.0);
+        assert_eq!(catch_up_probability(0.75, 100.0), 1.0);
+
+        let odds = race_odds(1.0, 3.0, 2.0);
+        assert_eq!((odds.honest_hashrate, odds.attacker_share, odds.deficit_blocks), (3.0, 0.25, 2.0));
+        assert_close(odds.probability, 1.0 / 9.0);
+    }
+}
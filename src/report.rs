@@ -0,0 +1,240 @@
+//! Reports of a run's calculations in the formats asked for.
+//!
+//! A single report goes to stdout, with everything else printed moved to
+//! stderr for the rest of the run, so it can be piped on; several, or any
+//! written to a directory, each go to a file of their own named after the
+//! command, e.g. `reorg_calc.json`.
+
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::calc::ReorgCalculation;
+use crate::error::{ReorgCalcError, Result, ResultContext};
+use crate::format::{write_csv, write_markdown, write_summary, Report};
+use crate::influx::write_influx;
+use crate::output::{set_output, Output};
+#[cfg(feature = "parquet")]
+use crate::parquet_file::write_parquet;
+use crate::say;
+#[cfg(feature = "templates")]
+use crate::template::ReportTemplate;
+
+/// Format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// What is printed anyway; no report.
+    Text,
+    Json,
+    Csv,
+    Markdown,
+    Parquet,
+    /// InfluxDB line protocol.
+    Influx,
+    /// One line of key=value pairs per calculation.
+    Summary,
+    /// Rendered through a Tera template.
+    Template,
+}
+
+impl ReportFormat {
+    /// Extension of the file this format is written to in a directory.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Text | ReportFormat::Template => "txt",
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Parquet => "parquet",
+            ReportFormat::Influx => "lp",
+            ReportFormat::Summary => "summary",
+        }
+    }
+}
+
+/// Where each report goes: stdout, while everything else printed goes to
+/// stderr, or a file of its own.
+pub struct ReportOutput {
+    outputs: Vec<(ReportFormat, ReportSink, Option<PathBuf>)>,
+    command: &'static str,
+    #[cfg(feature = "templates")]
+    template: Option<ReportTemplate>,
+}
+
+impl ReportOutput {
+    /// Set up `formats` for `command`, or nothing if they are all text:
+    /// one goes to stdout, sending other output to stderr for the rest of
+    /// the run, and several, or any with `dir`, each to its own file in `dir`.
+    /// `template` is the template file to render as well. When `quiet`,
+    /// everything else printed is discarded instead.
+    pub fn start(
+        command: &'static str,
+        formats: &[ReportFormat],
+        template: Option<&Path>,
+        dir: Option<&Path>,
+        quiet: bool,
+    ) -> Result<Option<Self>> {
+        let mut reports: Vec<ReportFormat> = Vec::new();
+        for &format in formats.iter().chain(template.map(|_| &ReportFormat::Template)) {
+            if format != ReportFormat::Text && !reports.contains(&format) {
+                reports.push(format);
+            }
+        }
+        if reports.is_empty() {
+            return Ok(None);
+        }
+        let to_stdout = reports.len() == 1 && formats.len() <= 1 && dir.is_none();
+        if reports.contains(&ReportFormat::Parquet) {
+            if !cfg!(feature = "parquet") {
+                return Err(ReorgCalcError::InvalidInput(
+                    "Parquet output not available. Compile with --features parquet".to_string(),
+                ));
+            }
+            if to_stdout && io::stdout().is_terminal() {
+                return Err(ReorgCalcError::InvalidInput(
+                    "--format parquet writes binary data; redirect stdout to a file or use --output-dir".to_string(),
+                ));
+            }
+        }
+        // report.html.tera is written as reorg_<command>.html
+        let template_extension = template
+            .and_then(|path| Path::new(path.file_stem()?).extension()?.to_str())
+            .unwrap_or("txt")
+            .to_string();
+        #[cfg(feature = "templates")]
+        let template = template.map(ReportTemplate::load).transpose()?;
+        #[cfg(not(feature = "templates"))]
+        if template.is_some() {
+            return Err(ReorgCalcError::InvalidInput(
+                "Templates not available. Compile with --features templates".to_string(),
+            ));
+        }
+
+        // Quiet runs discard everything else; errors still reach stderr
+        if quiet {
+            set_output(Output::Hidden);
+        } else if to_stdout {
+            set_output(Output::Stderr);
+        }
+        let outputs = if to_stdout {
+            vec![(reports[0], ReportSink::Stdout, None)]
+        } else {
+            let dir = dir.unwrap_or(Path::new("."));
+            reports
+                .into_iter()
+                .map(|format| {
+                    let extension = match format {
+                        ReportFormat::Template => &template_extension,
+                        format => format.extension(),
+                    };
+                    let path = dir.join(format!("reorg_{}.{}", command, extension));
+                    let file = fs::File::create(&path).context(format!("cannot create {}", path.display()))?;
+                    Ok((format, ReportSink::File(file), Some(path)))
+                })
+                .collect::<Result<_>>()?
+        };
+        Ok(Some(ReportOutput {
+            outputs,
+            command,
+            #[cfg(feature = "templates")]
+            template,
+        }))
+    }
+
+    /// Where the InfluxDB report goes, for monitors to stream their points to.
+    pub fn influx(&self) -> Option<&ReportSink> {
+        self.outputs.iter().find(|(format, _, _)| *format == ReportFormat::Influx).map(|(_, file, _)| file)
+    }
+
+    /// Write `runs`, each a calculation and its hashrate, to every report.
+    pub fn write(&self, runs: &[(ReorgCalculation, f64)], target_days: f64) -> Result<()> {
+        for (format, file, path) in &self.outputs {
+            let mut out = file;
+            match format {
+                ReportFormat::Json => {
+                    serde_json::to_writer_pretty(&mut out, &Report::new(self.command, target_days, runs))
+                        .map_err(io::Error::from)?;
+                    writeln!(out)?;
+                }
+                ReportFormat::Csv => write_csv(out, runs)?,
+                ReportFormat::Markdown => write_markdown(out, self.command, target_days, runs)?,
+                ReportFormat::Influx => write_influx(out, runs)?,
+                ReportFormat::Summary => write_summary(out, runs)?,
+                #[cfg(feature = "parquet")]
+                ReportFormat::Parquet => write_parquet(out, runs)?,
+                #[cfg(not(feature = "parquet"))]
+                ReportFormat::Parquet => unreachable!("checked when the report starts"),
+                #[cfg(feature = "templates")]
+                ReportFormat::Template => {
+                    if let Some(template) = &self.template {
+                        template.render(out, self.command, target_days, runs)?;
+                    }
+                }
+                #[cfg(not(feature = "templates"))]
+                ReportFormat::Template => unreachable!("checked when the report starts"),
+                ReportFormat::Text => {}
+            }
+            if let Some(path) = path {
+                say!("Report saved to: {}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where one report goes.
+#[derive(Debug)]
+pub enum ReportSink {
+    /// Stdout proper; everything else printed goes to stderr meanwhile.
+    Stdout,
+    File(fs::File),
+}
+
+impl ReportSink {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            ReportSink::Stdout => ReportSink::Stdout,
+            ReportSink::File(file) => ReportSink::File(file.try_clone()?),
+        })
+    }
+}
+
+impl Write for &ReportSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ReportSink::Stdout => io::stdout().write(buf),
+            ReportSink::File(file) => (&mut &*file).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ReportSink::Stdout => io::stdout().flush(),
+            ReportSink::File(file) => (&mut &*file).flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::temp_path;
+
+    #[test]
+    fn several_formats_each_get_a_file_named_after_the_command() {
+        let dir = temp_path("report-dir");
+        fs::create_dir_all(&dir).unwrap();
+        let formats = [ReportFormat::Text, ReportFormat::Csv, ReportFormat::Summary, ReportFormat::Csv];
+        let report = ReportOutput::start("sweep", &formats, None, Some(&dir), false).unwrap().unwrap();
+        assert!(report.influx().is_none());
+        report.write(&[], 3.0).unwrap();
+        assert!(dir.join("reorg_sweep.csv").exists());
+        assert!(dir.join("reorg_sweep.summary").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn text_alone_is_no_report() {
+        assert!(ReportOutput::start("calc", &[ReportFormat::Text], None, None, false).unwrap().is_none());
+    }
+}
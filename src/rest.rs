@@ -0,0 +1,197 @@
+//! Bitcoin Core REST backend.
+//!
+//! Talks to the unauthenticated REST interface a node serves when started
+//! with `-rest=1`. Headers come back as raw 80-byte records, up to
+//! [`REST_HEADERS_PER_REQUEST`] per request, and are parsed locally, which
+//! makes deep scans far cheaper than per-block RPC calls.
+
+use bitcoin::{block::Header, consensus::deserialize, BlockHash, Txid, Work};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{consecutive_runs, BlockSource, ChainTip};
+
+/// Most headers bitcoind returns from one `/rest/headers` request.
+pub const REST_HEADERS_PER_REQUEST: u64 = 2000;
+
+const HEADER_SIZE: usize = 80;
+
+#[derive(Deserialize)]
+struct ChainInfo {
+    chain: String,
+    blocks: u64,
+    bestblockhash: BlockHash,
+    difficulty: f64,
+}
+
+#[derive(Deserialize)]
+struct HeaderInfo {
+    height: u64,
+    confirmations: i64,
+    chainwork: String,
+}
+
+#[derive(Deserialize)]
+struct TransactionInfo {
+    blockhash: Option<BlockHash>,
+}
+
+/// A [`BlockSource`] reading from a node's REST interface.
+pub struct RestSource {
+    base_url: String,
+    /// Read from the chain name once; a node does not change chains.
+    network: OnceLock<NetworkParams>,
+}
+
+impl RestSource {
+    /// Use the REST interface of the node at `node_url`, e.g.
+    /// `http://127.0.0.1:48337` (the RPC port).
+    pub fn new(node_url: &str) -> Self {
+        Self {
+            base_url: format!("{}/rest", node_url.trim_end_matches('/')),
+            network: OnceLock::new(),
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Vec<u8>> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = minreq::get(&url)
+            .with_timeout(30)
+            .send()
+            .map_err(|e| ReorgCalcError::NodeUnreachable(format!("{}: {}", url, e)))?;
+        match response.status_code {
+            200 => Ok(response.into_bytes()),
+            404 => Err(ReorgCalcError::InvalidData(format!(
+                "{} returned HTTP 404 (is the node running with -rest=1?)",
+                url
+            ))),
+            code => Err(ReorgCalcError::InvalidData(format!("{} returned HTTP {}", url, code))),
+        }
+    }
+
+    fn get_json<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        serde_json::from_slice(&self.get(path)?)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("bad JSON from {}: {}", path, e)))
+    }
+
+    fn chain_info(&self) -> Result<ChainInfo> {
+        self.get_json("/chaininfo.json")
+    }
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash> {
+        let bytes = self.get(&format!("/blockhashbyheight/{}.bin", height))?;
+        deserialize(&bytes)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("bad block hash for height {}: {}", height, e)))
+    }
+
+    /// Headers of `count` consecutive blocks starting at `start_height`.
+    pub fn get_headers(&self, start_height: u64, count: u64) -> Result<Vec<Header>> {
+        let mut headers = Vec::with_capacity(count as usize);
+        if count == 0 {
+            return Ok(headers);
+        }
+        let mut next_hash = self.block_hash(start_height)?;
+
+        while (headers.len() as u64) < count {
+            let wanted = (count - headers.len() as u64).min(REST_HEADERS_PER_REQUEST);
+            let bytes = self.get(&format!("/headers/{}/{}.bin", wanted, next_hash))?;
+            if bytes.is_empty() || bytes.len() % HEADER_SIZE != 0 {
+                return Err(ReorgCalcError::InvalidData(format!(
+                    "unexpected headers response of {} bytes at height {}",
+                    bytes.len(),
+                    start_height + headers.len() as u64
+                )));
+            }
+
+            for chunk in bytes.chunks(HEADER_SIZE) {
+                let header: Header = deserialize(chunk)
+                    .map_err(|e| ReorgCalcError::InvalidData(format!("bad header: {}", e)))?;
+                headers.push(header);
+            }
+
+            // The next request starts at the block after the last one received
+            if (headers.len() as u64) < count {
+                let next_height = start_height + headers.len() as u64;
+                next_hash = self.block_hash(next_height)?;
+            }
+        }
+
+        headers.truncate(count as usize);
+        Ok(headers)
+    }
+}
+
+impl BlockSource for RestSource {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let info = self.chain_info()?;
+        Ok(ChainTip {
+            height: info.blocks,
+            hash: info.bestblockhash,
+        })
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        let mut headers = self.get_headers(height, 1)?;
+        Ok(headers.remove(0))
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        self.block_hash(height)
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        // Fetch each run of consecutive heights with as few requests as possible
+        let mut headers = Vec::with_capacity(heights.len());
+        for (start, count) in consecutive_runs(heights) {
+            headers.extend(self.get_headers(start, count)?);
+        }
+        Ok(headers)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        let headers = self.get_headers_batch(heights)?;
+        Ok(headers.iter().map(|header| header.bits.to_consensus()).collect())
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        let hash = self.block_hash(height)?;
+        let infos: Vec<HeaderInfo> = self.get_json(&format!("/headers/1/{}.json", hash))?;
+        let info = infos.first()
+            .ok_or_else(|| ReorgCalcError::InvalidData(format!("no header returned for height {}", height)))?;
+        Work::from_unprefixed_hex(&info.chainwork)
+            .map_err(|_| ReorgCalcError::InvalidData(format!("bad chainwork for height {}: {}", height, info.chainwork)))
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        Ok(self.chain_info()?.difficulty)
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        // Confirmed transactions are only served with -txindex
+        let tx: TransactionInfo = self.get_json(&format!("/tx/{}.json", txid))?;
+        let Some(hash) = tx.blockhash else {
+            return Ok(None);
+        };
+        let infos: Vec<HeaderInfo> = self.get_json(&format!("/headers/1/{}.json", hash))?;
+        let info = infos.first()
+            .ok_or_else(|| ReorgCalcError::InvalidData(format!("no header returned for block {}", hash)))?;
+        Ok((info.confirmations > 0).then_some(info.height))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        let infos: Vec<HeaderInfo> = self.get_json(&format!("/headers/1/{}.json", hash))?;
+        let info = infos.first()
+            .ok_or_else(|| ReorgCalcError::InvalidData(format!("no header returned for block {}", hash)))?;
+        Ok((info.confirmations > 0).then_some(info.height))
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        if let Some(network) = self.network.get() {
+            return Ok(*network);
+        }
+        let network = NetworkParams::from_chain(&self.chain_info()?.chain)?;
+        Ok(*self.network.get_or_init(|| network))
+    }
+}
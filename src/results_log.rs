@@ -0,0 +1,82 @@
+//! JSON Lines log of calculation results.
+//!
+//! Each line is one calculation in the `--format json` entry shape, so the
+//! file can be read back with any JSON Lines tool. Lines are keyed by the
+//! fork height and the tip hash: a tip fixes every block below it, so the
+//! key names the same fork block as its hash would, and rerunning against
+//! an unchanged chain adds nothing. Repeated watch runs therefore leave one
+//! line per tip, a time series of the attack cost.
+//!
+//! Records are only ever appended, one write per line. A line left partial
+//! by an interrupted write is skipped when the log is opened and
+//! terminated so the next record starts on its own line.
+
+use bitcoin::BlockHash;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+use crate::format::ReportEntry;
+
+/// Suggested log file name. Nothing is logged unless a log is named, with
+/// `--results-log` or `RESULTS_LOG`.
+pub const RESULTS_LOG: &str = "reorg_calculations.jsonl";
+
+/// The fields of a logged line that make up its key.
+#[derive(Deserialize)]
+struct LoggedKey {
+    fork_height: u64,
+    tip_hash: Option<BlockHash>,
+}
+
+/// An open results log.
+pub struct ResultsLog {
+    file: File,
+    seen: HashSet<(u64, BlockHash)>,
+}
+
+impl ResultsLog {
+    /// Open the log at `path`, creating it when missing, and load the keys
+    /// of the lines already in it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        let seen = text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<LoggedKey>(line).ok())
+            .filter_map(|key| Some((key.fork_height, key.tip_hash?)))
+            .collect();
+        if !text.is_empty() && !text.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Self { file, seen })
+    }
+
+    /// Append `calc`, made for `hashrate`, unless a line for the same fork
+    /// height and tip is already logged. Calculations without a tip hash,
+    /// such as manual ones, are always appended.
+    ///
+    /// Returns whether a line was written.
+    pub fn append(&mut self, calc: &ReorgCalculation, hashrate: f64) -> Result<bool> {
+        if let Some(tip_hash) = calc.tip_hash {
+            if !self.seen.insert((calc.fork_height, tip_hash)) {
+                return Ok(false);
+            }
+        }
+        let entry = ReportEntry {
+            hashrate,
+            calculation: calc,
+        };
+        let mut line = serde_json::to_vec(&entry).map_err(io::Error::from)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        Ok(true)
+    }
+}
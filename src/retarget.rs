@@ -0,0 +1,266 @@
+//! Difficulty retargeting on the attacker's chain.
+//!
+//! A private chain that crosses a retarget boundary gets a new difficulty
+//! computed from its own timestamps, exactly like the public one. The
+//! attacker is assumed to timestamp blocks honestly, so an old fork point
+//! (whose epoch started long ago) drives the first retarget to the maximum
+//! 4x decrease. Total work, and therefore time, barely changes with
+//! difficulty; what changes is the number of blocks.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+use crate::network::NetworkParams;
+use crate::source::BlockSource;
+
+/// Leading blocks of an epoch read for its difficulty. Minimum-difficulty
+/// blocks on the testnets carry difficulty 1, so the highest is taken.
+const EPOCH_SAMPLE: u64 = 6;
+
+/// First block of the retarget epoch a fork height falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochStart {
+    pub height: u64,
+    /// Compact target of the epoch, which every block of it shares apart
+    /// from testnet minimum-difficulty blocks.
+    pub bits: u32,
+    /// Block timestamp, Unix seconds.
+    pub time: u32,
+}
+
+impl EpochStart {
+    /// Read the epoch containing `fork_height` from `source`.
+    pub fn of<S: BlockSource + ?Sized>(source: &S, fork_height: u64, params: &NetworkParams) -> Result<Self> {
+        let interval = params.consensus().difficulty_adjustment_interval();
+        let height = fork_height / interval * interval;
+        let header = source.get_header(height)?;
+        Ok(Self {
+            height,
+            bits: header.bits.to_consensus(),
+            time: header.time,
+        })
+    }
+}
+
+/// Difficulty of the epoch starting at `start`, read from its first blocks
+/// up to `tip_height`.
+pub(crate) fn epoch_difficulty<S: BlockSource + ?Sized>(
+    source: &S,
+    start: u64,
+    tip_height: u64,
+    params: &NetworkParams,
+) -> Result<f64> {
+    let sample: Vec<u64> = (start..(start + EPOCH_SAMPLE).min(tip_height + 1)).collect();
+    let bits = source.get_bits_batch(&sample)?;
+    Ok(bits.iter().map(|&bits| params.difficulty(bits)).fold(0.0, f64::max))
+}
+
+/// The public chain's next retarget, projected from the pace of the
+/// current epoch so far, and the requirement at the difficulty it brings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NextRetarget {
+    /// First block of the next epoch.
+    pub height: u64,
+    pub blocks_remaining: u64,
+    /// When that block is expected.
+    pub eta: DateTime<Utc>,
+    /// Difficulty of the current epoch.
+    pub difficulty: f64,
+    pub projected_difficulty: f64,
+    /// Blocks needed, time and hashrate required mining at the projected
+    /// difficulty.
+    pub blocks_needed: f64,
+    pub time_required_days: f64,
+    pub hashrate_required: f64,
+}
+
+/// Project the retarget after the tip of `source` and redo `calc` at the
+/// difficulty it brings.
+///
+/// The epoch's blocks so far set the pace: the rest arrive at the same
+/// average interval, and the timespan they add up to adjusts the
+/// difficulty within the consensus limit of 4x either way.
+pub fn project_next_retarget<S: BlockSource + ?Sized>(
+    source: &S,
+    calc: &ReorgCalculation,
+    params: &NetworkParams,
+) -> Result<NextRetarget> {
+    let consensus = params.consensus();
+    let interval = consensus.difficulty_adjustment_interval();
+    let timespan = consensus.pow_target_timespan as f64;
+    let tip = source.get_tip()?;
+    let epoch = EpochStart::of(source, tip.height, params)?;
+    let difficulty = epoch_difficulty(source, epoch.height, tip.height, params)?;
+    let tip_time = source.get_header(tip.height)?.time;
+
+    let height = epoch.height + interval;
+    let mined = tip.height - epoch.height;
+    // Before the epoch has a second block there is no pace but the target one
+    let spacing = match mined {
+        0 => timespan / interval as f64,
+        _ => f64::from(tip_time.saturating_sub(epoch.time)) / mined as f64,
+    };
+    let projected_difficulty = match consensus.no_pow_retargeting || mined == 0 {
+        true => difficulty,
+        false => {
+            let actual = (spacing * (interval - 1) as f64).clamp(timespan / 4.0, timespan * 4.0);
+            (difficulty * timespan / actual).max(1.0)
+        }
+    };
+    let eta = i64::from(tip_time) + (spacing * (height - tip.height) as f64) as i64;
+
+    // The same work at the new difficulty takes a different number of blocks
+    let blocks_needed = (calc.blocks_needed * calc.current_difficulty / projected_difficulty).ceil();
+    let scale = blocks_needed * projected_difficulty / (calc.blocks_needed * calc.current_difficulty);
+    Ok(NextRetarget {
+        height,
+        blocks_remaining: height - tip.height,
+        eta: DateTime::from_timestamp(eta, 0).unwrap_or_default(),
+        difficulty,
+        projected_difficulty,
+        blocks_needed,
+        time_required_days: calc.time_required_days * scale,
+        hashrate_required: calc.hashrate_required * scale,
+    })
+}
+
+/// Run of attacker blocks mined at one difficulty.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AttackEpoch {
+    pub start_height: u64,
+    pub blocks: u64,
+    pub difficulty: f64,
+}
+
+/// Outcome of [`simulate_attack`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackSimulation {
+    /// Difficulty trajectory; consecutive epochs at the same difficulty are
+    /// merged.
+    pub epochs: Vec<AttackEpoch>,
+    pub blocks: u64,
+    /// Work mined, in difficulty units; at least the work asked for.
+    pub work: f64,
+    /// Mining time at the simulated hashrate.
+    pub seconds: f64,
+}
+
+/// Mine `work_needed` (in difficulty units) on a chain forked at
+/// `fork_height`, starting at Unix time `start_time` with `hashrate`
+/// hashes/second, and retarget at every epoch boundary.
+pub fn simulate_attack(
+    fork_height: u64,
+    epoch: EpochStart,
+    work_needed: f64,
+    hashrate: f64,
+    start_time: f64,
+    params: &NetworkParams,
+) -> AttackSimulation {
+    let consensus = params.consensus();
+    let interval = consensus.difficulty_adjustment_interval();
+    let timespan = consensus.pow_target_timespan as f64;
+    let seconds_per_difficulty = params.hashes_per_difficulty() / hashrate;
+
+    let mut difficulty = params.difficulty(epoch.bits);
+    // Retargets measure from the epoch's first block, which may predate the fork
+    let mut epoch_time = if epoch.height < fork_height {
+        epoch.time as f64
+    } else {
+        start_time + difficulty * seconds_per_difficulty
+    };
+
+    let mut simulation = AttackSimulation {
+        epochs: Vec::new(),
+        blocks: 0,
+        work: 0.0,
+        seconds: 0.0,
+    };
+    let mut height = fork_height;
+    loop {
+        let boundary = (height / interval + 1) * interval;
+        let remaining = ((work_needed - simulation.work) / difficulty).ceil().max(0.0) as u64;
+        let blocks = remaining.min(boundary - height);
+
+        match simulation.epochs.last_mut() {
+            _ if blocks == 0 => {}
+            // Steady epochs land on the same difficulty up to rounding
+            Some(last) if (last.difficulty - difficulty).abs() <= difficulty * 1e-9 => last.blocks += blocks,
+            _ => simulation.epochs.push(AttackEpoch {
+                start_height: height,
+                blocks,
+                difficulty,
+            }),
+        }
+        simulation.blocks += blocks;
+        simulation.work += blocks as f64 * difficulty;
+        simulation.seconds += blocks as f64 * difficulty * seconds_per_difficulty;
+        if blocks == remaining {
+            return simulation;
+        }
+
+        height = boundary;
+        if !consensus.no_pow_retargeting {
+            let last_time = start_time + simulation.seconds;
+            let actual = (last_time - epoch_time).clamp(timespan / 4.0, timespan * 4.0);
+            difficulty = (difficulty * timespan / actual).max(1.0);
+            epoch_time = last_time + difficulty * seconds_per_difficulty;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::calculate_reorg_requirements;
+    use crate::fixture::{assert_close, TestChain, DIFFICULTY_1, DIFFICULTY_8, GENESIS_TIME};
+    use crate::HASHES_PER_DIFFICULTY;
+    use bitcoin::Network;
+
+    #[test]
+    fn attacks_within_an_epoch_keep_its_difficulty() {
+        let epoch = EpochStart { height: 0, bits: DIFFICULTY_8, time: 0 };
+        let simulation = simulate_attack(100, epoch, 36.0, HASHES_PER_DIFFICULTY, 1e9, &NetworkParams::default());
+        assert_eq!(simulation.epochs, [AttackEpoch { start_height: 100, blocks: 5, difficulty: 8.0 }]);
+        assert_eq!((simulation.blocks, simulation.work, simulation.seconds), (5, 40.0, 40.0));
+    }
+
+    #[test]
+    fn an_old_epoch_retargets_to_the_largest_decrease() {
+        // The epoch began long before the attack, so its timespan is clamped at 4x
+        let epoch = EpochStart { height: 0, bits: DIFFICULTY_8, time: 0 };
+        let simulation = simulate_attack(2010, epoch, 68.0, HASHES_PER_DIFFICULTY, 1e9, &NetworkParams::default());
+        assert_eq!(
+            simulation.epochs,
+            [
+                AttackEpoch { start_height: 2010, blocks: 6, difficulty: 8.0 },
+                AttackEpoch { start_height: 2016, blocks: 10, difficulty: 2.0 },
+            ]
+        );
+        assert_eq!((simulation.blocks, simulation.work, simulation.seconds), (16, 68.0, 68.0));
+
+        // Without retargeting the difficulty stays and the epochs merge
+        let regtest = NetworkParams::from(Network::Regtest);
+        let epoch = EpochStart { bits: regtest.pow_limit.to_compact_lossy().to_consensus(), ..epoch };
+        let simulation = simulate_attack(2010, epoch, 20.0, HASHES_PER_DIFFICULTY, 1e9, &regtest);
+        assert_eq!(simulation.epochs, [AttackEpoch { start_height: 2010, blocks: 20, difficulty: 1.0 }]);
+    }
+
+    #[test]
+    fn next_retarget_follows_the_epoch_pace() {
+        // Blocks four times faster than the target spacing
+        let chain = TestChain::with_spacing(&[DIFFICULTY_1; 2101], 150);
+        let params = NetworkParams::default();
+        let calc = calculate_reorg_requirements(&chain, 2091, HASHES_PER_DIFFICULTY, 1.0).unwrap();
+        let next = project_next_retarget(&chain, &calc, &params).unwrap();
+        assert_eq!((next.height, next.blocks_remaining), (4032, 1932));
+        assert_eq!(next.eta.timestamp(), i64::from(GENESIS_TIME) + 150 * 4032);
+        assert_eq!((next.difficulty, next.projected_difficulty), (1.0, 4.0));
+
+        // The 10 blocks' work takes 2.5 blocks at difficulty 4, rounded up
+        assert_eq!(next.blocks_needed, 3.0);
+        assert_close(next.time_required_days, calc.time_required_days * 1.2);
+        assert_close(next.hashrate_required, calc.hashrate_required * 1.2);
+    }
+}
@@ -0,0 +1,77 @@
+//! Rotation of the plaintext results file.
+//!
+//! Appending every run to one file is fine for occasional use, but `watch`
+//! and `daemon` grow it without bound. A [`Rotation`] instead picks the file
+//! each save goes to: one per run, one per UTC day, or the same file moved
+//! aside under a timestamped name whenever it reaches a size.
+
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::units::parse_size;
+
+/// When to start a new results file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// A file per run, named after the time the run started, e.g.
+    /// `reorg_calculations-20250701T120000Z.txt`.
+    Run,
+    /// A file per UTC day, e.g. `reorg_calculations-2025-07-01.txt`.
+    Daily,
+    /// The file itself, moved aside to a name like the one for `Run` once a
+    /// save finds it holding at least this many bytes.
+    Size(u64),
+}
+
+/// Parse a rotation: `run`, `daily`, or a size such as `10MB`.
+pub fn parse_rotation(text: &str) -> Result<Rotation, String> {
+    match text.trim().to_ascii_lowercase().as_str() {
+        "run" => Ok(Rotation::Run),
+        "daily" | "day" => Ok(Rotation::Daily),
+        _ => parse_size(text)
+            .map(Rotation::Size)
+            .map_err(|e| format!("expected run, daily or a size such as 10MB: {}", e)),
+    }
+}
+
+impl Rotation {
+    /// The file to save to now instead of `base`, for a run that started at
+    /// `started`; by size, `base` is first moved aside if it is full.
+    pub fn current(&self, base: &Path, started: DateTime<Utc>) -> io::Result<PathBuf> {
+        match *self {
+            Rotation::Run => Ok(with_suffix(base, &started.format("%Y%m%dT%H%M%SZ").to_string())),
+            Rotation::Daily => Ok(with_suffix(base, &Utc::now().format("%Y-%m-%d").to_string())),
+            Rotation::Size(limit) => {
+                let full = match fs::metadata(base) {
+                    Ok(metadata) => metadata.len() >= limit,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+                    Err(e) => return Err(e),
+                };
+                if full {
+                    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+                    let mut aside = with_suffix(base, &stamp);
+                    // Never overwrite a file moved aside in the same second
+                    let mut n = 1;
+                    while aside.exists() {
+                        aside = with_suffix(base, &format!("{}-{}", stamp, n));
+                        n += 1;
+                    }
+                    fs::rename(base, aside)?;
+                }
+                Ok(base.to_path_buf())
+            }
+        }
+    }
+}
+
+/// `base` with `-suffix` between its stem and extension.
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let stem = base.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let name = match base.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, suffix),
+    };
+    base.with_file_name(name)
+}
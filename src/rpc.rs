@@ -0,0 +1,353 @@
+//! Bitcoin Core RPC backend: connection helpers and the [`BlockSource`] impl.
+
+use bitcoin::{block::Header, consensus::encode::deserialize_hex, Block, BlockHash, Transaction, Txid, Work};
+use bitcoincore_rpc::json::{GetBlockHeaderResult, GetChainTipsResultStatus};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::error::{ReorgCalcError, Result, RpcContext};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, BranchStatus, BranchTip, ChainTip};
+
+/// Create an RPC client and verify the node is reachable.
+///
+/// Connection and credential problems are reported as
+/// [`ReorgCalcError::NodeUnreachable`] and [`ReorgCalcError::AuthFailed`].
+pub fn connect_to_node(rpc_url: &str, rpc_user: &str, rpc_password: &str) -> Result<Client> {
+    let client = Client::new(
+        rpc_url,
+        Auth::UserPass(rpc_user.to_string(), rpc_password.to_string()),
+    )
+    .map_err(|e| ReorgCalcError::NodeUnreachable(e.to_string()))?;
+
+    // Test connection with a simple call that doesn't require network detection
+    client.get_block_count()
+        .rpc_context("Failed to connect to Bitcoin node")?;
+    Ok(client)
+}
+
+/// Maximum number of calls sent in one JSON-RPC batch request.
+pub const RPC_BATCH_SIZE: usize = 500;
+
+/// Send `method` once per entry of `params`, [`RPC_BATCH_SIZE`] calls per
+/// HTTP request, and return the results in order.
+fn batch_call<T: DeserializeOwned>(client: &Client, method: &str, params: &[serde_json::Value]) -> Result<Vec<T>> {
+    let jsonrpc = client.get_jsonrpc_client();
+    let mut results = Vec::with_capacity(params.len());
+
+    for chunk in params.chunks(RPC_BATCH_SIZE) {
+        let raw_params = chunk
+            .iter()
+            .map(serde_json::value::to_raw_value)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| ReorgCalcError::InvalidData(e.to_string()))?;
+        let requests: Vec<_> = raw_params
+            .iter()
+            .map(|raw| jsonrpc.build_request(method, Some(raw)))
+            .collect();
+
+        let responses = jsonrpc.send_batch(&requests)
+            .map_err(bitcoincore_rpc::Error::from)
+            .rpc_context(format!("Failed to send {} batch", method))?;
+        for response in responses {
+            let response = response
+                .ok_or_else(|| ReorgCalcError::InvalidData(format!("missing response in {} batch", method)))?;
+            let result = response.result()
+                .map_err(bitcoincore_rpc::Error::from)
+                .rpc_context(format!("{} failed in batch", method))?;
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+/// Verbose headers of the blocks at `heights`, in two batched round trips
+/// per [`RPC_BATCH_SIZE`] heights.
+fn header_info_batch(client: &Client, heights: &[u64]) -> Result<Vec<GetBlockHeaderResult>> {
+    let hash_params: Vec<_> = heights.iter().map(|&height| json!([height])).collect();
+    let hashes: Vec<BlockHash> = batch_call(client, "getblockhash", &hash_params)?;
+    let header_params: Vec<_> = hashes.iter().map(|hash| json!([hash, true])).collect();
+    batch_call(client, "getblockheader", &header_params)
+}
+
+/// Raw headers of the blocks at `heights`, batched like [`header_info_batch`].
+fn header_batch(client: &Client, heights: &[u64]) -> Result<Vec<Header>> {
+    let hash_params: Vec<_> = heights.iter().map(|&height| json!([height])).collect();
+    let hashes: Vec<BlockHash> = batch_call(client, "getblockhash", &hash_params)?;
+    let header_params: Vec<_> = hashes.iter().map(|hash| json!([hash, false])).collect();
+    let hexes: Vec<String> = batch_call(client, "getblockheader", &header_params)?;
+    hexes
+        .iter()
+        .map(|hex| {
+            deserialize_hex(hex).map_err(|e| ReorgCalcError::InvalidData(format!("bad header in batch: {}", e)))
+        })
+        .collect()
+}
+
+/// Coinbase transactions of the blocks at `heights`, in three batched
+/// round trips per [`RPC_BATCH_SIZE`] heights. Naming the block lets
+/// `getrawtransaction` find them without `-txindex`.
+fn coinbase_batch(client: &Client, heights: &[u64]) -> Result<Vec<Transaction>> {
+    let hash_params: Vec<_> = heights.iter().map(|&height| json!([height])).collect();
+    let hashes: Vec<BlockHash> = batch_call(client, "getblockhash", &hash_params)?;
+    let block_params: Vec<_> = hashes.iter().map(|hash| json!([hash, 1])).collect();
+    let blocks: Vec<serde_json::Value> = batch_call(client, "getblock", &block_params)?;
+    let tx_params = blocks
+        .iter()
+        .zip(&hashes)
+        .map(|(block, hash)| {
+            let txid = block["tx"][0].as_str()
+                .ok_or_else(|| ReorgCalcError::InvalidData(format!("block {} lists no transactions", hash)))?;
+            Ok(json!([txid, false, hash]))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let hexes: Vec<String> = batch_call(client, "getrawtransaction", &tx_params)?;
+    hexes
+        .iter()
+        .map(|hex| {
+            deserialize_hex(hex).map_err(|e| ReorgCalcError::InvalidData(format!("bad coinbase in batch: {}", e)))
+        })
+        .collect()
+}
+
+/// Full blocks at `heights`, in two batched round trips per
+/// [`RPC_BATCH_SIZE`] heights.
+fn block_batch(client: &Client, heights: &[u64]) -> Result<Vec<Block>> {
+    let hash_params: Vec<_> = heights.iter().map(|&height| json!([height])).collect();
+    let hashes: Vec<BlockHash> = batch_call(client, "getblockhash", &hash_params)?;
+    let block_params: Vec<_> = hashes.iter().map(|hash| json!([hash, 0])).collect();
+    let hexes: Vec<String> = batch_call(client, "getblock", &block_params)?;
+    hexes
+        .iter()
+        .map(|hex| deserialize_hex(hex).map_err(|e| ReorgCalcError::InvalidData(format!("bad block in batch: {}", e))))
+        .collect()
+}
+
+fn chainwork_from_info(info: &GetBlockHeaderResult) -> Result<Work> {
+    let bytes: [u8; 32] = info.chainwork.as_slice().try_into()
+        .map_err(|_| ReorgCalcError::InvalidData(format!("unexpected chainwork length for height {}", info.height)))?;
+    Ok(Work::from_be_bytes(bytes))
+}
+
+impl BlockSource for Client {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let height = self.get_block_count()
+            .rpc_context("Failed to get current block height")?;
+        let hash = BlockSource::get_block_hash(self, height)?;
+        Ok(ChainTip { height, hash })
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        RpcApi::get_block_hash(self, height)
+            .rpc_context(format!("Failed to get block hash for height {}", height))
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        let block_hash = BlockSource::get_block_hash(self, height)?;
+        self.get_block_header(&block_hash)
+            .rpc_context(format!("Failed to get block header for height {}", height))
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        header_batch(self, heights)
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        let block_hash = BlockSource::get_block_hash(self, height)?;
+        let info = self.get_block_header_info(&block_hash)
+            .rpc_context(format!("Failed to get block header for height {}", height))?;
+        chainwork_from_info(&info)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        header_info_batch(self, heights)?
+            .iter()
+            .map(|info| {
+                u32::from_str_radix(&info.bits, 16)
+                    .map_err(|_| ReorgCalcError::InvalidData(format!("invalid bits for height {}: {}", info.height, info.bits)))
+            })
+            .collect()
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        header_info_batch(self, heights)?.iter().map(chainwork_from_info).collect()
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        RpcApi::get_difficulty(self).rpc_context("Failed to get current difficulty")
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        // Confirmed transactions outside the wallet need the node's -txindex
+        let tx: serde_json::Value = self.call("getrawtransaction", &[json!(txid), json!(true)])
+            .rpc_context(format!("Failed to look up transaction {} (is the node running with -txindex?)", txid))?;
+        let Some(hash) = tx["blockhash"].as_str() else {
+            return Ok(None);
+        };
+        let hash: BlockHash = hash.parse()
+            .map_err(|_| ReorgCalcError::InvalidData(format!("bad block hash for transaction {}: {}", txid, hash)))?;
+        let info = self.get_block_header_info(&hash)
+            .rpc_context(format!("Failed to get block header {}", hash))?;
+        // A block that was reorged out reports -1 confirmations
+        Ok((info.confirmations > 0).then_some(info.height as u64))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        let info = self.get_block_header_info(hash)
+            .rpc_context(format!("Failed to get block header {}", hash))?;
+        Ok((info.confirmations > 0).then_some(info.height as u64))
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        coinbase_batch(self, heights)
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        block_batch(self, heights)
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        let tips: Vec<_> = RpcApi::get_chain_tips(self)
+            .rpc_context("Failed to get chain tips")?
+            .into_iter()
+            .filter(|tip| tip.status != GetChainTipsResultStatus::Active)
+            .collect();
+        // Chain work and targets of every branch tip in one round trip
+        let header_params: Vec<_> = tips.iter().map(|tip| json!([tip.hash, true])).collect();
+        let infos: Vec<GetBlockHeaderResult> = batch_call(self, "getblockheader", &header_params)?;
+        tips.iter()
+            .zip(&infos)
+            .map(|(tip, info)| {
+                let bits = u32::from_str_radix(&info.bits, 16)
+                    .map_err(|_| ReorgCalcError::InvalidData(format!("invalid bits for block {}: {}", tip.hash, info.bits)))?;
+                Ok(BranchTip {
+                    height: tip.height,
+                    hash: tip.hash,
+                    branch_length: tip.branch_length as u64,
+                    status: match tip.status {
+                        GetChainTipsResultStatus::ValidFork => BranchStatus::ValidFork,
+                        GetChainTipsResultStatus::ValidHeaders => BranchStatus::ValidHeaders,
+                        GetChainTipsResultStatus::HeadersOnly => BranchStatus::HeadersOnly,
+                        _ => BranchStatus::Invalid,
+                    },
+                    chainwork: chainwork_from_info(info)?,
+                    bits,
+                })
+            })
+            .collect()
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        // Read the raw chain name; older rust-bitcoin releases can't parse "testnet4"
+        let info: serde_json::Value = self.call("getblockchaininfo", &[])
+            .rpc_context("Failed to get blockchain info")?;
+        let chain = info["chain"].as_str()
+            .ok_or_else(|| ReorgCalcError::InvalidData("getblockchaininfo has no chain".to_string()))?;
+        NetworkParams::from_chain(chain)
+    }
+}
+
+/// A fixed set of RPC connections to one node.
+///
+/// A single [`Client`] serializes its requests over one socket, so parallel
+/// scans spread their calls across several clients instead. The node's
+/// network is read once and kept.
+pub struct RpcPool {
+    clients: Vec<Client>,
+    next: AtomicUsize,
+    network: OnceLock<NetworkParams>,
+}
+
+impl RpcPool {
+    /// Open `size` connections (at least one) to the node.
+    pub fn new(rpc_url: &str, rpc_user: &str, rpc_password: &str, size: usize) -> Result<Self> {
+        let clients = (0..size.max(1))
+            .map(|_| connect_to_node(rpc_url, rpc_user, rpc_password))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            clients,
+            next: AtomicUsize::new(0),
+            network: OnceLock::new(),
+        })
+    }
+
+    /// Number of open connections.
+    pub fn connections(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Pick the next connection in round-robin order.
+    pub fn client(&self) -> &Client {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        &self.clients[index]
+    }
+}
+
+impl BlockSource for RpcPool {
+    fn get_tip(&self) -> Result<ChainTip> {
+        self.client().get_tip()
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        BlockSource::get_block_hash(self.client(), height)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        self.client().get_header(height)
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        self.client().get_headers_batch(heights)
+    }
+
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        self.client().get_bits(height)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        self.client().get_bits_batch(heights)
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.client().get_chainwork(height)
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        self.client().get_chainwork_batch(heights)
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        BlockSource::get_difficulty(self.client())
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        self.client().get_transaction_height(txid)
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        self.client().get_block_height(hash)
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        self.client().get_coinbases(heights)
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        self.client().get_blocks(heights)
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        BlockSource::get_chain_tips(self.client())
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        if let Some(network) = self.network.get() {
+            return Ok(*network);
+        }
+        let network = self.client().get_network()?;
+        Ok(*self.network.get_or_init(|| network))
+    }
+}
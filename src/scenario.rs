@@ -0,0 +1,118 @@
+//! Named runs read from a scenario file.
+//!
+//! A planning session can be kept in a file and rerun in one go, the same
+//! TOML subset as the hardware file with one `[[scenario]]` table per run.
+//! Anything a scenario leaves out comes from the command line:
+//!
+//! ```toml
+//! [[scenario]]
+//! name = "last day"
+//! depth = 144             # or fork_height = 80000; default 100 below the tip
+//! hashrate = "500TH"      # or a number in hashes/second
+//! days = "12h"            # or a number of days
+//! margin_blocks = 6
+//! margin_percent = 5
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::toml_subset::{parse_tables, Table};
+use crate::units::{parse_days, parse_hashrate};
+
+/// Default scenario file, read from the working directory.
+pub const SCENARIO_FILE: &str = "scenarios.toml";
+
+/// One named run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_height: Option<u64>,
+    /// Fork this many blocks below the tip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u64>,
+    /// Hashrate in hashes/second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashrate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_days: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin_blocks: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin_percent: Option<f64>,
+}
+
+/// Read the scenarios in the file at `path`.
+pub fn load_scenarios(path: &Path) -> Result<Vec<Scenario>> {
+    let text = fs::read_to_string(path)?;
+    parse_scenarios(&text).map_err(|(line, reason)| ReorgCalcError::ScenarioFile {
+        path: path.display().to_string(),
+        line,
+        reason,
+    })
+}
+
+/// Parse the `[[scenario]]` tables of a scenario file, failing with the
+/// line number and reason.
+pub fn parse_scenarios(text: &str) -> std::result::Result<Vec<Scenario>, (usize, String)> {
+    let scenarios = parse_tables(text, "scenario")?
+        .into_iter()
+        .enumerate()
+        .map(|(index, table)| parse_scenario(index, table))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if scenarios.is_empty() {
+        return Err((1, "no [[scenario]] tables".to_string()));
+    }
+    Ok(scenarios)
+}
+
+fn parse_scenario(index: usize, table: Table) -> std::result::Result<Scenario, (usize, String)> {
+    let mut scenario = Scenario {
+        name: format!("scenario {}", index + 1),
+        fork_height: None,
+        depth: None,
+        hashrate: None,
+        target_days: None,
+        margin_blocks: None,
+        margin_percent: None,
+    };
+    for (line, key, value) in table.entries {
+        let at = |reason: String| (line, reason);
+        match key.as_str() {
+            "name" => scenario.name = value.string(&key).map_err(at)?,
+            "fork_height" => scenario.fork_height = Some(whole(value.number(&key).map_err(at)?, &key).map_err(at)?),
+            "depth" => scenario.depth = Some(whole(value.number(&key).map_err(at)?, &key).map_err(at)?),
+            "hashrate" => scenario.hashrate = Some(value.number_or(&key, parse_hashrate).map_err(at)?),
+            "days" => scenario.target_days = Some(value.number_or(&key, parse_days).map_err(at)?),
+            "margin_blocks" => {
+                scenario.margin_blocks = Some(whole(value.number(&key).map_err(at)?, &key).map_err(at)?)
+            }
+            "margin_percent" => scenario.margin_percent = Some(value.number(&key).map_err(at)?),
+            _ => {
+                return Err(at(format!(
+                    "unknown key {}; expected name, fork_height, depth, hashrate, days, margin_blocks or margin_percent",
+                    key
+                )))
+            }
+        }
+    }
+    if scenario.fork_height.is_some() && scenario.depth.is_some() {
+        return Err((table.line, "give fork_height or depth, not both".to_string()));
+    }
+    if scenario.hashrate == Some(0.0) || scenario.target_days == Some(0.0) {
+        return Err((table.line, "hashrate and days must be positive".to_string()));
+    }
+    Ok(scenario)
+}
+
+/// `number` as a whole block count or height.
+fn whole(number: f64, key: &str) -> std::result::Result<u64, String> {
+    if number.fract() == 0.0 && number <= u64::MAX as f64 {
+        Ok(number as u64)
+    } else {
+        Err(format!("{} must be a whole number", key))
+    }
+}
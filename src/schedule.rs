@@ -0,0 +1,49 @@
+//! Day-by-day plan of an attack at a fixed hashrate.
+//!
+//! Blocks come in at the steady rate that mines `blocks_needed` in exactly
+//! the time required, so whatever that time accounts for (efficiency,
+//! overhead, honest growth, retargets) carries over. Luck does not: the
+//! days are expected values, and `--simulate` gives their spread.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::calc::ReorgCalculation;
+use crate::SECONDS_PER_DAY;
+
+/// Days planned at most; a longer attack is cut off there.
+pub const MAX_SCHEDULE_DAYS: u64 = 366;
+
+/// Where an attack stands at the end of one day of mining.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleDay {
+    /// Day of the attack, from 1.
+    pub day: u64,
+    /// End of the day, or the projected overtake on the last one.
+    pub ends_at: DateTime<Utc>,
+    /// Blocks expected to be mined by then.
+    pub blocks: f64,
+    /// Fraction of the attack's work done by then.
+    pub work_share: f64,
+}
+
+/// The day-by-day plan of `calc`'s attack started at `start`, ending with
+/// the day it overtakes; empty for an attack that never finishes.
+pub fn attack_schedule(calc: &ReorgCalculation, start: DateTime<Utc>) -> Vec<ScheduleDay> {
+    let days = calc.time_required_days;
+    if !(days.is_finite() && days > 0.0) {
+        return Vec::new();
+    }
+    let last_day = (days.ceil() as u64).clamp(1, MAX_SCHEDULE_DAYS);
+    (1..=last_day)
+        .map(|day| {
+            let elapsed = (day as f64).min(days);
+            ScheduleDay {
+                day,
+                ends_at: start + Duration::milliseconds((elapsed * SECONDS_PER_DAY * 1000.0) as i64),
+                blocks: calc.blocks_needed * elapsed / days,
+                work_share: elapsed / days,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,268 @@
+//! Minimal HTTP/JSON server exposing the calculator.
+//!
+//! Endpoints:
+//!
+//! - `GET /calc?fork_height=<h>[&hashrate=<H/s>][&days=<d>]`
+//! - `GET /viable[?hashrate=<H/s>][&days=<d>]`
+//!
+//! Query values are percent-decoded and take the CLI's units, e.g.
+//! `hashrate=5.2TH` or `days=36h`. Connections are answered with JSON by a
+//! fixed pool of worker threads, and requests are capped in size. Reorgs of
+//! more than [`MAX_REORG_BLOCKS`] are refused, as a node without chain work
+//! lookups would have every one of their headers scanned for each request.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::calc::{calculate_reorg_requirements, find_viable_target_heights_with, Adjustments};
+use crate::error::{ReorgCalcError, Result};
+use crate::source::BlockSource;
+use crate::units::{parse_days, parse_hashrate};
+use crate::{say, warning};
+
+/// How long a client may take to send each part of its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections handled at once; further ones wait to be accepted.
+const WORKERS: usize = 8;
+
+/// Largest request line and headers read from a client.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// Longest reorg `/calc` answers, in blocks.
+pub const MAX_REORG_BLOCKS: u64 = 10_000;
+
+/// Parameters used when a request leaves them out.
+#[derive(Debug, Clone, Copy)]
+pub struct ServeDefaults {
+    pub hashrate: f64,
+    pub target_days: f64,
+    /// Applied to every calculation.
+    pub adjustments: Adjustments,
+}
+
+/// Listen on `addr` and answer requests until the process is stopped.
+pub fn serve<S: BlockSource + ?Sized>(source: &S, addr: &str, defaults: ServeDefaults) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    say!("Serving on http://{}", listener.local_addr()?);
+
+    // Accepting stops while every worker is busy and the queue is full
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(|| loop {
+                let stream = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                if let Err(e) = handle_connection(source, stream, defaults) {
+                    warning!("Warning: request failed: {}", e);
+                }
+            });
+        }
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => warning!("Warning: failed to accept connection: {}", e),
+            }
+        }
+        drop(sender);
+    });
+    Ok(())
+}
+
+fn handle_connection<S: BlockSource + ?Sized>(source: &S, mut stream: TcpStream, defaults: ServeDefaults) -> Result<()> {
+    // A client that stops sending would otherwise hold its thread forever
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES));
+    reader.read_line(&mut request_line)?;
+    // Drain headers up to the blank line; no endpoint takes a body
+    let mut line = String::new();
+    let complete = loop {
+        line.clear();
+        match reader.read_line(&mut line)? {
+            0 => break false,
+            1 | 2 => break true,
+            _ => {}
+        }
+    };
+    let too_large = !complete && reader.get_ref().limit() == 0;
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = if too_large {
+        (431, json!({ "error": format!("request is larger than {} bytes", MAX_REQUEST_BYTES) }))
+    } else if method != "GET" {
+        (405, json!({ "error": "only GET is supported" }))
+    } else {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        route(source, path, &parse_query(query), defaults)
+    };
+
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn route<S: BlockSource + ?Sized>(source: &S, path: &str, query: &HashMap<String, String>, defaults: ServeDefaults) -> (u16, Value) {
+    let hashrate = match param(query, "hashrate", defaults.hashrate, parse_hashrate) {
+        Ok(v) => v,
+        Err(e) => return (400, json!({ "error": e })),
+    };
+    let target_days = match param(query, "days", defaults.target_days, parse_days) {
+        Ok(v) => v,
+        Err(e) => return (400, json!({ "error": e })),
+    };
+
+    match path {
+        "/calc" => {
+            let fork_height = match query.get("fork_height").map(|v| v.parse::<u64>()) {
+                Some(Ok(h)) => h,
+                Some(Err(_)) => return (400, json!({ "error": "invalid fork_height" })),
+                None => return (400, json!({ "error": "missing fork_height" })),
+            };
+            match source.get_tip() {
+                Ok(tip) if (tip.height + 1).saturating_sub(fork_height) > MAX_REORG_BLOCKS => {
+                    let error = format!("reorgs of more than {} blocks are not served", MAX_REORG_BLOCKS);
+                    return (422, json!({ "error": error }));
+                }
+                Ok(_) => {}
+                Err(e) => return error_response(&e),
+            }
+            match calculate_reorg_requirements(source, fork_height, hashrate, target_days) {
+                Ok(calc) => match source
+                    .get_network()
+                    .and_then(|params| defaults.adjustments.apply(source, calc, hashrate, target_days, &params))
+                {
+                    Ok(calc) => (200, json!(calc)),
+                    Err(e) => error_response(&e),
+                },
+                Err(e) => error_response(&e),
+            }
+        }
+        "/viable" => match find_viable_target_heights_with(source, hashrate, target_days, &defaults.adjustments) {
+            Ok(heights) => (
+                200,
+                json!({ "hashrate": hashrate, "target_days": target_days, "viable_heights": heights }),
+            ),
+            Err(e) => error_response(&e),
+        },
+        _ => (404, json!({ "error": "not found" })),
+    }
+}
+
+fn error_response(error: &ReorgCalcError) -> (u16, Value) {
+    let status = match error {
+        ReorgCalcError::ForkAboveTip { .. } => 422,
+        ReorgCalcError::NodeUnreachable(_) | ReorgCalcError::AuthFailed => 502,
+        _ => 500,
+    };
+    (status, json!({ "error": error.to_string() }))
+}
+
+fn param(
+    query: &HashMap<String, String>,
+    key: &str,
+    default: f64,
+    parse: fn(&str) -> std::result::Result<f64, String>,
+) -> std::result::Result<f64, String> {
+    match query.get(key) {
+        Some(v) => parse(v).map_err(|e| format!("invalid {}: {}", key, e)),
+        None => Ok(default),
+    }
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` for space, as browsers encode query strings.
+/// Malformed escapes are kept as they are.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = hex {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+                decoded.push(b'%');
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        502 => "Bad Gateway",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{TestChain, DIFFICULTY_1};
+
+    fn defaults() -> ServeDefaults {
+        ServeDefaults { hashrate: crate::HASHES_PER_DIFFICULTY, target_days: 1.0, adjustments: Adjustments::default() }
+    }
+
+    #[test]
+    fn queries_are_percent_decoded() {
+        let query = parse_query("hashrate=5.2%20TH&days=36h&note=a+b&bad=%zz&flag");
+        assert_eq!(query["hashrate"], "5.2 TH");
+        assert_eq!(query["note"], "a b");
+        assert_eq!(query["bad"], "%zz");
+        assert!(!query.contains_key("flag"));
+    }
+
+    #[test]
+    fn deep_forks_are_refused() {
+        let chain = TestChain::uniform(MAX_REORG_BLOCKS as usize + 1, DIFFICULTY_1).without_chainwork();
+        let calc = |fork_height: &str| route(&chain, "/calc", &parse_query(&format!("fork_height={}", fork_height)), defaults());
+        let (status, body) = calc("1");
+        assert_eq!(status, 200);
+        assert_eq!(body["blocks_to_reorg"], MAX_REORG_BLOCKS);
+        assert_eq!(calc("0").0, 422);
+        assert_eq!(calc("x").0, 400);
+        assert_eq!(route(&chain, "/calc", &HashMap::new(), defaults()).0, 400);
+    }
+}
@@ -0,0 +1,171 @@
+//! Monte Carlo simulation of mining variance.
+//!
+//! Blocks arrive as a Poisson process, so the time to mine a given amount of
+//! work varies from one attempt to the next, and the fewer blocks it takes
+//! the wider the spread. Each run mines the attacker's chain block by block
+//! with exponentially distributed block times and, when the honest network
+//! is modeled, races an honest chain mined the same way.
+
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::calc::ReorgCalculation;
+use crate::network::NetworkParams;
+use crate::SECONDS_PER_DAY;
+
+/// Runs give up after this many times the longer of the expected and the
+/// target time.
+pub const HORIZON_FACTOR: f64 = 10.0;
+
+/// Spread of completion times over simulated attacks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MiningSimulation {
+    pub runs: u64,
+    /// Completion time percentiles in days; `None` (`null` in JSON) when
+    /// that share of runs had not finished by `horizon_days`.
+    pub p10_days: Option<f64>,
+    pub p50_days: Option<f64>,
+    pub p90_days: Option<f64>,
+    pub target_days: f64,
+    /// Share of runs that finished within `target_days`.
+    pub within_target: f64,
+    /// Time after which an unfinished run counts as never finishing.
+    pub horizon_days: f64,
+}
+
+/// Simulate `runs` (at least one) attacks on `calc` at `hashrate`
+/// hashes/second.
+///
+/// The attacker has to mine the existing work plus `calc.margin`, at the
+/// difficulties of `calc.attacker_epochs` when retargets were simulated and
+/// the current difficulty otherwise. With `calc.honest_growth` the honest
+/// chain grows at its hashrate and the current difficulty until overtaken.
+pub fn simulate_mining(
+    calc: &ReorgCalculation,
+    runs: u64,
+    hashrate: f64,
+    target_days: f64,
+    params: &NetworkParams,
+) -> MiningSimulation {
+    let expected_days = if calc.time_required_days.is_finite() { calc.time_required_days } else { 0.0 };
+    let race = Race {
+        lead: calc.lead_blocks() * calc.current_difficulty,
+        current_difficulty: calc.current_difficulty,
+        schedule: calc.attacker_epochs.iter().map(|epoch| (epoch.blocks, epoch.difficulty)).collect(),
+        attacker_seconds_per_difficulty: params.hashes_per_difficulty() / hashrate,
+        honest_block_seconds: calc
+            .honest_growth
+            .map(|growth| calc.current_difficulty * params.hashes_per_difficulty() / growth.hashrate),
+        horizon: HORIZON_FACTOR * expected_days.max(target_days) * SECONDS_PER_DAY,
+    };
+
+    let mut times: Vec<f64> = (0..runs).into_par_iter().map(|_| race.run(&mut rand::thread_rng())).collect();
+    times.sort_by(f64::total_cmp);
+
+    let target_seconds = target_days * SECONDS_PER_DAY;
+    let percentile = |share: f64| {
+        let seconds = times[((times.len() - 1) as f64 * share).round() as usize];
+        seconds.is_finite().then(|| seconds / SECONDS_PER_DAY)
+    };
+    MiningSimulation {
+        runs,
+        p10_days: percentile(0.1),
+        p50_days: percentile(0.5),
+        p90_days: percentile(0.9),
+        target_days,
+        within_target: times.iter().filter(|&&seconds| seconds <= target_seconds).count() as f64 / runs as f64,
+        horizon_days: race.horizon / SECONDS_PER_DAY,
+    }
+}
+
+/// Inputs shared by every run, work in difficulty units.
+struct Race {
+    /// Work the attacker needs beyond whatever the honest chain adds.
+    lead: f64,
+    current_difficulty: f64,
+    /// Attacker blocks per difficulty; the last difficulty carries on past
+    /// the end, and an empty schedule mines at the current difficulty.
+    schedule: Vec<(u64, f64)>,
+    attacker_seconds_per_difficulty: f64,
+    honest_block_seconds: Option<f64>,
+    horizon: f64,
+}
+
+impl Race {
+    /// Seconds until the attacker's chain has the most work, or infinity if
+    /// it still trails at the horizon.
+    fn run<R: Rng>(&self, rng: &mut R) -> f64 {
+        let last = self.schedule.last().map_or(self.current_difficulty, |&(_, difficulty)| difficulty);
+        let mut difficulties = self
+            .schedule
+            .iter()
+            .flat_map(|&(blocks, difficulty)| std::iter::repeat_n(difficulty, blocks as usize))
+            .chain(std::iter::repeat(last));
+
+        let mut time = 0.0;
+        let mut attacker_work = 0.0;
+        let mut honest_work = 0.0;
+        let mut next_honest = self.honest_block_seconds.map_or(f64::INFINITY, |mean| exponential(rng, mean));
+        loop {
+            let difficulty = difficulties.next().unwrap_or(last);
+            time += exponential(rng, difficulty * self.attacker_seconds_per_difficulty);
+            if time > self.horizon {
+                return f64::INFINITY;
+            }
+            // Honest blocks found before this one count against it
+            while next_honest <= time {
+                honest_work += self.current_difficulty;
+                next_honest += self.honest_block_seconds.map_or(f64::INFINITY, |mean| exponential(rng, mean));
+            }
+            attacker_work += difficulty;
+            // Whole blocks sum to the lead only up to rounding
+            if attacker_work >= (self.lead + honest_work) * (1.0 - 1e-9) {
+                return time;
+            }
+        }
+    }
+}
+
+/// Exponentially distributed sample with the given mean.
+fn exponential<R: Rng>(rng: &mut R, mean: f64) -> f64 {
+    -mean * (1.0 - rng.gen::<f64>()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::calculate_from_work;
+    use crate::HASHES_PER_DIFFICULTY;
+
+    /// 10,000 difficulty-1 blocks mined at one a second.
+    fn long_attack() -> ReorgCalculation {
+        calculate_from_work(0, 9_999, 10_000.0, 1.0, HASHES_PER_DIFFICULTY, 1.0, &NetworkParams::default())
+    }
+
+    #[test]
+    fn long_attacks_finish_close_to_the_expected_time() {
+        let calc = long_attack();
+        let expected_days = calc.time_required_days;
+        let simulation = simulate_mining(&calc, 200, HASHES_PER_DIFFICULTY, expected_days * 2.0, &NetworkParams::default());
+        let (p10, p50, p90) = (simulation.p10_days.unwrap(), simulation.p50_days.unwrap(), simulation.p90_days.unwrap());
+        // Over 10,000 blocks the spread is about 1%
+        assert!(p10 <= p50 && p50 <= p90);
+        assert!((p50 / expected_days - 1.0).abs() < 0.05, "median {} days, expected {}", p50, expected_days);
+        assert_eq!(simulation.within_target, 1.0);
+        assert_eq!(simulation.horizon_days, HORIZON_FACTOR * expected_days * 2.0);
+
+        let rushed = simulate_mining(&calc, 50, HASHES_PER_DIFFICULTY, expected_days / 2.0, &NetworkParams::default());
+        assert_eq!(rushed.within_target, 0.0);
+    }
+
+    #[test]
+    fn a_faster_honest_chain_is_never_overtaken() {
+        let params = NetworkParams::default();
+        let target_days = 1_000.0 / SECONDS_PER_DAY;
+        let calc = long_attack().with_honest_growth(2.0 * HASHES_PER_DIFFICULTY, HASHES_PER_DIFFICULTY, target_days, &params);
+        let simulation = simulate_mining(&calc, 20, HASHES_PER_DIFFICULTY, target_days, &params);
+        assert_eq!((simulation.p10_days, simulation.p50_days, simulation.p90_days), (None, None, None));
+        assert_eq!(simulation.within_target, 0.0);
+    }
+}
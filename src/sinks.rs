@@ -0,0 +1,523 @@
+//! Everywhere a run's calculations and monitor events are sent.
+//!
+//! Every calculation is appended to the plaintext results file, rotated if
+//! asked, and to whichever of the JSON Lines log, the SQLite and PostgreSQL
+//! databases, InfluxDB and the MQTT broker are configured. Monitor events
+//! are recorded the same way, alerts fire to the webhooks and chats, and
+//! monitors keep their state across restarts through [`ResultSinks`] too.
+
+use chrono::{DateTime, Utc};
+use std::env;
+use std::path::PathBuf;
+
+use crate::alert::{Alert, AlertRules};
+#[cfg(feature = "webhook")]
+use crate::alert::post_webhook;
+use crate::calc::ReorgCalculation;
+#[cfg(feature = "webhook")]
+use crate::chat::ChatService;
+use crate::chat::{ChatWebhook, TelegramBot};
+use crate::error::{ReorgCalcError, Result, ResultContext};
+use crate::format::save_to_file;
+#[cfg(feature = "influx")]
+use crate::influx::post_influx;
+use crate::influx::write_influx;
+use crate::monitor::{MonitorEvent, MonitorEventKind, SeenBlock};
+use crate::mqtt::MqttPublisher;
+#[cfg(feature = "postgres")]
+use crate::postgres_sink::PostgresSink;
+use crate::report::{ReportOutput, ReportSink};
+use crate::results_log::ResultsLog;
+use crate::rotation::Rotation;
+use crate::source::{BlockSource, ChainTip};
+use crate::state::MonitorState;
+#[cfg(feature = "sqlite")]
+use crate::store::{ResultsStore, StoreQuery};
+use crate::track::{branch_progress, BranchWatch};
+use crate::{say, warning};
+
+/// The plaintext results file, the JSON Lines log, the databases and the
+/// other sinks calculations and events go to, opened on the first save.
+pub struct ResultSinks {
+    text: PathBuf,
+    rotation: Option<Rotation>,
+    started: DateTime<Utc>,
+    /// Whether any saved calculation fits its target time.
+    viable: bool,
+    log_path: String,
+    log: Option<ResultsLog>,
+    #[cfg(feature = "sqlite")]
+    db_path: String,
+    #[cfg(feature = "sqlite")]
+    store: Option<ResultsStore>,
+    #[cfg(feature = "postgres")]
+    postgres: Option<PostgresSink>,
+    /// InfluxDB write endpoint and API token.
+    #[cfg(feature = "influx")]
+    influx: Option<(String, Option<String>)>,
+    /// Where the InfluxDB report goes, for monitors' points.
+    influx_out: Option<ReportSink>,
+    mqtt: Option<MqttPublisher>,
+    alerts: AlertRules,
+    /// Readings of the node's branches, for `competing` alert rules.
+    branches: Option<BranchWatch>,
+    /// Where monitors keep their state across restarts.
+    state_path: Option<PathBuf>,
+    #[cfg(feature = "webhook")]
+    webhook: Option<String>,
+    #[cfg(feature = "webhook")]
+    chat: Vec<ChatWebhook>,
+    #[cfg(feature = "webhook")]
+    telegram: Option<TelegramBot>,
+}
+
+impl ResultSinks {
+    /// The plaintext results file `text`, and the log and database named by
+    /// `RESULTS_LOG` and `RESULTS_DB` if they are set; without them nothing
+    /// but the results file is written.
+    pub fn new(text: PathBuf) -> Self {
+        ResultSinks {
+            text,
+            rotation: None,
+            started: Utc::now(),
+            viable: false,
+            log_path: env::var("RESULTS_LOG").unwrap_or_default(),
+            log: None,
+            #[cfg(feature = "sqlite")]
+            db_path: env::var("RESULTS_DB").unwrap_or_default(),
+            #[cfg(feature = "sqlite")]
+            store: None,
+            #[cfg(feature = "postgres")]
+            postgres: None,
+            #[cfg(feature = "influx")]
+            influx: None,
+            influx_out: None,
+            mqtt: None,
+            alerts: AlertRules::default(),
+            branches: None,
+            state_path: None,
+            #[cfg(feature = "webhook")]
+            webhook: None,
+            #[cfg(feature = "webhook")]
+            chat: Vec::new(),
+            #[cfg(feature = "webhook")]
+            telegram: None,
+        }
+    }
+
+    /// Whether any calculation saved so far fits its target time.
+    pub fn viable(&self) -> bool {
+        self.viable
+    }
+
+    /// Start a new results file by `rotation`, or keep appending to one.
+    pub fn set_rotation(&mut self, rotation: Option<Rotation>) {
+        self.rotation = rotation;
+    }
+
+    /// Append every calculation to the JSON Lines log at `path`.
+    pub fn set_log_path(&mut self, path: impl Into<String>) {
+        self.log_path = path.into();
+    }
+
+    /// Store every calculation in the SQLite database at `path`.
+    #[cfg(feature = "sqlite")]
+    pub fn set_db_path(&mut self, path: impl Into<String>) {
+        self.db_path = path.into();
+    }
+
+    /// Write every calculation as InfluxDB points to `out` as it is saved.
+    pub fn stream_influx(&mut self, out: ReportSink) {
+        self.influx_out = Some(out);
+    }
+
+    /// Check every calculation and reorg against `alerts`, reading the
+    /// node's branches over `competing_window` seconds if a rule needs them.
+    pub fn set_alerts(&mut self, alerts: AlertRules, competing_window: u64) {
+        if alerts.watches_branches() {
+            self.branches = Some(BranchWatch::new(competing_window));
+        }
+        self.alerts = alerts;
+    }
+
+    /// Keep monitors' state in the file at `path` across restarts.
+    pub fn set_state_path(&mut self, path: PathBuf) {
+        self.state_path = Some(path);
+    }
+
+    /// Publish every calculation, and monitor events, through `mqtt` as well.
+    pub fn publish_mqtt(&mut self, mqtt: MqttPublisher) {
+        self.mqtt = Some(mqtt);
+    }
+
+    /// POST every alert that fires to `url` as well.
+    pub fn post_webhook(&mut self, url: &str) -> Result<()> {
+        #[cfg(feature = "webhook")]
+        {
+            self.webhook = Some(url.to_string());
+            Ok(())
+        }
+        #[cfg(not(feature = "webhook"))]
+        {
+            let _ = url;
+            Err(ReorgCalcError::InvalidInput(
+                "Webhooks not available. Compile with --features webhook".to_string(),
+            ))
+        }
+    }
+
+    /// Post everything saved, and every alert that fires, to the chat
+    /// `webhooks` as well.
+    pub fn post_chat(&mut self, webhooks: &[ChatWebhook]) -> Result<()> {
+        #[cfg(feature = "webhook")]
+        {
+            self.chat = webhooks.to_vec();
+            Ok(())
+        }
+        #[cfg(not(feature = "webhook"))]
+        {
+            let _ = webhooks;
+            Err(ReorgCalcError::InvalidInput(
+                "Chat webhooks not available. Compile with --features webhook".to_string(),
+            ))
+        }
+    }
+
+    /// Send everything saved, and every alert that fires, to the Telegram
+    /// `bot` configured as well; without the webhook feature that only warns,
+    /// so a shared `.env` does not stop other builds.
+    pub fn post_telegram(&mut self, bot: TelegramBot) {
+        #[cfg(feature = "webhook")]
+        {
+            self.telegram = Some(bot);
+        }
+        #[cfg(not(feature = "webhook"))]
+        {
+            let _ = bot;
+            warning!("Warning: TELEGRAM_BOT_TOKEN is set, but Telegram needs a build with --features webhook");
+        }
+    }
+
+    /// Post `message` to each chat webhook and the Telegram bot, warning
+    /// about those that fail.
+    #[cfg(feature = "webhook")]
+    fn chat(&self, message: impl Fn(ChatService) -> serde_json::Value) {
+        for webhook in &self.chat {
+            if let Err(e) = post_webhook(&webhook.url, &message(webhook.service)) {
+                warning!("Warning: {} {}", webhook.service, e);
+            }
+        }
+        if let Some(bot) = &self.telegram {
+            if let Err(e) = bot.send(message(ChatService::Telegram)) {
+                warning!("Warning: Telegram {}", e);
+            }
+        }
+    }
+
+    /// Announce `alert` and hand it to the sinks for alerts; a failure only
+    /// warns, so monitoring carries on.
+    fn fire(&mut self, alert: &Alert) {
+        say!("Alert: {}", alert.describe());
+        self.record(&[MonitorEvent { time: alert.time, kind: alert.event_kind() }]);
+        #[cfg(feature = "webhook")]
+        if let Some(url) = &self.webhook {
+            if let Err(e) = post_webhook(url, alert) {
+                warning!("Warning: {}", e);
+            }
+        }
+        #[cfg(feature = "webhook")]
+        self.chat(|service| service.alert_message(alert));
+    }
+
+    /// Send everything saved to the InfluxDB write endpoint `url` as well,
+    /// with the API token in `INFLUX_TOKEN` if it is set.
+    pub fn post_influx(&mut self, url: &str) -> Result<()> {
+        #[cfg(feature = "influx")]
+        {
+            self.influx = Some((url.to_string(), env::var("INFLUX_TOKEN").ok()));
+            Ok(())
+        }
+        #[cfg(not(feature = "influx"))]
+        {
+            let _ = url;
+            Err(ReorgCalcError::InvalidInput(
+                "InfluxDB output not available. Compile with --features influx".to_string(),
+            ))
+        }
+    }
+
+    /// Send everything saved, and monitor events, to the database at `url`
+    /// as well.
+    pub fn connect_postgres(&mut self, url: &str) -> Result<()> {
+        #[cfg(feature = "postgres")]
+        {
+            self.postgres = Some(PostgresSink::connect(url).context("cannot connect to --db-url")?);
+            Ok(())
+        }
+        #[cfg(not(feature = "postgres"))]
+        {
+            let _ = url;
+            Err(ReorgCalcError::InvalidInput(
+                "PostgreSQL sink not available. Compile with --features postgres".to_string(),
+            ))
+        }
+    }
+
+    /// Record `events` in the database and publish the alerts among them,
+    /// if there are sinks for them; a failure only warns, so monitoring
+    /// carries on.
+    pub fn record(&mut self, events: &[MonitorEvent]) {
+        if let Some(mqtt) = &mut self.mqtt {
+            for event in events {
+                if let Err(e) = mqtt.publish_event(event) {
+                    warning!("Warning: failed to publish {} event: {}", event.kind.name(), e);
+                }
+            }
+        }
+        #[cfg(feature = "postgres")]
+        if let Some(postgres) = &mut self.postgres {
+            for event in events {
+                if let Err(e) = postgres.record(event) {
+                    warning!("Warning: failed to record {} event: {}", event.kind.name(), e);
+                }
+            }
+        }
+        let new_height = events.iter().find_map(|event| match event.kind {
+            MonitorEventKind::NewTip { height, .. } => Some(height),
+            _ => None,
+        });
+        for event in events {
+            if let MonitorEventKind::Reorg { height, hash, fork_height, depth, displaced } = &event.kind {
+                let replaced = ChainTip { height: *height, hash: *hash };
+                self.reorg(event.time, replaced, *fork_height, *depth, displaced);
+                let alerts = self.alerts.check_reorg(event.time, *fork_height, *depth, new_height.unwrap_or(*height));
+                for alert in &alerts {
+                    self.fire(alert);
+                }
+            }
+        }
+    }
+
+    /// The state `command` saved for the chain of `source` in the state
+    /// file, with the alerts that held restored, or a fresh one.
+    pub fn restore_state(&mut self, source: &dyn BlockSource, command: &str) -> Result<MonitorState> {
+        let genesis = source.get_block_hash(0)?;
+        let Some(path) = &self.state_path else {
+            return Ok(MonitorState::new(command, genesis));
+        };
+        let saved = MonitorState::load(path).context(format!("cannot read --state {}", path.display()))?;
+        match saved {
+            Some(state) if state.belongs_to(command, genesis) => {
+                let tip = state.seen.tip().map_or_else(String::new, |tip| format!(" at tip {}", tip.height));
+                say!("Resuming the {} session saved in {}{}", command, path.display(), tip);
+                self.alerts.restore(&state.alerts);
+                Ok(state)
+            }
+            Some(state) => {
+                warning!(
+                    "Warning: {} holds a {} session for another chain or command; starting afresh",
+                    path.display(),
+                    state.command
+                );
+                Ok(MonitorState::new(command, genesis))
+            }
+            None => Ok(MonitorState::new(command, genesis)),
+        }
+    }
+
+    /// Save `state`, with the alerts holding now, to the state file; a
+    /// failure only warns, like failing to save results.
+    pub fn save_state(&self, state: &mut MonitorState) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        state.alerts = self.alerts.held();
+        if let Err(e) = state.save(path) {
+            warning!("Warning: failed to save the state to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Read the branches `source` knows of, for `competing` alert rules, and
+    /// fire the alerts of those out-mining the active chain.
+    pub fn watch_branches(&mut self, source: &dyn BlockSource) {
+        let Some(watch) = &mut self.branches else {
+            return;
+        };
+        let alerts = match branch_progress(source).and_then(|branches| Ok((branches, source.get_network()?))) {
+            Ok((branches, params)) => {
+                let rates = watch.observe(Utc::now(), &branches);
+                self.alerts.check_branches(&rates, &params)
+            }
+            Err(e) => {
+                warning!("Warning: reading the chain tips failed: {}", e);
+                return;
+            }
+        };
+        for alert in &alerts {
+            self.fire(alert);
+        }
+    }
+
+    /// Print a reorg seen at `time`, which replaced the tip `replaced` and
+    /// displaced `depth` blocks from `fork_height` up, and store it.
+    fn reorg(&mut self, time: DateTime<Utc>, replaced: ChainTip, fork_height: u64, depth: u64, displaced: &[SeenBlock]) {
+        say!(
+            "\n[{}] Reorg: {} blocks displaced from height {}, replacing tip {} ({})",
+            time.format("%Y-%m-%d %H:%M:%S UTC"),
+            depth,
+            fork_height,
+            replaced.height,
+            replaced.hash
+        );
+        for block in displaced {
+            say!("  {} {} mined {}", block.height, block.hash, block.time.format("%Y-%m-%d %H:%M:%S UTC"));
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            let stored = self.store().and_then(|store| match store {
+                Some(store) => store.insert_reorg(time, replaced, fork_height, displaced),
+                None => Ok(()),
+            });
+            if let Err(e) = stored {
+                warning!("Warning: failed to store the reorg: {}", e);
+            }
+        }
+    }
+
+    /// The SQLite store, opened the first time it is needed, or `None` if
+    /// no database was named.
+    #[cfg(feature = "sqlite")]
+    fn store(&mut self) -> Result<Option<&ResultsStore>> {
+        if self.store.is_none() && !self.db_path.is_empty() {
+            let store = ResultsStore::open(&self.db_path).context(format!("cannot open results database {}", self.db_path))?;
+            self.store = Some(store);
+        }
+        Ok(self.store.as_ref())
+    }
+
+    /// The stored calculations `query` asks for, each with its hashrate.
+    #[cfg(feature = "sqlite")]
+    pub fn stored(&self, query: &StoreQuery) -> Result<Vec<(ReorgCalculation, f64)>> {
+        let path = &self.db_path;
+        if path.is_empty() {
+            return Err(ReorgCalcError::InvalidInput(
+                "no results database given; name it with --results-db or RESULTS_DB".to_string(),
+            ));
+        }
+        if !std::path::Path::new(path).exists() {
+            return Err(ReorgCalcError::InvalidInput(format!(
+                "no results database at {:?}; calculations are stored there by runs given the same --results-db",
+                path
+            )));
+        }
+        ResultsStore::open(path)?.query(query)
+    }
+
+    /// Save `runs`, each a calculation and its hashrate, to every sink.
+    pub fn save(&mut self, runs: &[(ReorgCalculation, f64)]) -> Result<()> {
+        self.viable |= runs
+            .iter()
+            .any(|(calc, _)| calc.target_days.is_some_and(|days| calc.time_required_days <= days));
+        let alerts: Vec<Alert> = runs.iter().flat_map(|(calc, hashrate)| self.alerts.check(calc, *hashrate)).collect();
+        for alert in &alerts {
+            self.fire(alert);
+        }
+        #[cfg(feature = "webhook")]
+        if !runs.is_empty() {
+            self.chat(|service| service.calculations_message(runs));
+        }
+        // One entry per stretch of runs at the same hashrate
+        for stretch in runs.chunk_by(|(_, a), (_, b)| a == b) {
+            let calculations: Vec<ReorgCalculation> = stretch.iter().map(|(calc, _)| calc.clone()).collect();
+            let text = match self.rotation {
+                Some(rotation) => rotation.current(&self.text, self.started)?,
+                None => self.text.clone(),
+            };
+            save_to_file(&calculations, text, stretch[0].1)?;
+        }
+        if self.log.is_none() && !self.log_path.is_empty() {
+            let log = ResultsLog::open(&self.log_path).context(format!("cannot open results log {}", self.log_path))?;
+            self.log = Some(log);
+        }
+        if let Some(log) = &mut self.log {
+            for (calc, hashrate) in runs {
+                log.append(calc, *hashrate)?;
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.store()? {
+            for (calc, hashrate) in runs {
+                store.insert(calc, *hashrate)?;
+            }
+        }
+        #[cfg(feature = "postgres")]
+        if let Some(postgres) = &mut self.postgres {
+            for (calc, hashrate) in runs {
+                postgres.insert(calc, *hashrate)?;
+            }
+        }
+        #[cfg(feature = "influx")]
+        if let Some((url, token)) = &self.influx {
+            post_influx(url, token.as_deref(), runs)?;
+        }
+        if let Some(out) = &self.influx_out {
+            write_influx(out, runs)?;
+        }
+        if let Some(mqtt) = &mut self.mqtt {
+            for (calc, hashrate) in runs {
+                mqtt.publish_calculation(calc, *hashrate)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Save `runs`, each a calculation and its hashrate, to `sinks` and write
+/// the reports if there are any.
+pub fn save_results(
+    runs: &[(ReorgCalculation, f64)],
+    sinks: &mut ResultSinks,
+    target_days: f64,
+    report: Option<&ReportOutput>,
+) -> Result<()> {
+    sinks.save(runs)?;
+    if let Some(report) = report {
+        report.write(runs, target_days)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{temp_path, TestChain, DIFFICULTY_1};
+    use crate::calc::calculate_reorg_requirements;
+
+    #[test]
+    fn saving_fills_the_results_file_and_log() {
+        let text = temp_path("sinks.txt");
+        let log = temp_path("sinks.jsonl");
+        let mut sinks = ResultSinks::new(text.clone());
+        sinks.set_log_path(log.to_string_lossy());
+        let chain = TestChain::uniform(200, DIFFICULTY_1);
+        let slow = calculate_reorg_requirements(&chain, 100, 1.0, 1.0).unwrap();
+        sinks.save(&[(slow, 1.0)]).unwrap();
+        assert!(!sinks.viable());
+        let fast = calculate_reorg_requirements(&chain, 190, 1e15, 1.0).unwrap();
+        sinks.save(&[(fast, 1e15)]).unwrap();
+        assert!(sinks.viable());
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 2);
+        assert!(std::fs::metadata(&text).unwrap().len() > 0);
+        let _ = std::fs::remove_file(text);
+        let _ = std::fs::remove_file(log);
+    }
+
+    #[test]
+    fn without_a_state_file_monitors_start_afresh() {
+        let chain = TestChain::uniform(10, DIFFICULTY_1);
+        let mut sinks = ResultSinks::new(temp_path("sinks-state.txt"));
+        let state = sinks.restore_state(&chain, "watch").unwrap();
+        assert_eq!(state.command, "watch");
+        assert!(state.previous.is_none());
+    }
+}
@@ -0,0 +1,671 @@
+//! Record-and-replay snapshots of the chain data a run reads.
+//!
+//! A [`RecordingSource`] passes every call through to the source it wraps
+//! and keeps the answers: tips, hashes, headers, targets, chain work,
+//! coinbases, blocks and lookups. Saved to a file, they make a [`Snapshot`] that a
+//! [`ReplaySource`] answers from later, so anyone with the file repeats the
+//! exact calculation without the node, or a node on another chain. Calls a
+//! run did not make cannot be answered, so a snapshot replays with the
+//! arguments it was recorded with.
+//!
+//! The file is binary: a magic number, then one section per kind of answer,
+//! each a count followed by its entries. Numbers are little-endian, and
+//! headers and hashes use their consensus encoding.
+
+use bitcoin::{block::Header, consensus, Block, BlockHash, Transaction, Txid, Work};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, BranchStatus, BranchTip, ChainTip};
+
+const MAGIC: &[u8; 8] = b"RCSNAP\x00\x01";
+
+/// Source calls that may be unsupported, which a replay has to refuse in
+/// turn so the run takes the same fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Lookup {
+    Chainwork,
+    Transaction,
+    Block,
+    ChainTips,
+    Coinbase,
+    FullBlock,
+}
+
+impl Lookup {
+    const ALL: [Lookup; 6] = [
+        Lookup::Chainwork,
+        Lookup::Transaction,
+        Lookup::Block,
+        Lookup::ChainTips,
+        Lookup::Coinbase,
+        Lookup::FullBlock,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Lookup::Chainwork => "chainwork",
+            Lookup::Transaction => "transaction lookup",
+            Lookup::Block => "block lookup by hash",
+            Lookup::ChainTips => "chain tips",
+            Lookup::Coinbase => "coinbase transactions",
+            Lookup::FullBlock => "full blocks",
+        }
+    }
+}
+
+/// Everything a run read from its source.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// When the run was recorded.
+    pub recorded: Option<DateTime<Utc>>,
+    /// Each tip read, in order, as the chain may grow during a run.
+    tips: Vec<ChainTip>,
+    /// Each difficulty read, in order.
+    difficulties: Vec<f64>,
+    hashes: BTreeMap<u64, BlockHash>,
+    headers: BTreeMap<u64, Header>,
+    bits: BTreeMap<u64, u32>,
+    chainwork: BTreeMap<u64, Work>,
+    transactions: BTreeMap<Txid, Option<u64>>,
+    blocks: BTreeMap<BlockHash, Option<u64>>,
+    chain_tips: Option<Vec<BranchTip>>,
+    network: Option<NetworkParams>,
+    /// The node's `getnetworkhashps`, for `--hashrate-percent`.
+    pub network_hashrate: Option<f64>,
+    unsupported: BTreeSet<Lookup>,
+    coinbases: BTreeMap<u64, Transaction>,
+    full_blocks: BTreeMap<u64, Block>,
+}
+
+impl Snapshot {
+    /// The first tip the run read.
+    pub fn tip(&self) -> Option<ChainTip> {
+        self.tips.first().copied()
+    }
+
+    /// Number of answers held.
+    pub fn responses(&self) -> usize {
+        self.tips.len()
+            + self.difficulties.len()
+            + self.hashes.len()
+            + self.headers.len()
+            + self.bits.len()
+            + self.chainwork.len()
+            + self.transactions.len()
+            + self.blocks.len()
+            + usize::from(self.chain_tips.is_some())
+            + usize::from(self.network.is_some())
+            + usize::from(self.network_hashrate.is_some())
+            + self.unsupported.len()
+            + self.coinbases.len()
+            + self.full_blocks.len()
+    }
+
+    /// Load the snapshot saved at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ReorgCalcError::InvalidData("not a snapshot file".into()));
+        }
+        let recorded = match reader.u64()? {
+            0 => None,
+            _ => Some(DateTime::from_timestamp(reader.u64()? as i64, 0).unwrap_or_default()),
+        };
+        let mut snapshot = Snapshot { recorded, ..Snapshot::default() };
+        for _ in 0..reader.u64()? {
+            snapshot.tips.push(ChainTip { height: reader.u64()?, hash: reader.decode(32)? });
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.difficulties.push(reader.f64()?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.hashes.insert(reader.u64()?, reader.decode(32)?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.headers.insert(reader.u64()?, reader.decode(80)?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.bits.insert(reader.u64()?, reader.u32()?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.chainwork.insert(reader.u64()?, reader.work()?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.transactions.insert(reader.decode(32)?, reader.height()?);
+        }
+        for _ in 0..reader.u64()? {
+            snapshot.blocks.insert(reader.decode(32)?, reader.height()?);
+        }
+        if reader.u64()? != 0 {
+            let mut tips = Vec::new();
+            for _ in 0..reader.u64()? {
+                tips.push(reader.branch()?);
+            }
+            snapshot.chain_tips = Some(tips);
+        }
+        if reader.u64()? != 0 {
+            let length = reader.u64()? as usize;
+            let name = reader.take(length)?;
+            snapshot.network = Some(NetworkParams::from_chain(&String::from_utf8_lossy(name))?);
+        }
+        if reader.u64()? != 0 {
+            snapshot.network_hashrate = Some(reader.f64()?);
+        }
+        for _ in 0..reader.u64()? {
+            let index = reader.u64()? as usize;
+            let lookup = Lookup::ALL
+                .get(index)
+                .ok_or_else(|| ReorgCalcError::InvalidData(format!("unknown lookup {} in snapshot", index)))?;
+            snapshot.unsupported.insert(*lookup);
+        }
+        // Snapshots recorded before coinbases were kept end here
+        if !reader.bytes.is_empty() {
+            for _ in 0..reader.u64()? {
+                let height = reader.u64()?;
+                let length = reader.u64()? as usize;
+                snapshot.coinbases.insert(height, reader.decode(length)?);
+            }
+        }
+        if !reader.bytes.is_empty() {
+            for _ in 0..reader.u64()? {
+                let height = reader.u64()?;
+                let length = reader.u64()? as usize;
+                snapshot.full_blocks.insert(height, reader.decode(length)?);
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Save the snapshot to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut out = MAGIC.to_vec();
+        match self.recorded {
+            Some(time) => {
+                put_u64(&mut out, 1);
+                put_u64(&mut out, time.timestamp() as u64);
+            }
+            None => put_u64(&mut out, 0),
+        }
+        put_u64(&mut out, self.tips.len() as u64);
+        for tip in &self.tips {
+            put_u64(&mut out, tip.height);
+            out.extend(consensus::serialize(&tip.hash));
+        }
+        put_u64(&mut out, self.difficulties.len() as u64);
+        for difficulty in &self.difficulties {
+            out.extend(difficulty.to_le_bytes());
+        }
+        put_u64(&mut out, self.hashes.len() as u64);
+        for (height, hash) in &self.hashes {
+            put_u64(&mut out, *height);
+            out.extend(consensus::serialize(hash));
+        }
+        put_u64(&mut out, self.headers.len() as u64);
+        for (height, header) in &self.headers {
+            put_u64(&mut out, *height);
+            out.extend(consensus::serialize(header));
+        }
+        put_u64(&mut out, self.bits.len() as u64);
+        for (height, bits) in &self.bits {
+            put_u64(&mut out, *height);
+            out.extend(bits.to_le_bytes());
+        }
+        put_u64(&mut out, self.chainwork.len() as u64);
+        for (height, work) in &self.chainwork {
+            put_u64(&mut out, *height);
+            out.extend(work.to_be_bytes());
+        }
+        put_u64(&mut out, self.transactions.len() as u64);
+        for (txid, height) in &self.transactions {
+            out.extend(consensus::serialize(txid));
+            put_height(&mut out, *height);
+        }
+        put_u64(&mut out, self.blocks.len() as u64);
+        for (hash, height) in &self.blocks {
+            out.extend(consensus::serialize(hash));
+            put_height(&mut out, *height);
+        }
+        match &self.chain_tips {
+            Some(tips) => {
+                put_u64(&mut out, 1);
+                put_u64(&mut out, tips.len() as u64);
+                for tip in tips {
+                    put_branch(&mut out, tip);
+                }
+            }
+            None => put_u64(&mut out, 0),
+        }
+        match self.network {
+            Some(params) => {
+                let name = params.network.to_core_arg();
+                put_u64(&mut out, 1);
+                put_u64(&mut out, name.len() as u64);
+                out.extend(name.as_bytes());
+            }
+            None => put_u64(&mut out, 0),
+        }
+        match self.network_hashrate {
+            Some(hashrate) => {
+                put_u64(&mut out, 1);
+                out.extend(hashrate.to_le_bytes());
+            }
+            None => put_u64(&mut out, 0),
+        }
+        put_u64(&mut out, self.unsupported.len() as u64);
+        for lookup in &self.unsupported {
+            put_u64(&mut out, Lookup::ALL.iter().position(|known| known == lookup).unwrap_or_default() as u64);
+        }
+        put_u64(&mut out, self.coinbases.len() as u64);
+        for (height, coinbase) in &self.coinbases {
+            let bytes = consensus::serialize(coinbase);
+            put_u64(&mut out, *height);
+            put_u64(&mut out, bytes.len() as u64);
+            out.extend(bytes);
+        }
+        put_u64(&mut out, self.full_blocks.len() as u64);
+        for (height, block) in &self.full_blocks {
+            let bytes = consensus::serialize(block);
+            put_u64(&mut out, *height);
+            put_u64(&mut out, bytes.len() as u64);
+            out.extend(bytes);
+        }
+        fs::write(path, out)?;
+        Ok(())
+    }
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_height(out: &mut Vec<u8>, height: Option<u64>) {
+    match height {
+        Some(height) => {
+            put_u64(out, 1);
+            put_u64(out, height);
+        }
+        None => put_u64(out, 0),
+    }
+}
+
+fn put_branch(out: &mut Vec<u8>, tip: &BranchTip) {
+    put_u64(out, tip.height);
+    out.extend(consensus::serialize(&tip.hash));
+    put_u64(out, tip.branch_length);
+    let status = [BranchStatus::ValidFork, BranchStatus::ValidHeaders, BranchStatus::HeadersOnly, BranchStatus::Invalid];
+    put_u64(out, status.iter().position(|&status| status == tip.status).unwrap_or_default() as u64);
+    out.extend(tip.chainwork.to_be_bytes());
+    out.extend(tip.bits.to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < count {
+            return Err(ReorgCalcError::InvalidData("snapshot file is truncated".into()));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn work(&mut self) -> Result<Work> {
+        Ok(Work::from_be_bytes(self.array()?))
+    }
+
+    fn height(&mut self) -> Result<Option<u64>> {
+        Ok(match self.u64()? {
+            0 => None,
+            _ => Some(self.u64()?),
+        })
+    }
+
+    fn decode<T: consensus::Decodable>(&mut self, size: usize) -> Result<T> {
+        consensus::deserialize(self.take(size)?)
+            .map_err(|e| ReorgCalcError::InvalidData(format!("corrupt snapshot: {}", e)))
+    }
+
+    fn branch(&mut self) -> Result<BranchTip> {
+        let height = self.u64()?;
+        let hash = self.decode(32)?;
+        let branch_length = self.u64()?;
+        let status = match self.u64()? {
+            0 => BranchStatus::ValidFork,
+            1 => BranchStatus::ValidHeaders,
+            2 => BranchStatus::HeadersOnly,
+            _ => BranchStatus::Invalid,
+        };
+        Ok(BranchTip { height, hash, branch_length, status, chainwork: self.work()?, bits: self.u32()? })
+    }
+}
+
+/// A [`BlockSource`] that keeps every answer `inner` gives.
+pub struct RecordingSource<S> {
+    inner: S,
+    snapshot: Mutex<Snapshot>,
+}
+
+impl<S: BlockSource> RecordingSource<S> {
+    /// Start recording the calls made to `inner`.
+    pub fn new(inner: S) -> Self {
+        let snapshot = Snapshot { recorded: Some(Utc::now()), ..Snapshot::default() };
+        Self { inner, snapshot: Mutex::new(snapshot) }
+    }
+
+    /// Also keep the node's `getnetworkhashps`, which is read outside the
+    /// source.
+    pub fn record_network_hashrate(&self, hashrate: f64) {
+        self.lock().network_hashrate = Some(hashrate);
+    }
+
+    /// The answers recorded so far.
+    pub fn snapshot(&self) -> Snapshot {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Snapshot> {
+        self.snapshot.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record `result` with `keep`, or that `lookup` is unsupported.
+    fn record<T>(&self, lookup: Lookup, result: Result<T>, keep: impl FnOnce(&mut Snapshot, &T)) -> Result<T> {
+        match &result {
+            Ok(value) => keep(&mut self.lock(), value),
+            Err(ReorgCalcError::Unsupported(_)) => {
+                self.lock().unsupported.insert(lookup);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+}
+
+impl<S: BlockSource> BlockSource for RecordingSource<S> {
+    fn get_tip(&self) -> Result<ChainTip> {
+        let tip = self.inner.get_tip()?;
+        self.lock().tips.push(tip);
+        Ok(tip)
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        let hash = self.inner.get_block_hash(height)?;
+        self.lock().hashes.entry(height).or_insert(hash);
+        Ok(hash)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        let header = self.inner.get_header(height)?;
+        self.lock().headers.entry(height).or_insert(header);
+        Ok(header)
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        let headers = self.inner.get_headers_batch(heights)?;
+        let mut snapshot = self.lock();
+        for (&height, header) in heights.iter().zip(&headers) {
+            snapshot.headers.entry(height).or_insert(*header);
+        }
+        Ok(headers)
+    }
+
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        let bits = self.inner.get_bits(height)?;
+        self.lock().bits.entry(height).or_insert(bits);
+        Ok(bits)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        let bits = self.inner.get_bits_batch(heights)?;
+        let mut snapshot = self.lock();
+        for (&height, &bits) in heights.iter().zip(&bits) {
+            snapshot.bits.entry(height).or_insert(bits);
+        }
+        Ok(bits)
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.record(Lookup::Chainwork, self.inner.get_chainwork(height), |snapshot, &work| {
+            snapshot.chainwork.entry(height).or_insert(work);
+        })
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        self.record(Lookup::Chainwork, self.inner.get_chainwork_batch(heights), |snapshot, works| {
+            for (&height, &work) in heights.iter().zip(works) {
+                snapshot.chainwork.entry(height).or_insert(work);
+            }
+        })
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        let difficulty = self.inner.get_difficulty()?;
+        self.lock().difficulties.push(difficulty);
+        Ok(difficulty)
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        self.record(Lookup::Transaction, self.inner.get_transaction_height(txid), |snapshot, &height| {
+            snapshot.transactions.insert(*txid, height);
+        })
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        self.record(Lookup::Block, self.inner.get_block_height(hash), |snapshot, &height| {
+            snapshot.blocks.insert(*hash, height);
+        })
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        self.record(Lookup::Coinbase, self.inner.get_coinbases(heights), |snapshot, coinbases| {
+            for (&height, coinbase) in heights.iter().zip(coinbases) {
+                snapshot.coinbases.entry(height).or_insert_with(|| coinbase.clone());
+            }
+        })
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        self.record(Lookup::FullBlock, self.inner.get_blocks(heights), |snapshot, blocks| {
+            for (&height, block) in heights.iter().zip(blocks) {
+                snapshot.full_blocks.entry(height).or_insert_with(|| block.clone());
+            }
+        })
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        self.record(Lookup::ChainTips, self.inner.get_chain_tips(), |snapshot, tips| {
+            snapshot.chain_tips = Some(tips.clone());
+        })
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        let network = self.inner.get_network()?;
+        self.lock().network = Some(network);
+        Ok(network)
+    }
+}
+
+/// A [`BlockSource`] answering from a [`Snapshot`], as the recorded source
+/// did.
+pub struct ReplaySource {
+    snapshot: Snapshot,
+    tip_reads: AtomicUsize,
+    difficulty_reads: AtomicUsize,
+}
+
+impl ReplaySource {
+    pub fn new(snapshot: Snapshot) -> Self {
+        Self { snapshot, tip_reads: AtomicUsize::new(0), difficulty_reads: AtomicUsize::new(0) }
+    }
+
+    /// The snapshot being replayed.
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// The recorded `getnetworkhashps`.
+    pub fn network_hashrate(&self) -> Result<f64> {
+        self.snapshot.network_hashrate.ok_or_else(|| missing("network hashrate".into()))
+    }
+
+    fn check(&self, lookup: Lookup) -> Result<()> {
+        if self.snapshot.unsupported.contains(&lookup) {
+            return Err(ReorgCalcError::Unsupported(lookup.name()));
+        }
+        Ok(())
+    }
+
+    fn header(&self, height: u64) -> Option<&Header> {
+        self.snapshot.headers.get(&height)
+    }
+}
+
+fn missing(what: String) -> ReorgCalcError {
+    ReorgCalcError::InvalidData(format!(
+        "the snapshot holds no {}; replay it with the arguments it was recorded with",
+        what
+    ))
+}
+
+/// The `count`-th of `answers`, the last once there are no more.
+fn in_order<T: Copy>(answers: &[T], count: &AtomicUsize, what: &str) -> Result<T> {
+    let index = count.fetch_add(1, Ordering::Relaxed).min(answers.len().saturating_sub(1));
+    answers.get(index).copied().ok_or_else(|| missing(what.to_string()))
+}
+
+impl BlockSource for ReplaySource {
+    fn get_tip(&self) -> Result<ChainTip> {
+        in_order(&self.snapshot.tips, &self.tip_reads, "tip")
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        match self.snapshot.hashes.get(&height) {
+            Some(hash) => Ok(*hash),
+            None => self.get_header(height).map(|header| header.block_hash()),
+        }
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        self.header(height).copied().ok_or_else(|| missing(format!("header for block {}", height)))
+    }
+
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        match (self.snapshot.bits.get(&height), self.header(height)) {
+            (Some(bits), _) => Ok(*bits),
+            (None, Some(header)) => Ok(header.bits.to_consensus()),
+            (None, None) => Err(missing(format!("target for block {}", height))),
+        }
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        self.check(Lookup::Chainwork)?;
+        self.snapshot.chainwork.get(&height).copied().ok_or_else(|| missing(format!("chain work at block {}", height)))
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        in_order(&self.snapshot.difficulties, &self.difficulty_reads, "difficulty")
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        self.check(Lookup::Transaction)?;
+        self.snapshot.transactions.get(txid).copied().ok_or_else(|| missing(format!("transaction {}", txid)))
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        self.check(Lookup::Block)?;
+        self.snapshot.blocks.get(hash).copied().ok_or_else(|| missing(format!("block {}", hash)))
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        self.check(Lookup::Coinbase)?;
+        heights
+            .iter()
+            .map(|height| {
+                self.snapshot.coinbases.get(height).cloned().ok_or_else(|| missing(format!("coinbase of block {}", height)))
+            })
+            .collect()
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        self.check(Lookup::FullBlock)?;
+        heights
+            .iter()
+            .map(|height| self.snapshot.full_blocks.get(height).cloned().ok_or_else(|| missing(format!("block {}", height))))
+            .collect()
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        self.check(Lookup::ChainTips)?;
+        self.snapshot.chain_tips.clone().ok_or_else(|| missing("chain tips".into()))
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        Ok(self.snapshot.network.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::calculate_reorg_requirements;
+    use crate::fixture::{temp_path, TestChain, DIFFICULTY_1, DIFFICULTY_8};
+
+    #[test]
+    fn a_saved_run_replays_the_same_calculation() {
+        let mut bits = vec![DIFFICULTY_1; 30];
+        bits.extend([DIFFICULTY_8; 10]);
+        let recorder = RecordingSource::new(TestChain::new(&bits).without_chainwork());
+        let recorded = calculate_reorg_requirements(&recorder, 25, 1e15, 1.0).unwrap();
+        recorder.record_network_hashrate(2e15);
+
+        let path = temp_path("round-trip.snapshot");
+        recorder.snapshot().save(&path).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(snapshot.responses(), recorder.snapshot().responses());
+        assert_eq!(snapshot.recorded.map(|time| time.timestamp()), recorder.snapshot().recorded.map(|time| time.timestamp()));
+
+        let replay = ReplaySource::new(snapshot);
+        let replayed = calculate_reorg_requirements(&replay, 25, 1e15, 1.0).unwrap();
+        assert_eq!((replayed.current_height, replayed.fork_hash), (recorded.current_height, recorded.fork_hash));
+        assert_eq!((replayed.total_work, replayed.blocks_needed), (recorded.total_work, recorded.blocks_needed));
+        assert_eq!(replay.network_hashrate().unwrap(), 2e15);
+        // The source had no chain work, and nothing read below the fork
+        assert!(matches!(replay.get_chainwork(39), Err(ReorgCalcError::Unsupported("chainwork"))));
+        assert!(matches!(replay.get_header(3), Err(ReorgCalcError::InvalidData(_))));
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let path = temp_path("not-a.snapshot");
+        fs::write(&path, b"RCSNAP\x00\x02").unwrap();
+        assert!(matches!(Snapshot::load(&path), Err(ReorgCalcError::InvalidData(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
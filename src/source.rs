@@ -0,0 +1,225 @@
+//! Abstraction over where chain data comes from.
+//!
+//! The calculation code only talks to a [`BlockSource`], so alternative
+//! backends (or fixtures) can be plugged in without touching it. A Bitcoin
+//! Core RPC client is the default implementation (see [`crate::rpc`]).
+
+use bitcoin::{block::Header, Block, BlockHash, Transaction, Txid, Work};
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+
+/// Height and hash of the best block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainTip {
+    pub height: u64,
+    pub hash: BlockHash,
+}
+
+/// Tip of a branch off the active chain that the node knows of, as
+/// `getchaintips` lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchTip {
+    pub height: u64,
+    pub hash: BlockHash,
+    /// Blocks on the branch since the last one it shares with the active chain.
+    pub branch_length: u64,
+    pub status: BranchStatus,
+    /// Cumulative chain work up to and including the branch tip.
+    pub chainwork: Work,
+    /// Compact target of the branch tip.
+    pub bits: u32,
+}
+
+/// How far the node got validating a branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchStatus {
+    /// Fully validated, just not the most work.
+    ValidFork,
+    /// Every block is there, but not all were validated.
+    ValidHeaders,
+    /// Valid headers whose blocks the node does not all have.
+    HeadersOnly,
+    /// Has an invalid block, so it can never become active.
+    Invalid,
+}
+
+impl BranchStatus {
+    /// The status as `getchaintips` names it.
+    pub fn name(self) -> &'static str {
+        match self {
+            BranchStatus::ValidFork => "valid-fork",
+            BranchStatus::ValidHeaders => "valid-headers",
+            BranchStatus::HeadersOnly => "headers-only",
+            BranchStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// Read-only access to the active chain.
+///
+/// Implementations must be `Sync` so long spans can be fetched in parallel.
+pub trait BlockSource: Sync {
+    /// Current best block.
+    fn get_tip(&self) -> Result<ChainTip>;
+
+    /// Hash of the active-chain block at `height`.
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        Ok(self.get_header(height)?.block_hash())
+    }
+
+    /// Header of the active-chain block at `height`.
+    fn get_header(&self, height: u64) -> Result<Header>;
+
+    /// Headers of the blocks at `heights`, in the same order.
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        heights.iter().map(|&height| self.get_header(height)).collect()
+    }
+
+    /// Compact target (`nBits`) of the active-chain block at `height`.
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        Ok(self.get_header(height)?.bits.to_consensus())
+    }
+
+    /// Compact targets of the blocks at `heights`, in the same order.
+    ///
+    /// Backends that can answer many lookups per round trip override this.
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        heights.iter().map(|&height| self.get_bits(height)).collect()
+    }
+
+    /// Cumulative chain work up to and including the block at `height`.
+    fn get_chainwork(&self, height: u64) -> Result<Work>;
+
+    /// Cumulative chain work of the blocks at `heights`, in the same order.
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        heights.iter().map(|&height| self.get_chainwork(height)).collect()
+    }
+
+    /// Difficulty the network currently requires for the next block.
+    fn get_difficulty(&self) -> Result<f64>;
+
+    /// Height of the active-chain block containing `txid`, or `None` while
+    /// the transaction is unconfirmed.
+    ///
+    /// Sources that cannot look up transactions return
+    /// [`ReorgCalcError::Unsupported`].
+    fn get_transaction_height(&self, _txid: &Txid) -> Result<Option<u64>> {
+        Err(ReorgCalcError::Unsupported("transaction lookup"))
+    }
+
+    /// Height of the block `hash`, or `None` if it is not on the active
+    /// chain (it was reorged out).
+    ///
+    /// Sources that cannot look up blocks by hash return
+    /// [`ReorgCalcError::Unsupported`].
+    fn get_block_height(&self, _hash: &BlockHash) -> Result<Option<u64>> {
+        Err(ReorgCalcError::Unsupported("block lookup by hash"))
+    }
+
+    /// Coinbase transactions of the blocks at `heights`, in the same order.
+    ///
+    /// Sources that only hold headers return
+    /// [`ReorgCalcError::Unsupported`].
+    fn get_coinbases(&self, _heights: &[u64]) -> Result<Vec<Transaction>> {
+        Err(ReorgCalcError::Unsupported("coinbase transactions"))
+    }
+
+    /// Full blocks at `heights`, in the same order.
+    ///
+    /// Sources that only hold headers, or cannot fetch raw blocks, return
+    /// [`ReorgCalcError::Unsupported`].
+    fn get_blocks(&self, _heights: &[u64]) -> Result<Vec<Block>> {
+        Err(ReorgCalcError::Unsupported("full blocks"))
+    }
+
+    /// Tips of the branches off the active chain the source knows of,
+    /// such as stale blocks and competing chains.
+    ///
+    /// Sources that only see the active chain return
+    /// [`ReorgCalcError::Unsupported`].
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        Err(ReorgCalcError::Unsupported("chain tips"))
+    }
+
+    /// Proof-of-work parameters of the chain being read.
+    ///
+    /// Sources that cannot tell which chain they serve assume testnet4.
+    fn get_network(&self) -> Result<NetworkParams> {
+        Ok(NetworkParams::default())
+    }
+}
+
+impl<T: BlockSource + ?Sized> BlockSource for &T {
+    fn get_tip(&self) -> Result<ChainTip> {
+        (**self).get_tip()
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash> {
+        (**self).get_block_hash(height)
+    }
+
+    fn get_header(&self, height: u64) -> Result<Header> {
+        (**self).get_header(height)
+    }
+
+    fn get_headers_batch(&self, heights: &[u64]) -> Result<Vec<Header>> {
+        (**self).get_headers_batch(heights)
+    }
+
+    fn get_bits(&self, height: u64) -> Result<u32> {
+        (**self).get_bits(height)
+    }
+
+    fn get_bits_batch(&self, heights: &[u64]) -> Result<Vec<u32>> {
+        (**self).get_bits_batch(heights)
+    }
+
+    fn get_chainwork(&self, height: u64) -> Result<Work> {
+        (**self).get_chainwork(height)
+    }
+
+    fn get_chainwork_batch(&self, heights: &[u64]) -> Result<Vec<Work>> {
+        (**self).get_chainwork_batch(heights)
+    }
+
+    fn get_difficulty(&self) -> Result<f64> {
+        (**self).get_difficulty()
+    }
+
+    fn get_transaction_height(&self, txid: &Txid) -> Result<Option<u64>> {
+        (**self).get_transaction_height(txid)
+    }
+
+    fn get_block_height(&self, hash: &BlockHash) -> Result<Option<u64>> {
+        (**self).get_block_height(hash)
+    }
+
+    fn get_coinbases(&self, heights: &[u64]) -> Result<Vec<Transaction>> {
+        (**self).get_coinbases(heights)
+    }
+
+    fn get_blocks(&self, heights: &[u64]) -> Result<Vec<Block>> {
+        (**self).get_blocks(heights)
+    }
+
+    fn get_chain_tips(&self) -> Result<Vec<BranchTip>> {
+        (**self).get_chain_tips()
+    }
+
+    fn get_network(&self) -> Result<NetworkParams> {
+        (**self).get_network()
+    }
+}
+
+/// Split `heights` into runs of consecutive heights, as `(start, count)`.
+pub(crate) fn consecutive_runs(heights: &[u64]) -> Vec<(u64, u64)> {
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    for &height in heights {
+        match runs.last_mut() {
+            Some((start, count)) if *start + *count == height => *count += 1,
+            _ => runs.push((height, 1)),
+        }
+    }
+    runs
+}
@@ -0,0 +1,80 @@
+//! Monitor state kept on disk across restarts.
+//!
+//! `watch`, `daemon`, `countdown` and `track` build up what they know as
+//! they run: the blocks of the chain seen last, the first calculation of a
+//! pinned fork, a reading of the attack chain to measure its hashrate from,
+//! and which alert rules hold. Saved after every refresh and loaded at
+//! start, a restart picks the session up where it stopped: a reorg while
+//! it was down is still reported, and alerts already sent are not sent
+//! again. The state is tied to the chain's genesis block and the command
+//! that saved it, so one for another network or mode is left alone.
+
+use bitcoin::BlockHash;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::calc::ReorgCalculation;
+use crate::error::Result;
+use crate::monitor::SeenChain;
+use crate::track::AttackProgress;
+
+/// Everything a monitor carries from one refresh to the next.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonitorState {
+    /// Genesis block of the chain the state was saved on.
+    pub genesis: Option<BlockHash>,
+    /// Command that saved it, e.g. `watch`.
+    pub command: String,
+    #[serde(default)]
+    pub seen: SeenChain,
+    /// First calculation of the fork being watched, while it stays put.
+    #[serde(default)]
+    pub first: Option<ReorgCalculation>,
+    /// The last calculation, to show what changed since.
+    #[serde(default)]
+    pub previous: Option<ReorgCalculation>,
+    /// Alert rules that held at their last check: the rule text and the
+    /// depth or branch it held for.
+    #[serde(default)]
+    pub alerts: Vec<(String, u64)>,
+    /// First reading of the attack chain on its current fork.
+    #[serde(default)]
+    pub attack_start: Option<AttackProgress>,
+}
+
+impl MonitorState {
+    /// A fresh state for `command` on the chain starting at `genesis`.
+    pub fn new(command: &str, genesis: BlockHash) -> Self {
+        MonitorState { genesis: Some(genesis), command: command.to_string(), ..MonitorState::default() }
+    }
+
+    /// Load the state saved at `path`, or `None` if there is none yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(serde_json::from_str(&text).map_err(io::Error::from)?))
+    }
+
+    /// Whether the state was saved by `command` on the chain starting at
+    /// `genesis`.
+    pub fn belongs_to(&self, command: &str, genesis: BlockHash) -> bool {
+        self.command == command && self.genesis == Some(genesis)
+    }
+
+    /// Save the state to `path`, replacing the file only once the new one
+    /// is written in full.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::from)?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".tmp");
+        fs::write(&partial, json)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! SQLite store of calculation results.
+//!
+//! Every saved calculation becomes a row holding the figures worth
+//! filtering on and the full calculation as JSON, so long-running use
+//! builds up a record that can be searched by fork height, date and
+//! viability, here or with any SQLite client. Reorgs seen while monitoring
+//! go in a table of their own, with the blocks they displaced as JSON. Timestamps are stored as
+//! RFC 3339 text in UTC with a fixed precision, which sorts and compares
+//! the same as the times themselves.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use crate::calc::ReorgCalculation;
+use crate::error::{ReorgCalcError, Result};
+use crate::monitor::SeenBlock;
+use crate::source::ChainTip;
+
+/// Suggested database file name. Nothing is stored unless a database is
+/// named, with `--results-db` or `RESULTS_DB`.
+pub const RESULTS_DB: &str = "reorg_calculations.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS calculations (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        fork_height INTEGER NOT NULL,
+        current_height INTEGER NOT NULL,
+        fork_hash TEXT,
+        tip_hash TEXT,
+        hashrate REAL NOT NULL,
+        blocks_needed REAL NOT NULL,
+        time_required_days REAL,
+        hashrate_required REAL,
+        target_days REAL,
+        viable INTEGER,
+        calculation TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS calculations_fork_height ON calculations (fork_height);
+    CREATE INDEX IF NOT EXISTS calculations_timestamp ON calculations (timestamp);
+    CREATE TABLE IF NOT EXISTS reorgs (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        fork_height INTEGER NOT NULL,
+        depth INTEGER NOT NULL,
+        replaced_height INTEGER NOT NULL,
+        replaced_hash TEXT NOT NULL,
+        displaced TEXT NOT NULL
+    );
+";
+
+/// Which stored calculations to return, newest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreQuery {
+    pub fork_min: Option<u64>,
+    pub fork_max: Option<u64>,
+    /// Made at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Made before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Only calculations that did, or did not, fit their target time.
+    pub viable: Option<bool>,
+    pub limit: Option<usize>,
+}
+
+/// An open results database.
+pub struct ResultsStore {
+    connection: Connection,
+}
+
+impl ResultsStore {
+    /// Open the database at `path`, creating it and its table when missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = Connection::open(path).map_err(database)?;
+        connection.execute_batch(SCHEMA).map_err(database)?;
+        Ok(Self { connection })
+    }
+
+    /// Store `calc`, made for `hashrate`.
+    pub fn insert(&self, calc: &ReorgCalculation, hashrate: f64) -> Result<()> {
+        let json = serde_json::to_string(calc).map_err(|e| ReorgCalcError::Database(e.to_string()))?;
+        let viable = calc.target_days.map(|days| calc.time_required_days <= days);
+        self.connection
+            .execute(
+                "INSERT INTO calculations (timestamp, fork_height, current_height, fork_hash, tip_hash, hashrate,
+                     blocks_needed, time_required_days, hashrate_required, target_days, viable, calculation)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    timestamp(calc.timestamp),
+                    calc.fork_height as i64,
+                    calc.current_height as i64,
+                    calc.fork_hash.map(|hash| hash.to_string()),
+                    calc.tip_hash.map(|hash| hash.to_string()),
+                    hashrate,
+                    calc.blocks_needed,
+                    finite(calc.time_required_days),
+                    finite(calc.hashrate_required),
+                    calc.target_days,
+                    viable,
+                    json,
+                ],
+            )
+            .map_err(database)?;
+        Ok(())
+    }
+
+    /// Store a reorg seen at `time` that replaced the tip `replaced` and
+    /// the blocks `displaced` from `fork_height` up.
+    pub fn insert_reorg(
+        &self,
+        time: DateTime<Utc>,
+        replaced: ChainTip,
+        fork_height: u64,
+        displaced: &[SeenBlock],
+    ) -> Result<()> {
+        let json = serde_json::to_string(displaced).map_err(|e| ReorgCalcError::Database(e.to_string()))?;
+        self.connection
+            .execute(
+                "INSERT INTO reorgs (timestamp, fork_height, depth, replaced_height, replaced_hash, displaced)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    timestamp(time),
+                    fork_height as i64,
+                    (replaced.height + 1).saturating_sub(fork_height) as i64,
+                    replaced.height as i64,
+                    replaced.hash.to_string(),
+                    json,
+                ],
+            )
+            .map_err(database)?;
+        Ok(())
+    }
+
+    /// Stored calculations matching `query`, each with the hashrate it was
+    /// made for, newest first.
+    pub fn query(&self, query: &StoreQuery) -> Result<Vec<(ReorgCalculation, f64)>> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(fork_min) = query.fork_min {
+            conditions.push("fork_height >= ?");
+            values.push(Box::new(fork_min as i64));
+        }
+        if let Some(fork_max) = query.fork_max {
+            conditions.push("fork_height <= ?");
+            values.push(Box::new(fork_max as i64));
+        }
+        if let Some(since) = query.since {
+            conditions.push("timestamp >= ?");
+            values.push(Box::new(timestamp(since)));
+        }
+        if let Some(until) = query.until {
+            conditions.push("timestamp < ?");
+            values.push(Box::new(timestamp(until)));
+        }
+        if let Some(viable) = query.viable {
+            conditions.push("viable = ?");
+            values.push(Box::new(viable));
+        }
+        let mut sql = "SELECT calculation, hashrate FROM calculations".to_string();
+        if !conditions.is_empty() {
+            sql += &format!(" WHERE {}", conditions.join(" AND "));
+        }
+        sql += " ORDER BY timestamp DESC, id DESC";
+        if let Some(limit) = query.limit {
+            sql += &format!(" LIMIT {}", limit);
+        }
+
+        let mut statement = self.connection.prepare(&sql).map_err(database)?;
+        let rows = statement
+            .query_map(rusqlite::params_from_iter(values.iter()), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })
+            .map_err(database)?;
+        rows.map(|row| {
+            let (json, hashrate) = row.map_err(database)?;
+            let calc = serde_json::from_str(&json)
+                .map_err(|e| ReorgCalcError::Database(format!("unreadable stored calculation: {}", e)))?;
+            Ok((calc, hashrate))
+        })
+        .collect()
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// `value`, or `NULL` for an attack that never finishes.
+fn finite(value: f64) -> Option<f64> {
+    value.is_finite().then_some(value)
+}
+
+fn database(e: rusqlite::Error) -> ReorgCalcError {
+    ReorgCalcError::Database(e.to_string())
+}
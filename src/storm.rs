@@ -0,0 +1,151 @@
+//! Testnet4 block storms.
+//!
+//! A miner that sets each block's timestamp more than 20 minutes after its
+//! parent's can mine it at difficulty 1, and nothing stops the next block
+//! doing the same. Runs of such blocks are mined in seconds while their
+//! timestamps race ahead, and the chain catches up on them later. A span
+//! that takes in a storm holds many more blocks than work: reorging through
+//! it costs about what the blocks outside the storm do.
+
+use bitcoin::Work;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::work_to_f64;
+use crate::output::progress_bar;
+use crate::progress;
+use crate::source::BlockSource;
+use crate::work::{block_work, SCAN_BATCH_SIZE};
+
+/// Consecutive minimum-difficulty blocks that make a storm.
+pub const STORM_BLOCKS: u64 = 6;
+
+/// One run of consecutive minimum-difficulty blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlockStorm {
+    pub start_height: u64,
+    pub end_height: u64,
+    pub blocks: u64,
+    /// Seconds the run's timestamps advance from its first block's parent
+    /// to its last block; far more than it took to mine.
+    pub timestamp_seconds: i64,
+}
+
+/// Block storms within a span.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockStorms {
+    /// Runs of at least this many minimum-difficulty blocks were counted.
+    pub min_blocks: u64,
+    pub storms: Vec<BlockStorm>,
+    /// Blocks of the span in a storm.
+    pub storm_blocks: u64,
+    /// Blocks in the span.
+    pub span_blocks: u64,
+    /// Fraction of the span's total work the storm blocks contribute.
+    pub work_share: f64,
+}
+
+impl BlockStorms {
+    /// Blocks of the span outside the storms, which hold nearly all its
+    /// work.
+    pub fn other_blocks(&self) -> u64 {
+        self.span_blocks - self.storm_blocks
+    }
+}
+
+/// Find runs of at least `min_blocks` consecutive minimum-difficulty blocks
+/// from `fork_height` to `current_height` inclusive.
+///
+/// Runs are cut at the ends of the span, so a storm across the fork point
+/// counts only its blocks being replaced. Networks without the 20-minute
+/// rule have none and are not scanned.
+pub fn find_block_storms<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+    min_blocks: u64,
+) -> Result<BlockStorms> {
+    let params = source.get_network()?;
+    let span_blocks = current_height + 1 - fork_height;
+    let mut found = BlockStorms {
+        min_blocks,
+        storms: Vec::new(),
+        storm_blocks: 0,
+        span_blocks,
+        work_share: 0.0,
+    };
+    if !params.consensus().allow_min_difficulty_blocks {
+        return Ok(found);
+    }
+    if min_blocks == 0 {
+        return Err(ReorgCalcError::InvalidData("a block storm needs at least one block".into()));
+    }
+
+    progress!("Scanning blocks {} to {} for block storms...", fork_height, current_height);
+    // The fork parent's timestamp starts the first run's advance
+    let first = fork_height.saturating_sub(1);
+    let heights: Vec<u64> = (first..=current_height).collect();
+    let pb = progress_bar(heights.len() as u64);
+    let batches: Result<Vec<Vec<(u32, i64)>>> = heights
+        .par_chunks(SCAN_BATCH_SIZE)
+        .map(|heights| {
+            let headers = source.get_headers_batch(heights)?;
+            pb.inc(heights.len() as u64);
+            Ok(headers.iter().map(|header| (header.bits.to_consensus(), i64::from(header.time))).collect())
+        })
+        .collect();
+    pb.finish_and_clear();
+    let blocks: Vec<(u32, i64)> = batches?.into_iter().flatten().collect();
+    let offset = (fork_height - first) as usize;
+
+    let min_bits = params.pow_limit.to_compact_lossy().to_consensus();
+    let zero = Work::from_be_bytes([0; 32]);
+    let (mut storm_work, mut total_work) = (zero, zero);
+    let mut run_start = None;
+    for index in 0..span_blocks as usize {
+        let (bits, _) = blocks[index + offset];
+        total_work = total_work + block_work(bits);
+        if bits == min_bits {
+            run_start.get_or_insert(index);
+        } else if let Some(start) = run_start.take() {
+            add_storm(&mut found, &blocks, offset, fork_height, start, index, &mut storm_work);
+        }
+    }
+    if let Some(start) = run_start {
+        add_storm(&mut found, &blocks, offset, fork_height, start, span_blocks as usize, &mut storm_work);
+    }
+    found.work_share = work_to_f64(storm_work) / work_to_f64(total_work);
+    Ok(found)
+}
+
+/// Record the run of span blocks `start..end` if it is long enough.
+fn add_storm(
+    found: &mut BlockStorms,
+    blocks: &[(u32, i64)],
+    offset: usize,
+    fork_height: u64,
+    start: usize,
+    end: usize,
+    storm_work: &mut Work,
+) {
+    let length = (end - start) as u64;
+    if length < found.min_blocks {
+        return;
+    }
+    // Genesis has no parent to measure from
+    let parent_time = match (start + offset).checked_sub(1) {
+        Some(parent) => blocks[parent].1,
+        None => blocks[start + offset].1,
+    };
+    for &(bits, _) in &blocks[start + offset..end + offset] {
+        *storm_work = *storm_work + block_work(bits);
+    }
+    found.storm_blocks += length;
+    found.storms.push(BlockStorm {
+        start_height: fork_height + start as u64,
+        end_height: fork_height + end as u64 - 1,
+        blocks: length,
+        timestamp_seconds: blocks[end + offset - 1].1 - parent_time,
+    });
+}
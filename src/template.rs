@@ -0,0 +1,76 @@
+//! Reports rendered through user-provided Tera templates.
+//!
+//! The template sees the same [`Report`] as `--format json`: `version`,
+//! `generated_at`, `command`, `target_days` and `results`, each result a
+//! calculation's fields plus the `hashrate` it was made for. Figures that
+//! are infinite, for attacks that never finish, are `null`. Two filters
+//! format figures the way the text output does: `hashrate` (`1.00 PH/s`)
+//! and `days` (`2.5`).
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tera::{Context, Tera, Value};
+
+use crate::calc::ReorgCalculation;
+use crate::error::{ReorgCalcError, Result};
+use crate::format::{format_days, format_hashrate, Report};
+
+/// A report template, parsed and ready to render.
+pub struct ReportTemplate {
+    tera: Tera,
+    name: String,
+}
+
+impl ReportTemplate {
+    /// Read and parse the template at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let source = fs::read_to_string(path)
+            .map_err(|e| ReorgCalcError::Template(format!("cannot read {}: {}", name, e)))?;
+        let mut tera = Tera::default();
+        tera.register_filter("hashrate", figure_filter(format_hashrate));
+        tera.register_filter("days", figure_filter(format_days));
+        tera.add_raw_template(&name, &source).map_err(template)?;
+        Ok(Self { tera, name })
+    }
+
+    /// Render the report of `runs`, each a calculation and the hashrate it
+    /// was made for, produced by `command`.
+    pub fn render(
+        &self,
+        mut writer: impl Write,
+        command: &str,
+        target_days: f64,
+        runs: &[(ReorgCalculation, f64)],
+    ) -> Result<()> {
+        let context = Context::from_serialize(Report::new(command, target_days, runs)).map_err(template)?;
+        let text = self.tera.render(&self.name, &context).map_err(template)?;
+        writer.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// A filter formatting a number with `format`; `null` is an infinite figure.
+fn figure_filter(
+    format: fn(f64) -> String,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> + Send + Sync {
+    move |value, _| match value {
+        Value::Null => Ok(Value::String("infinite".to_string())),
+        Value::Number(number) => Ok(Value::String(format(number.as_f64().unwrap_or(f64::NAN)))),
+        other => Err(tera::Error::msg(format!("expected a number, got {}", other))),
+    }
+}
+
+fn template(e: tera::Error) -> ReorgCalcError {
+    // Tera puts the line and the actual problem in the error's sources
+    let mut reason = e.to_string();
+    let mut source = std::error::Error::source(&e);
+    while let Some(cause) = source {
+        reason = format!("{}: {}", reason, cause);
+        source = cause.source();
+    }
+    ReorgCalcError::Template(reason)
+}
@@ -0,0 +1,245 @@
+//! Timestamp rules on a planned attack chain.
+//!
+//! Every block must be timestamped after the median of its 11 predecessors
+//! (BIP113 median-time-past) and no more than two hours ahead of the node's
+//! clock. An attacker timestamping real blocks with the time they are mined
+//! can break the first rule when the chain it builds on runs ahead of real
+//! time, which is what stacked 20-minute-rule blocks do. Planning walks the
+//! chain block by block, raising such timestamps to the earliest valid one
+//! and recording every correction.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::error::Result;
+use crate::min_difficulty::{min_difficulty_gap, MAX_FUTURE_DRIFT};
+use crate::network::NetworkParams;
+use crate::source::BlockSource;
+
+/// Blocks whose timestamps take part in the median-time-past.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Most blocks of a plan that are checked one by one.
+pub const MAX_TIMELINE_BLOCKS: u64 = 1_000_000;
+
+/// Timestamp rule broken by the planned chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum TimestampViolation {
+    /// The natural timestamp was not after the median-time-past; the plan
+    /// uses `median_time_past + 1` instead.
+    MedianTimePast { height: u64, time: i64, median_time_past: i64 },
+    /// The timestamp is more than two hours ahead of when the block is mined.
+    FutureDrift { height: u64, time: i64, limit: i64 },
+}
+
+/// Result of checking a planned chain against the timestamp rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineCheck {
+    /// Blocks checked, at most [`MAX_TIMELINE_BLOCKS`].
+    pub blocks_checked: u64,
+    /// Whether the plan was longer than the blocks checked.
+    pub truncated: bool,
+    /// Blocks whose timestamp had to be raised above median-time-past.
+    pub median_time_past_raised: u64,
+    /// Blocks timestamped beyond the allowed future drift.
+    pub future_drift_violations: u64,
+    /// First violation of each rule.
+    pub first_violations: Vec<TimestampViolation>,
+}
+
+impl TimelineCheck {
+    pub fn is_clean(&self) -> bool {
+        self.median_time_past_raised == 0 && self.future_drift_violations == 0
+    }
+}
+
+/// Shape of the planned attack chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttackPlan {
+    pub fork_height: u64,
+    /// Real (full-difficulty) blocks to mine.
+    pub real_blocks: u64,
+    /// Seconds to mine each real block.
+    pub block_seconds: f64,
+    /// Minimum-difficulty blocks inserted before the first real block.
+    pub upfront_min_difficulty: u64,
+    /// Minimum-difficulty blocks inserted after each real block, on average.
+    pub min_difficulty_per_block: f64,
+    /// Unix time the attack starts.
+    pub start_time: i64,
+}
+
+/// Timestamps of the [`MEDIAN_TIME_SPAN`] blocks up to `fork_height - 1`,
+/// oldest first.
+pub fn parent_timestamps<S: BlockSource + ?Sized>(source: &S, fork_height: u64) -> Result<Vec<i64>> {
+    let first = fork_height.saturating_sub(MEDIAN_TIME_SPAN as u64);
+    let heights: Vec<u64> = (first..fork_height).collect();
+    let headers = source.get_headers_batch(&heights)?;
+    Ok(headers.iter().map(|header| header.time as i64).collect())
+}
+
+/// Walk `plan` on top of blocks timestamped `parent_times` (oldest first).
+///
+/// Real blocks are timestamped with the time they are mined and
+/// minimum-difficulty blocks with the earliest time the 20-minute rule
+/// allows.
+pub fn check_timeline(plan: &AttackPlan, parent_times: &[i64], params: &NetworkParams) -> TimelineCheck {
+    let gap = min_difficulty_gap(params) as i64;
+    let total = plan.real_blocks as f64
+        + plan.upfront_min_difficulty as f64
+        + plan.min_difficulty_per_block * plan.real_blocks as f64;
+
+    let mut chain = Chain {
+        recent: parent_times.iter().copied().collect(),
+        height: plan.fork_height,
+        check: TimelineCheck {
+            blocks_checked: 0,
+            truncated: total > MAX_TIMELINE_BLOCKS as f64,
+            median_time_past_raised: 0,
+            future_drift_violations: 0,
+            first_violations: Vec::new(),
+        },
+    };
+    let mut owed = plan.upfront_min_difficulty as f64;
+    let mut mined_at = plan.start_time;
+
+    for block in 0..=plan.real_blocks {
+        // Minimum-difficulty blocks go in as soon as they are owed
+        while owed >= 1.0 {
+            let time = chain.recent.back().copied().unwrap_or(mined_at) + gap;
+            if !chain.add(time, mined_at) {
+                return chain.check;
+            }
+            owed -= 1.0;
+        }
+        if block == plan.real_blocks {
+            break;
+        }
+        mined_at = plan.start_time + ((block + 1) as f64 * plan.block_seconds) as i64;
+        if !chain.add(mined_at, mined_at) {
+            return chain.check;
+        }
+        owed += plan.min_difficulty_per_block;
+    }
+    chain.check
+}
+
+/// Planned chain as it is walked.
+struct Chain {
+    /// Timestamps of the latest [`MEDIAN_TIME_SPAN`] blocks, oldest first.
+    recent: VecDeque<i64>,
+    height: u64,
+    check: TimelineCheck,
+}
+
+impl Chain {
+    /// Append a block timestamped `time` and mined at `mined_at`, raising
+    /// the timestamp to the median-time-past if needed. Returns false once
+    /// the check limit is reached.
+    fn add(&mut self, time: i64, mined_at: i64) -> bool {
+        let mut time = time;
+        let median_time_past = median(&self.recent);
+        if time <= median_time_past {
+            self.check.median_time_past_raised += 1;
+            self.record(TimestampViolation::MedianTimePast {
+                height: self.height,
+                time,
+                median_time_past,
+            });
+            time = median_time_past + 1;
+        }
+        let limit = mined_at + MAX_FUTURE_DRIFT as i64;
+        if time > limit {
+            self.check.future_drift_violations += 1;
+            self.record(TimestampViolation::FutureDrift {
+                height: self.height,
+                time,
+                limit,
+            });
+        }
+
+        self.recent.push_back(time);
+        if self.recent.len() > MEDIAN_TIME_SPAN {
+            self.recent.pop_front();
+        }
+        self.height += 1;
+        self.check.blocks_checked += 1;
+        self.check.blocks_checked < MAX_TIMELINE_BLOCKS
+    }
+
+    /// Keep the first violation of each rule.
+    fn record(&mut self, violation: TimestampViolation) {
+        let seen = self
+            .check
+            .first_violations
+            .iter()
+            .any(|first| std::mem::discriminant(first) == std::mem::discriminant(&violation));
+        if !seen {
+            self.check.first_violations.push(violation);
+        }
+    }
+}
+
+fn median(times: &VecDeque<i64>) -> i64 {
+    let mut sorted: Vec<i64> = times.iter().copied().collect();
+    sorted.sort_unstable();
+    sorted.get(sorted.len() / 2).copied().unwrap_or(i64::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{TestChain, DIFFICULTY_1, GENESIS_TIME};
+
+    /// Eleven blocks ten minutes apart, the last at `tip`.
+    fn parents(tip: i64) -> Vec<i64> {
+        (0..MEDIAN_TIME_SPAN as i64).map(|i| tip - 600 * (10 - i)).collect()
+    }
+
+    fn plan(upfront_min_difficulty: u64) -> AttackPlan {
+        AttackPlan {
+            fork_height: 11,
+            real_blocks: 5,
+            block_seconds: 600.0,
+            upfront_min_difficulty,
+            min_difficulty_per_block: 0.0,
+            start_time: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn honest_timestamps_pass() {
+        let check = check_timeline(&plan(0), &parents(1_000_000), &NetworkParams::default());
+        assert!(check.is_clean());
+        assert_eq!((check.blocks_checked, check.truncated), (5, false));
+        assert!(check.first_violations.is_empty());
+    }
+
+    #[test]
+    fn stacked_min_difficulty_blocks_break_both_rules() {
+        let start = 1_000_000;
+        let check = check_timeline(&plan(10), &parents(start), &NetworkParams::default());
+        assert_eq!(check.blocks_checked, 15);
+        // The sixth 20-minute block lands 7206 s ahead of real time...
+        assert_eq!(check.future_drift_violations, 5);
+        // ...and the median then runs ahead of every real block
+        assert_eq!(check.median_time_past_raised, 5);
+        assert_eq!(
+            check.first_violations,
+            [
+                TimestampViolation::FutureDrift { height: 16, time: start + 7206, limit: start + 7200 },
+                TimestampViolation::MedianTimePast { height: 21, time: start + 600, median_time_past: start + 6005 },
+            ]
+        );
+    }
+
+    #[test]
+    fn parent_timestamps_end_below_the_fork() {
+        let chain = TestChain::uniform(30, DIFFICULTY_1);
+        let times = parent_timestamps(&chain, 20).unwrap();
+        let expected: Vec<i64> = (9..20).map(|height| i64::from(GENESIS_TIME) + 600 * height).collect();
+        assert_eq!(times, expected);
+        assert_eq!(parent_timestamps(&chain, 3).unwrap().len(), 3);
+    }
+}
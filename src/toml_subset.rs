@@ -0,0 +1,137 @@
+//! The TOML subset the hardware and scenario files are written in.
+//!
+//! Files are arrays of tables (`[[name]]`) of `key = value` lines, where a
+//! value is a quoted string or a number, and `#` starts a comment. That
+//! covers both formats without pulling in a full TOML parser.
+
+/// A `[[name]]` table: the line it started on and its keys in order, each
+/// with its line.
+pub(crate) struct Table {
+    pub line: usize,
+    pub entries: Vec<(usize, String, Value)>,
+}
+
+/// A value in a table.
+pub(crate) enum Value {
+    String(String),
+    Number(f64),
+}
+
+impl Value {
+    fn parse(text: &str) -> Result<Self, String> {
+        if let Some(quoted) = text.strip_prefix('"') {
+            return quoted
+                .strip_suffix('"')
+                .filter(|inner| !inner.contains('"'))
+                .map(|inner| Value::String(inner.to_string()))
+                .ok_or_else(|| format!("unterminated string {}", text));
+        }
+        text.replace('_', "")
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("expected a number or a quoted string, got {}", text))
+    }
+
+    pub fn string(self, key: &str) -> Result<String, String> {
+        match self {
+            Value::String(text) => Ok(text),
+            Value::Number(_) => Err(format!("{} must be a quoted string", key)),
+        }
+    }
+
+    pub fn number(self, key: &str) -> Result<f64, String> {
+        match self {
+            Value::Number(number) if number >= 0.0 && number.is_finite() => Ok(number),
+            _ => Err(format!("{} must be a non-negative number", key)),
+        }
+    }
+
+    /// A string parsed with `parse`, or a plain non-negative number.
+    pub fn number_or(self, key: &str, parse: impl Fn(&str) -> Result<f64, String>) -> Result<f64, String> {
+        match self {
+            Value::String(text) => parse(&text),
+            number => number.number(key),
+        }
+    }
+}
+
+/// Split `text` into its `[[name]]` tables, failing with the line number
+/// and reason.
+pub(crate) fn parse_tables(text: &str, name: &str) -> Result<Vec<Table>, (usize, String)> {
+    let header = format!("[[{}]]", name);
+    let mut tables: Vec<Table> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let content = strip_comment(raw).trim();
+        if content.is_empty() {
+            continue;
+        }
+        if content == header {
+            tables.push(Table { line, entries: Vec::new() });
+            continue;
+        }
+        if content.starts_with('[') {
+            return Err((line, format!("unknown table {}; expected {}", content, header)));
+        }
+        let (key, value) = content
+            .split_once('=')
+            .ok_or_else(|| (line, format!("expected key = value, got {:?}", content)))?;
+        let table = tables
+            .last_mut()
+            .ok_or_else(|| (line, format!("key outside a {} table", header)))?;
+        let value = Value::parse(value.trim()).map_err(|reason| (line, reason))?;
+        table.entries.push((line, key.trim().to_string(), value));
+    }
+    Ok(tables)
+}
+
+/// `line` up to a `#` that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_keep_their_lines() {
+        let text = "# devices\n[[device]]\nname = \"S21 # Pro\" # comment\nhashrate = 200_000\n\n[[device]]\nwatts = 3.5\n";
+        let tables = parse_tables(text, "device").unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].line, 2);
+        let entries: Vec<_> = tables[0].entries.iter().map(|(line, key, _)| (*line, key.as_str())).collect();
+        assert_eq!(entries, [(3, "name"), (4, "hashrate")]);
+        match &tables[0].entries[0].2 {
+            Value::String(name) => assert_eq!(name, "S21 # Pro"),
+            Value::Number(_) => panic!("name parsed as a number"),
+        }
+        match tables[0].entries[1].2 {
+            Value::Number(hashrate) => assert_eq!(hashrate, 200_000.0),
+            Value::String(_) => panic!("hashrate parsed as a string"),
+        }
+        assert_eq!(tables[1].line, 6);
+    }
+
+    /// Line of the error `text` is rejected with.
+    fn error_line(text: &str) -> Option<usize> {
+        parse_tables(text, "device").err().map(|(line, _)| line)
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        assert_eq!(error_line("name = \"x\""), Some(1));
+        assert_eq!(error_line("[[device]]\n[other]"), Some(2));
+        assert_eq!(error_line("[[device]]\nname"), Some(2));
+        assert_eq!(error_line("[[device]]\nname = \"open"), Some(2));
+        assert_eq!(error_line("[[device]]\nwatts = lots"), Some(2));
+    }
+}
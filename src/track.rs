@@ -0,0 +1,307 @@
+//! Progress of a private attack chain against the public one.
+//!
+//! With the attack mined on a node of its own, both chains can be read as
+//! [`BlockSource`]s. The fork is the first height at which their blocks
+//! differ, found by binary search over block hashes, and progress is the
+//! work each chain has on top of it: the attack overtakes once its work
+//! exceeds the public chain's, which is when nodes would switch to it.
+//! Comparing the tips of any two nodes, e.g. either side of a network
+//! partition, works the same way, and so does measuring the branches a
+//! single node knows of besides its active chain: a competing chain whose
+//! blocks reach the node is a reorg attempt already in flight. Measured
+//! again over time, a branch gaining work faster than the active chain
+//! means someone is mining it at more than the honest hashrate.
+
+use bitcoin::Work;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::NetworkParams;
+use crate::source::{BlockSource, BranchStatus, BranchTip, ChainTip};
+use crate::work::{calculate_chain_work, span_work};
+use crate::SECONDS_PER_DAY;
+
+/// Where an attack chain stands against the public chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttackProgress {
+    pub time: DateTime<Utc>,
+    /// First height at which the chains differ: the first public block the
+    /// attack replaces.
+    pub fork_height: u64,
+    pub public_height: u64,
+    pub attack_height: u64,
+    /// Sum of the difficulties of the public blocks from the fork height.
+    pub public_work: f64,
+    /// Sum of the difficulties of the attack blocks from the fork height.
+    pub attack_work: f64,
+    /// Difficulty the attack chain's next block needs.
+    pub attack_difficulty: f64,
+}
+
+impl AttackProgress {
+    /// Work the attack still lacks to match the public chain; negative
+    /// once it leads.
+    pub fn work_gap(&self) -> f64 {
+        self.public_work - self.attack_work
+    }
+
+    /// Whether the attack chain has more work than the public one.
+    pub fn overtaken(&self) -> bool {
+        self.attack_work > self.public_work
+    }
+
+    /// Share of the public work the attack has mined, in percent.
+    pub fn percent_complete(&self) -> f64 {
+        if self.public_work > 0.0 {
+            100.0 * self.attack_work / self.public_work
+        } else {
+            100.0
+        }
+    }
+
+    /// Attack blocks at its next difficulty until it has more work than the
+    /// public chain has now.
+    pub fn blocks_remaining(&self) -> u64 {
+        if self.overtaken() {
+            0
+        } else if self.attack_difficulty > 0.0 {
+            (self.work_gap() / self.attack_difficulty).floor() as u64 + 1
+        } else {
+            u64::MAX
+        }
+    }
+
+    /// Days until the attack overtakes, mining at `hashrate` while the
+    /// public chain grows at `honest_hashrate`, or `None` if it never
+    /// closes the gap.
+    pub fn days_to_overtake(&self, hashrate: f64, honest_hashrate: f64, params: &NetworkParams) -> Option<f64> {
+        if self.overtaken() {
+            return Some(0.0);
+        }
+        let closing = hashrate - honest_hashrate;
+        if !(closing > 0.0 && closing.is_finite()) {
+            return None;
+        }
+        Some(self.work_gap() * params.hashes_per_difficulty() / closing / SECONDS_PER_DAY)
+    }
+
+    /// Hashrate that overtakes within `days` while the public chain grows
+    /// at `honest_hashrate`.
+    pub fn hashrate_to_overtake(&self, days: f64, honest_hashrate: f64, params: &NetworkParams) -> f64 {
+        self.work_gap().max(0.0) * params.hashes_per_difficulty() / (days * SECONDS_PER_DAY) + honest_hashrate
+    }
+}
+
+/// A node's best block and the work behind it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TipWork {
+    pub tip: ChainTip,
+    /// Cumulative chain work up to and including the tip.
+    pub chainwork: Work,
+    /// Difficulty the node requires for the next block.
+    pub difficulty: f64,
+}
+
+/// Best block of `source` with its chain work.
+pub fn tip_work<S: BlockSource + ?Sized>(source: &S) -> Result<TipWork> {
+    let tip = source.get_tip()?;
+    Ok(TipWork {
+        tip,
+        chainwork: source.get_chainwork(tip.height)?,
+        difficulty: source.get_difficulty()?,
+    })
+}
+
+/// First height at which the active chains of `public` and `attack`
+/// differ, or one past the lower tip if one chain contains the other.
+pub fn common_fork_height<P, A>(public: &P, attack: &A) -> Result<u64>
+where
+    P: BlockSource + ?Sized,
+    A: BlockSource + ?Sized,
+{
+    let top = public.get_tip()?.height.min(attack.get_tip()?.height);
+    fork_height_below(public, attack, top)
+}
+
+fn fork_height_below<P, A>(public: &P, attack: &A, top: u64) -> Result<u64>
+where
+    P: BlockSource + ?Sized,
+    A: BlockSource + ?Sized,
+{
+    let same = |height| -> Result<bool> { Ok(public.get_block_hash(height)? == attack.get_block_hash(height)?) };
+    if !same(0)? {
+        return Err(ReorgCalcError::InvalidData(
+            "the two nodes do not share a genesis block; are they on the same network?".to_string(),
+        ));
+    }
+    // Blocks match up to the fork and differ from it on
+    let (mut low, mut high) = (0, top + 1);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if same(mid)? {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low + 1)
+}
+
+/// Measure the attack chain read from `attack` against the public chain
+/// read from `public`.
+pub fn attack_progress<P, A>(public: &P, attack: &A) -> Result<AttackProgress>
+where
+    P: BlockSource + ?Sized,
+    A: BlockSource + ?Sized,
+{
+    let public_height = public.get_tip()?.height;
+    let attack_height = attack.get_tip()?.height;
+    let fork_height = fork_height_below(public, attack, public_height.min(attack_height))?;
+    let params = public.get_network()?;
+    Ok(AttackProgress {
+        time: Utc::now(),
+        fork_height,
+        public_height,
+        attack_height,
+        public_work: work_from(public, fork_height, public_height, &params)?,
+        attack_work: work_from(attack, fork_height, attack_height, &params)?,
+        attack_difficulty: attack.get_difficulty()?,
+    })
+}
+
+/// Measure every branch `source` knows of that could still become active,
+/// against its active chain, most work first. Invalid branches are left out.
+pub fn branch_progress<S: BlockSource + ?Sized>(source: &S) -> Result<Vec<(BranchTip, AttackProgress)>> {
+    let tip = source.get_tip()?;
+    let active_work = source.get_chainwork(tip.height)?;
+    let params = source.get_network()?;
+    let mut branches = Vec::new();
+    for branch in source.get_chain_tips()? {
+        if branch.status == BranchStatus::Invalid {
+            continue;
+        }
+        let fork_height = branch.height + 1 - branch.branch_length.min(branch.height);
+        let base = source.get_chainwork(fork_height - 1)?;
+        let work_since = |work: Work| -> Result<f64> {
+            let since = span_work(base, work).ok_or_else(|| {
+                ReorgCalcError::InvalidData(format!("branch {} has less work than its fork point", branch.hash))
+            })?;
+            Ok(params.work_to_difficulty(since))
+        };
+        let progress = AttackProgress {
+            time: Utc::now(),
+            fork_height,
+            public_height: tip.height,
+            attack_height: branch.height,
+            public_work: work_since(active_work)?,
+            attack_work: work_since(branch.chainwork)?,
+            attack_difficulty: params.difficulty(branch.bits),
+        };
+        branches.push((branch, progress));
+    }
+    branches.sort_by(|(_, a), (_, b)| b.attack_work.total_cmp(&a.attack_work));
+    Ok(branches)
+}
+
+/// How fast a branch gained work against the active chain between two
+/// readings of [`branch_progress`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchRate {
+    pub branch: BranchTip,
+    /// The branch as last measured.
+    pub progress: AttackProgress,
+    /// Seconds between the readings compared.
+    pub seconds: f64,
+    /// Work the branch gained in that time, in difficulty units.
+    pub branch_gain: f64,
+    /// Work the active chain gained in that time.
+    pub active_gain: f64,
+}
+
+impl BranchRate {
+    /// Branch work gained per unit gained by the active chain; infinite if
+    /// only the branch grew.
+    pub fn ratio(&self) -> f64 {
+        if self.active_gain > 0.0 {
+            self.branch_gain / self.active_gain
+        } else if self.branch_gain > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the branch gained work faster than the active chain.
+    pub fn competing(&self) -> bool {
+        self.branch_gain > self.active_gain
+    }
+
+    /// Hashrate mining the branch, from the work it gained.
+    pub fn hashrate(&self, params: &NetworkParams) -> f64 {
+        if self.seconds > 0.0 {
+            self.branch_gain * params.hashes_per_difficulty() / self.seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Readings of a node's branches over a rolling window, to tell how fast
+/// each one is growing.
+///
+/// A branch is known across readings by its fork height, which stays put
+/// while its tip moves on.
+#[derive(Debug, Clone)]
+pub struct BranchWatch {
+    window: Duration,
+    readings: VecDeque<(DateTime<Utc>, Vec<AttackProgress>)>,
+}
+
+impl BranchWatch {
+    /// A watch comparing readings up to `window_seconds` apart.
+    pub fn new(window_seconds: u64) -> Self {
+        BranchWatch {
+            window: Duration::seconds(window_seconds.min(i64::MAX as u64) as i64),
+            readings: VecDeque::new(),
+        }
+    }
+
+    /// Add a reading of `branches` taken at `time` and return how fast each
+    /// one seen before has grown since the oldest reading in the window.
+    pub fn observe(&mut self, time: DateTime<Utc>, branches: &[(BranchTip, AttackProgress)]) -> Vec<BranchRate> {
+        // Keep the newest reading from before the window as its start
+        while self.readings.len() > 1 && time - self.readings[1].0 >= self.window {
+            self.readings.pop_front();
+        }
+        let rates = branches
+            .iter()
+            .filter_map(|(branch, progress)| {
+                let then = self
+                    .readings
+                    .iter()
+                    .flat_map(|(_, reading)| reading)
+                    .find(|then| then.fork_height == progress.fork_height)?;
+                Some(BranchRate {
+                    branch: *branch,
+                    progress: progress.clone(),
+                    seconds: (progress.time - then.time).num_milliseconds() as f64 / 1000.0,
+                    branch_gain: progress.attack_work - then.attack_work,
+                    active_gain: progress.public_work - then.public_work,
+                })
+            })
+            .collect();
+        self.readings.push_back((time, branches.iter().map(|(_, progress)| progress.clone()).collect()));
+        rates
+    }
+}
+
+/// Work of the blocks from `fork_height` to `height`, none if the chain
+/// ends below the fork.
+fn work_from<S: BlockSource + ?Sized>(source: &S, fork_height: u64, height: u64, params: &NetworkParams) -> Result<f64> {
+    if height < fork_height {
+        return Ok(0.0);
+    }
+    Ok(params.work_to_difficulty(calculate_chain_work(source, fork_height, height)?))
+}
@@ -1,8 +1,6 @@
 #[cfg(feature = "tui")]
 use anyhow::Result;
 #[cfg(feature = "tui")]
-use bitcoincore_rpc::{Client, RpcApi};
-#[cfg(feature = "tui")]
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -13,9 +11,21 @@ use ratatui::{
 };
 #[cfg(feature = "tui")]
 use std::io;
+#[cfg(feature = "tui")]
+use std::sync::mpsc;
+#[cfg(feature = "tui")]
+use std::thread;
+#[cfg(feature = "tui")]
+use std::time::Duration;
+
+#[cfg(feature = "tui")]
+use testnet4_reorg_calculator::{
+    Adjustments, AsyncBlockSource, BlockSource, HashBlockSubscriber, Output, ReorgCalculation, RpcPool, format_hashrate,
+    set_output,
+};
 
 #[cfg(feature = "tui")]
-use crate::{ReorgCalculation, format_hashrate};
+type PendingResult = mpsc::Receiver<testnet4_reorg_calculator::Result<Vec<ReorgCalculation>>>;
 
 #[cfg(feature = "tui")]
 pub struct TuiApp {
@@ -26,13 +36,14 @@ pub struct TuiApp {
     pub status_message: String,
     pub hashrate: f64,
     pub target_days: f64,
+    pub adjustments: Adjustments,
     pub current_height: u64,
     pub is_calculating: bool,
 }
 
 #[cfg(feature = "tui")]
 impl TuiApp {
-    pub fn new(hashrate: f64, target_days: f64, current_height: u64) -> Self {
+    pub fn new(hashrate: f64, target_days: f64, adjustments: Adjustments, current_height: u64) -> Self {
         Self {
             should_quit: false,
             current_tab: 0,
@@ -41,6 +52,7 @@ impl TuiApp {
             status_message: "Ready to calculate".to_string(),
             hashrate,
             target_days,
+            adjustments,
             current_height,
             is_calculating: false,
         }
@@ -61,9 +73,11 @@ impl TuiApp {
 
 #[cfg(feature = "tui")]
 pub fn run_tui(
-    client: Client,
+    pool: RpcPool,
     hashrate: f64,
     target_days: f64,
+    adjustments: Adjustments,
+    zmq: Option<String>,
 ) -> Result<()> {
     // Setup terminal
     crossterm::terminal::enable_raw_mode()?;
@@ -71,13 +85,15 @@ pub fn run_tui(
     crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
+    // Warnings and progress would draw over the screen
+    set_output(Output::Hidden);
 
     // Create app
-    let current_height = client.get_block_count()?;
-    let mut app = TuiApp::new(hashrate, target_days, current_height);
+    let current_height = pool.get_tip()?.height;
+    let mut app = TuiApp::new(hashrate, target_days, adjustments, current_height);
 
     // Main loop
-    let result = run_app(&mut terminal, &mut app, client);
+    let result = run_app(&mut terminal, &mut app, pool, zmq);
 
     // Restore terminal
     crossterm::terminal::disable_raw_mode()?;
@@ -91,9 +107,60 @@ pub fn run_tui(
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut TuiApp,
-    _client: Client,
+    pool: RpcPool,
+    zmq: Option<String>,
 ) -> Result<()> {
+    // Calculations run on a tokio runtime so the UI keeps redrawing meanwhile
+    let runtime = tokio::runtime::Runtime::new()?;
+    let connections = pool.connections();
+    let source = AsyncBlockSource::new(pool, connections);
+    let mut pending: Option<PendingResult> = None;
+
+    // Forward ZMQ block notifications so a new block triggers a recalculation
+    let (block_tx, block_rx) = mpsc::channel();
+    if let Some(endpoint) = zmq {
+        thread::spawn(move || {
+            let Ok(mut subscriber) = HashBlockSubscriber::connect(&endpoint) else {
+                return;
+            };
+            while let Ok(block) = subscriber.wait_for_block(Duration::from_secs(3600)) {
+                if block.is_some() && block_tx.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     loop {
+        let mut new_block = false;
+        while block_rx.try_recv().is_ok() {
+            new_block = true;
+        }
+        if new_block && !app.is_calculating {
+            app.status_message = "New block, recalculating...".to_string();
+            pending = Some(start_calculation(app, &runtime, &source));
+        }
+
+        if let Some(rx) = &pending {
+            if let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok(calculations) => {
+                        app.status_message = format!("Found {} viable heights", calculations.len());
+                        if let Some(calc) = calculations.first() {
+                            app.current_height = calc.current_height;
+                        }
+                        app.calculations = calculations;
+                    }
+                    Err(e) => {
+                        app.status_message = format!("Calculation failed: {}", e);
+                    }
+                }
+                app.progress = 1.0;
+                app.is_calculating = false;
+                pending = None;
+            }
+        }
+
         terminal.draw(|f| ui(f, app))?;
 
         if crossterm::event::poll(std::time::Duration::from_millis(250))? {
@@ -111,12 +178,9 @@ fn run_app(
                     crossterm::event::KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
                         app.quit();
                     }
-                    crossterm::event::KeyCode::Char('r') => {
-                        if !app.is_calculating {
-                            app.is_calculating = true;
-                            app.status_message = "Calculating viable heights...".to_string();
-                            // TODO: Start calculation in background
-                        }
+                    crossterm::event::KeyCode::Char('r') if !app.is_calculating => {
+                        app.status_message = "Calculating viable heights...".to_string();
+                        pending = Some(start_calculation(app, &runtime, &source));
                     }
                     _ => {}
                 }
@@ -130,6 +194,24 @@ fn run_app(
     Ok(())
 }
 
+#[cfg(feature = "tui")]
+fn start_calculation(
+    app: &mut TuiApp,
+    runtime: &tokio::runtime::Runtime,
+    source: &AsyncBlockSource<RpcPool>,
+) -> PendingResult {
+    app.is_calculating = true;
+    app.progress = 0.0;
+
+    let (tx, rx) = mpsc::channel();
+    let source = source.clone();
+    let (hashrate, target_days, adjustments) = (app.hashrate, app.target_days, app.adjustments);
+    runtime.spawn(async move {
+        let _ = tx.send(source.find_viable_calculations(hashrate, target_days, adjustments).await);
+    });
+    rx
+}
+
 #[cfg(feature = "tui")]
 fn ui(f: &mut Frame, app: &TuiApp) {
     let chunks = Layout::default()
@@ -186,7 +268,7 @@ fn render_parameters_tab(f: &mut Frame, area: Rect, app: &TuiApp) {
         .block(Block::default().borders(Borders::ALL).title("Current Settings"));
     f.render_widget(hashrate_para, chunks[1]);
 
-    let target_text = format!("Target Time: {:.1} days", app.target_days);
+    let target_text = format!("Target Time: {:.1} days    Tip Height: {}", app.target_days, app.current_height);
     let target_para = Paragraph::new(target_text)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(target_para, chunks[2]);
@@ -278,6 +360,12 @@ fn render_progress_tab(f: &mut Frame, area: Rect, app: &TuiApp) {
 
 // Non-TUI compilation support
 #[cfg(not(feature = "tui"))]
-pub fn run_tui(_client: Client, _hashrate: f64, _target_days: f64) -> Result<()> {
+pub fn run_tui(
+    _pool: RpcPool,
+    _hashrate: f64,
+    _target_days: f64,
+    _adjustments: Adjustments,
+    _zmq: Option<String>,
+) -> Result<()> {
     Err(anyhow::anyhow!("TUI mode not available. Compile with --features tui"))
 }
@@ -0,0 +1,177 @@
+//! Parsing of hashrates and durations written with units.
+//!
+//! Hashrates take an optional SI prefix and `H` or `H/s` (`5.2TH`,
+//! `300gh/s`, `1.5P`), durations a run of number-unit pairs (`36h`,
+//! `2d12h`, `90m`), file sizes an optional SI prefix and `B` (`10MB`,
+//! `500k`), and bitcoin values `BTC` or `sat` (`0.5BTC`, `10000sat`). Bare numbers keep their old meaning of hashes/second, days and,
+//! for intervals, seconds, so existing configurations still parse.
+
+use crate::SECONDS_PER_DAY;
+
+/// Parse a hashrate in hashes/second, e.g. `1e15`, `5.2TH` or `300gh/s`.
+pub fn parse_hashrate(text: &str) -> Result<f64, String> {
+    let trimmed = text.trim();
+    let (number, unit) = split_number(trimmed)
+        .ok_or_else(|| format!("expected a hashrate such as 5.2TH or 300gh/s, got '{}'", trimmed))?;
+    let unit = unit.trim();
+    let lower = unit.to_ascii_lowercase();
+    let lower = lower.strip_suffix("/s").unwrap_or(&lower);
+    let prefix = lower.strip_suffix('h').unwrap_or(lower);
+    let scale = match prefix {
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        "p" => 1e15,
+        "e" => 1e18,
+        _ => {
+            return Err(format!(
+                "unknown hashrate unit '{}' (expected H, KH, MH, GH, TH, PH or EH, optionally with /s)",
+                unit
+            ))
+        }
+    };
+    positive(number * scale).ok_or_else(|| "hashrate must be positive".to_string())
+}
+
+/// Parse a duration in days, e.g. `3`, `36h` or `2d12h`.
+///
+/// Units are `s`, `m`, `h`, `d` and `w`; a bare number is days.
+pub fn parse_days(text: &str) -> Result<f64, String> {
+    parse_seconds(text, SECONDS_PER_DAY, "3, 36h or 2d12h").map(|seconds| seconds / SECONDS_PER_DAY)
+}
+
+/// Parse an interval in whole seconds, e.g. `60`, `10m` or `1h30m`.
+///
+/// Units are as for [`parse_days`], but a bare number is seconds.
+pub fn parse_interval(text: &str) -> Result<u64, String> {
+    let seconds = parse_seconds(text, 1.0, "60, 10m or 1h30m")?.round();
+    if seconds < 1.0 {
+        return Err("interval must be at least one second".to_string());
+    }
+    Ok(seconds as u64)
+}
+
+/// Parse a duration in seconds, reading a lone number in units of `bare`.
+fn parse_seconds(text: &str, bare: f64, examples: &str) -> Result<f64, String> {
+    let trimmed = text.trim();
+    let mut rest = trimmed;
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest)
+            .ok_or_else(|| format!("expected a duration such as {}, got '{}'", examples, trimmed))?;
+        let unit_len = after.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_len);
+        let scale = match unit.to_ascii_lowercase().as_str() {
+            // Only a lone number takes the default unit
+            "" if rest.len() == trimmed.len() && after.is_empty() => bare,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => SECONDS_PER_DAY,
+            "w" => 7.0 * SECONDS_PER_DAY,
+            "" => return Err(format!("missing unit after {} in '{}' (expected s, m, h, d or w)", number, trimmed)),
+            _ => {
+                return Err(format!("unknown duration unit '{}' (expected s, m, h, d or w)", unit))
+            }
+        };
+        seconds += number * scale;
+        rest = after.trim_start();
+    }
+    positive(seconds).ok_or_else(|| "duration must be positive".to_string())
+}
+
+/// Parse a file size in bytes, e.g. `1000000`, `10MB` or `500k`.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let (number, unit) =
+        split_number(trimmed).ok_or_else(|| format!("expected a size such as 10MB or 500k, got '{}'", trimmed))?;
+    let unit = unit.trim();
+    let lower = unit.to_ascii_lowercase();
+    let prefix = lower.strip_suffix('b').unwrap_or(&lower);
+    let scale = match prefix {
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        _ => return Err(format!("unknown size unit '{}' (expected B, KB, MB, GB or TB)", unit)),
+    };
+    positive((number * scale).floor())
+        .map(|bytes| bytes as u64)
+        .ok_or_else(|| "size must be at least one byte".to_string())
+}
+
+/// Parse a bitcoin value in satoshis, e.g. `0.5`, `0.5BTC` or `10000sat`.
+///
+/// A bare number is bitcoin.
+pub fn parse_btc(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let (number, unit) =
+        split_number(trimmed).ok_or_else(|| format!("expected a value such as 0.5BTC or 10000sat, got '{}'", trimmed))?;
+    let unit = unit.trim();
+    let scale = match unit.to_ascii_lowercase().as_str() {
+        "" | "btc" => 1e8,
+        "sat" | "sats" => 1.0,
+        _ => return Err(format!("unknown value unit '{}' (expected BTC or sat)", unit)),
+    };
+    positive((number * scale).round())
+        .map(|sats| sats as u64)
+        .ok_or_else(|| "value must be at least one satoshi".to_string())
+}
+
+/// Split the longest leading number off `text`.
+fn split_number(text: &str) -> Option<(f64, &str)> {
+    let end = text
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .filter(|&end| text[..end].bytes().all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b)))
+        .rfind(|&end| text[..end].parse::<f64>().is_ok())?;
+    Some((text[..end].parse().ok()?, &text[end..]))
+}
+
+fn positive(value: f64) -> Option<f64> {
+    (value > 0.0 && value.is_finite()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashrates_take_si_prefixes() {
+        assert_eq!(parse_hashrate("1e15"), Ok(1e15));
+        assert_eq!(parse_hashrate("5.2TH"), Ok(5.2e12));
+        assert_eq!(parse_hashrate("300gh/s"), Ok(300e9));
+        assert_eq!(parse_hashrate("1.5P"), Ok(1.5e15));
+        assert!(parse_hashrate("5QH").is_err());
+        assert!(parse_hashrate("0").is_err());
+        assert!(parse_hashrate("fast").is_err());
+    }
+
+    #[test]
+    fn durations_sum_their_parts() {
+        assert_eq!(parse_days("3"), Ok(3.0));
+        assert_eq!(parse_days("36h"), Ok(1.5));
+        assert_eq!(parse_days("2d12h"), Ok(2.5));
+        assert_eq!(parse_days("1w"), Ok(7.0));
+        assert!(parse_days("2d12").is_err());
+        assert!(parse_days("3y").is_err());
+        assert_eq!(parse_interval("60"), Ok(60));
+        assert_eq!(parse_interval("1h30m"), Ok(5400));
+        assert!(parse_interval("0.2").is_err());
+    }
+
+    #[test]
+    fn sizes_and_values_round_to_units() {
+        assert_eq!(parse_size("1000000"), Ok(1_000_000));
+        assert_eq!(parse_size("10MB"), Ok(10_000_000));
+        assert_eq!(parse_size("500k"), Ok(500_000));
+        assert!(parse_size("0.5").is_err());
+        assert_eq!(parse_btc("0.5"), Ok(50_000_000));
+        assert_eq!(parse_btc("0.5BTC"), Ok(50_000_000));
+        assert_eq!(parse_btc("10000sat"), Ok(10_000));
+        assert!(parse_btc("1mBTC").is_err());
+    }
+}
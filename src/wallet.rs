@@ -0,0 +1,114 @@
+//! What a reorg does to a wallet's own transactions.
+//!
+//! Every wallet transaction confirmed at or above the fork height loses its
+//! confirmations when the new chain takes over. If the attacker mines it
+//! again it confirms anew, in one of the new chain's blocks, so it can have
+//! at most as many confirmations as that chain has blocks; left out, it is
+//! unconfirmed until some later block takes it, and one the attacker
+//! double-spends never confirms.
+
+use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "rpc")]
+use bitcoincore_rpc::{Client, RpcApi};
+#[cfg(feature = "rpc")]
+use serde_json::json;
+
+#[cfg(feature = "rpc")]
+use crate::error::{ReorgCalcError, Result, RpcContext};
+#[cfg(feature = "rpc")]
+use crate::source::BlockSource;
+
+/// A wallet transaction confirmed in a block the reorg replaces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    pub txid: Txid,
+    /// How the wallet sees it, e.g. `receive`, `send` or `generate`; more
+    /// than one, comma-separated, when it both sends and receives.
+    pub category: String,
+    /// Net change to the wallet's balance, satoshis.
+    pub amount: i64,
+    pub height: u64,
+    /// Confirmations it has now.
+    pub confirmations: u64,
+}
+
+/// A wallet's transactions in the reorg span.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletImpact {
+    pub wallet: String,
+    /// Most confirmations a transaction can have on the attacker's chain
+    /// once it replaces the span: one per block of that chain.
+    pub new_chain_confirmations: u64,
+    /// Affected transactions, oldest first.
+    pub transactions: Vec<WalletTransaction>,
+}
+
+impl WalletImpact {
+    /// Transactions that end up with fewer confirmations than now even if
+    /// the attacker mines them again.
+    pub fn below_current(&self) -> usize {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.confirmations > self.new_chain_confirmations)
+            .count()
+    }
+}
+
+/// Find the transactions of the wallet `wallet` (named `name`) confirmed
+/// from `fork_height` up, which a reorg replaced by `new_chain_blocks`
+/// blocks would unconfirm.
+///
+/// Uses `listsinceblock` from the fork parent, so the wallet must be loaded
+/// on the node `source` reads.
+#[cfg(feature = "rpc")]
+pub fn wallet_impact<S: BlockSource + ?Sized>(
+    wallet: &Client,
+    name: &str,
+    source: &S,
+    fork_height: u64,
+    new_chain_blocks: u64,
+) -> Result<WalletImpact> {
+    // Without a fork parent, everything the wallet has is listed
+    let params = match fork_height.checked_sub(1) {
+        Some(parent) => vec![json!(source.get_block_hash(parent)?)],
+        None => Vec::new(),
+    };
+    let since: serde_json::Value = wallet.call("listsinceblock", &params)
+        .rpc_context(format!("Failed to list the transactions of wallet '{}' (is it loaded?)", name))?;
+    let entries = since["transactions"].as_array()
+        .ok_or_else(|| ReorgCalcError::InvalidData("listsinceblock returned no transactions".to_string()))?;
+
+    let mut transactions: Vec<WalletTransaction> = Vec::new();
+    for entry in entries {
+        // Unconfirmed and conflicted entries are not in any block
+        let (Some(height), Some(confirmations)) = (entry["blockheight"].as_u64(), entry["confirmations"].as_i64()) else {
+            continue;
+        };
+        if height < fork_height || confirmations <= 0 {
+            continue;
+        }
+        let txid: Txid = entry["txid"].as_str().unwrap_or_default().parse()
+            .map_err(|_| ReorgCalcError::InvalidData(format!("bad txid in listsinceblock: {}", entry["txid"])))?;
+        let category = entry["category"].as_str().unwrap_or("unknown");
+        let amount = (entry["amount"].as_f64().unwrap_or_default() * 1e8).round() as i64;
+        match transactions.iter_mut().find(|tx| tx.txid == txid) {
+            Some(tx) => {
+                if !tx.category.split(',').any(|known| known == category) {
+                    tx.category = format!("{},{}", tx.category, category);
+                }
+                tx.amount += amount;
+            }
+            None => transactions.push(WalletTransaction {
+                txid,
+                category: category.to_string(),
+                amount,
+                height,
+                confirmations: confirmations as u64,
+            }),
+        }
+    }
+    transactions.sort_by(|a, b| a.height.cmp(&b.height).then(a.txid.cmp(&b.txid)));
+    Ok(WalletImpact { wallet: name.to_string(), new_chain_confirmations: new_chain_blocks, transactions })
+}
@@ -0,0 +1,56 @@
+//! JavaScript bindings for running the calculator in a web page.
+//!
+//! Browsers can only fetch asynchronously, so the page pulls headers from an
+//! esplora API itself (`/block-height/:height`, `/block/:hash/header`) and
+//! hands the header hex to these functions. No [`BlockSource`](crate::BlockSource)
+//! runs here: the build has only the maths, not an HTTP client.
+
+use bitcoin::{block::Header, consensus::encode::deserialize_hex, Work};
+use wasm_bindgen::prelude::*;
+
+use crate::calc::calculate_from_work;
+use crate::network::NetworkParams;
+use crate::work;
+
+/// Difficulty of a compact `bits` target.
+#[wasm_bindgen(js_name = bitsToDifficulty)]
+pub fn bits_to_difficulty(bits: u32) -> f64 {
+    work::bits_to_difficulty(bits)
+}
+
+/// Total work of the given hex-encoded block headers, in testnet4 difficulty
+/// units.
+#[wasm_bindgen(js_name = chainWorkFromHeaders)]
+pub fn chain_work_from_headers(headers: Vec<String>) -> Result<f64, JsError> {
+    let mut total_work = Work::from_be_bytes([0; 32]);
+    for hex in &headers {
+        let header: Header = deserialize_hex(hex.trim()).map_err(|e| JsError::new(&e.to_string()))?;
+        total_work = total_work + work::block_work(header.bits.to_consensus());
+    }
+    Ok(NetworkParams::default().work_to_difficulty(total_work))
+}
+
+/// Reorg requirements as a JSON-encoded `ReorgCalculation`.
+#[wasm_bindgen(js_name = calculateReorg)]
+pub fn calculate_reorg(
+    fork_height: u64,
+    current_height: u64,
+    total_work: f64,
+    current_difficulty: f64,
+    hashrate: f64,
+    target_days: f64,
+) -> Result<String, JsError> {
+    if fork_height > current_height {
+        return Err(JsError::new("fork height exceeds current height"));
+    }
+    let calc = calculate_from_work(
+        fork_height,
+        current_height,
+        total_work,
+        current_difficulty,
+        hashrate,
+        target_days,
+        &NetworkParams::default(),
+    );
+    serde_json::to_string(&calc).map_err(|e| JsError::new(&e.to_string()))
+}
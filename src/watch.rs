@@ -0,0 +1,370 @@
+//! Monitors that keep recalculating as the chain grows.
+//!
+//! `watch` follows one fork point, `daemon` a set of depths and `countdown`
+//! a deadline, each recalculating whenever its [`BlockTrigger`] fires, and
+//! `track` follows an attack chain mined on a second node. They save to the
+//! [`ResultSinks`], which record reorgs, fire alerts and keep the monitor's
+//! state across restarts, and run until the process is stopped, riding out
+//! node failures with a warning until the next cycle.
+
+use chrono::{DateTime, Utc};
+use std::thread;
+use std::time::Duration;
+
+use crate::calc::{calculate_reorg_requirements, find_deepest_fork_height, ReorgCalculation};
+use crate::commands::{run_calc, ForkPoint};
+use crate::error::Result;
+use crate::format::{display_burial, display_changes, display_progress, format_days, format_hashrate};
+use crate::model::Model;
+use crate::monitor::{tip_events, MonitorEvent, MonitorEventKind};
+use crate::sinks::ResultSinks;
+use crate::source::BlockSource;
+use crate::track::{attack_progress, AttackProgress};
+use crate::work::ScanOptions;
+use crate::zmq::HashBlockSubscriber;
+use crate::SECONDS_PER_DAY;
+use crate::{say, warning};
+
+/// Decides when watch-style loops run their next cycle: immediately on a ZMQ
+/// block notification if configured, otherwise after `interval` seconds.
+pub struct BlockTrigger {
+    subscriber: Option<HashBlockSubscriber>,
+    interval: Duration,
+}
+
+impl BlockTrigger {
+    /// Subscribe to the `zmq` endpoint if one is given, polling every
+    /// `interval` seconds if there is none or it cannot be reached.
+    pub fn new(zmq: Option<&str>, interval: u64) -> Self {
+        let subscriber = zmq.and_then(|endpoint| match HashBlockSubscriber::connect(endpoint) {
+            Ok(subscriber) => {
+                say!("Subscribed to block notifications at {}", endpoint);
+                Some(subscriber)
+            }
+            Err(e) => {
+                warning!("Warning: ZMQ unavailable, falling back to polling: {}", e);
+                None
+            }
+        });
+        Self {
+            subscriber,
+            interval: Duration::from_secs(interval),
+        }
+    }
+
+    /// When the next cycles run, e.g. `every 10m`.
+    pub fn describe(&self) -> String {
+        match self.subscriber {
+            Some(_) => format!("on each new block (or every {})", interval_text(self.interval)),
+            None => format!("every {}", interval_text(self.interval)),
+        }
+    }
+
+    /// Block until the next cycle is due.
+    pub fn wait(&mut self) {
+        if let Some(subscriber) = &mut self.subscriber {
+            match subscriber.wait_for_block(self.interval) {
+                Ok(_) => return,
+                Err(e) => {
+                    warning!("Warning: ZMQ connection lost, falling back to polling: {}", e);
+                    self.subscriber = None;
+                }
+            }
+        }
+        thread::sleep(self.interval);
+    }
+}
+
+/// `interval` the way `--interval` takes it, e.g. `45s`, `10m` or `1h30m`.
+fn interval_text(interval: Duration) -> String {
+    let seconds = interval.as_secs();
+    let parts = [(seconds / 3600, "h"), (seconds / 60 % 60, "m"), (seconds % 60, "s")];
+    parts.iter().filter(|(count, _)| *count > 0).map(|(count, unit)| format!("{}{}", count, unit)).collect()
+}
+
+/// Recalculate for `fork` on every `trigger`, showing what changed since
+/// the last calculation and how much deeper the first one is buried.
+pub fn run_watch(
+    source: &dyn BlockSource,
+    fork: ForkPoint,
+    trigger: &mut BlockTrigger,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+    sinks: &mut ResultSinks,
+) -> Result<()> {
+    say!("\nWatching: recalculating {} (Ctrl-C to stop)...", trigger.describe());
+    // Holds the first calculation, while the fork point stays where it was
+    let mut state = sinks.restore_state(source, "watch")?;
+
+    loop {
+        // Like the daemon, ride out node failures until the next cycle
+        match tip_events(source, &mut state.seen) {
+            Ok(events) => sinks.record(&events),
+            Err(e) => warning!("Warning: reading the tip failed: {}", e),
+        }
+        sinks.watch_branches(source);
+        let calc = fork
+            .resolve(source)
+            .and_then(|fork_height| run_calc(source, fork_height, hashrate, target_days, model, &ScanOptions::default()));
+        match calc {
+            Ok(calc) => {
+                if let Some(previous) = &state.previous {
+                    display_changes(previous, &calc);
+                }
+                match &state.first {
+                    Some(first) if first.fork_height != calc.fork_height => {}
+                    Some(first) if first.current_height < calc.current_height => display_burial(first, &calc),
+                    Some(_) => {}
+                    None => state.first = Some(calc.clone()),
+                }
+                if let Err(e) = sinks.save(&[(calc.clone(), hashrate)]) {
+                    warning!("Warning: failed to save results: {}", e);
+                }
+                state.previous = Some(calc);
+            }
+            Err(e) => {
+                warning!("Warning: recalculation failed: {}", e);
+                sinks.record(&[MonitorEvent::now(MonitorEventKind::RecalculationFailed { reason: e.to_string() })]);
+            }
+        }
+        sinks.save_state(&mut state);
+        trigger.wait();
+    }
+}
+
+/// Recalculate each of `depths` below the tip on every `trigger`.
+pub fn run_daemon(
+    source: &dyn BlockSource,
+    depths: &[u64],
+    trigger: &mut BlockTrigger,
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+    sinks: &mut ResultSinks,
+) -> Result<()> {
+    say!("\nDaemon started: depths {:?}, recalculating {} (Ctrl-C to stop)...", depths, trigger.describe());
+    let mut state = sinks.restore_state(source, "daemon")?;
+
+    loop {
+        // Keep running through transient node failures; the next cycle retries
+        match tip_events(source, &mut state.seen) {
+            Ok(events) => sinks.record(&events),
+            Err(e) => warning!("Warning: reading the tip failed: {}", e),
+        }
+        sinks.watch_branches(source);
+        match recalculate_depths(source, depths, hashrate, target_days, model) {
+            Ok(calculations) => {
+                for calc in &calculations {
+                    say!(
+                        "[{}] depth {} (fork {}): {:.2} days, {} for {} days{}",
+                        calc.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                        calc.current_height - calc.fork_height,
+                        calc.fork_height,
+                        calc.time_required_days,
+                        format_hashrate(calc.hashrate_required),
+                        format_days(target_days),
+                        if calc.time_required_days <= target_days { " [viable]" } else { "" }
+                    );
+                }
+                let runs: Vec<(ReorgCalculation, f64)> = calculations.into_iter().map(|calc| (calc, hashrate)).collect();
+                if let Err(e) = sinks.save(&runs) {
+                    warning!("Warning: failed to save results: {}", e);
+                }
+            }
+            Err(e) => {
+                warning!("Warning: recalculation failed: {}", e);
+                sinks.record(&[MonitorEvent::now(MonitorEventKind::RecalculationFailed { reason: e.to_string() })]);
+            }
+        }
+        sinks.save_state(&mut state);
+        trigger.wait();
+    }
+}
+
+/// Count down to `deadline`, showing on every `trigger` the deepest fork
+/// still viable and what `fork` needs in the time left; returns once the
+/// deadline has passed.
+pub fn run_countdown(
+    source: &dyn BlockSource,
+    fork: ForkPoint,
+    deadline: DateTime<Utc>,
+    trigger: &mut BlockTrigger,
+    hashrate: f64,
+    model: Model,
+    sinks: &mut ResultSinks,
+) -> Result<()> {
+    let deadline_text = deadline.format("%Y-%m-%d %H:%M:%S UTC");
+    say!("\nCounting down to {}, refreshing {} (Ctrl-C to stop)...", deadline_text, trigger.describe());
+    let mut state = sinks.restore_state(source, "countdown")?;
+
+    loop {
+        let days = (deadline - Utc::now()).num_milliseconds() as f64 / (1000.0 * SECONDS_PER_DAY);
+        if days <= 0.0 {
+            say!("\nThe deadline {} has passed", deadline_text);
+            return Ok(());
+        }
+        match countdown_step(source, fork, days, hashrate, model) {
+            Ok((deepest, calc)) => {
+                say!("\n=== Countdown to {} ===", deadline_text);
+                say!("Time Remaining: {} days ({:.2} hours)", format_days(days), days * 24.0);
+                match &deepest {
+                    Some(deepest) => say!(
+                        "Deepest Viable Fork: {} ({} blocks to reorg) at {}",
+                        deepest.fork_height,
+                        deepest.blocks_to_reorg,
+                        format_hashrate(hashrate)
+                    ),
+                    None => say!("Deepest Viable Fork: none, not even the tip block at {}", format_hashrate(hashrate)),
+                }
+                let was = state.previous.as_ref().map_or_else(String::new, |previous| {
+                    format!(" (was {})", format_hashrate(previous.hashrate_required))
+                });
+                say!(
+                    "Hashrate Required for Fork {} ({} blocks): {}{}",
+                    calc.fork_height,
+                    calc.blocks_to_reorg,
+                    format_hashrate(calc.hashrate_required),
+                    was
+                );
+                say!(
+                    "Time Required at {}: {} days{}",
+                    format_hashrate(hashrate),
+                    format_days(calc.time_required_days),
+                    if calc.time_required_days <= days { " [viable]" } else { "" }
+                );
+                if let Err(e) = sinks.save(&[(calc.clone(), hashrate)]) {
+                    warning!("Warning: failed to save results: {}", e);
+                }
+                state.previous = Some(calc);
+            }
+            Err(e) => {
+                warning!("Warning: recalculation failed: {}", e);
+                sinks.record(&[MonitorEvent::now(MonitorEventKind::RecalculationFailed { reason: e.to_string() })]);
+            }
+        }
+        sinks.save_state(&mut state);
+        trigger.wait();
+    }
+}
+
+/// Follow the attack chain of `attack` against the `public` one, showing
+/// its progress on every `trigger`.
+pub fn run_track(
+    public: &dyn BlockSource,
+    attack: &dyn BlockSource,
+    trigger: &mut BlockTrigger,
+    hashrate: f64,
+    model: Model,
+    sinks: &mut ResultSinks,
+) -> Result<()> {
+    say!("\nTracking the attack chain, refreshing {} (Ctrl-C to stop)...", trigger.describe());
+    let params = public.get_network()?;
+    // Holds the first reading on the current fork, to measure the attack's hashrate from
+    let mut state = sinks.restore_state(public, "track")?;
+
+    loop {
+        match track_step(public, attack, &mut state.attack_start, hashrate, model) {
+            Ok((progress, hashrate, honest_hashrate)) => {
+                display_progress(&progress, hashrate, honest_hashrate, &params);
+            }
+            Err(e) => warning!("Warning: reading the chains failed: {}", e),
+        }
+        sinks.save_state(&mut state);
+        trigger.wait();
+    }
+}
+
+/// The attack's progress with the hashrates to project it at: the attack's
+/// measured since `first` once it mined on the same fork (`hashrate` until
+/// then), and the honest one of `model` or the public chain's measured.
+fn track_step(
+    public: &dyn BlockSource,
+    attack: &dyn BlockSource,
+    first: &mut Option<AttackProgress>,
+    hashrate: f64,
+    model: Model,
+) -> Result<(AttackProgress, f64, f64)> {
+    let progress = attack_progress(public, attack)?;
+    let params = public.get_network()?;
+    let honest_hashrate = model.honest_hashrate(public)?;
+    let start = match first {
+        Some(start) if start.fork_height == progress.fork_height => start,
+        _ => first.insert(progress.clone()),
+    };
+    let seconds = (progress.time - start.time).num_milliseconds() as f64 / 1000.0;
+    let mined = progress.attack_work - start.attack_work;
+    let measured = (seconds > 0.0 && mined > 0.0).then(|| mined * params.hashes_per_difficulty() / seconds);
+    Ok((progress, measured.unwrap_or(hashrate), honest_hashrate))
+}
+
+/// The deepest fork viable in the `days` left, if any, and the calculation
+/// for the countdown's fork target (100 blocks below the tip by default).
+fn countdown_step(
+    source: &dyn BlockSource,
+    fork: ForkPoint,
+    days: f64,
+    hashrate: f64,
+    model: Model,
+) -> Result<(Option<ReorgCalculation>, ReorgCalculation)> {
+    let adjustments = model.adjustments(source)?;
+    let params = source.get_network()?;
+    let deepest = find_deepest_fork_height(source, hashrate, days, &adjustments, |calc| calc.time_required_days <= days)?;
+    let fork_height = match fork.resolve(source)? {
+        Some(fork_height) => fork_height,
+        None => source.get_tip()?.height.saturating_sub(100),
+    };
+    let calc = calculate_reorg_requirements(source, fork_height, hashrate, days)?;
+    let calc = adjustments.apply(source, calc, hashrate, days, &params)?;
+    Ok((deepest, model.annotate(calc, hashrate, days, &params)))
+}
+
+fn recalculate_depths(
+    source: &dyn BlockSource,
+    depths: &[u64],
+    hashrate: f64,
+    target_days: f64,
+    model: Model,
+) -> Result<Vec<ReorgCalculation>> {
+    let current_height = source.get_tip()?.height;
+    let adjustments = model.adjustments(source)?;
+    let params = source.get_network()?;
+    depths
+        .iter()
+        .map(|&depth| {
+            let fork_height = current_height.saturating_sub(depth);
+            let calc = calculate_reorg_requirements(source, fork_height, hashrate, target_days)?;
+            adjustments.apply(source, calc, hashrate, target_days, &params)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{TestChain, DIFFICULTY_1};
+
+    #[test]
+    fn intervals_read_the_way_they_are_given() {
+        assert_eq!(interval_text(Duration::from_secs(45)), "45s");
+        assert_eq!(interval_text(Duration::from_secs(600)), "10m");
+        assert_eq!(interval_text(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(BlockTrigger::new(None, 3600).describe(), "every 1h");
+    }
+
+    #[test]
+    fn the_daemon_recalculates_each_depth_below_the_tip() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        let calculations = recalculate_depths(&chain, &[10, 100, 1000], 1e12, 3.0, Model::default()).unwrap();
+        let forks: Vec<u64> = calculations.iter().map(|calc| calc.fork_height).collect();
+        assert_eq!(forks, [289, 199, 0]);
+    }
+
+    #[test]
+    fn the_countdown_targets_100_blocks_below_the_tip_by_default() {
+        let chain = TestChain::uniform(300, DIFFICULTY_1);
+        let (deepest, calc) = countdown_step(&chain, ForkPoint::Suggested, 1.0, 1e15, Model::default()).unwrap();
+        assert_eq!(calc.fork_height, 199);
+        assert!(deepest.is_some_and(|deepest| deepest.fork_height <= calc.fork_height));
+    }
+}
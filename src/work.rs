@@ -0,0 +1,716 @@
+//! Difficulty and chain work calculations.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use bitcoin::{BlockHash, CompactTarget, Target, Work};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{ReorgCalcError, Result};
+use crate::network::{work_to_f64, NetworkParams};
+use crate::output::progress_bar;
+use crate::progress;
+use crate::source::BlockSource;
+
+/// Blocks requested from the source per call during a scan.
+pub(crate) const SCAN_BATCH_SIZE: usize = 500;
+
+/// Return the difficulty of the block at `block_height`.
+///
+/// Only the 80-byte header is needed, so sources fetch that rather than the
+/// full block.
+pub fn get_block_difficulty<S: BlockSource + ?Sized>(source: &S, block_height: u64) -> Result<f64> {
+    // Use bits to calculate difficulty directly
+    let bits = source.get_bits(block_height)?;
+    let difficulty = bits_to_difficulty(bits);
+    Ok(difficulty)
+}
+
+/// Convert a compact `bits` target into a difficulty relative to `0x1d00ffff`,
+/// the limit of mainnet and the testnets (see
+/// [`NetworkParams::difficulty`](crate::NetworkParams::difficulty) for other
+/// networks).
+///
+/// Decoding follows consensus rules via rust-bitcoin, so negative or
+/// overflowing compact encodings are handled like the node handles them.
+pub fn bits_to_difficulty(bits: u32) -> f64 {
+    Target::from_compact(CompactTarget::from_consensus(bits)).difficulty_float()
+}
+
+/// Expected number of hashes to mine one block with compact target `bits`.
+pub fn block_work(bits: u32) -> Work {
+    Target::from_compact(CompactTarget::from_consensus(bits)).to_work()
+}
+
+/// `work`, a number of hashes, as an exact decimal integer.
+pub fn work_to_decimal(work: Work) -> String {
+    let mut digits = work.to_be_bytes();
+    // Base 10^9 groups, least significant first
+    let mut groups = Vec::new();
+    while digits.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0u64;
+        for byte in digits.iter_mut() {
+            let value = (remainder << 8) | *byte as u64;
+            *byte = (value / 1_000_000_000) as u8;
+            remainder = value % 1_000_000_000;
+        }
+        groups.push(remainder);
+    }
+    let mut text = groups.pop().unwrap_or(0).to_string();
+    for group in groups.iter().rev() {
+        text.push_str(&format!("{:09}", group));
+    }
+    text
+}
+
+/// Smallest number of blocks, each worth `block_work`, whose combined work
+/// reaches `total_work`.
+///
+/// Computed with exact 256-bit arithmetic; saturates at `u128::MAX`.
+pub fn blocks_to_match(total_work: Work, block_work: Work) -> u128 {
+    let zero = Work::from_be_bytes([0; 32]);
+    if block_work == zero {
+        return u128::MAX;
+    }
+    
+    // Long division using only the doubling and subtraction `Work` provides
+    let mut multiples = vec![block_work];
+    while let Some(&largest) = multiples.last() {
+        if largest > total_work || largest > total_work - largest {
+            break;
+        }
+        multiples.push(largest + largest);
+    }
+    
+    let mut remainder = total_work;
+    let mut quotient: u128 = 0;
+    for (shift, &multiple) in multiples.iter().enumerate().rev() {
+        if multiple <= remainder {
+            if shift >= 128 {
+                return u128::MAX;
+            }
+            remainder = remainder - multiple;
+            quotient += 1 << shift;
+        }
+    }
+    
+    if remainder > zero {
+        quotient.saturating_add(1)
+    } else {
+        quotient
+    }
+}
+
+/// Total work of the blocks from `fork_height` to `current_height` inclusive.
+///
+/// Uses [`chain_work_from_chainwork`] when the source reports cumulative
+/// chainwork, and falls back to [`scan_chain_work`] otherwise.
+pub fn calculate_chain_work<S: BlockSource + ?Sized>(source: &S, fork_height: u64, current_height: u64) -> Result<Work> {
+    calculate_chain_work_with(source, fork_height, current_height, &ScanOptions::default())
+}
+
+/// [`calculate_chain_work`] with checkpointing for the scan fallback.
+pub fn calculate_chain_work_with<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+    options: &ScanOptions,
+) -> Result<Work> {
+    match chain_work_from_chainwork(source, fork_height, current_height) {
+        Err(ReorgCalcError::Unsupported(_)) => scan_chain_work_with(source, fork_height, current_height, options),
+        result => result,
+    }
+}
+
+/// Total work of the blocks from `fork_height` to `current_height` inclusive,
+/// as chainwork(tip) minus chainwork(fork parent).
+///
+/// Two lookups regardless of the span length.
+pub fn chain_work_from_chainwork<S: BlockSource + ?Sized>(source: &S, fork_height: u64, current_height: u64) -> Result<Work> {
+    let tip_work = source.get_chainwork(current_height)?;
+    match fork_height.checked_sub(1) {
+        Some(parent_height) => {
+            let parent_work = source.get_chainwork(parent_height)?;
+            span_work(parent_work, tip_work).ok_or_else(|| {
+                ReorgCalcError::InvalidData(format!(
+                    "chainwork at height {} exceeds chainwork at height {}",
+                    parent_height, current_height
+                ))
+            })
+        }
+        None => Ok(tip_work),
+    }
+}
+
+/// Blocks [`estimate_network_hashrate`] looks back over, about a day's worth.
+pub const HASHRATE_WINDOW: u64 = 144;
+
+/// The network's hashrate as measured over recent blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NetworkHashrate {
+    /// Hashes/second.
+    pub hashrate: f64,
+    /// Blocks measured, ending at the tip.
+    pub blocks: u64,
+    /// Seconds between the timestamps of the first and last of them.
+    pub seconds: i64,
+}
+
+/// Estimate the hashrate of the network in hashes/second from the work and
+/// timestamps of the last `window` blocks.
+///
+/// Block timestamps are only loosely ordered, so short windows give noisy
+/// estimates.
+pub fn estimate_network_hashrate<S: BlockSource + ?Sized>(source: &S, window: u64) -> Result<f64> {
+    Ok(measure_network_hashrate(source, window)?.hashrate)
+}
+
+/// [`estimate_network_hashrate`] with the span it was measured over; fewer
+/// than `window` blocks near genesis.
+pub fn measure_network_hashrate<S: BlockSource + ?Sized>(source: &S, window: u64) -> Result<NetworkHashrate> {
+    let tip_height = source.get_tip()?.height;
+    let window = window.min(tip_height);
+    if window == 0 {
+        return Err(ReorgCalcError::InvalidData("no blocks to estimate the network hashrate from".to_string()));
+    }
+    
+    let start_height = tip_height - window;
+    let headers = source.get_headers_batch(&[start_height, tip_height])?;
+    let elapsed = headers[1].time as i64 - headers[0].time as i64;
+    if elapsed <= 0 {
+        return Err(ReorgCalcError::InvalidData(format!(
+            "timestamps don't advance between blocks {} and {}",
+            start_height, tip_height
+        )));
+    }
+    
+    let work = calculate_chain_work(source, start_height + 1, tip_height)?;
+    Ok(NetworkHashrate { hashrate: work_to_f64(work) / elapsed as f64, blocks: window, seconds: elapsed })
+}
+
+/// Minimum-difficulty blocks within a span.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinDifficultyBlocks {
+    /// Blocks at the network's easiest target, which testnets allow after
+    /// 20 minutes without a block.
+    pub count: u64,
+    /// Blocks in the span.
+    pub span_blocks: u64,
+    /// Combined work of the minimum-difficulty blocks, in difficulty units.
+    pub work: f64,
+    /// Fraction of the span's total work they contribute.
+    pub work_share: f64,
+}
+
+/// Count the minimum-difficulty blocks from `fork_height` to
+/// `current_height` inclusive and the share of the work they add.
+///
+/// Needs every block's bits, so the whole span is scanned. Networks without
+/// the 20-minute rule report none without scanning.
+pub fn count_min_difficulty_blocks<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+) -> Result<MinDifficultyBlocks> {
+    let params = source.get_network()?;
+    let span_blocks = current_height + 1 - fork_height;
+    let mut blocks = MinDifficultyBlocks {
+        count: 0,
+        span_blocks,
+        work: 0.0,
+        work_share: 0.0,
+    };
+    if !params.consensus().allow_min_difficulty_blocks {
+        return Ok(blocks);
+    }
+    
+    progress!("Scanning blocks {} to {} for minimum-difficulty blocks...", fork_height, current_height);
+    let pb = progress_bar(span_blocks);
+    let heights: Vec<u64> = (fork_height..=current_height).collect();
+    let batches: Result<Vec<Vec<u32>>> = heights
+        .par_chunks(SCAN_BATCH_SIZE)
+        .map(|heights| {
+            let bits = source.get_bits_batch(heights)?;
+            pb.inc(heights.len() as u64);
+            Ok(bits)
+        })
+        .collect();
+    pb.finish_and_clear();
+    
+    let min_bits = params.pow_limit.to_compact_lossy().to_consensus();
+    let zero = Work::from_be_bytes([0; 32]);
+    let (mut min_work, mut total_work) = (zero, zero);
+    for bits in batches?.into_iter().flatten() {
+        let work = block_work(bits);
+        total_work = total_work + work;
+        if bits == min_bits {
+            blocks.count += 1;
+            min_work = min_work + work;
+        }
+    }
+    blocks.work = params.work_to_difficulty(min_work);
+    blocks.work_share = work_to_f64(min_work) / work_to_f64(total_work);
+    Ok(blocks)
+}
+
+/// Gaps largest first kept by [`measure_block_intervals`].
+pub const LARGEST_GAPS: usize = 5;
+
+/// The time between one block and its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlockGap {
+    pub height: u64,
+    /// Seconds from the parent's timestamp; negative when the block is
+    /// timestamped before it.
+    pub seconds: i64,
+}
+
+/// Inter-block times within a span.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockIntervals {
+    /// Intervals measured, one per block of the span.
+    pub count: u64,
+    pub mean_seconds: f64,
+    pub median_seconds: f64,
+    /// 95th percentile, by nearest rank.
+    pub p95_seconds: i64,
+    /// Largest gaps, largest first.
+    pub largest: Vec<BlockGap>,
+}
+
+/// Measure the times between the blocks from `fork_height` to
+/// `current_height` inclusive and their parents.
+///
+/// Timestamps need only be later than the median of the previous 11, so
+/// single intervals can be negative; the mean is the span's elapsed time
+/// over its blocks and is not affected.
+pub fn measure_block_intervals<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+) -> Result<BlockIntervals> {
+    let start = fork_height.max(1);
+    if start > current_height {
+        return Err(ReorgCalcError::InvalidData("the span has no block after genesis to time".into()));
+    }
+    
+    progress!("Scanning blocks {} to {} for their timestamps...", start - 1, current_height);
+    let pb = progress_bar(current_height + 2 - start);
+    let heights: Vec<u64> = (start - 1..=current_height).collect();
+    let batches: Result<Vec<Vec<u32>>> = heights
+        .par_chunks(SCAN_BATCH_SIZE)
+        .map(|heights| {
+            let headers = source.get_headers_batch(heights)?;
+            pb.inc(heights.len() as u64);
+            Ok(headers.iter().map(|header| header.time).collect())
+        })
+        .collect();
+    pb.finish_and_clear();
+    
+    let times: Vec<i64> = batches?.into_iter().flatten().map(i64::from).collect();
+    let mut gaps: Vec<BlockGap> = times
+        .windows(2)
+        .zip(start..)
+        .map(|(pair, height)| BlockGap { height, seconds: pair[1] - pair[0] })
+        .collect();
+    let count = gaps.len();
+    let mut seconds: Vec<i64> = gaps.iter().map(|gap| gap.seconds).collect();
+    seconds.sort_unstable();
+    let median_seconds = if count % 2 == 1 {
+        seconds[count / 2] as f64
+    } else {
+        (seconds[count / 2 - 1] + seconds[count / 2]) as f64 / 2.0
+    };
+    let p95_seconds = seconds[(count * 95).div_ceil(100) - 1];
+    gaps.sort_by(|a, b| b.seconds.cmp(&a.seconds).then(a.height.cmp(&b.height)));
+    gaps.truncate(LARGEST_GAPS);
+    Ok(BlockIntervals {
+        count: count as u64,
+        mean_seconds: (times[count] - times[0]) as f64 / count as f64,
+        median_seconds,
+        p95_seconds,
+        largest: gaps,
+    })
+}
+
+/// One block of a span and the span's work up to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BlockWork {
+    pub height: u64,
+    /// Compact target; hex in JSON, as the node reports it.
+    #[serde(serialize_with = "serialize_bits")]
+    pub bits: u32,
+    pub difficulty: f64,
+    /// Work of the span's blocks up to and including this one, in
+    /// difficulty units.
+    pub cumulative_work: f64,
+    /// Fraction of the span's total work this block contributes.
+    pub work_share: f64,
+}
+
+fn serialize_bits<S: serde::Serializer>(bits: &u32, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:08x}", bits))
+}
+
+/// Every block from `fork_height` to `current_height` inclusive with its
+/// difficulty and the running total of the work, for auditing which blocks
+/// dominate it.
+///
+/// Needs every block's bits, so the whole span is scanned.
+pub fn scan_block_works<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+) -> Result<Vec<BlockWork>> {
+    let params = source.get_network()?;
+    progress!("Scanning blocks {} to {} for their work...", fork_height, current_height);
+    let pb = progress_bar(current_height + 1 - fork_height);
+    let heights: Vec<u64> = (fork_height..=current_height).collect();
+    let batches: Result<Vec<Vec<u32>>> = heights
+        .par_chunks(SCAN_BATCH_SIZE)
+        .map(|heights| {
+            let bits = source.get_bits_batch(heights)?;
+            pb.inc(heights.len() as u64);
+            Ok(bits)
+        })
+        .collect();
+    pb.finish_and_clear();
+    let bits: Vec<u32> = batches?.into_iter().flatten().collect();
+    
+    // Summed exactly, so the last running total is the span's work
+    let zero = Work::from_be_bytes([0; 32]);
+    let total_work = work_to_f64(bits.iter().fold(zero, |work, &bits| work + block_work(bits)));
+    let mut cumulative = zero;
+    Ok(heights
+        .iter()
+        .zip(bits)
+        .map(|(&height, bits)| {
+            let work = block_work(bits);
+            cumulative = cumulative + work;
+            BlockWork {
+                height,
+                bits,
+                difficulty: params.difficulty(bits),
+                cumulative_work: params.work_to_difficulty(cumulative),
+                work_share: work_to_f64(work) / total_work,
+            }
+        })
+        .collect())
+}
+
+/// One retarget epoch's part of a span.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpanEpoch {
+    /// First height of the epoch, which may lie before the span.
+    pub start_height: u64,
+    /// Difficulty of the epoch's regular blocks.
+    pub difficulty: f64,
+    /// Blocks of the epoch inside the span.
+    pub blocks: u64,
+    /// Of those, blocks at the minimum difficulty.
+    pub min_difficulty_blocks: u64,
+    /// Work of the epoch's blocks inside the span, in difficulty units.
+    pub work: f64,
+    /// Fraction of the span's work they contribute.
+    pub work_share: f64,
+}
+
+/// Group `blocks`, a run of consecutive blocks from [`scan_block_works`],
+/// by retarget epoch.
+pub fn span_epochs(blocks: &[BlockWork], params: &NetworkParams) -> Vec<SpanEpoch> {
+    let interval = params.consensus().difficulty_adjustment_interval();
+    let min_bits = params.pow_limit.to_compact_lossy().to_consensus();
+    let mut epochs: Vec<SpanEpoch> = Vec::new();
+    let mut previous_work = 0.0;
+    for block in blocks {
+        let start_height = block.height / interval * interval;
+        if epochs.last().is_none_or(|epoch| epoch.start_height != start_height) {
+            epochs.push(SpanEpoch {
+                start_height,
+                difficulty: params.difficulty(min_bits),
+                blocks: 0,
+                min_difficulty_blocks: 0,
+                work: 0.0,
+                work_share: 0.0,
+            });
+        }
+        let Some(epoch) = epochs.last_mut() else { continue };
+        epoch.blocks += 1;
+        if block.bits == min_bits {
+            epoch.min_difficulty_blocks += 1;
+        } else {
+            epoch.difficulty = block.difficulty;
+        }
+        epoch.work += block.cumulative_work - previous_work;
+        epoch.work_share += block.work_share;
+        previous_work = block.cumulative_work;
+    }
+    epochs
+}
+
+/// Work added between two cumulative chainwork values, or `None` if
+/// `parent_work` is the larger one.
+pub(crate) fn span_work(parent_work: Work, tip_work: Work) -> Option<Work> {
+    (parent_work <= tip_work).then(|| tip_work - parent_work)
+}
+
+/// Progress of a [`scan_chain_work_with`] run, saved so it can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    pub fork_height: u64,
+    /// First height not yet included in `work`.
+    pub next_height: u64,
+    /// Hash of the block at `next_height - 1`, used to detect a reorg before resuming.
+    pub last_hash: BlockHash,
+    /// Work of the blocks from `fork_height` to `next_height - 1`.
+    pub work: Work,
+}
+
+impl ScanCheckpoint {
+    /// Read a checkpoint, or `None` if the file does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| ReorgCalcError::InvalidData(format!("bad checkpoint {}: {}", path.display(), e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the checkpoint, replacing the file atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ReorgCalcError::InvalidData(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Checkpoint and interruption settings for long scans.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanOptions<'a> {
+    /// File progress is written to after every round of batches; removed
+    /// once the scan completes.
+    pub checkpoint: Option<&'a Path>,
+    /// Continue from the checkpoint file when it belongs to the same fork
+    /// height and its last block is still on the active chain.
+    pub resume: bool,
+    /// Once set, the scan saves its progress at the next round boundary and
+    /// returns [`ReorgCalcError::Interrupted`].
+    pub stop: Option<&'a AtomicBool>,
+    /// Print no notes or progress bar, whatever [`set_output`](crate::set_output)
+    /// says, as when the calculator is embedded.
+    pub quiet: bool,
+}
+
+/// Sum the work of every block from `fork_height` to `current_height`
+/// inclusive, one header at a time.
+///
+/// This is the fallback for sources without chainwork, and a way to verify
+/// [`chain_work_from_chainwork`]. Small ranges are fetched sequentially;
+/// larger ranges are fetched in parallel batches with a progress bar.
+pub fn scan_chain_work<S: BlockSource + ?Sized>(source: &S, fork_height: u64, current_height: u64) -> Result<Work> {
+    scan_chain_work_with(source, fork_height, current_height, &ScanOptions::default())
+}
+
+/// [`scan_chain_work`] with checkpoints, resume and interruption.
+pub fn scan_chain_work_with<S: BlockSource + ?Sized>(
+    source: &S,
+    fork_height: u64,
+    current_height: u64,
+    options: &ScanOptions,
+) -> Result<Work> {
+    let params = source.get_network()?;
+    let mut scan = Scan {
+        fork_height,
+        next_height: fork_height,
+        work: Work::from_be_bytes([0; 32]),
+    };
+    
+    if let (true, Some(path)) = (options.resume, options.checkpoint) {
+        if let Some(checkpoint) = ScanCheckpoint::load(path)? {
+            if scan.can_resume(source, &checkpoint, current_height)? {
+                if !options.quiet {
+                    progress!("Resuming scan at block {} from {}", checkpoint.next_height, path.display());
+                }
+                scan.next_height = checkpoint.next_height;
+                scan.work = checkpoint.work;
+            } else {
+                if !options.quiet {
+                    progress!("Ignoring checkpoint {}: it belongs to another scan or the chain has changed", path.display());
+                }
+            }
+        }
+    }
+    
+    let remaining = (current_height + 1).saturating_sub(scan.next_height);
+    if remaining > 0 && remaining <= 100 {
+        // Use simple sequential method for small ranges
+        calculate_chain_work_sequential(source, &params, &mut scan, current_height, options)?;
+    } else if remaining > 0 {
+        // Use optimized parallel method for large ranges
+        calculate_chain_work_parallel(source, &params, &mut scan, current_height, options)?;
+    }
+    
+    if let Some(path) = options.checkpoint {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(scan.work)
+}
+
+/// Running state of a scan: blocks below `next_height` are summed in `work`.
+struct Scan {
+    fork_height: u64,
+    next_height: u64,
+    work: Work,
+}
+
+impl Scan {
+    fn can_resume<S: BlockSource + ?Sized>(&self, source: &S, checkpoint: &ScanCheckpoint, current_height: u64) -> Result<bool> {
+        if checkpoint.fork_height != self.fork_height
+            || checkpoint.next_height <= checkpoint.fork_height
+            || checkpoint.next_height > current_height + 1
+        {
+            return Ok(false);
+        }
+        Ok(source.get_block_hash(checkpoint.next_height - 1)? == checkpoint.last_hash)
+    }
+    
+    fn checkpoint<S: BlockSource + ?Sized>(&self, source: &S, path: &Path) -> Result<()> {
+        ScanCheckpoint {
+            fork_height: self.fork_height,
+            next_height: self.next_height,
+            last_hash: source.get_block_hash(self.next_height - 1)?,
+            work: self.work,
+        }
+        .save(path)
+    }
+}
+
+fn calculate_chain_work_sequential<S: BlockSource + ?Sized>(
+    source: &S,
+    params: &NetworkParams,
+    scan: &mut Scan,
+    current_height: u64,
+    options: &ScanOptions,
+) -> Result<()> {
+    if !options.quiet {
+        progress!("Calculating chain work from block {} to {}...", scan.next_height, current_height);
+    }
+    
+    let heights: Vec<u64> = (scan.next_height..=current_height).collect();
+    let bits = source.get_bits_batch(&heights)?;
+    
+    for (&height, &bits) in heights.iter().zip(&bits) {
+        scan.work = scan.work + block_work(bits);
+        
+        if !options.quiet && (height % 1000 == 0 || height == current_height) {
+            progress!("  Processed block {} (difficulty: {:.2})", height, params.difficulty(bits));
+        }
+    }
+    scan.next_height = current_height + 1;
+    
+    Ok(())
+}
+
+fn calculate_chain_work_parallel<S: BlockSource + ?Sized>(
+    source: &S,
+    params: &NetworkParams,
+    scan: &mut Scan,
+    current_height: u64,
+    options: &ScanOptions,
+) -> Result<()> {
+    let total_blocks = current_height + 1 - scan.next_height;
+    if !options.quiet {
+        progress!("Calculating chain work from block {} to {} ({} blocks)...", scan.next_height, current_height, total_blocks);
+    }
+    
+    // Setup progress bar
+    let pb = if options.quiet { ProgressBar::hidden() } else { progress_bar(total_blocks) };
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} blocks ({eta})")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("#>-"));
+    
+    // Each batch is one backend round trip; ten batches run in parallel at a time
+    let batch_size = SCAN_BATCH_SIZE as u64;
+    let round_size = batch_size * 10;
+    
+    for round_start in (scan.next_height..=current_height).step_by(round_size as usize) {
+        let round_end = (round_start + round_size - 1).min(current_height);
+        let batches: Vec<Vec<u64>> = (round_start..=round_end)
+            .step_by(batch_size as usize)
+            .map(|start| (start..=(start + batch_size - 1).min(round_end)).collect())
+            .collect();
+        
+        let batch_results: Result<Vec<Vec<u32>>> = batches
+            .par_iter()
+            .map(|heights| {
+                let bits = source.get_bits_batch(heights)?;
+                pb.inc(heights.len() as u64);
+                Ok(bits)
+            })
+            .collect();
+        
+        // Add this round's work to total
+        for bits in batch_results?.into_iter().flatten() {
+            scan.work = scan.work + block_work(bits);
+        }
+        scan.next_height = round_end + 1;
+        pb.println(format!(
+            "  Processed up to block {} (current total work: {:.2})",
+            round_end,
+            params.work_to_difficulty(scan.work)
+        ));
+        
+        if let Some(path) = options.checkpoint {
+            scan.checkpoint(source, path)?;
+        }
+        let stopped = options.stop.is_some_and(|stop| stop.load(Ordering::SeqCst));
+        if stopped && round_end < current_height {
+            pb.abandon();
+            return Err(ReorgCalcError::Interrupted { next_height: scan.next_height });
+        }
+    }
+    
+    pb.finish_with_message("Chain work calculation complete");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Work of `n` difficulty-1 blocks.
+    fn units(n: u64) -> Work {
+        (1..n).fold(block_work(0x1d00ffff), |total, _| total + block_work(0x1d00ffff))
+    }
+
+    #[test]
+    fn difficulty_one_block_work() {
+        assert_eq!(bits_to_difficulty(0x1d00ffff), 1.0);
+        // The exact work, 2^256 / (target + 1); `HASHES_PER_DIFFICULTY` rounds it to 2^32
+        assert_eq!(work_to_decimal(block_work(0x1d00ffff)), "4295032833");
+        assert_eq!(work_to_decimal(Work::from_be_bytes([0; 32])), "0");
+        // Halving the target doubles the work
+        assert_eq!(block_work(0x1c7fff80), units(2));
+    }
+
+    #[test]
+    fn blocks_to_match_rounds_up() {
+        assert_eq!(blocks_to_match(units(9), units(3)), 3);
+        assert_eq!(blocks_to_match(units(10), units(3)), 4);
+        assert_eq!(blocks_to_match(units(1), units(5)), 1);
+        assert_eq!(blocks_to_match(Work::from_be_bytes([0; 32]), units(1)), 0);
+        assert_eq!(blocks_to_match(units(1), Work::from_be_bytes([0; 32])), u128::MAX);
+    }
+}
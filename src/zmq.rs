@@ -0,0 +1,226 @@
+//! Subscriber for bitcoind's `zmqpubhashblock` notifications.
+//!
+//! Implements just enough of ZMTP 3.0 (NULL security, SUB socket) to receive
+//! `hashblock` messages over TCP, so watch-style modes can react to new
+//! blocks immediately instead of polling.
+
+use bitcoin::hashes::Hash;
+use bitcoin::BlockHash;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use crate::error::{ReorgCalcError, Result};
+
+const HASHBLOCK_TOPIC: &[u8] = b"hashblock";
+const FLAG_MORE: u8 = 0x01;
+const FLAG_LONG: u8 = 0x02;
+const FLAG_COMMAND: u8 = 0x04;
+/// Upper bound for the rest of a frame once its first byte has arrived.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest frame accepted; hashblock frames are 32 bytes, so anything near
+/// this is a broken or hostile peer rather than a notification.
+const MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// A connection to a `zmqpubhashblock` endpoint.
+pub struct HashBlockSubscriber {
+    stream: TcpStream,
+}
+
+impl HashBlockSubscriber {
+    /// Connect to `endpoint` (`tcp://host:port` or `host:port`) and subscribe
+    /// to `hashblock`.
+    pub fn connect(endpoint: &str) -> Result<Self> {
+        let addr = endpoint.strip_prefix("tcp://").unwrap_or(endpoint);
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| ReorgCalcError::NodeUnreachable(format!("ZMQ {}: {}", endpoint, e)))?;
+        stream.set_read_timeout(Some(FRAME_TIMEOUT))?;
+        let mut subscriber = Self { stream };
+        subscriber.handshake()?;
+        Ok(subscriber)
+    }
+
+    fn handshake(&mut self) -> Result<()> {
+        // Greeting: signature, version 3.0, NULL mechanism, as-server = 0
+        let mut greeting = [0u8; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        self.stream.write_all(&greeting)?;
+
+        let mut peer = [0u8; 64];
+        self.stream.read_exact(&mut peer)?;
+        if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 {
+            return Err(ReorgCalcError::InvalidData("not a ZMTP 3 endpoint".to_string()));
+        }
+
+        // READY command announcing a SUB socket
+        let mut ready = vec![5];
+        ready.extend_from_slice(b"READY");
+        ready.push(11);
+        ready.extend_from_slice(b"Socket-Type");
+        ready.extend_from_slice(&3u32.to_be_bytes());
+        ready.extend_from_slice(b"SUB");
+        self.write_frame(FLAG_COMMAND, &ready)?;
+
+        let (flags, body) = self.read_frame()?;
+        if flags & FLAG_COMMAND == 0 || !body.starts_with(b"\x05READY") {
+            return Err(ReorgCalcError::InvalidData("unexpected ZMTP handshake reply".to_string()));
+        }
+
+        // ZMTP 3.0 subscriptions are a message whose first byte is 1
+        let mut subscribe = vec![1];
+        subscribe.extend_from_slice(HASHBLOCK_TOPIC);
+        self.write_frame(0, &subscribe)
+    }
+
+    fn write_frame(&mut self, flags: u8, body: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(body.len() + 9);
+        if body.len() > 255 {
+            frame.push(flags | FLAG_LONG);
+            frame.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        } else {
+            frame.push(flags);
+            frame.push(body.len() as u8);
+        }
+        frame.extend_from_slice(body);
+        self.stream.write_all(&frame)?;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<(u8, Vec<u8>)> {
+        let mut flags = [0u8; 1];
+        self.stream.read_exact(&mut flags)?;
+        self.read_frame_body(flags[0])
+    }
+
+    fn read_frame_body(&mut self, flags: u8) -> Result<(u8, Vec<u8>)> {
+        let len = if flags & FLAG_LONG != 0 {
+            let mut len = [0u8; 8];
+            self.stream.read_exact(&mut len)?;
+            let len = u64::from_be_bytes(len);
+            if len > MAX_FRAME_SIZE as u64 {
+                return Err(ReorgCalcError::InvalidData(format!(
+                    "ZMQ frame of {} bytes exceeds the {} byte limit",
+                    len, MAX_FRAME_SIZE
+                )));
+            }
+            len as usize
+        } else {
+            let mut len = [0u8; 1];
+            self.stream.read_exact(&mut len)?;
+            len[0] as usize
+        };
+        let mut body = vec![0u8; len];
+        self.stream.read_exact(&mut body)?;
+        Ok((flags, body))
+    }
+
+    /// Wait up to `timeout` for the next block notification.
+    ///
+    /// Returns `Ok(None)` if no block arrived in time.
+    pub fn wait_for_block(&mut self, timeout: Duration) -> Result<Option<BlockHash>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            // Only the first byte honours `timeout`; a frame in progress is
+            // read to completion so the stream never desynchronises.
+            self.stream.set_read_timeout(Some(remaining))?;
+            let mut flags = [0u8; 1];
+            match self.stream.read_exact(&mut flags) {
+                Ok(()) => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.into()),
+            }
+            self.stream.set_read_timeout(Some(FRAME_TIMEOUT))?;
+
+            let mut parts = vec![self.read_frame_body(flags[0])?];
+            while parts.last().is_some_and(|(flags, _)| flags & FLAG_MORE != 0) {
+                parts.push(self.read_frame()?);
+            }
+            if parts[0].0 & FLAG_COMMAND != 0 {
+                // PING and friends; nothing to do for a subscriber
+                continue;
+            }
+
+            // [topic, 32-byte hash in display order, 4-byte sequence]
+            if parts.len() >= 2 && parts[0].1 == HASHBLOCK_TOPIC && parts[1].1.len() == 32 {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&parts[1].1);
+                bytes.reverse();
+                return Ok(Some(BlockHash::from_byte_array(bytes)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// A `zmqpubhashblock` endpoint that completes the handshake and sends
+    /// `frames`, each a flags byte, length and body, once subscribed.
+    fn publisher(frames: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 64];
+            stream.read_exact(&mut greeting).unwrap();
+            greeting[32] = 1;
+            stream.write_all(&greeting).unwrap();
+
+            let mut header = [0u8; 2];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(header[0], FLAG_COMMAND);
+            let mut ready = vec![0u8; header[1] as usize];
+            stream.read_exact(&mut ready).unwrap();
+            assert!(ready.ends_with(b"Socket-Type\x00\x00\x00\x03SUB"));
+            stream.write_all(b"\x04\x06\x05READY").unwrap();
+
+            let mut subscribe = [0u8; 12];
+            stream.read_exact(&mut subscribe).unwrap();
+            assert_eq!(&subscribe, b"\x00\x0a\x01hashblock");
+            stream.write_all(&frames).unwrap();
+            // Hold the connection open until the subscriber is done
+            let _ = stream.read(&mut [0u8; 1]);
+        });
+        format!("tcp://{}", addr)
+    }
+
+    #[test]
+    fn hashblock_notifications_are_decoded() {
+        let hash: Vec<u8> = (0..32).collect();
+        let mut frames = b"\x04\x05\x04PING".to_vec();
+        frames.extend_from_slice(b"\x01\x09hashblock\x01\x20");
+        frames.extend_from_slice(&hash);
+        frames.extend_from_slice(b"\x00\x04\x00\x00\x00\x00");
+
+        let mut subscriber = HashBlockSubscriber::connect(&publisher(frames)).unwrap();
+        let block = subscriber.wait_for_block(Duration::from_secs(5)).unwrap().unwrap();
+        let mut expected: [u8; 32] = hash.try_into().unwrap();
+        expected.reverse();
+        assert_eq!(block, BlockHash::from_byte_array(expected));
+        assert_eq!(subscriber.wait_for_block(Duration::from_millis(50)).unwrap(), None);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut frames = vec![FLAG_LONG];
+        frames.extend_from_slice(&(2u64 << 20).to_be_bytes());
+
+        let mut subscriber = HashBlockSubscriber::connect(&publisher(frames)).unwrap();
+        match subscriber.wait_for_block(Duration::from_secs(5)) {
+            Err(ReorgCalcError::InvalidData(message)) => assert!(message.contains("exceeds")),
+            other => panic!("expected an oversized frame error, got {:?}", other),
+        }
+    }
+}